@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use dupdupninja_core::{Error, Result};
+
+pub struct OptSpec {
+    pub long: &'static str,
+    pub alias: Option<&'static str>,
+    pub value_name: Option<&'static str>,
+    pub help: &'static str,
+}
+
+impl OptSpec {
+    pub const fn flag(long: &'static str, help: &'static str) -> Self {
+        Self {
+            long,
+            alias: None,
+            value_name: None,
+            help,
+        }
+    }
+
+    pub const fn value(long: &'static str, value_name: &'static str, help: &'static str) -> Self {
+        Self {
+            long,
+            alias: None,
+            value_name: Some(value_name),
+            help,
+        }
+    }
+
+    pub const fn alias(mut self, alias: &'static str) -> Self {
+        self.alias = Some(alias);
+        self
+    }
+
+    fn usage(&self) -> String {
+        match self.value_name {
+            Some(name) => format!("{} <{name}>", self.long),
+            None => self.long.to_string(),
+        }
+    }
+}
+
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub about: &'static str,
+    pub options: &'static [OptSpec],
+}
+
+impl CommandSpec {
+    fn option(&self, arg: &str) -> Option<&OptSpec> {
+        self.options
+            .iter()
+            .find(|opt| opt.long == arg || opt.alias == Some(arg))
+    }
+
+    fn matches_name(&self, name: &str) -> bool {
+        self.name == name || self.aliases.contains(&name)
+    }
+}
+
+/// Parsed options for one subcommand. Values are keyed by the option's long name.
+#[derive(Debug, Default)]
+pub struct Matches {
+    values: HashMap<&'static str, Vec<String>>,
+    flags: Vec<&'static str>,
+}
+
+impl Matches {
+    pub fn flag(&self, long: &str) -> bool {
+        self.flags.contains(&long)
+    }
+
+    /// Returns whichever of the mutually exclusive flags was given last.
+    pub fn last_flag(&self, choices: &[&'static str]) -> Option<&'static str> {
+        self.flags
+            .iter()
+            .rev()
+            .find(|flag| choices.contains(flag))
+            .copied()
+    }
+
+    pub fn value(&self, long: &str) -> Option<&str> {
+        self.values
+            .get(long)
+            .and_then(|values| values.last())
+            .map(String::as_str)
+    }
+
+    pub fn values(&self, long: &str) -> &[String] {
+        self.values.get(long).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn parse<T: FromStr>(&self, long: &str) -> Result<Option<T>> {
+        match self.value(long) {
+            Some(raw) => raw
+                .parse()
+                .map(Some)
+                .map_err(|_| Error::InvalidArgument(format!("invalid {long} value: {raw}"))),
+            None => Ok(None),
+        }
+    }
+
+    pub fn required(&self, long: &str) -> Result<&str> {
+        self.value(long)
+            .ok_or_else(|| Error::InvalidArgument(format!("missing {long}")))
+    }
+}
+
+pub fn find_command<'a>(commands: &[&'a CommandSpec], name: &str) -> Option<&'a CommandSpec> {
+    commands.iter().copied().find(|cmd| cmd.matches_name(name))
+}
+
+/// Parses subcommand arguments. Returns `None` when help was requested and printed.
+pub fn parse(
+    spec: &CommandSpec,
+    mut args: impl Iterator<Item = String>,
+) -> Result<Option<Matches>> {
+    let mut matches = Matches::default();
+    while let Some(arg) = args.next() {
+        if arg == "--help" || arg == "-h" {
+            print_command_help(spec);
+            return Ok(None);
+        }
+        let (name, inline) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let Some(opt) = spec.option(name) else {
+            return Err(Error::InvalidArgument(format!(
+                "unknown arg for `{}`: {arg}",
+                spec.name
+            )));
+        };
+        match opt.value_name {
+            Some(value_name) => {
+                let value = match inline {
+                    Some(value) => value,
+                    None => args.next().ok_or_else(|| {
+                        Error::InvalidArgument(format!(
+                            "missing value for {} <{value_name}>",
+                            opt.long
+                        ))
+                    })?,
+                };
+                matches.values.entry(opt.long).or_default().push(value);
+            }
+            None => {
+                if inline.is_some() {
+                    return Err(Error::InvalidArgument(format!(
+                        "{} does not take a value",
+                        opt.long
+                    )));
+                }
+                matches.flags.push(opt.long);
+            }
+        }
+    }
+    Ok(Some(matches))
+}
+
+pub fn print_command_help(spec: &CommandSpec) {
+    println!("dupdupninja {} - {}", spec.name, spec.about);
+    println!();
+    println!("USAGE:");
+    println!("  dupdupninja {} [OPTIONS]", spec.name);
+    if !spec.aliases.is_empty() {
+        println!();
+        println!("ALIASES: {}", spec.aliases.join(", "));
+    }
+    println!();
+    println!("OPTIONS:");
+    let rows: Vec<(String, &str)> = spec
+        .options
+        .iter()
+        .map(|opt| {
+            let mut usage = opt.usage();
+            if let Some(alias) = opt.alias {
+                usage = format!("{usage}, {alias}");
+            }
+            (usage, opt.help)
+        })
+        .chain(std::iter::once(("-h, --help".to_string(), "Print help")))
+        .collect();
+    let width = rows.iter().map(|(usage, _)| usage.len()).max().unwrap_or(0);
+    for (usage, help) in rows {
+        println!("  {usage:<width$}  {help}");
+    }
+}
+
+pub fn print_app_help(commands: &[&CommandSpec], notes: &str) {
+    println!("dupdupninja");
+    println!();
+    println!("USAGE:");
+    println!("  dupdupninja <COMMAND> [OPTIONS]");
+    println!();
+    println!("COMMANDS:");
+    let width = commands.iter().map(|cmd| cmd.name.len()).max().unwrap_or(0);
+    for cmd in commands {
+        println!("  {:<width$}  {}", cmd.name, cmd.about);
+    }
+    println!();
+    println!("Run `dupdupninja <COMMAND> --help` for command options.");
+    if !notes.is_empty() {
+        println!();
+        println!("NOTES:");
+        print!("{notes}");
+    }
+}
@@ -15,10 +15,14 @@ use crossterm::terminal::{
 };
 use crossterm::{execute, ExecutableCommand};
 use dupdupninja_core::db::SqliteScanStore;
-use dupdupninja_core::models::ScanRootKind;
+use dupdupninja_core::format::{format_bytes, hex_encode};
+use dupdupninja_core::models::{
+    FileListRow, FileListSort, ScanResult, ScanRootKind, SnapshotStorage,
+};
 use dupdupninja_core::scan::{
-    prescan, scan_to_sqlite_with_progress_and_totals, PrescanProgress, ScanCancelToken, ScanConfig,
-    ScanProgress, ScanTotals,
+    prescan_for_scan, regenerate_snapshots, scan_to_sqlite_with_progress,
+    scan_to_sqlite_with_progress_and_totals, scan_with_prescan, PrescanProgress, ScanCancelToken,
+    ScanConfig, ScanProgress, ScanTotals,
 };
 use ratatui::backend::CrosstermBackend;
 use ratatui::layout::{Constraint, Direction, Layout};
@@ -29,189 +33,485 @@ use ratatui::widgets::{Block, Borders, Clear, Gauge, List, ListItem, ListState,
 use ratatui::Terminal;
 use sysinfo::{Pid, ProcessesToUpdate, System};
 
+mod args;
 mod web;
+mod ws;
+
+use crate::args::{CommandSpec, Matches, OptSpec};
+
+const SCAN_COMMAND: CommandSpec = CommandSpec {
+    name: "scan",
+    aliases: &[],
+    about: "Scan a folder or drive into a fileset",
+    options: &[
+        OptSpec::value(
+            "--root",
+            "path",
+            "Folder or mount point to scan (repeatable)",
+        )
+        .alias("--path"),
+        OptSpec::value("--db", "fileset.ddn", "Fileset path (default: data dir)"),
+        OptSpec::flag("--folder", "Treat the root as a folder (default)"),
+        OptSpec::flag("--drive", "Treat the root as a whole drive"),
+        OptSpec::flag("--concurrent", "Process files concurrently (default)"),
+        OptSpec::flag("--single-threaded", "Process files one at a time"),
+        OptSpec::value("--threads", "n", "Worker threads for concurrent processing"),
+        OptSpec::value(
+            "--ffprobe-jobs",
+            "n",
+            "Most ffprobe processes run at once (default 4)",
+        ),
+        OptSpec::value("--exclude", "glob", "Skip matching paths (repeatable)"),
+        OptSpec::value(
+            "--exclude-mount",
+            "path",
+            "Skip this directory and everything below it (repeatable)",
+        ),
+        OptSpec::flag(
+            "--one-file-system",
+            "Do not descend into other filesystems (mounts, /proc, network shares)",
+        ),
+        OptSpec::flag(
+            "--absolute-paths",
+            "Store absolute paths: the DB can move, the scanned folder cannot",
+        ),
+        OptSpec::flag(
+            "--case-insensitive-paths",
+            "Treat paths differing only in case as one file (Windows, macOS volumes)",
+        ),
+        OptSpec::flag(
+            "--gitignore",
+            "Also skip what .gitignore, .ignore and global git excludes ignore",
+        ),
+        OptSpec::value(
+            "--min-size",
+            "bytes",
+            "Skip files smaller than this (K/M/G suffixes)",
+        ),
+        OptSpec::flag("--include-empty", "Also store zero-byte files"),
+        OptSpec::flag(
+            "--no-raw-ffprobe",
+            "Store parsed codec columns only, not the full ffprobe JSON",
+        ),
+        OptSpec::flag(
+            "--hash-only",
+            "Only hash: no file type detection, ffprobe metadata or snapshots",
+        ),
+        OptSpec::flag("--capture-snapshots", "Capture video snapshots"),
+        OptSpec::flag("--no-snapshots", "Do not capture video snapshots (default)"),
+        OptSpec::value(
+            "--snapshots-per-video",
+            "n",
+            "Snapshots per video (default 3)",
+        ),
+        OptSpec::value(
+            "--snapshot-max-dim",
+            "px",
+            "Max snapshot dimension (default 1024)",
+        ),
+        OptSpec::value(
+            "--snapshot-dir",
+            "dir",
+            "Write snapshots as files here instead of into the fileset",
+        ),
+        OptSpec::flag("--quiet", "Do not render progress"),
+        OptSpec::flag("--no-prescan", "Skip counting files first (no percentage)"),
+        OptSpec::flag(
+            "--single-pass",
+            "Walk the tree once, keeping the prescan's file list in memory",
+        ),
+        OptSpec::flag(
+            "--chunk-dedup",
+            "Store chunk fingerprints of large files for `overlaps` (reads them again)",
+        ),
+        OptSpec::flag(
+            "--resume",
+            "Continue an interrupted scan into the same --db",
+        ),
+        OptSpec::flag(
+            "--dry-run",
+            "Count what would be scanned without hashing or writing the fileset",
+        ),
+    ],
+};
 
-fn main() {
-    if let Err(err) = real_main() {
-        eprintln!("error: {err}");
-        std::process::exit(2);
-    }
-}
+const SNAPSHOTS_COMMAND: CommandSpec = CommandSpec {
+    name: "snapshots",
+    aliases: &[],
+    about: "Capture snapshots for videos and HEIC/RAW images that have none",
+    options: &[
+        OptSpec::value("--db", "fileset.ddn", "Fileset to update"),
+        OptSpec::value(
+            "--root",
+            "path",
+            "Where the files live now (default: scanned root)",
+        ),
+        OptSpec::value(
+            "--snapshots-per-video",
+            "n",
+            "Snapshots per video (default 3)",
+        ),
+        OptSpec::value(
+            "--snapshot-max-dim",
+            "px",
+            "Max snapshot dimension (default 1024)",
+        ),
+        OptSpec::value(
+            "--snapshot-dir",
+            "dir",
+            "Write snapshots as files here instead of into the fileset",
+        ),
+        OptSpec::flag("--quiet", "Do not render progress"),
+    ],
+};
 
-fn real_main() -> dupdupninja_core::Result<()> {
-    let mut args = std::env::args().skip(1);
-    let Some(cmd) = args.next() else {
-        print_help();
-        return Ok(());
-    };
+const MATCHES_COMMAND: CommandSpec = CommandSpec {
+    name: "matches",
+    aliases: &[],
+    about: "Browse exact and similar matches in a fileset",
+    options: &[
+        OptSpec::value("--db", "fileset.ddn", "Fileset to read"),
+        OptSpec::value("--mode", "all|similar|exact", "Match kinds to show"),
+        OptSpec::flag("--all", "Same as --mode all"),
+        OptSpec::flag("--similar", "Same as --mode similar"),
+        OptSpec::flag("--exact", "Same as --mode exact"),
+        OptSpec::flag("--tui", "Force the interactive view"),
+        OptSpec::flag("--plain", "Force plain output"),
+        OptSpec::value("--max-files", "n", "Max files to group (default 500)"),
+        OptSpec::value("--ahash", "n", "aHash distance threshold (default 10)"),
+        OptSpec::value("--dhash", "n", "dHash distance threshold (default 10)"),
+        OptSpec::value("--phash", "n", "pHash distance threshold (default 8)"),
+    ],
+};
 
-    match cmd.as_str() {
-        "--help" | "-h" | "help" => {
-            print_help();
-            Ok(())
-        }
-        "scan" => run_scan_command(&mut args),
-        "matches" => run_matches_command(&mut args),
-        "web" => {
-            let mut port: u16 = 4455;
-            while let Some(arg) = args.next() {
-                match arg.as_str() {
-                    "--port" => {
-                        if let Some(val) = args.next() {
-                            port = val.parse().map_err(|_| {
-                                dupdupninja_core::Error::InvalidArgument(format!(
-                                    "invalid --port value: {val}"
-                                ))
-                            })?;
-                        }
-                    }
-                    _ => {
-                        return Err(dupdupninja_core::Error::InvalidArgument(format!(
-                            "unknown arg: {arg}"
-                        )));
-                    }
-                }
-            }
-            web::run_web_server(port)?;
-            Ok(())
-        }
-        _ => Err(dupdupninja_core::Error::InvalidArgument(format!(
-            "unknown command: {cmd}"
-        ))),
-    }
-}
+const LIST_COMMAND: CommandSpec = CommandSpec {
+    name: "list",
+    aliases: &[],
+    about: "Print files stored in a fileset",
+    options: &[
+        OptSpec::value("--db", "fileset.ddn", "Fileset to read"),
+        OptSpec::flag("--duplicates-only", "Only files with an exact duplicate"),
+        OptSpec::value("--limit", "n", "Max rows (default 100)"),
+        OptSpec::value("--offset", "n", "Rows to skip"),
+        OptSpec::value(
+            "--sort",
+            "size|reclaimable|name",
+            "Order by size or reclaimable space (largest first), or path",
+        ),
+        OptSpec::flag("--json", "Emit rows as JSON"),
+    ],
+};
 
-fn print_help() {
-    println!(
-        r#"dupdupninja
+const DEDUPE_COMMAND: CommandSpec = CommandSpec {
+    name: "dedupe",
+    aliases: &[],
+    about: "Remove exact duplicates, keeping one file per group; ignored groups are skipped",
+    options: &[
+        OptSpec::value("--db", "fileset.ddn", "Fileset to read"),
+        OptSpec::value(
+            "--keep",
+            "oldest|newest|shortest-path",
+            "Which file of each group to keep",
+        ),
+        OptSpec::flag("--trash", "Move duplicates to the trash"),
+        OptSpec::flag("--delete", "Delete duplicates permanently"),
+        OptSpec::flag(
+            "--symlink",
+            "Replace duplicates with symlinks to the kept file",
+        ),
+        OptSpec::flag("--dry-run", "Only report what would happen (default)"),
+    ],
+};
+
+const REPORT_COMMAND: CommandSpec = CommandSpec {
+    name: "report",
+    aliases: &[],
+    about: "Summarize a fileset: totals, duplicates and file types",
+    options: &[OptSpec::value("--db", "fileset.ddn", "Fileset to read")],
+};
+
+const OVERLAPS_COMMAND: CommandSpec = CommandSpec {
+    name: "overlaps",
+    aliases: &[],
+    about: "List files sharing large segments (needs a scan with --chunk-dedup)",
+    options: &[
+        OptSpec::value("--db", "fileset.ddn", "Fileset to read"),
+        OptSpec::value(
+            "--min-shared",
+            "MiB",
+            "Least shared content to report a pair (default 64)",
+        ),
+    ],
+};
+
+const VERIFY_COMMAND: CommandSpec = CommandSpec {
+    name: "verify",
+    aliases: &[],
+    about: "Re-hash files and report bit rot or missing files",
+    options: &[
+        OptSpec::value("--db", "fileset.ddn", "Fileset to verify"),
+        OptSpec::value(
+            "--root",
+            "path",
+            "Where the files live now (default: scanned root)",
+        ),
+        OptSpec::value("--sample", "fraction", "Verify a random fraction, e.g. 0.1"),
+        OptSpec::value("--seed", "n", "Seed for --sample, for repeatable runs"),
+    ],
+};
+
+const EXPORT_COMMAND: CommandSpec = CommandSpec {
+    name: "export",
+    aliases: &[],
+    about: "Export fileset rows as CSV or JSON lines",
+    options: &[
+        OptSpec::value("--db", "fileset.ddn", "Fileset to read"),
+        OptSpec::value("--out", "path", "Output file (default: stdout)"),
+        OptSpec::value(
+            "--format",
+            "csv|jsonl",
+            "CSV, or one JSON object per line (default: csv)",
+        ),
+        OptSpec::flag("--duplicates-only", "Only files with an exact duplicate"),
+    ],
+};
+
+const PRUNE_COMMAND: CommandSpec = CommandSpec {
+    name: "prune",
+    aliases: &[],
+    about: "Drop fileset rows whose files no longer exist",
+    options: &[
+        OptSpec::value("--db", "fileset.ddn", "Fileset to update"),
+        OptSpec::flag("--dry-run", "Only report what would be removed"),
+    ],
+};
 
-USAGE:
-  dupdupninja scan --root <path> [--db <fileset.ddn>] [--drive|--folder] [--single-threaded|--concurrent] [--capture-snapshots|--no-snapshots] [--snapshots-per-video <n>] [--snapshot-max-dim <px>]
-  dupdupninja matches --db <sqlite_path> [--mode <all|similar|exact>] [--tui|--plain] [--max-files <n>] [--ahash <n>] [--dhash <n>] [--phash <n>]
-  dupdupninja web [--port <port>]
+const COMPACT_COMMAND: CommandSpec = CommandSpec {
+    name: "compact",
+    aliases: &[],
+    about: "Reclaim unused space in a fileset database",
+    options: &[
+        OptSpec::value("--db", "fileset.ddn", "Fileset to compact"),
+        OptSpec::value(
+            "--sidecar-dir",
+            "dir",
+            "First move embedded snapshots out to files here",
+        ),
+    ],
+};
+
+const MERGE_COMMAND: CommandSpec = CommandSpec {
+    name: "merge",
+    aliases: &[],
+    about: "Copy another fileset's files into a fileset, matching rows by path",
+    options: &[
+        OptSpec::value("--into", "a.ddn", "Fileset to merge into"),
+        OptSpec::value("--from", "b.ddn", "Fileset to copy from; left unchanged"),
+    ],
+};
+
+const SERVE_COMMAND: CommandSpec = CommandSpec {
+    name: "serve",
+    aliases: &["web"],
+    about: "Run the local web UI",
+    options: &[
+        OptSpec::value("--port", "port", "Port to listen on (default 4455)"),
+        OptSpec::value("--bind", "ip", "Address to listen on (default 127.0.0.1)"),
+        OptSpec::flag("--open", "Open the UI in the default browser"),
+        OptSpec::value(
+            "--token",
+            "token",
+            "Require this bearer token to start or cancel scans (env: DUPDUP_WEB_TOKEN)",
+        ),
+        OptSpec::value(
+            "--max-jobs",
+            "n",
+            "Finished scans kept in history (default 50, 0 = all)",
+        ),
+        OptSpec::value(
+            "--max-concurrent-scans",
+            "n",
+            "Scans run at once; the rest wait in a queue (default 2, 0 = no limit)",
+        ),
+    ],
+};
 
-NOTES:
-  - Filesets are stored as standalone SQLite DBs (one per scan).
+const COMMANDS: &[&CommandSpec] = &[
+    &SCAN_COMMAND,
+    &SNAPSHOTS_COMMAND,
+    &MATCHES_COMMAND,
+    &LIST_COMMAND,
+    &DEDUPE_COMMAND,
+    &REPORT_COMMAND,
+    &OVERLAPS_COMMAND,
+    &VERIFY_COMMAND,
+    &EXPORT_COMMAND,
+    &PRUNE_COMMAND,
+    &COMPACT_COMMAND,
+    &MERGE_COMMAND,
+    &SERVE_COMMAND,
+];
+
+const HELP_NOTES: &str = "  - Filesets are stored as standalone SQLite DBs (one per scan).
   - `scan` writes live progress in-place in the terminal (no scrolling log spam).
   - Snapshot capture is optional in CLI scan mode (`--capture-snapshots`).
   - Scan processing is concurrent by default.
-  - UI crates are present but stubbed; the CLI is the initial entrypoint.
-  - Web UI listens on http://127.0.0.1:4455 by default.
-"#
-    );
+  - Web UI listens on http://127.0.0.1:4455 by default (`serve --bind` to change).
+";
+
+/// Exit status for a run stopped by Ctrl-C, matching the shell's 128 + SIGINT.
+const EXIT_CANCELLED: i32 = 130;
+
+fn main() {
+    match real_main() {
+        Ok(()) => {}
+        Err(dupdupninja_core::Error::Cancelled) => std::process::exit(EXIT_CANCELLED),
+        Err(err) => {
+            eprintln!("error: {err}");
+            std::process::exit(2);
+        }
+    }
 }
 
-fn run_scan_command(args: &mut impl Iterator<Item = String>) -> dupdupninja_core::Result<()> {
-    let mut root: Option<PathBuf> = None;
-    let mut db: Option<PathBuf> = None;
-    let mut root_kind = ScanRootKind::Folder;
-    let mut concurrent_processing = true;
-    let mut capture_snapshots = false;
-    let mut snapshots_per_video: u32 = 3;
-    let mut snapshot_max_dim: u32 = 1024;
-
-    while let Some(arg) = args.next() {
-        match arg.as_str() {
-            "--root" | "--path" => {
-                let value = args.next().ok_or_else(|| {
-                    dupdupninja_core::Error::InvalidArgument(
-                        "missing value for --root <path>".to_string(),
-                    )
-                })?;
-                root = Some(PathBuf::from(value));
-            }
-            "--db" => {
-                let value = args.next().ok_or_else(|| {
-                    dupdupninja_core::Error::InvalidArgument(
-                        "missing value for --db <path>".to_string(),
-                    )
-                })?;
-                db = Some(PathBuf::from(value));
-            }
-            "--drive" => root_kind = ScanRootKind::Drive,
-            "--folder" => root_kind = ScanRootKind::Folder,
-            "--single-threaded" => concurrent_processing = false,
-            "--concurrent" => concurrent_processing = true,
-            "--capture-snapshots" => capture_snapshots = true,
-            "--no-snapshots" => capture_snapshots = false,
-            "--snapshots-per-video" => {
-                let value = args.next().ok_or_else(|| {
-                    dupdupninja_core::Error::InvalidArgument(
-                        "missing value for --snapshots-per-video <n>".to_string(),
-                    )
-                })?;
-                snapshots_per_video = value.parse().map_err(|_| {
-                    dupdupninja_core::Error::InvalidArgument(format!(
-                        "invalid --snapshots-per-video value: {value}"
-                    ))
-                })?;
-            }
-            "--snapshot-max-dim" => {
-                let value = args.next().ok_or_else(|| {
-                    dupdupninja_core::Error::InvalidArgument(
-                        "missing value for --snapshot-max-dim <px>".to_string(),
-                    )
-                })?;
-                snapshot_max_dim = value.parse().map_err(|_| {
-                    dupdupninja_core::Error::InvalidArgument(format!(
-                        "invalid --snapshot-max-dim value: {value}"
-                    ))
-                })?;
-            }
-            _ => {
-                return Err(dupdupninja_core::Error::InvalidArgument(format!(
-                    "unknown arg: {arg}"
-                )));
-            }
+fn real_main() -> dupdupninja_core::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let Some(cmd) = args.next() else {
+        args::print_app_help(COMMANDS, HELP_NOTES);
+        return Ok(());
+    };
+    if matches!(cmd.as_str(), "--help" | "-h" | "help") {
+        match args
+            .next()
+            .and_then(|name| args::find_command(COMMANDS, &name))
+        {
+            Some(spec) => args::print_command_help(spec),
+            None => args::print_app_help(COMMANDS, HELP_NOTES),
         }
+        return Ok(());
     }
 
-    let root = root.ok_or_else(|| {
-        dupdupninja_core::Error::InvalidArgument("missing --root <path>".to_string())
+    let spec = args::find_command(COMMANDS, &cmd).ok_or_else(|| {
+        dupdupninja_core::Error::InvalidArgument(format!("unknown command: {cmd}"))
     })?;
-    if capture_snapshots && snapshots_per_video == 0 {
-        return Err(dupdupninja_core::Error::InvalidArgument(
-            "--snapshots-per-video must be > 0 when --capture-snapshots is enabled".to_string(),
-        ));
-    }
-    if capture_snapshots && snapshot_max_dim == 0 {
+    let Some(matches) = args::parse(spec, args)? else {
+        return Ok(());
+    };
+    match spec.name {
+        "scan" => run_scan_command(&matches),
+        "snapshots" => run_snapshots_command(&matches),
+        "matches" => run_matches_command(&matches),
+        "list" => run_list_command(&matches),
+        "dedupe" => run_dedupe_command(&matches),
+        "report" => run_report_command(&matches),
+        "overlaps" => run_overlaps_command(&matches),
+        "verify" => run_verify_command(&matches),
+        "export" => run_export_command(&matches),
+        "prune" => run_prune_command(&matches),
+        "compact" => run_compact_command(&matches),
+        "merge" => run_merge_command(&matches),
+        "serve" => run_serve_command(&matches),
+        _ => unreachable!("command spec without handler"),
+    }
+}
+
+fn run_scan_command(args: &Matches) -> dupdupninja_core::Result<()> {
+    let root = PathBuf::from(args.required("--root")?);
+    let roots: Vec<PathBuf> = args.values("--root").iter().map(PathBuf::from).collect();
+    let db = args.value("--db").map(PathBuf::from);
+    let root_kind = match args.last_flag(&["--drive", "--folder"]) {
+        Some("--drive") => ScanRootKind::Drive,
+        _ => ScanRootKind::Folder,
+    };
+    let concurrent_processing =
+        args.last_flag(&["--single-threaded", "--concurrent"]) != Some("--single-threaded");
+    let hash_only = args.flag("--hash-only");
+    let capture_snapshots = !hash_only
+        && args.last_flag(&["--capture-snapshots", "--no-snapshots"])
+            == Some("--capture-snapshots");
+    let snapshots_per_video: u32 = args.parse("--snapshots-per-video")?.unwrap_or(3);
+    let snapshot_max_dim: u32 = args.parse("--snapshot-max-dim")?.unwrap_or(1024);
+    let threads: usize = args.parse("--threads")?.unwrap_or(0);
+    let ffprobe_concurrency: usize = args.parse("--ffprobe-jobs")?.unwrap_or(4);
+    let min_size_bytes = match args.value("--min-size") {
+        Some(raw) => parse_byte_size(raw).ok_or_else(|| {
+            dupdupninja_core::Error::InvalidArgument(format!("invalid --min-size value: {raw}"))
+        })?,
+        None => 0,
+    };
+    let exclude = args.values("--exclude").to_vec();
+    let quiet = args.flag("--quiet");
+    let run_prescan = !args.flag("--no-prescan");
+
+    if ffprobe_concurrency == 0 {
         return Err(dupdupninja_core::Error::InvalidArgument(
-            "--snapshot-max-dim must be > 0 when --capture-snapshots is enabled".to_string(),
+            "--ffprobe-jobs must be > 0".to_string(),
         ));
     }
-    let db = db.unwrap_or_else(|| scan_db_path(&root));
-    let store = SqliteScanStore::open(&db)?;
     let cfg = ScanConfig {
-        root: root.clone(),
+        root,
+        roots: if roots.len() > 1 { roots } else { Vec::new() },
         root_kind,
-        hash_files: true,
-        perceptual_hashes: true,
         capture_snapshots,
+        detect_file_type: !hash_only,
+        extract_metadata: !hash_only,
         snapshots_per_video,
         snapshot_max_dim,
+        snapshot_storage: snapshot_storage_arg(args),
         concurrent_processing,
+        threads,
+        exclude,
+        exclude_mounts: args
+            .values("--exclude-mount")
+            .iter()
+            .map(PathBuf::from)
+            .collect(),
+        stay_on_filesystem: args.flag("--one-file-system"),
+        min_size_bytes,
+        resume: args.flag("--resume"),
+        ffprobe_concurrency,
+        store_raw_ffprobe: !args.flag("--no-raw-ffprobe"),
+        single_pass: args.flag("--single-pass"),
+        chunk_dedup: args.flag("--chunk-dedup"),
+        use_gitignore: args.flag("--gitignore"),
+        store_absolute_paths: args.flag("--absolute-paths"),
+        case_insensitive_paths: args.flag("--case-insensitive-paths"),
+        sort_entries: true,
+        ignore_empty_files: !args.flag("--include-empty"),
+        dry_run: args.flag("--dry-run"),
+        ..Default::default()
     };
+    cfg.validate()?;
+    let root = cfg.base_root().to_path_buf();
+    let db = db.unwrap_or_else(|| scan_db_path(&cfg.scan_roots()[0]));
+    if cfg.dry_run {
+        return run_dry_run_scan(&cfg, &db, quiet);
+    }
+    let store = SqliteScanStore::open(&db)?;
     let snapshots_label = snapshot_settings_label(
         cfg.capture_snapshots,
         cfg.snapshots_per_video,
         cfg.snapshot_max_dim,
     );
 
-    let mut tui = match ScanTui::start() {
-        Ok(tui) => Some(tui),
-        Err(err) => {
-            eprintln!("warning: failed to initialize TUI ({err}); using plain progress output");
-            None
+    let mut tui = if quiet {
+        None
+    } else {
+        match ScanTui::start() {
+            Ok(tui) => Some(tui),
+            Err(err) => {
+                eprintln!("warning: failed to initialize TUI ({err}); using plain progress output");
+                None
+            }
         }
     };
-    let mut plain_progress = if tui.is_none() {
+    let mut plain_progress = if tui.is_none() && !quiet {
         Some(TerminalProgress::new())
     } else {
         None
     };
     let visual_mode = detect_visual_mode();
     let mut ui_state = ScanUiState::new(
-        root.clone(),
+        root,
         db.clone(),
         root_kind,
         visual_mode,
@@ -219,46 +519,58 @@ fn run_scan_command(args: &mut impl Iterator<Item = String>) -> dupdupninja_core
         snapshots_label.clone(),
     );
     let cancel_token = ScanCancelToken::new();
+    if let Err(err) = install_interrupt_handler(&cancel_token) {
+        eprintln!("warning: failed to install Ctrl-C handler ({err})");
+    }
     let mut cancel_watcher = CancelInputWatcher::start(cancel_token.clone(), tui.is_some());
     if let Some(ui) = tui.as_mut() {
         let _ = ui.render(&ui_state);
-    } else {
-        println!("root: {}", root.display());
+    } else if !quiet {
+        for scan_root in cfg.scan_roots() {
+            println!("root: {}", scan_root.display());
+        }
         println!("root kind: {}", root_kind_label(root_kind));
         println!("db: {}", db.display());
         println!("snapshots: {snapshots_label}");
     }
 
-    let prescan_result = prescan(&cfg, Some(&cancel_token), |update: &PrescanProgress| {
-        if cancel_token.is_cancelled() {
-            ui_state.on_cancel_requested();
-        }
-        if let Some(ui) = tui.as_mut() {
-            ui_state.on_prescan_progress(update);
-            if ui_state.should_render(false) {
-                let _ = ui.render(&ui_state);
+    let prescan_result = if !run_prescan {
+        Ok(None)
+    } else {
+        prescan_for_scan(&cfg, Some(&cancel_token), |update: &PrescanProgress| {
+            if cancel_token.is_cancelled() {
+                ui_state.on_cancel_requested();
             }
-        }
-        if let Some(progress) = plain_progress.as_mut() {
-            progress.draw_prescan(update);
-        }
-    });
-    let totals = match prescan_result {
-        Ok(totals) => {
+            if let Some(ui) = tui.as_mut() {
+                ui_state.on_prescan_progress(update);
+                if ui_state.should_render(false) {
+                    let _ = ui.render(&ui_state);
+                }
+            }
+            if let Some(progress) = plain_progress.as_mut() {
+                progress.draw_prescan(update);
+            }
+        })
+        .map(Some)
+    };
+    let prescanned = match prescan_result {
+        Ok(None) => None,
+        Ok(Some(prescanned)) => {
+            let totals = prescanned.totals;
             if let Some(progress) = plain_progress.as_mut() {
                 progress.finish_line();
             }
             if let Some(ui) = tui.as_mut() {
                 ui_state.on_prescan_done(totals);
                 let _ = ui.render(&ui_state);
-            } else {
+            } else if !quiet {
                 println!(
                     "prescan complete: {} files, {}",
                     totals.files,
-                    human_bytes(totals.bytes)
+                    format_bytes(totals.bytes)
                 );
             }
-            totals
+            Some(prescanned)
         }
         Err(dupdupninja_core::Error::Cancelled) => {
             cancel_watcher.stop();
@@ -272,31 +584,37 @@ fn run_scan_command(args: &mut impl Iterator<Item = String>) -> dupdupninja_core
             drop(tui);
             println!("scan cancelled during prescan");
             println!("fileset: {}", db.display());
-            return Ok(());
+            return Err(dupdupninja_core::Error::Cancelled);
         }
         Err(err) => return Err(err),
     };
 
-    let result = scan_to_sqlite_with_progress_and_totals(
-        &cfg,
-        &store,
-        Some(&cancel_token),
-        Some(totals),
-        |update: &ScanProgress| {
-            if cancel_token.is_cancelled() {
-                ui_state.on_cancel_requested();
-            }
-            if let Some(ui) = tui.as_mut() {
-                ui_state.on_scan_progress(update);
-                if ui_state.should_render(false) {
-                    let _ = ui.render(&ui_state);
-                }
-            }
-            if let Some(progress) = plain_progress.as_mut() {
-                progress.draw_scan(update);
+    let on_progress = |update: &ScanProgress| {
+        if cancel_token.is_cancelled() {
+            ui_state.on_cancel_requested();
+        }
+        if let Some(ui) = tui.as_mut() {
+            ui_state.on_scan_progress(update);
+            if ui_state.should_render(false) {
+                let _ = ui.render(&ui_state);
             }
-        },
-    );
+        }
+        if let Some(progress) = plain_progress.as_mut() {
+            progress.draw_scan(update);
+        }
+    };
+    let result = match prescanned {
+        Some(prescanned) => {
+            scan_with_prescan(&cfg, &store, Some(&cancel_token), prescanned, on_progress)
+        }
+        None => scan_to_sqlite_with_progress_and_totals(
+            &cfg,
+            &store,
+            Some(&cancel_token),
+            None,
+            on_progress,
+        ),
+    };
     if let Some(progress) = plain_progress.as_mut() {
         progress.finish_line();
     }
@@ -310,10 +628,18 @@ fn run_scan_command(args: &mut impl Iterator<Item = String>) -> dupdupninja_core
                 }
             }
             drop(tui);
+            let retried = match result.stats.files_failed_after_retries {
+                0 => String::new(),
+                n => format!(" ({n} after retries)"),
+            };
             println!(
-                "scan complete: {} files, {} hashed, {} skipped",
-                result.stats.files_seen, result.stats.files_hashed, result.stats.files_skipped
+                "scan complete: {} files, {} hashed, {} skipped{retried}, {} filtered",
+                result.stats.files_seen,
+                result.stats.files_hashed,
+                result.stats.files_skipped,
+                result.stats.files_filtered
             );
+            print_scan_warnings(&result);
             println!("fileset: {}", db.display());
             Ok(())
         }
@@ -327,12 +653,92 @@ fn run_scan_command(args: &mut impl Iterator<Item = String>) -> dupdupninja_core
             drop(tui);
             println!("scan cancelled");
             println!("fileset (partial): {}", db.display());
-            Ok(())
+            Err(dupdupninja_core::Error::Cancelled)
+        }
+        Err(err @ dupdupninja_core::Error::DiskFull(_)) => {
+            drop(tui);
+            println!("scan stopped: not enough space on the fileset's disk");
+            println!("fileset (partial): {}", db.display());
+            println!("free some space and rerun with --resume to continue");
+            Err(err)
         }
         Err(err) => Err(err),
     }
 }
 
+/// `scan --dry-run`: compares against `db` when it exists and never creates it.
+fn run_dry_run_scan(cfg: &ScanConfig, db: &Path, quiet: bool) -> dupdupninja_core::Result<()> {
+    let store = if db.exists() {
+        SqliteScanStore::open_read_only(db)?
+    } else {
+        SqliteScanStore::open_in_memory()?
+    };
+    let cancel_token = ScanCancelToken::new();
+    if let Err(err) = install_interrupt_handler(&cancel_token) {
+        eprintln!("warning: failed to install Ctrl-C handler ({err})");
+    }
+    let mut plain_progress = (!quiet).then(TerminalProgress::new);
+    let result = scan_to_sqlite_with_progress(cfg, &store, Some(&cancel_token), |update| {
+        if let Some(progress) = plain_progress.as_mut() {
+            progress.draw_scan(update);
+        }
+    });
+    if let Some(progress) = plain_progress.as_mut() {
+        progress.finish_line();
+    }
+    let result = result?;
+    let stats = &result.stats;
+    println!(
+        "dry run: {} files, {}; {} filtered, {} unreadable",
+        stats.files_seen,
+        format_bytes(stats.bytes_seen),
+        stats.files_filtered,
+        stats.files_skipped
+    );
+    println!(
+        "  {} unchanged since the last scan, {} to hash ({})",
+        stats.files_reused,
+        stats.files_to_hash,
+        format_bytes(stats.bytes_to_hash)
+    );
+    print_scan_warnings(&result);
+    Ok(())
+}
+
+/// How many unreadable files to list after the summary line.
+const SCAN_WARNINGS_SHOWN: usize = 5;
+
+fn print_scan_warnings(result: &ScanResult) {
+    let Some(summary) = result.warnings_summary() else {
+        return;
+    };
+    println!("warnings: {summary}");
+    for warning in result.warnings.iter().take(SCAN_WARNINGS_SHOWN) {
+        println!("  {}: {}", warning.path.display(), warning.reason);
+    }
+    if result.warnings.len() > SCAN_WARNINGS_SHOWN {
+        println!(
+            "  ... and {} more",
+            result.warnings.len() - SCAN_WARNINGS_SHOWN
+        );
+    }
+}
+
+/// Routes SIGINT to the scan's cancel token so the scan stops at the next file
+/// boundary and commits what it has, instead of the process dying mid-transaction.
+/// A second Ctrl-C while the scan is winding down exits immediately.
+fn install_interrupt_handler(cancel_token: &ScanCancelToken) -> std::io::Result<()> {
+    let flag = cancel_token.shared_flag();
+    // Registered first so it only sees the flag already set by an earlier Ctrl-C.
+    signal_hook::flag::register_conditional_shutdown(
+        signal_hook::consts::SIGINT,
+        EXIT_CANCELLED,
+        std::sync::Arc::clone(&flag),
+    )?;
+    signal_hook::flag::register(signal_hook::consts::SIGINT, flag)?;
+    Ok(())
+}
+
 struct CancelInputWatcher {
     stop_tx: Option<mpsc::Sender<()>>,
     handle: Option<thread::JoinHandle<()>>,
@@ -365,6 +771,11 @@ impl CancelInputWatcher {
                             cancel_token.cancel();
                             break;
                         }
+                        // Raw mode swallows SIGINT, so Ctrl-C arrives as a key press.
+                        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            cancel_token.cancel();
+                            break;
+                        }
                         _ => {}
                     },
                     Ok(_) => {}
@@ -517,7 +928,7 @@ impl ScanUiState {
         }
     }
 
-    fn on_done(&mut self, result: &dupdupninja_core::models::ScanResult) {
+    fn on_done(&mut self, result: &ScanResult) {
         self.phase = "done";
         self.current_step = "complete".to_string();
         self.active_tasks.clear();
@@ -813,14 +1224,14 @@ fn draw_scan_ui(frame: &mut ratatui::Frame<'_>, state: &ScanUiState) {
             "files {} | dirs {} | bytes {}",
             state.prescan_files,
             state.prescan_dirs,
-            human_bytes(state.prescan_bytes)
+            format_bytes(state.prescan_bytes)
         )),
     ]));
     detail_lines.push(Line::from(vec![
         Span::styled("Totals: ", Style::default().fg(Color::Gray)),
         Span::raw(format!(
             "{} | elapsed {}",
-            human_bytes(state.total_bytes),
+            format_bytes(state.total_bytes),
             human_elapsed(state.started_at.elapsed())
         )),
     ]));
@@ -860,11 +1271,11 @@ fn draw_scan_ui(frame: &mut ratatui::Frame<'_>, state: &ScanUiState) {
         .unwrap_or_else(|| "n/a".to_string());
     let rss = state
         .process_rss_bytes
-        .map(human_bytes)
+        .map(format_bytes)
         .unwrap_or_else(|| "n/a".to_string());
     let vmem = state
         .process_vmem_bytes
-        .map(human_bytes)
+        .map(format_bytes)
         .unwrap_or_else(|| "n/a".to_string());
     let threads = state
         .process_threads
@@ -904,8 +1315,7 @@ fn active_task_step_style(step: &str, pretty: bool) -> Style {
         return Style::default();
     }
     match step {
-        "blake3" => Style::default().fg(Color::Rgb(34, 197, 94)),
-        "sha256" => Style::default().fg(Color::Rgb(59, 130, 246)),
+        "blake3/sha256" => Style::default().fg(Color::Rgb(34, 197, 94)),
         "ffprobe metadata" => Style::default().fg(Color::Rgb(251, 191, 36)),
         "ahash/dhash/phash" => Style::default().fg(Color::Rgb(244, 114, 182)),
         "video snapshots" => Style::default().fg(Color::Rgb(168, 85, 247)),
@@ -926,7 +1336,7 @@ fn detect_visual_mode() -> VisualMode {
     }
 }
 
-fn scan_db_path(root: &Path) -> PathBuf {
+pub(crate) fn scan_db_path(root: &Path) -> PathBuf {
     let ts = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap_or_default()
@@ -934,14 +1344,19 @@ fn scan_db_path(root: &Path) -> PathBuf {
     let name = sanitize_fileset_name(root);
     let file_name = format!("{name}-{ts}.ddn");
 
-    let mut base = default_fileset_dir();
-    if std::fs::create_dir_all(&base).is_err() {
-        let mut fallback = std::env::temp_dir();
-        fallback.push(file_name);
-        return fallback;
+    let base = default_fileset_dir();
+    match dupdupninja_core::files::ensure_writable_dir(&base) {
+        Ok(()) => base.join(file_name),
+        Err(err) => {
+            let fallback = std::env::temp_dir().join(file_name);
+            eprintln!(
+                "warning: cannot write filesets to {} ({err}); using {}",
+                base.display(),
+                fallback.display()
+            );
+            fallback
+        }
     }
-    base.push(file_name);
-    base
 }
 
 fn sanitize_fileset_name(root: &Path) -> String {
@@ -964,7 +1379,12 @@ fn sanitize_fileset_name(root: &Path) -> String {
     }
 }
 
-fn default_fileset_dir() -> PathBuf {
+/// Where new filesets go: the `fileset_dir` the desktop app's settings name, or a
+/// folder in the user data dir.
+pub(crate) fn default_fileset_dir() -> PathBuf {
+    if let Some(dir) = configured_fileset_dir() {
+        return dir;
+    }
     let base = dirs::data_dir().unwrap_or_else(std::env::temp_dir);
     let mut path = base;
     path.push("dupdupninja");
@@ -972,6 +1392,18 @@ fn default_fileset_dir() -> PathBuf {
     path
 }
 
+/// Reads `fileset_dir` from the GTK app's `settings.txt`, so every front end puts
+/// new filesets in the same place.
+fn configured_fileset_dir() -> Option<PathBuf> {
+    let path = dirs::config_dir()?.join("dupdupninja").join("settings.txt");
+    let contents = fs::read_to_string(path).ok()?;
+    contents.lines().find_map(|line| {
+        let (key, value) = line.trim().split_once('=')?;
+        let value = value.trim();
+        (key.trim() == "fileset_dir" && !value.is_empty()).then(|| PathBuf::from(value))
+    })
+}
+
 fn human_elapsed(d: Duration) -> String {
     let total = d.as_secs();
     let days = total / 86_400;
@@ -1012,6 +1444,7 @@ fn snapshot_settings_label(
 struct TerminalProgress {
     last_render: Instant,
     last_width: usize,
+    scan_started: Option<Instant>,
 }
 
 impl TerminalProgress {
@@ -1021,6 +1454,7 @@ impl TerminalProgress {
                 .checked_sub(Duration::from_millis(250))
                 .unwrap_or_else(Instant::now),
             last_width: 0,
+            scan_started: None,
         }
     }
 
@@ -1033,32 +1467,40 @@ impl TerminalProgress {
             "prescan | files {:>8} | dirs {:>7} | bytes {:>10} | {}",
             progress.files_seen,
             progress.dirs_seen,
-            human_bytes(progress.bytes_seen),
+            format_bytes(progress.bytes_seen),
             current
         );
         self.render_line(&line);
     }
 
     fn draw_scan(&mut self, progress: &ScanProgress) {
+        let started = *self.scan_started.get_or_insert_with(Instant::now);
         if !self.should_render(false) {
             return;
         }
-        let pct = if progress.total_files > 0 {
-            (progress.files_seen as f64 / progress.total_files as f64) * 100.0
-        } else {
-            0.0
-        };
+        let elapsed = started.elapsed().as_secs_f64().max(0.001);
+        let throughput = format!(
+            "{:.0} files/s {}/s",
+            progress.files_seen as f64 / elapsed,
+            format_bytes((progress.bytes_seen as f64 / elapsed) as u64)
+        );
         let step = progress.current_step.as_deref().unwrap_or("scan");
         let current = shorten_path(&progress.current_path, 38);
+        let counts = if progress.total_files > 0 {
+            let ratio = (progress.files_seen as f64 / progress.total_files as f64).min(1.0);
+            format!(
+                "scan [{}] {:>5.1}% | files {:>8}/{:<8}",
+                text_bar(ratio, 20),
+                ratio * 100.0,
+                progress.files_seen,
+                progress.total_files
+            )
+        } else {
+            format!("scan | files {:>8}", progress.files_seen)
+        };
         let mut line = format!(
-            "scan {:>5.1}% | files {:>8}/{:<8} | hashed {:>8} | skipped {:>6} | {} | {}",
-            pct,
-            progress.files_seen,
-            progress.total_files,
-            progress.files_hashed,
-            progress.files_skipped,
-            step,
-            current
+            "{counts} | hashed {:>8} | skipped {:>6} | {throughput} | {} | {}",
+            progress.files_hashed, progress.files_skipped, step, current
         );
         if let Some(task) = progress.active_tasks.first() {
             line.push_str(&format!(
@@ -1099,6 +1541,11 @@ impl TerminalProgress {
     }
 }
 
+fn text_bar(ratio: f64, width: usize) -> String {
+    let filled = ((ratio.clamp(0.0, 1.0) * width as f64).round() as usize).min(width);
+    format!("{}{}", "#".repeat(filled), "-".repeat(width - filled))
+}
+
 fn shorten_path(path: &Path, max: usize) -> String {
     let full = path.display().to_string();
     let char_count = full.chars().count();
@@ -1117,99 +1564,96 @@ fn shorten_path(path: &Path, max: usize) -> String {
     format!("...{tail}")
 }
 
-fn human_bytes(bytes: u64) -> String {
-    const KB: f64 = 1024.0;
-    const MB: f64 = KB * 1024.0;
-    const GB: f64 = MB * 1024.0;
-    const TB: f64 = GB * 1024.0;
-    let b = bytes as f64;
-    if b >= TB {
-        format!("{:.2} TB", b / TB)
-    } else if b >= GB {
-        format!("{:.2} GB", b / GB)
-    } else if b >= MB {
-        format!("{:.2} MB", b / MB)
-    } else if b >= KB {
-        format!("{:.2} KB", b / KB)
-    } else {
-        format!("{bytes} B")
-    }
-}
+fn run_snapshots_command(args: &Matches) -> dupdupninja_core::Result<()> {
+    let db = PathBuf::from(args.required("--db")?);
+    let snapshots_per_video: u32 = args.parse("--snapshots-per-video")?.unwrap_or(3);
+    let snapshot_max_dim: u32 = args.parse("--snapshot-max-dim")?.unwrap_or(1024);
 
-fn run_matches_command(args: &mut impl Iterator<Item = String>) -> dupdupninja_core::Result<()> {
-    let mut db: Option<PathBuf> = None;
-    let mut max_files: usize = 500;
-    let mut mode = MatchMode::All;
-    let mut use_tui: Option<bool> = None;
-    let mut thresholds = SimilarityThresholds {
-        ahash: 10,
-        dhash: 10,
-        phash: 8,
+    let store = SqliteScanStore::open(&db)?;
+    let root = match args.value("--root") {
+        Some(root) => PathBuf::from(root),
+        None => store
+            .get_fileset_metadata()?
+            .and_then(|meta| fileset_root_path(&meta))
+            .ok_or_else(|| {
+                dupdupninja_core::Error::InvalidArgument(
+                    "fileset has no root path; pass --root".to_string(),
+                )
+            })?,
+    };
+    let cfg = ScanConfig {
+        root: root.clone(),
+        capture_snapshots: true,
+        snapshots_per_video,
+        snapshot_max_dim,
+        snapshot_storage: snapshot_storage_arg(args),
+        ..Default::default()
     };
 
-    while let Some(arg) = args.next() {
-        match arg.as_str() {
-            "--db" => db = args.next().map(PathBuf::from),
-            "--mode" => {
-                let value = args.next().ok_or_else(|| {
-                    dupdupninja_core::Error::InvalidArgument(
-                        "missing value for --mode <all|similar|exact>".to_string(),
-                    )
-                })?;
-                mode = MatchMode::parse(&value)?;
-            }
-            "--all" => mode = MatchMode::All,
-            "--similar" => mode = MatchMode::Similar,
-            "--exact" => mode = MatchMode::Exact,
-            "--tui" => use_tui = Some(true),
-            "--plain" => use_tui = Some(false),
-            "--max-files" => {
-                if let Some(val) = args.next() {
-                    max_files = val.parse().map_err(|_| {
-                        dupdupninja_core::Error::InvalidArgument(format!(
-                            "invalid --max-files value: {val}"
-                        ))
-                    })?;
-                }
-            }
-            "--ahash" => {
-                if let Some(val) = args.next() {
-                    thresholds.ahash = val.parse().map_err(|_| {
-                        dupdupninja_core::Error::InvalidArgument(format!(
-                            "invalid --ahash value: {val}"
-                        ))
-                    })?;
-                }
-            }
-            "--dhash" => {
-                if let Some(val) = args.next() {
-                    thresholds.dhash = val.parse().map_err(|_| {
-                        dupdupninja_core::Error::InvalidArgument(format!(
-                            "invalid --dhash value: {val}"
-                        ))
-                    })?;
-                }
-            }
-            "--phash" => {
-                if let Some(val) = args.next() {
-                    thresholds.phash = val.parse().map_err(|_| {
-                        dupdupninja_core::Error::InvalidArgument(format!(
-                            "invalid --phash value: {val}"
-                        ))
-                    })?;
-                }
-            }
-            _ => {
-                return Err(dupdupninja_core::Error::InvalidArgument(format!(
-                    "unknown arg: {arg}"
-                )));
+    let cancel_token = ScanCancelToken::new();
+    if let Err(err) = install_interrupt_handler(&cancel_token) {
+        eprintln!("warning: failed to install Ctrl-C handler ({err})");
+    }
+    let mut progress = if args.flag("--quiet") {
+        None
+    } else {
+        println!("root: {}", root.display());
+        println!("db: {}", db.display());
+        Some(TerminalProgress::new())
+    };
+    let result = regenerate_snapshots(
+        &store,
+        &root,
+        &cfg,
+        Some(&cancel_token),
+        |update: &ScanProgress| {
+            if let Some(progress) = progress.as_mut() {
+                progress.draw_scan(update);
             }
+        },
+    );
+    if let Some(progress) = progress.as_mut() {
+        progress.finish_line();
+    }
+    match result {
+        Ok(result) => {
+            println!(
+                "snapshots complete: {} files, {} captured, {} failed",
+                result.stats.files_seen, result.stats.files_hashed, result.stats.files_skipped
+            );
+            print_scan_warnings(&result);
+            Ok(())
+        }
+        Err(dupdupninja_core::Error::Cancelled) => {
+            println!("snapshots cancelled");
+            Err(dupdupninja_core::Error::Cancelled)
         }
+        Err(err) => Err(err),
     }
+}
+
+fn run_matches_command(args: &Matches) -> dupdupninja_core::Result<()> {
+    let db = PathBuf::from(args.required("--db")?);
+    let max_files: usize = args.parse("--max-files")?.unwrap_or(500);
+    let mut mode = match args.value("--mode") {
+        Some(value) => MatchMode::parse(value)?,
+        None => MatchMode::All,
+    };
+    match args.last_flag(&["--all", "--similar", "--exact"]) {
+        Some("--all") => mode = MatchMode::All,
+        Some("--similar") => mode = MatchMode::Similar,
+        Some("--exact") => mode = MatchMode::Exact,
+        _ => {}
+    }
+    let use_tui = args
+        .last_flag(&["--tui", "--plain"])
+        .map(|flag| flag == "--tui");
+    let thresholds = SimilarityThresholds {
+        ahash: args.parse("--ahash")?.unwrap_or(10),
+        dhash: args.parse("--dhash")?.unwrap_or(10),
+        phash: args.parse("--phash")?.unwrap_or(8),
+    };
 
-    let db = db.ok_or_else(|| {
-        dupdupninja_core::Error::InvalidArgument("missing --db <path>".to_string())
-    })?;
     let store = SqliteScanStore::open(&db)?;
     let path_resolver = FilesetPathResolver::from_store(&store);
 
@@ -1343,6 +1787,613 @@ struct MatchGroup {
     entries: Vec<MatchEntry>,
 }
 
+const ROW_PAGE_SIZE: usize = 1000;
+
+fn run_list_command(args: &Matches) -> dupdupninja_core::Result<()> {
+    let db = PathBuf::from(args.required("--db")?);
+    let limit: usize = args.parse("--limit")?.unwrap_or(100);
+    let offset: usize = args.parse("--offset")?.unwrap_or(0);
+    let sort = match args.value("--sort") {
+        None | Some("name") | Some("path") => FileListSort::Path,
+        Some("size") => FileListSort::SizeDesc,
+        Some("reclaimable") => FileListSort::ReclaimableDesc,
+        Some(other) => {
+            return Err(dupdupninja_core::Error::InvalidArgument(format!(
+                "invalid --sort value: {other} (expected size|reclaimable|name)"
+            )));
+        }
+    };
+    let store = SqliteScanStore::open(&db)?;
+    let rows = store.list_files_sorted(sort, args.flag("--duplicates-only"), limit, offset)?;
+
+    if args.flag("--json") {
+        let rows: Vec<FileListRowDto> = rows.iter().map(FileListRowDto::from_row).collect();
+        let json = serde_json::to_string_pretty(&rows).map_err(|err| {
+            dupdupninja_core::Error::InvalidArgument(format!("failed to encode JSON: {err}"))
+        })?;
+        println!("{json}");
+        return Ok(());
+    }
+
+    let type_width = rows
+        .iter()
+        .filter_map(|row| row.file_type.as_deref())
+        .map(str::len)
+        .max()
+        .unwrap_or(0)
+        .max(4);
+    println!("{:>10}  {:<type_width$}  PATH", "SIZE", "TYPE");
+    for row in &rows {
+        println!(
+            "{:>10}  {:<type_width$}  {}",
+            format_bytes(row.size_bytes),
+            row.file_type.as_deref().unwrap_or("-"),
+            row.path.display()
+        );
+    }
+    Ok(())
+}
+
+#[derive(serde::Serialize)]
+struct FileListRowDto {
+    id: i64,
+    path: String,
+    size_bytes: u64,
+    modified_at_secs: Option<u64>,
+    file_type: Option<String>,
+    blake3: Option<String>,
+    sha256: Option<String>,
+    ahash: Option<u64>,
+    dhash: Option<u64>,
+    phash: Option<u64>,
+    ffmpeg_metadata: Option<String>,
+}
+
+impl FileListRowDto {
+    fn from_row(row: &FileListRow) -> Self {
+        Self {
+            id: row.id,
+            path: row.path.display().to_string(),
+            size_bytes: row.size_bytes,
+            modified_at_secs: row
+                .modified_at
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs()),
+            file_type: row.file_type.clone(),
+            blake3: row.blake3.as_ref().map(hex_encode),
+            sha256: row.sha256.as_ref().map(hex_encode),
+            ahash: row.ahash,
+            dhash: row.dhash,
+            phash: row.phash,
+            ffmpeg_metadata: row.ffmpeg_metadata.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum KeepPolicy {
+    Oldest,
+    Newest,
+    ShortestPath,
+}
+
+impl KeepPolicy {
+    fn parse(raw: &str) -> dupdupninja_core::Result<Self> {
+        match raw {
+            "oldest" => Ok(Self::Oldest),
+            "newest" => Ok(Self::Newest),
+            "shortest-path" => Ok(Self::ShortestPath),
+            _ => Err(dupdupninja_core::Error::InvalidArgument(format!(
+                "invalid --keep value: {raw} (expected oldest|newest|shortest-path)"
+            ))),
+        }
+    }
+
+    /// Index of the file to keep. Ties fall back to the lexicographically first path.
+    fn pick(self, rows: &[FileListRow]) -> usize {
+        let by_path = |a: &FileListRow, b: &FileListRow| a.path.cmp(&b.path);
+        let best = rows.iter().enumerate().min_by(|(_, a), (_, b)| {
+            let primary = match self {
+                // Rows without an mtime sort last for both time-based policies.
+                Self::Oldest => match (a.modified_at, b.modified_at) {
+                    (Some(x), Some(y)) => x.cmp(&y),
+                    (x, y) => y.is_some().cmp(&x.is_some()),
+                },
+                Self::Newest => match (a.modified_at, b.modified_at) {
+                    (Some(x), Some(y)) => y.cmp(&x),
+                    (x, y) => y.is_some().cmp(&x.is_some()),
+                },
+                Self::ShortestPath => a.path.as_os_str().len().cmp(&b.path.as_os_str().len()),
+            };
+            primary.then_with(|| by_path(a, b))
+        });
+        best.map(|(idx, _)| idx).unwrap_or(0)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DedupeAction {
+    DryRun,
+    Trash,
+    Delete,
+    Symlink,
+}
+
+impl DedupeAction {
+    fn verb(self) -> &'static str {
+        match self {
+            Self::DryRun => "would remove",
+            Self::Trash => "trashed",
+            Self::Delete => "deleted",
+            Self::Symlink => "symlinked",
+        }
+    }
+}
+
+fn run_dedupe_command(args: &Matches) -> dupdupninja_core::Result<()> {
+    let db = PathBuf::from(args.required("--db")?);
+    let keep = KeepPolicy::parse(args.required("--keep")?)?;
+    let action = if args.flag("--dry-run") {
+        DedupeAction::DryRun
+    } else {
+        match args.last_flag(&["--trash", "--delete", "--symlink"]) {
+            Some("--trash") => DedupeAction::Trash,
+            Some("--delete") => DedupeAction::Delete,
+            Some("--symlink") => DedupeAction::Symlink,
+            _ => DedupeAction::DryRun,
+        }
+    };
+    let store = SqliteScanStore::open(&db)?;
+    let path_resolver = FilesetPathResolver::from_store(&store);
+
+    let mut groups: HashMap<[u8; 32], Vec<FileListRow>> = HashMap::new();
+    let mut offset = 0;
+    loop {
+        let rows = store.list_files_with_duplicates(false, ROW_PAGE_SIZE, offset)?;
+        let fetched = rows.len();
+        for row in rows {
+            // Mirrors the duplicate query: blake3 when present, otherwise sha256.
+            let key = row.blake3.or(row.sha256);
+            if let Some(key) = key {
+                groups.entry(key).or_default().push(row);
+            }
+        }
+        if fetched < ROW_PAGE_SIZE {
+            break;
+        }
+        offset += fetched;
+    }
+    let mut groups: Vec<Vec<FileListRow>> =
+        groups.into_values().filter(|rows| rows.len() > 1).collect();
+    groups.sort_by(|a, b| {
+        b[0].size_bytes
+            .cmp(&a[0].size_bytes)
+            .then_with(|| a[0].path.cmp(&b[0].path))
+    });
+
+    let mut removed = 0_u64;
+    let mut reclaimed = 0_u64;
+    let mut skipped = 0_u64;
+    for rows in &groups {
+        let keeper_idx = keep.pick(rows);
+        let keeper = &rows[keeper_idx];
+        let keeper_path = path_resolver.resolve_path(&keeper.path)?;
+        println!("keep  {}", keeper_path.display());
+        for (idx, row) in rows.iter().enumerate() {
+            if idx == keeper_idx {
+                continue;
+            }
+            let dup_path = path_resolver.resolve_path(&row.path)?;
+            match dedupe_one(action, &keeper_path, &dup_path) {
+                Ok(()) => {
+                    println!("  {} {}", action.verb(), dup_path.display());
+                    if action != DedupeAction::DryRun {
+                        store.delete_file_by_path(&row.path)?;
+                    }
+                    removed += 1;
+                    reclaimed += row.size_bytes;
+                }
+                Err(reason) => {
+                    eprintln!("  skipped {}: {reason}", dup_path.display());
+                    skipped += 1;
+                }
+            }
+        }
+    }
+
+    let summary = format!(
+        "{} group(s), {removed} duplicate(s), {} reclaimable",
+        groups.len(),
+        format_bytes(reclaimed)
+    );
+    if action == DedupeAction::DryRun {
+        println!("dry run: {summary}");
+        println!("pass --trash, --delete or --symlink to apply");
+    } else {
+        println!("done: {summary}");
+    }
+    if skipped > 0 {
+        println!("{skipped} file(s) skipped");
+    }
+    Ok(())
+}
+
+fn dedupe_one(action: DedupeAction, keeper: &Path, duplicate: &Path) -> Result<(), String> {
+    let meta = fs::symlink_metadata(duplicate).map_err(|err| err.to_string())?;
+    if meta.file_type().is_symlink() {
+        return Err("already a symlink".to_string());
+    }
+    let same_file = match (fs::canonicalize(keeper), fs::canonicalize(duplicate)) {
+        (Ok(a), Ok(b)) => a == b,
+        (Err(err), _) | (_, Err(err)) => return Err(err.to_string()),
+    };
+    if same_file {
+        return Err("same file as the kept copy".to_string());
+    }
+    match dupdupninja_core::hash::files_identical(keeper, duplicate) {
+        Ok(true) => {}
+        Ok(false) => return Err("contents differ from the kept copy".to_string()),
+        Err(err) => return Err(err.to_string()),
+    }
+    let result = match action {
+        DedupeAction::DryRun => Ok(()),
+        DedupeAction::Trash => move_path_to_trash(duplicate),
+        DedupeAction::Delete => delete_path_permanently(duplicate),
+        DedupeAction::Symlink => replace_with_symlink_and_trash(duplicate, keeper),
+    };
+    result.map_err(|err| err.to_string())
+}
+
+fn run_report_command(args: &Matches) -> dupdupninja_core::Result<()> {
+    const TOP_TYPES: usize = 10;
+
+    let db = PathBuf::from(args.required("--db")?);
+    let store = SqliteScanStore::open(&db)?;
+
+    println!("fileset: {}", db.display());
+    match store.get_fileset_metadata()? {
+        Some(meta) => {
+            let root = fileset_root_path(&meta).unwrap_or_else(|| meta.root_path.clone());
+            println!("  name:       {}", meta.name);
+            println!(
+                "  root:       {} ({})",
+                root.display(),
+                root_kind_label(meta.root_kind)
+            );
+            println!(
+                "  drive:      {}",
+                meta.drive.label.as_deref().unwrap_or("-")
+            );
+            println!("  host os:    {} {}", meta.host_os, meta.host_os_version);
+            println!("  created at: {}", format_system_time_utc(meta.created_at));
+            println!(
+                "  status:     {}",
+                if meta.status.is_empty() {
+                    "-"
+                } else {
+                    &meta.status
+                }
+            );
+        }
+        None => println!("  (no fileset metadata)"),
+    }
+
+    println!();
+    println!("files:            {}", store.count_files()?);
+    println!("total size:       {}", format_bytes(store.total_bytes()?));
+    println!("duplicate groups: {}", store.duplicate_group_count()?);
+    println!(
+        "reclaimable:      {}",
+        format_bytes(store.reclaimable_bytes()?)
+    );
+
+    let mut types = store.type_breakdown()?;
+    let label = |t: &dupdupninja_core::FileTypeStats| {
+        t.file_type
+            .clone()
+            .unwrap_or_else(|| "(unknown)".to_string())
+    };
+    let width = types.iter().map(|t| label(t).len()).max().unwrap_or(0);
+
+    println!();
+    println!("top types by count:");
+    for t in types.iter().take(TOP_TYPES) {
+        println!(
+            "  {:<width$}  {:>8} files  {:>10}",
+            label(t),
+            t.files,
+            format_bytes(t.bytes)
+        );
+    }
+
+    types.sort_by(|a, b| {
+        b.bytes
+            .cmp(&a.bytes)
+            .then_with(|| a.file_type.cmp(&b.file_type))
+    });
+    println!();
+    println!("top types by size:");
+    for t in types.iter().take(TOP_TYPES) {
+        println!(
+            "  {:<width$}  {:>10}  {:>8} files",
+            label(t),
+            format_bytes(t.bytes),
+            t.files
+        );
+    }
+    Ok(())
+}
+
+fn run_overlaps_command(args: &Matches) -> dupdupninja_core::Result<()> {
+    let db = PathBuf::from(args.required("--db")?);
+    let min_shared_mib: u64 = args.parse("--min-shared")?.unwrap_or(64);
+    let store = SqliteScanStore::open(&db)?;
+    let overlaps = store.files_sharing_chunks(min_shared_mib.saturating_mul(1024 * 1024))?;
+    if overlaps.is_empty() {
+        println!("no overlapping files found");
+        return Ok(());
+    }
+    for overlap in &overlaps {
+        println!("{} shared", format_bytes(overlap.shared_bytes));
+        println!("  {}", overlap.a.path.display());
+        println!("  {}", overlap.b.path.display());
+    }
+    Ok(())
+}
+
+fn run_verify_command(args: &Matches) -> dupdupninja_core::Result<()> {
+    let db = PathBuf::from(args.required("--db")?);
+    let root = args.value("--root").map(PathBuf::from);
+    let sample: f64 = args.parse("--sample")?.unwrap_or(1.0);
+    if !(sample > 0.0 && sample <= 1.0) {
+        return Err(dupdupninja_core::Error::InvalidArgument(
+            "--sample must be in (0, 1]".to_string(),
+        ));
+    }
+    let mut rng = SampleRng::new(args.parse("--seed")?);
+
+    let store = SqliteScanStore::open(&db)?;
+    let path_resolver = FilesetPathResolver::from_store(&store);
+    let cancel_token = ScanCancelToken::new();
+    if let Err(err) = install_interrupt_handler(&cancel_token) {
+        eprintln!("warning: failed to install Ctrl-C handler ({err})");
+    }
+
+    let mut checked = 0_u64;
+    let mut no_hash = 0_u64;
+    let mut missing = 0_u64;
+    let mut mismatched = 0_u64;
+    let mut unreadable = 0_u64;
+    for row in store.iter_files() {
+        let row = row?;
+        if cancel_token.is_cancelled() {
+            break;
+        }
+        if sample < 1.0 && !rng.chance(sample) {
+            continue;
+        }
+        let Some(expected) = row.blake3 else {
+            no_hash += 1;
+            continue;
+        };
+        let path = match &root {
+            Some(root) if row.path.is_relative() => root.join(&row.path),
+            _ => path_resolver.resolve_path(&row.path)?,
+        };
+        checked += 1;
+        match dupdupninja_core::hash::blake3_file(&path, Some(&cancel_token)) {
+            Ok(actual) if actual == expected => {}
+            Err(dupdupninja_core::Error::Cancelled) => {
+                checked -= 1;
+                break;
+            }
+            Ok(_) => {
+                mismatched += 1;
+                println!("MISMATCH  {}", path.display());
+            }
+            Err(dupdupninja_core::Error::Io(err)) if err.kind() == std::io::ErrorKind::NotFound => {
+                missing += 1;
+                println!("MISSING   {}", path.display());
+            }
+            Err(err) => {
+                unreadable += 1;
+                println!("ERROR     {}: {err}", path.display());
+            }
+        }
+    }
+
+    println!(
+        "verified {checked} file(s): {} ok, {mismatched} mismatched, {missing} missing, {unreadable} unreadable",
+        checked - mismatched - missing - unreadable
+    );
+    if no_hash > 0 {
+        println!("{no_hash} file(s) have no stored blake3 and were not checked");
+    }
+    if cancel_token.is_cancelled() {
+        println!("verify cancelled");
+        return Err(dupdupninja_core::Error::Cancelled);
+    }
+    if mismatched + missing + unreadable > 0 {
+        let _ = std::io::stdout().flush();
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// xorshift64* — enough to pick a uniform sample of rows without a rand dependency.
+struct SampleRng(u64);
+
+impl SampleRng {
+    fn new(seed: Option<u64>) -> Self {
+        let seed = seed.unwrap_or_else(|| {
+            let nanos = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos() as u64;
+            nanos ^ u64::from(std::process::id()).rotate_left(32)
+        });
+        // A zero state would stay zero forever.
+        Self(seed.max(1))
+    }
+
+    fn chance(&mut self, probability: f64) -> bool {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        let value = self.0.wrapping_mul(0x2545_f491_4f6c_dd1d);
+        ((value >> 11) as f64 / (1_u64 << 53) as f64) < probability
+    }
+}
+
+fn run_serve_command(args: &Matches) -> dupdupninja_core::Result<()> {
+    let defaults = web::WebServerOptions::default();
+    web::run_web_server(web::WebServerOptions {
+        bind: args.parse("--bind")?.unwrap_or(defaults.bind),
+        port: args.parse("--port")?.unwrap_or(defaults.port),
+        open_browser: args.flag("--open"),
+        token: args
+            .value("--token")
+            .map(str::to_string)
+            .or_else(|| std::env::var("DUPDUP_WEB_TOKEN").ok())
+            .filter(|token| !token.is_empty()),
+        max_jobs: args.parse("--max-jobs")?.unwrap_or(defaults.max_jobs),
+        max_concurrent_scans: args
+            .parse("--max-concurrent-scans")?
+            .unwrap_or(defaults.max_concurrent_scans),
+    })
+}
+
+fn run_export_command(args: &Matches) -> dupdupninja_core::Result<()> {
+    let db = PathBuf::from(args.required("--db")?);
+    let duplicates_only = args.flag("--duplicates-only");
+    let export = match args.value("--format") {
+        None | Some("csv") => dupdupninja_core::export::export_csv::<Box<dyn Write>>,
+        Some("jsonl") => dupdupninja_core::export::export_jsonl::<Box<dyn Write>>,
+        Some(other) => {
+            return Err(dupdupninja_core::Error::InvalidArgument(format!(
+                "invalid --format value: {other} (expected csv|jsonl)"
+            )));
+        }
+    };
+    let store = SqliteScanStore::open(&db)?;
+    let mut out: Box<dyn Write> = match args.value("--out") {
+        Some(path) => Box::new(std::io::BufWriter::new(fs::File::create(path)?)),
+        None => Box::new(std::io::BufWriter::new(std::io::stdout().lock())),
+    };
+    export(&store, &mut out, duplicates_only)
+}
+
+fn run_prune_command(args: &Matches) -> dupdupninja_core::Result<()> {
+    let db = PathBuf::from(args.required("--db")?);
+    let dry_run = args.flag("--dry-run");
+    let store = SqliteScanStore::open(&db)?;
+    let path_resolver = FilesetPathResolver::from_store(&store);
+
+    let mut missing = Vec::new();
+    for row in store.iter_files() {
+        let row = row?;
+        let path = path_resolver.resolve_path(&row.path)?;
+        if fs::symlink_metadata(&path).is_err() {
+            missing.push(row.path);
+        }
+    }
+
+    for path in &missing {
+        println!("{}", path.display());
+        if !dry_run {
+            store.delete_file_by_path(path)?;
+        }
+    }
+    if dry_run {
+        println!("{} missing file(s) would be removed", missing.len());
+    } else {
+        println!("Removed {} missing file(s)", missing.len());
+    }
+    Ok(())
+}
+
+fn run_compact_command(args: &Matches) -> dupdupninja_core::Result<()> {
+    let db = PathBuf::from(args.required("--db")?);
+    let before = fs::metadata(&db)?.len();
+    let store = SqliteScanStore::open(&db)?;
+    if let Some(dir) = args.value("--sidecar-dir") {
+        let moved = store.move_snapshots_to_sidecars(Path::new(dir))?;
+        println!("Moved {moved} snapshots to {dir}");
+    }
+    store.compact()?;
+    drop(store);
+    let after = fs::metadata(&db)?.len();
+    println!(
+        "Compacted {}: {} -> {}",
+        db.display(),
+        format_bytes(before),
+        format_bytes(after)
+    );
+    Ok(())
+}
+
+fn run_merge_command(args: &Matches) -> dupdupninja_core::Result<()> {
+    let into = PathBuf::from(args.required("--into")?);
+    let from = PathBuf::from(args.required("--from")?);
+    if !from.is_file() {
+        return Err(dupdupninja_core::Error::InvalidArgument(format!(
+            "no fileset at {}",
+            from.display()
+        )));
+    }
+    let target = SqliteScanStore::open(&into)?;
+    let report = target.merge_from(&from)?;
+    if let Some((ours, theirs)) = &report.root_mismatch {
+        println!(
+            "warning: root paths differ ({} vs {}); equal relative paths may be different files",
+            ours.display(),
+            theirs.display()
+        );
+    }
+    for path in &report.conflicts {
+        println!("conflict: {}", path.display());
+    }
+    println!(
+        "Merged {} into {}: {} added, {} updated, {} unchanged, {} conflicting",
+        from.display(),
+        into.display(),
+        report.added,
+        report.updated,
+        report.unchanged,
+        report.conflicts.len()
+    );
+    Ok(())
+}
+
+/// `--snapshot-dir`, resolved against the fileset's directory when relative.
+fn snapshot_storage_arg(args: &Matches) -> SnapshotStorage {
+    match args.value("--snapshot-dir") {
+        Some(dir) => SnapshotStorage::Sidecar {
+            dir: PathBuf::from(dir),
+        },
+        None => SnapshotStorage::InDb,
+    }
+}
+
+/// Parses sizes like `4096`, `10K`, `1.5M` or `2GB` (binary units).
+fn parse_byte_size(raw: &str) -> Option<u64> {
+    let raw = raw.trim();
+    let split = raw
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(raw.len());
+    let (number, unit) = raw.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1 << 10,
+        "M" | "MB" | "MIB" => 1 << 20,
+        "G" | "GB" | "GIB" => 1 << 30,
+        "T" | "TB" | "TIB" => 1 << 40,
+        _ => return None,
+    };
+    Some((number * multiplier as f64) as u64)
+}
+
 fn collect_exact_duplicate_groups(
     store: &SqliteScanStore,
     max_files: usize,
@@ -1367,7 +2418,7 @@ fn collect_exact_duplicate_groups(
         } else {
             file.sha256.map(ExactKey::Sha256)
         };
-        if let Some(key) = key {
+        if let Some(key) = key.filter(|_| file.size_bytes > 0) {
             groups.entry(key).or_default().push(idx);
         }
     }
@@ -1404,7 +2455,7 @@ fn collect_exact_duplicate_groups(
         out.push(MatchGroup {
             kind: MatchGroupKind::Exact,
             title: format!("exact {} [{}:{}]", entries.len(), algo, short_hash),
-            summary: format!("{} files | {}", entries.len(), human_bytes(group_size)),
+            summary: format!("{} files | {}", entries.len(), format_bytes(group_size)),
             confidence_pct: 100.0,
             sort_size_bytes: group_size,
             entries,
@@ -1455,7 +2506,7 @@ fn collect_similar_groups(
         }
     }
 
-    groups.sort_by(|a, b| b.1.len().cmp(&a.1.len()));
+    groups.sort_by_key(|g| std::cmp::Reverse(g.1.len()));
 
     let mut out = Vec::new();
     for (anchor_idx, members) in groups {
@@ -1482,7 +2533,11 @@ fn collect_similar_groups(
         out.push(MatchGroup {
             kind: MatchGroupKind::Similar,
             title: format!("similar {} (best {:.1}%)", entries.len(), best),
-            summary: format!("{} files | ref {}", entries.len(), human_bytes(anchor_size)),
+            summary: format!(
+                "{} files | ref {}",
+                entries.len(),
+                format_bytes(anchor_size)
+            ),
             confidence_pct: best.min(99.99),
             sort_size_bytes: anchor_size,
             entries,
@@ -1514,16 +2569,21 @@ fn print_matches_plain(mode: MatchMode, thresholds: SimilarityThresholds, groups
             .count();
         if exact_count > 0 {
             println!("Exact duplicate groups (blake3/sha256):");
-            let mut idx = 1usize;
-            for group in groups.iter().filter(|g| g.kind == MatchGroupKind::Exact) {
+            for (idx, group) in groups
+                .iter()
+                .filter(|g| g.kind == MatchGroupKind::Exact)
+                .enumerate()
+            {
                 println!(
                     "Group {}: {} | {} | confidence {:.2}%",
-                    idx, group.title, group.summary, group.confidence_pct
+                    idx + 1,
+                    group.title,
+                    group.summary,
+                    group.confidence_pct
                 );
                 for entry in &group.entries {
                     println!("  {}", entry.path.display());
                 }
-                idx += 1;
             }
         }
     }
@@ -1540,11 +2600,17 @@ fn print_matches_plain(mode: MatchMode, thresholds: SimilarityThresholds, groups
                 "Similar groups (pHash primary, thresholds: phash<= {}, dhash<= {}, ahash<= {}):",
                 thresholds.phash, thresholds.dhash, thresholds.ahash
             );
-            let mut idx = 1usize;
-            for group in groups.iter().filter(|g| g.kind == MatchGroupKind::Similar) {
+            for (idx, group) in groups
+                .iter()
+                .filter(|g| g.kind == MatchGroupKind::Similar)
+                .enumerate()
+            {
                 println!(
                     "Group {}: {} | {} | confidence {:.2}%",
-                    idx, group.title, group.summary, group.confidence_pct
+                    idx + 1,
+                    group.title,
+                    group.summary,
+                    group.confidence_pct
                 );
                 for entry in &group.entries {
                     if let Some(detail) = &entry.detail {
@@ -1553,7 +2619,6 @@ fn print_matches_plain(mode: MatchMode, thresholds: SimilarityThresholds, groups
                         println!("  {}", entry.path.display());
                     }
                 }
-                idx += 1;
             }
         }
     }
@@ -1960,10 +3025,10 @@ fn run_matches_tui(
                         }
                     }
                 }
-                KeyCode::Char('f') | KeyCode::Char('F') => {
-                    if state.selected_entry_context().is_some() {
-                        state.open_ffmpeg_popup_for_selected();
-                    }
+                KeyCode::Char('f') | KeyCode::Char('F')
+                    if state.selected_entry_context().is_some() =>
+                {
+                    state.open_ffmpeg_popup_for_selected();
                 }
                 KeyCode::Delete => {
                     let action = if key.modifiers.contains(KeyModifiers::SHIFT) {
@@ -2200,94 +3265,43 @@ fn reveal_in_file_browser(path: &Path) -> std::io::Result<()> {
 }
 
 fn move_path_to_trash(path: &Path) -> std::io::Result<()> {
-    #[cfg(target_os = "linux")]
+    match dupdupninja_core::files::move_to_trash(path) {
+        Ok(()) => Ok(()),
+        Err(dupdupninja_core::Error::Io(err)) => Err(err),
+        Err(err) => Err(std::io::Error::other(err.to_string())),
+    }
+}
+
+fn open_in_browser(url: &str) -> std::io::Result<()> {
+    let url = OsStr::new(url);
+    #[cfg(target_os = "windows")]
     {
-        if try_status_command("gio", &[OsStr::new("trash"), path.as_os_str()])? {
-            return Ok(());
-        }
-        if try_status_command(
-            "kioclient5",
-            &[OsStr::new("move"), path.as_os_str(), OsStr::new("trash:/")],
-        )? {
-            return Ok(());
-        }
-        if try_status_command(
-            "kioclient",
-            &[OsStr::new("move"), path.as_os_str(), OsStr::new("trash:/")],
-        )? {
-            return Ok(());
-        }
-        if try_status_command("trash-put", &[path.as_os_str()])? {
-            return Ok(());
-        }
-        if try_status_command("gvfs-trash", &[path.as_os_str()])? {
+        let args = [OsStr::new("/C"), OsStr::new("start"), OsStr::new(""), url];
+        if try_spawn_command("cmd", &args)? {
             return Ok(());
         }
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::NotFound,
-            "no trash command found (tried gio/kioclient5/kioclient/trash-put/gvfs-trash)",
-        ));
     }
 
     #[cfg(target_os = "macos")]
     {
-        let escaped = applescript_escape(path);
-        let script = format!("tell application \"Finder\" to delete POSIX file \"{escaped}\"");
-        if try_status_command("osascript", &[OsStr::new("-e"), OsStr::new(&script)])? {
+        if try_spawn_command("open", &[url])? {
             return Ok(());
         }
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            "osascript failed to move file to trash",
-        ));
     }
 
-    #[cfg(target_os = "windows")]
+    #[cfg(target_os = "linux")]
     {
-        let escaped = powershell_single_quote_escape(path);
-        let script = format!(
-            "Add-Type -AssemblyName Microsoft.VisualBasic; [Microsoft.VisualBasic.FileIO.FileSystem]::DeleteFile('{escaped}', 'OnlyErrorDialogs', 'SendToRecycleBin')"
-        );
-        if try_status_command(
-            "powershell",
-            &[
-                OsStr::new("-NoProfile"),
-                OsStr::new("-NonInteractive"),
-                OsStr::new("-Command"),
-                OsStr::new(&script),
-            ],
-        )? || try_status_command(
-            "pwsh",
-            &[
-                OsStr::new("-NoProfile"),
-                OsStr::new("-NonInteractive"),
-                OsStr::new("-Command"),
-                OsStr::new(&script),
-            ],
-        )? {
+        if try_spawn_command("xdg-open", &[url])? {
             return Ok(());
         }
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            "failed to move file to Recycle Bin via PowerShell",
-        ));
     }
 
-    #[allow(unreachable_code)]
     Err(std::io::Error::new(
-        std::io::ErrorKind::Unsupported,
-        "trash is not implemented for this OS",
+        std::io::ErrorKind::NotFound,
+        "no browser launcher found",
     ))
 }
 
-fn try_status_command(program: &str, args: &[&OsStr]) -> std::io::Result<bool> {
-    match Command::new(program).args(args).status() {
-        Ok(status) => Ok(status.success()),
-        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(false),
-        Err(err) => Err(err),
-    }
-}
-
 fn try_spawn_command(program: &str, args: &[&OsStr]) -> std::io::Result<bool> {
     match Command::new(program).args(args).spawn() {
         Ok(_) => Ok(true),
@@ -2296,18 +3310,6 @@ fn try_spawn_command(program: &str, args: &[&OsStr]) -> std::io::Result<bool> {
     }
 }
 
-#[cfg(target_os = "macos")]
-fn applescript_escape(path: &Path) -> String {
-    path.to_string_lossy()
-        .replace('\\', "\\\\")
-        .replace('"', "\\\"")
-}
-
-#[cfg(target_os = "windows")]
-fn powershell_single_quote_escape(path: &Path) -> String {
-    path.to_string_lossy().replace('\'', "''")
-}
-
 struct MatchesTui {
     terminal: Terminal<CrosstermBackend<std::io::Stdout>>,
 }
@@ -2426,13 +3428,13 @@ fn draw_matches_ui(frame: &mut ratatui::Frame<'_>, state: &MatchesUiState) {
                     list_items.push(ListItem::new(format!(
                         "    • {}  [{}]",
                         e.path.display(),
-                        human_bytes(e.size_bytes)
+                        format_bytes(e.size_bytes)
                     )));
                 } else {
                     list_items.push(ListItem::new(format!(
                         "    • {}  [{} | {}]",
                         e.path.display(),
-                        human_bytes(e.size_bytes),
+                        format_bytes(e.size_bytes),
                         detail
                     )));
                 }
@@ -2474,7 +3476,7 @@ fn draw_matches_ui(frame: &mut ratatui::Frame<'_>, state: &MatchesUiState) {
                 g.kind,
                 g.confidence_pct,
                 g.entries.len(),
-                human_bytes(g.sort_size_bytes),
+                format_bytes(g.sort_size_bytes),
             )
         }
         Some(VisibleRow::Entry(gidx, eidx)) => {
@@ -2565,7 +3567,7 @@ fn format_match_entry_details(entry: &MatchEntry) -> String {
     let mut lines = Vec::new();
     lines.push(format!("Path: {}", entry.path.display()));
     lines.push(format!("DB file id: {}", entry.file_id));
-    lines.push(format!("Size: {}", human_bytes(entry.size_bytes)));
+    lines.push(format!("Size: {}", format_bytes(entry.size_bytes)));
     let modified = entry
         .modified_at
         .map(format_system_time_utc)
@@ -1,72 +1,243 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::convert::Infallible;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use axum::extract::{Path, Query, State};
+use axum::http::{header, HeaderMap, Request, StatusCode};
+use axum::middleware::{self, Next};
 use axum::response::sse::{Event, KeepAlive, Sse};
 use axum::response::{Html, IntoResponse};
-use axum::routing::{get, post};
+use axum::routing::{delete, get, post};
 use axum::{Form, Json, Router};
-use dupdupninja_core::db::SqliteScanStore;
-use dupdupninja_core::models::{FileListRow, ScanResult, ScanRootKind};
+use dupdupninja_core::db::{PooledStore, SqliteScanStore, SqliteStorePool};
+use dupdupninja_core::format::hex_encode;
+use dupdupninja_core::models::{DuplicateGroup, ScanResult, ScanRootKind, ScanWarning};
 use dupdupninja_core::scan::{
-    scan_to_sqlite_with_progress, ScanCancelToken, ScanConfig, ScanProgress,
+    prescan, scan_to_sqlite_with_progress_and_totals, ScanCancelToken, ScanConfig, ScanProgress,
 };
 use dupdupninja_core::{Error, Result};
 use serde::{Deserialize, Serialize};
-use tokio::sync::{broadcast, Mutex};
-use tokio_stream::wrappers::BroadcastStream;
+use tokio::sync::{broadcast, watch, Mutex};
+use tokio_stream::wrappers::{BroadcastStream, WatchStream};
 use tokio_stream::StreamExt;
 
-pub fn run_web_server(port: u16) -> Result<()> {
+use crate::ws;
+
+pub struct WebServerOptions {
+    pub bind: IpAddr,
+    pub port: u16,
+    /// Launch the default browser once the listener is bound.
+    pub open_browser: bool,
+    /// When set, `/scan` and `/cancel/:id` require this bearer token.
+    pub token: Option<String>,
+    /// Finished jobs kept in history; 0 keeps everything.
+    pub max_jobs: usize,
+    /// Scans run at once; later ones wait as `Pending`. 0 runs every scan immediately.
+    pub max_concurrent_scans: usize,
+}
+
+impl Default for WebServerOptions {
+    fn default() -> Self {
+        Self {
+            bind: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            port: 4455,
+            open_browser: false,
+            token: None,
+            max_jobs: 50,
+            max_concurrent_scans: 2,
+        }
+    }
+}
+
+pub fn run_web_server(options: WebServerOptions) -> Result<()> {
     let runtime = tokio::runtime::Builder::new_multi_thread()
         .enable_all()
         .build()
         .map_err(Error::Io)?;
-    runtime.block_on(run_web_server_async(port))
+    let result = runtime.block_on(run_web_server_async(options));
+    // Scans that ignored cancellation past the grace period must not keep the process alive.
+    runtime.shutdown_timeout(Duration::from_secs(1));
+    result
 }
 
-async fn run_web_server_async(port: u16) -> Result<()> {
+async fn run_web_server_async(options: WebServerOptions) -> Result<()> {
     let (events_tx, _) = broadcast::channel(200);
+    let media_notice = tokio::task::spawn_blocking(dupdupninja_core::video::media_tools_notice)
+        .await
+        .ok()
+        .flatten();
+    if let Some(notice) = media_notice {
+        eprintln!("warning: {notice}");
+    }
     let state = Arc::new(AppState {
         inner: Mutex::new(InnerState::new()),
         events_tx,
+        token: options.token.clone(),
+        scan_tasks: std::sync::Mutex::new(Vec::new()),
+        shutdown: watch::channel(false).0,
+        max_jobs: options.max_jobs,
+        max_concurrent_scans: options.max_concurrent_scans,
+        store_pools: std::sync::Mutex::new(HashMap::new()),
+        media_notice,
     });
 
+    let mutating = Router::new()
+        .route("/scan", post(start_scan_handler))
+        .route("/cancel/:id", post(cancel_scan_handler))
+        .route("/api/cancel-all", post(cancel_all_handler))
+        .route("/api/jobs/:id", delete(delete_job_handler))
+        .route("/api/jobs/clear", post(clear_jobs_handler))
+        .route(
+            "/api/filesets/:id/files/:file_id/delete",
+            post(delete_file_handler),
+        )
+        .route(
+            "/api/filesets/:id/files/:file_id/trash",
+            post(trash_file_handler),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            Arc::clone(&state),
+            require_token,
+        ))
+        .route_layer(middleware::from_fn(require_same_origin));
+
     let app = Router::new()
         .route("/", get(ui_index))
         .route("/events", get(sse_events))
-        .route("/scan", post(start_scan_handler))
-        .route("/cancel/:id", post(cancel_scan_handler))
+        .route("/ws", get(ws_events))
+        .merge(mutating)
         .route("/api/jobs", get(list_jobs_handler))
         .route("/api/filesets/:id/matches", get(list_matches_handler))
+        .route("/api/filesets/:id/export", get(export_handler))
         .route(
             "/api/filesets/:id/snapshots/:file_id/:index",
             get(snapshot_handler),
         )
-        .with_state(state);
+        .with_state(Arc::clone(&state));
+
+    let addr = SocketAddr::new(options.bind, options.port);
+    let server = axum::Server::try_bind(&addr)
+        .map_err(|err| Error::InvalidArgument(format!("failed to bind {addr}: {err}")))?;
+    eprintln!("dupdupninja web UI listening on http://{addr}/ (Ctrl-C stops the server and cancels running scans)");
+    if options.token.is_some() {
+        eprintln!("scan and cancel requests require the configured token");
+    }
 
-    let addr = ([127, 0, 0, 1], port).into();
-    eprintln!("dupdupninja web UI listening on http://127.0.0.1:{port}/");
+    if options.open_browser {
+        // A wildcard bind is not a browsable address; open loopback instead.
+        let browse_ip = match options.bind {
+            IpAddr::V4(ip) if ip.is_unspecified() => IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(ip) if ip.is_unspecified() => IpAddr::V6(Ipv6Addr::LOCALHOST),
+            ip => ip,
+        };
+        let url = format!("http://{}/", SocketAddr::new(browse_ip, options.port));
+        if let Err(err) = crate::open_in_browser(&url) {
+            eprintln!("warning: failed to open browser ({err}); visit {url}");
+        }
+    }
 
-    axum::Server::bind(&addr)
+    server
         .serve(app.into_make_service())
+        .with_graceful_shutdown({
+            let state = Arc::clone(&state);
+            async move {
+                let _ = tokio::signal::ctrl_c().await;
+                // Ends open SSE streams, which would otherwise hold the shutdown open.
+                state.shutdown.send_replace(true);
+            }
+        })
         .await
         .map_err(|err| Error::InvalidArgument(format!("server error: {err}")))?;
 
+    cancel_scans_for_shutdown(&state).await;
     Ok(())
 }
 
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
+/// Cancels pending and running scans, then gives their blocking tasks a moment to
+/// commit the partial batch and mark their filesets incomplete.
+async fn cancel_scans_for_shutdown(state: &Arc<AppState>) {
+    {
+        let guard = state.inner.lock().await;
+        for job in &guard.jobs {
+            if matches!(job.status, JobStatus::Pending | JobStatus::Running) {
+                job.cancel.cancel();
+            }
+        }
+    }
+    let tasks: Vec<_> = match state.scan_tasks.lock() {
+        Ok(mut tasks) => tasks.drain(..).collect(),
+        Err(_) => return,
+    };
+    let pending = tasks.iter().filter(|task| !task.is_finished()).count();
+    if pending == 0 {
+        return;
+    }
+    eprintln!("waiting for {pending} scan(s) to stop...");
+    let wait_all = async {
+        for task in tasks {
+            let _ = task.await;
+        }
+    };
+    if tokio::time::timeout(SHUTDOWN_GRACE, wait_all)
+        .await
+        .is_err()
+    {
+        eprintln!("warning: scans did not stop within {SHUTDOWN_GRACE:?}; exiting anyway");
+    }
+}
+
 struct AppState {
     inner: Mutex<InnerState>,
     events_tx: broadcast::Sender<ServerEvent>,
+    token: Option<String>,
+    scan_tasks: std::sync::Mutex<Vec<tokio::task::JoinHandle<()>>>,
+    shutdown: watch::Sender<bool>,
+    max_jobs: usize,
+    max_concurrent_scans: usize,
+    /// Read connections per fileset, shared by request handlers.
+    store_pools: std::sync::Mutex<HashMap<PathBuf, SqliteStorePool>>,
+    /// Shown atop the page when ffprobe or ffmpeg is missing; checked once at startup.
+    media_notice: Option<&'static str>,
+}
+
+/// Connections kept per fileset for request handlers; scans open their own writer.
+const STORE_POOL_CONNS: usize = 4;
+
+impl AppState {
+    /// Blocks while the pool is exhausted, so only call from blocking tasks.
+    fn store(&self, db_path: &std::path::Path) -> Result<PooledStore> {
+        let pool = {
+            let mut pools = self.store_pools.lock().unwrap_or_else(|e| e.into_inner());
+            match pools.get(db_path) {
+                Some(pool) => pool.clone(),
+                None => {
+                    let pool = SqliteScanStore::open_pool(db_path, STORE_POOL_CONNS)?;
+                    pools.insert(db_path.to_path_buf(), pool.clone());
+                    pool
+                }
+            }
+        };
+        pool.get()
+    }
+
+    /// Closes pools for filesets no job refers to any more.
+    fn prune_store_pools(&self, jobs: &[ScanJob]) {
+        let mut pools = self.store_pools.lock().unwrap_or_else(|e| e.into_inner());
+        pools.retain(|path, _| jobs.iter().any(|job| &job.db_path == path));
+    }
 }
 
 struct InnerState {
     next_id: u64,
     jobs: Vec<ScanJob>,
+    /// Pending scans in start order; see `schedule_scans`.
+    queue: VecDeque<QueuedScan>,
+    running_scans: usize,
 }
 
 impl InnerState {
@@ -74,7 +245,26 @@ impl InnerState {
         Self {
             next_id: 1,
             jobs: Vec::new(),
+            queue: VecDeque::new(),
+            running_scans: 0,
+        }
+    }
+
+    /// Drops the oldest finished jobs beyond `max_jobs`. Pending and running jobs are
+    /// never removed, since their background task still updates them by id.
+    fn trim_jobs(&mut self, max_jobs: usize) {
+        if max_jobs == 0 {
+            return;
         }
+        let mut excess = self.jobs.len().saturating_sub(max_jobs);
+        self.jobs.retain(|job| {
+            if excess > 0 && job.status.is_finished() {
+                excess -= 1;
+                false
+            } else {
+                true
+            }
+        });
     }
 }
 
@@ -85,12 +275,22 @@ struct ScanJob {
     db_path: PathBuf,
     status: JobStatus,
     progress: Option<ScanProgress>,
+    /// The last `RECENT_WARNINGS` unreadable files; `warning_count` has the total.
+    recent_warnings: VecDeque<ScanWarning>,
+    warning_count: u64,
+    /// Exact-duplicate totals, refreshed every `DUPLICATE_STATS_INTERVAL` while scanning.
+    duplicate_groups: u64,
+    reclaimable_bytes: u64,
+    stats_refreshed_at: Option<Instant>,
     started_at: Instant,
     finished_at: Option<Instant>,
     error: Option<String>,
     cancel: ScanCancelToken,
 }
 
+const RECENT_WARNINGS: usize = 5;
+const DUPLICATE_STATS_INTERVAL: Duration = Duration::from_secs(5);
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
 #[serde(rename_all = "snake_case")]
 enum JobStatus {
@@ -101,6 +301,12 @@ enum JobStatus {
     Failed,
 }
 
+impl JobStatus {
+    fn is_finished(self) -> bool {
+        !matches!(self, Self::Pending | Self::Running)
+    }
+}
+
 #[derive(Clone, Debug, Serialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 enum ServerEvent {
@@ -109,6 +315,12 @@ enum ServerEvent {
         root: String,
         db_path: String,
     },
+    /// The file walk that gives later `ScanProgress` events their totals finished.
+    PrescanDone {
+        id: u64,
+        total_files: u64,
+        total_bytes: u64,
+    },
     ScanProgress {
         id: u64,
         progress: ProgressDto,
@@ -140,6 +352,23 @@ struct ProgressDto {
     current_path: String,
     current_step: Option<String>,
     active_tasks: Vec<String>,
+    warning: Option<ScanWarningDto>,
+    eta_secs: Option<u64>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct ScanWarningDto {
+    path: String,
+    reason: String,
+}
+
+impl From<&ScanWarning> for ScanWarningDto {
+    fn from(warning: &ScanWarning) -> Self {
+        Self {
+            path: warning.path.display().to_string(),
+            reason: warning.reason.clone(),
+        }
+    }
 }
 
 impl From<&ScanProgress> for ProgressDto {
@@ -158,6 +387,8 @@ impl From<&ScanProgress> for ProgressDto {
                 .iter()
                 .map(|task| format!("{}: {}", task.step, task.path.display()))
                 .collect(),
+            warning: progress.warning.as_ref().map(ScanWarningDto::from),
+            eta_secs: progress.eta_secs,
         }
     }
 }
@@ -167,6 +398,8 @@ struct ScanStatsDto {
     files_seen: u64,
     files_hashed: u64,
     files_skipped: u64,
+    files_filtered: u64,
+    files_unreadable: usize,
 }
 
 impl From<&ScanResult> for ScanStatsDto {
@@ -175,6 +408,8 @@ impl From<&ScanResult> for ScanStatsDto {
             files_seen: result.stats.files_seen,
             files_hashed: result.stats.files_hashed,
             files_skipped: result.stats.files_skipped,
+            files_filtered: result.stats.files_filtered,
+            files_unreadable: result.warnings.len(),
         }
     }
 }
@@ -196,17 +431,44 @@ struct JobDto {
     db_path: String,
     status: JobStatus,
     progress: Option<ProgressDto>,
+    warnings: Vec<ScanWarningDto>,
+    warning_count: u64,
+    duplicate_groups: u64,
+    reclaimable_bytes: u64,
     error: Option<String>,
     started_secs: u64,
     finished_secs: Option<u64>,
 }
 
+#[derive(Serialize)]
+struct CancelAllDto {
+    cancelled: u64,
+}
+
 #[derive(Deserialize)]
 struct MatchesQuery {
     limit: Option<usize>,
     offset: Option<usize>,
 }
 
+#[derive(Deserialize)]
+struct ExportQuery {
+    format: Option<String>,
+}
+
+#[derive(Clone, Copy)]
+enum ExportFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Serialize)]
+struct ExportJson {
+    fileset_id: u64,
+    name: String,
+    groups: Vec<MatchGroupDto>,
+}
+
 #[derive(Serialize)]
 struct MatchGroupDto {
     key: String,
@@ -216,7 +478,13 @@ struct MatchGroupDto {
 #[derive(Serialize)]
 struct MatchesResponse {
     fileset_id: u64,
+    /// Settings of the scan that produced the fileset, when it recorded them.
+    scan_config: Option<serde_json::Value>,
     groups: Vec<MatchGroupDto>,
+    total_groups: u64,
+    limit: usize,
+    offset: usize,
+    has_more: bool,
 }
 
 #[derive(Serialize)]
@@ -227,35 +495,181 @@ struct FileDto {
     file_type: Option<String>,
 }
 
-async fn ui_index() -> Html<String> {
-    Html(render_ui())
+async fn ui_index(State(state): State<Arc<AppState>>) -> Html<String> {
+    Html(render_ui(state.token.is_some(), state.media_notice))
+}
+
+#[derive(Deserialize)]
+struct TokenQuery {
+    token: Option<String>,
+}
+
+/// Accepts `Authorization: Bearer <token>` or `?token=<token>`.
+async fn require_token<B>(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<TokenQuery>,
+    request: Request<B>,
+    next: Next<B>,
+) -> axum::response::Response {
+    let Some(expected) = state.token.as_deref() else {
+        return next.run(request).await;
+    };
+    let bearer = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let provided = bearer.or(query.token.as_deref());
+    match provided {
+        Some(provided) if tokens_match(provided, expected) => next.run(request).await,
+        _ => (StatusCode::UNAUTHORIZED, "missing or invalid token").into_response(),
+    }
+}
+
+/// Without a token any page the user visits could post a form here, so requests a
+/// browser marks as coming from another site are refused.
+async fn require_same_origin<B>(request: Request<B>, next: Next<B>) -> axum::response::Response {
+    if same_origin_request(request.headers()) {
+        next.run(request).await
+    } else {
+        (StatusCode::FORBIDDEN, "cross-origin request refused").into_response()
+    }
+}
+
+fn same_origin_request(headers: &HeaderMap) -> bool {
+    let fetch_site_ok = headers
+        .get("sec-fetch-site")
+        .and_then(|value| value.to_str().ok())
+        .map_or(true, |site| matches!(site, "same-origin" | "none"));
+    fetch_site_ok && ws::origin_allowed(headers)
+}
+
+/// Length-independent comparison so response timing does not leak a token prefix.
+fn tokens_match(provided: &str, expected: &str) -> bool {
+    let (a, b) = (provided.as_bytes(), expected.as_bytes());
+    let mut diff = a.len() ^ b.len();
+    for i in 0..a.len().max(b.len()) {
+        let x = a.get(i).copied().unwrap_or(0);
+        let y = b.get(i).copied().unwrap_or(0);
+        diff |= usize::from(x ^ y);
+    }
+    diff == 0
 }
 
 async fn sse_events(
     State(state): State<Arc<AppState>>,
 ) -> Sse<impl tokio_stream::Stream<Item = std::result::Result<Event, Infallible>>> {
     let rx = state.events_tx.subscribe();
-    let stream = BroadcastStream::new(rx).filter_map(|item| match item {
+    let events = BroadcastStream::new(rx).filter_map(|item| match item {
         Ok(event) => {
             let payload = match serde_json::to_string(&event) {
                 Ok(payload) => payload,
                 Err(_) => return None,
             };
-            Some(Ok(Event::default().data(payload)))
+            Some(Some(Ok(Event::default().data(payload))))
         }
         Err(_) => None,
     });
+    let shutdown = WatchStream::new(state.shutdown.subscribe())
+        .filter(|stopping| *stopping)
+        .map(|_| None);
+    let stream = events.merge(shutdown).map_while(|item| item);
 
     Sse::new(stream).keep_alive(KeepAlive::new().interval(std::time::Duration::from_secs(10)))
 }
 
+/// Same feed as `/events`, for networks whose proxies buffer SSE.
+async fn ws_events(
+    State(state): State<Arc<AppState>>,
+    request: Request<axum::body::Body>,
+) -> axum::response::Response {
+    let Some(accept) = ws::accept_key(request.headers()) else {
+        return (StatusCode::BAD_REQUEST, "expected a WebSocket upgrade").into_response();
+    };
+    if !ws::origin_allowed(request.headers()) {
+        return (StatusCode::FORBIDDEN, "cross-origin WebSocket upgrade").into_response();
+    }
+    let events = state.events_tx.subscribe();
+    let shutdown = state.shutdown.subscribe();
+    tokio::spawn(async move {
+        if let Ok(upgraded) = hyper::upgrade::on(request).await {
+            forward_events_over_ws(upgraded, events, shutdown).await;
+        }
+    });
+
+    (
+        StatusCode::SWITCHING_PROTOCOLS,
+        [
+            (header::UPGRADE, "websocket".to_string()),
+            (header::CONNECTION, "Upgrade".to_string()),
+            (header::SEC_WEBSOCKET_ACCEPT, accept),
+        ],
+    )
+        .into_response()
+}
+
+async fn forward_events_over_ws(
+    upgraded: hyper::upgrade::Upgraded,
+    mut events: broadcast::Receiver<ServerEvent>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let (mut reader, mut writer) = tokio::io::split(upgraded);
+    // Frame reads are not cancel-safe, so they run in their own task instead of in select!.
+    let (frames_tx, mut frames_rx) = tokio::sync::mpsc::channel(8);
+    let reader_task = tokio::spawn(async move {
+        loop {
+            let frame = ws::read_frame(&mut reader).await;
+            let done = !matches!(frame, Ok(ws::ClientFrame::Ping(_) | ws::ClientFrame::Other));
+            if frames_tx.send(frame).await.is_err() || done {
+                break;
+            }
+        }
+    });
+
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => {
+                    let Ok(payload) = serde_json::to_string(&event) else {
+                        continue;
+                    };
+                    if ws::write_text(&mut writer, &payload).await.is_err() {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            frame = frames_rx.recv() => match frame {
+                Some(Ok(ws::ClientFrame::Ping(payload))) => {
+                    if ws::write_pong(&mut writer, &payload).await.is_err() {
+                        break;
+                    }
+                }
+                Some(Ok(ws::ClientFrame::Other)) => {}
+                Some(Ok(ws::ClientFrame::Close)) => {
+                    let _ = ws::write_close(&mut writer).await;
+                    break;
+                }
+                Some(Err(_)) | None => break,
+            },
+            // The watch guard is not Send, so drop it inside the branch future.
+            _ = async { drop(shutdown.wait_for(|stopping| *stopping).await) } => {
+                let _ = ws::write_close(&mut writer).await;
+                break;
+            }
+        }
+    }
+    reader_task.abort();
+}
+
 async fn start_scan_handler(
     State(state): State<Arc<AppState>>,
     Form(form): Form<ScanForm>,
 ) -> axum::response::Response {
     match start_scan(state, form).await {
         Ok(_) => redirect_home(),
-        Err(err) => (axum::http::StatusCode::BAD_REQUEST, err.to_string()).into_response(),
+        Err(err) => (error_status(&err), err.to_string()).into_response(),
     }
 }
 
@@ -265,14 +679,55 @@ async fn cancel_scan_handler(
 ) -> axum::response::Response {
     let mut guard = state.inner.lock().await;
     if let Some(job) = guard.jobs.iter_mut().find(|job| job.id == id) {
-        if job.status == JobStatus::Running {
+        if !job.status.is_finished() {
             job.cancel.cancel();
         }
     }
+    schedule_scans(&state, &mut guard);
 
     redirect_home()
 }
 
+/// Cancels every queued or running scan. Each scan task (or `schedule_scans`, for
+/// queued ones) broadcasts its own `ScanCancelled` once it stops, so a job that
+/// completes first still reports `ScanDone`, and jobs whose token was already
+/// flipped are not counted again.
+async fn cancel_all_handler(State(state): State<Arc<AppState>>) -> Json<CancelAllDto> {
+    let mut guard = state.inner.lock().await;
+    let mut cancelled = 0;
+    for job in guard.jobs.iter() {
+        if !job.status.is_finished() && !job.cancel.is_cancelled() {
+            job.cancel.cancel();
+            cancelled += 1;
+        }
+    }
+    schedule_scans(&state, &mut guard);
+    Json(CancelAllDto { cancelled })
+}
+
+async fn delete_job_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<u64>,
+) -> axum::response::Response {
+    let mut guard = state.inner.lock().await;
+    let Some(pos) = guard.jobs.iter().position(|job| job.id == id) else {
+        return (StatusCode::NOT_FOUND, "Unknown job").into_response();
+    };
+    if !guard.jobs[pos].status.is_finished() {
+        return (StatusCode::CONFLICT, "Job is still running").into_response();
+    }
+    guard.jobs.remove(pos);
+    state.prune_store_pools(&guard.jobs);
+    StatusCode::NO_CONTENT.into_response()
+}
+
+async fn clear_jobs_handler(State(state): State<Arc<AppState>>) -> axum::response::Response {
+    let mut guard = state.inner.lock().await;
+    guard.jobs.retain(|job| !job.status.is_finished());
+    state.prune_store_pools(&guard.jobs);
+    StatusCode::NO_CONTENT.into_response()
+}
+
 async fn list_jobs_handler(State(state): State<Arc<AppState>>) -> Json<Vec<JobDto>> {
     let guard = state.inner.lock().await;
     let jobs = guard
@@ -285,6 +740,14 @@ async fn list_jobs_handler(State(state): State<Arc<AppState>>) -> Json<Vec<JobDt
             db_path: job.db_path.display().to_string(),
             status: job.status,
             progress: job.progress.as_ref().map(ProgressDto::from),
+            warnings: job
+                .recent_warnings
+                .iter()
+                .map(ScanWarningDto::from)
+                .collect(),
+            warning_count: job.warning_count,
+            duplicate_groups: job.duplicate_groups,
+            reclaimable_bytes: job.reclaimable_bytes,
             error: job.error.clone(),
             started_secs: job.started_at.elapsed().as_secs(),
             finished_secs: job
@@ -313,23 +776,36 @@ async fn list_matches_handler(
         return (axum::http::StatusCode::NOT_FOUND, "Unknown fileset").into_response();
     };
 
-    let limit = query.limit.unwrap_or(200).clamp(1, 2000);
+    // Pages are counted in groups, not rows.
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
     let offset = query.offset.unwrap_or(0);
 
     let result = tokio::task::spawn_blocking(move || {
-        let store = SqliteScanStore::open(&db_path)?;
-        let rows = store.list_files_with_duplicates(limit, offset)?;
-        Ok::<_, Error>(group_matches(rows))
+        let store = state.store(&db_path)?;
+        let total_groups = store.duplicate_group_count()?;
+        let groups = store.duplicate_groups(true, limit, offset)?;
+        let scan_config = store
+            .get_scan_config()?
+            .and_then(|json| serde_json::from_str(&json).ok());
+        Ok::<_, Error>((total_groups, groups, scan_config))
     })
     .await;
 
     match result {
-        Ok(Ok(groups)) => Json(MatchesResponse {
-            fileset_id: id,
-            groups,
-        })
-        .into_response(),
-        Ok(Err(err)) => (axum::http::StatusCode::BAD_REQUEST, err.to_string()).into_response(),
+        Ok(Ok((total_groups, groups, scan_config))) => {
+            let has_more = (offset + groups.len()) < total_groups as usize;
+            Json(MatchesResponse {
+                fileset_id: id,
+                scan_config,
+                groups: groups.into_iter().map(MatchGroupDto::from).collect(),
+                total_groups,
+                limit,
+                offset,
+                has_more,
+            })
+            .into_response()
+        }
+        Ok(Err(err)) => (error_status(&err), err.to_string()).into_response(),
         Err(_) => (
             axum::http::StatusCode::INTERNAL_SERVER_ERROR,
             "background task failed",
@@ -338,9 +814,134 @@ async fn list_matches_handler(
     }
 }
 
+async fn export_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<u64>,
+    Query(query): Query<ExportQuery>,
+) -> impl IntoResponse {
+    let format = match query.format.as_deref().unwrap_or("csv") {
+        "csv" => ExportFormat::Csv,
+        "json" => ExportFormat::Json,
+        other => {
+            return (
+                StatusCode::BAD_REQUEST,
+                format!("unknown export format: {other}"),
+            )
+                .into_response()
+        }
+    };
+
+    let db_path = {
+        let guard = state.inner.lock().await;
+        guard
+            .jobs
+            .iter()
+            .find(|job| job.id == id)
+            .map(|job| job.db_path.clone())
+    };
+
+    let Some(db_path) = db_path else {
+        return (axum::http::StatusCode::NOT_FOUND, "Unknown fileset").into_response();
+    };
+
+    let result = tokio::task::spawn_blocking(move || {
+        let store = state.store(&db_path)?;
+        build_export(id, &store, format)
+    })
+    .await;
+
+    match result {
+        Ok(Ok((name, body))) => {
+            let (content_type, extension) = match format {
+                ExportFormat::Csv => ("text/csv; charset=utf-8", "csv"),
+                ExportFormat::Json => ("application/json", "json"),
+            };
+            let disposition = format!(
+                "attachment; filename=\"{}.{extension}\"",
+                export_file_stem(&name, id)
+            );
+            (
+                [
+                    (header::CONTENT_TYPE, content_type.to_string()),
+                    (header::CONTENT_DISPOSITION, disposition),
+                ],
+                body,
+            )
+                .into_response()
+        }
+        Ok(Err(err)) => (error_status(&err), err.to_string()).into_response(),
+        Err(_) => (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            "background task failed",
+        )
+            .into_response(),
+    }
+}
+
+/// Returns the fileset name and the encoded export.
+fn build_export(
+    id: u64,
+    store: &SqliteScanStore,
+    format: ExportFormat,
+) -> Result<(String, Vec<u8>)> {
+    let name = store
+        .get_fileset_metadata()?
+        .map(|meta| meta.name)
+        .unwrap_or_default();
+    let body = match format {
+        ExportFormat::Csv => {
+            let mut out = Vec::new();
+            dupdupninja_core::export::export_csv(store, &mut out, false)?;
+            out
+        }
+        ExportFormat::Json => {
+            const PAGE_GROUPS: usize = 500;
+            let mut groups = Vec::new();
+            loop {
+                let page = store.duplicate_groups(true, PAGE_GROUPS, groups.len())?;
+                let done = page.len() < PAGE_GROUPS;
+                groups.extend(page.into_iter().map(MatchGroupDto::from));
+                if done {
+                    break;
+                }
+            }
+            let export = ExportJson {
+                fileset_id: id,
+                name: name.clone(),
+                groups,
+            };
+            serde_json::to_vec_pretty(&export)
+                .map_err(|err| Error::InvalidArgument(format!("failed to encode export: {err}")))?
+        }
+    };
+    Ok((name, body))
+}
+
+/// Keeps the header value a plain-ASCII filename without quotes or path separators.
+fn export_file_stem(name: &str, id: u64) -> String {
+    let stem: String = name
+        .trim()
+        .chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.' | ' ') {
+                ch
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let stem = stem.trim_matches(['.', ' ']);
+    if stem.is_empty() {
+        format!("fileset-{id}")
+    } else {
+        stem.to_string()
+    }
+}
+
 async fn snapshot_handler(
     State(state): State<Arc<AppState>>,
     Path((id, file_id, index)): Path<(u64, i64, u32)>,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
     let db_path = {
         let guard = state.inner.lock().await;
@@ -356,20 +957,32 @@ async fn snapshot_handler(
     };
 
     let snapshot = tokio::task::spawn_blocking(move || {
-        let store = SqliteScanStore::open(&db_path)?;
+        let store = state.store(&db_path)?;
         let snaps = store.list_file_snapshots(file_id)?;
         Ok::<_, Error>(snaps.into_iter().find(|snap| snap.snapshot_index == index))
     })
     .await;
 
     match snapshot {
-        Ok(Ok(Some(snap))) => (
-            [(axum::http::header::CONTENT_TYPE, "image/avif")],
-            snap.image_avif,
-        )
-            .into_response(),
+        Ok(Ok(Some(snap))) => {
+            // Snapshots never change once stored, so a content hash is a stable validator.
+            let etag = format!("\"{}\"", blake3::hash(&snap.image_avif).to_hex());
+            let cache_headers = [
+                (header::CACHE_CONTROL, SNAPSHOT_CACHE_CONTROL.to_string()),
+                (header::ETAG, etag.clone()),
+            ];
+            if if_none_match_hits(&headers, &etag) {
+                return (StatusCode::NOT_MODIFIED, cache_headers).into_response();
+            }
+            (
+                cache_headers,
+                [(header::CONTENT_TYPE, snap.mime)],
+                snap.image_avif,
+            )
+                .into_response()
+        }
         Ok(Ok(None)) => (axum::http::StatusCode::NOT_FOUND, "Snapshot not found").into_response(),
-        Ok(Err(err)) => (axum::http::StatusCode::BAD_REQUEST, err.to_string()).into_response(),
+        Ok(Err(err)) => (error_status(&err), err.to_string()).into_response(),
         Err(_) => (
             axum::http::StatusCode::INTERNAL_SERVER_ERROR,
             "background task failed",
@@ -378,6 +991,138 @@ async fn snapshot_handler(
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FileAction {
+    Delete,
+    Trash,
+}
+
+async fn delete_file_handler(
+    State(state): State<Arc<AppState>>,
+    Path((id, file_id)): Path<(u64, i64)>,
+) -> axum::response::Response {
+    file_action(state, id, file_id, FileAction::Delete).await
+}
+
+async fn trash_file_handler(
+    State(state): State<Arc<AppState>>,
+    Path((id, file_id)): Path<(u64, i64)>,
+) -> axum::response::Response {
+    file_action(state, id, file_id, FileAction::Trash).await
+}
+
+async fn file_action(
+    state: Arc<AppState>,
+    id: u64,
+    file_id: i64,
+    action: FileAction,
+) -> axum::response::Response {
+    let db_path = {
+        let guard = state.inner.lock().await;
+        guard
+            .jobs
+            .iter()
+            .find(|job| job.id == id)
+            .map(|job| job.db_path.clone())
+    };
+
+    let Some(db_path) = db_path else {
+        return (StatusCode::NOT_FOUND, "Unknown fileset").into_response();
+    };
+
+    let state_for_task = state.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        let store = state_for_task
+            .store(&db_path)
+            .map_err(|err| (error_status(&err), err.to_string()))?;
+        apply_file_action(&store, file_id, action)
+    })
+    .await;
+
+    match result {
+        Ok(Ok(())) => {
+            let _ = state.events_tx.send(ServerEvent::MatchesUpdated { id });
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Ok(Err((status, message))) => (status, message).into_response(),
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "background task failed").into_response(),
+    }
+}
+
+fn error_status(err: &Error) -> StatusCode {
+    match err {
+        Error::InvalidArgument(_) | Error::NotAFileset(_) => StatusCode::BAD_REQUEST,
+        Error::NotFound(_) => StatusCode::NOT_FOUND,
+        Error::Io(e) if e.kind() == std::io::ErrorKind::NotFound => StatusCode::NOT_FOUND,
+        Error::Io(e) if e.kind() == std::io::ErrorKind::PermissionDenied => StatusCode::FORBIDDEN,
+        Error::Cancelled => StatusCode::CONFLICT,
+        Error::DiskFull(_) => StatusCode::INSUFFICIENT_STORAGE,
+        Error::Io(_)
+        | Error::Sqlite(_)
+        | Error::Migration(_)
+        | Error::Ffmpeg(_)
+        | Error::Trash(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Only removes a file when another member of its exact-duplicate group still exists
+/// on disk and matches it byte-for-byte.
+fn apply_file_action(
+    store: &SqliteScanStore,
+    file_id: i64,
+    action: FileAction,
+) -> std::result::Result<(), (StatusCode, String)> {
+    let core_error = |err: Error| (error_status(&err), err.to_string());
+    let Some(record) = store.get_file_by_id(file_id).map_err(core_error)? else {
+        return Err((StatusCode::NOT_FOUND, "Unknown file".to_string()));
+    };
+    let peers = store.exact_duplicates_of(file_id).map_err(core_error)?;
+    if peers.is_empty() {
+        return Err((
+            StatusCode::CONFLICT,
+            "file is not part of a duplicate group".to_string(),
+        ));
+    }
+
+    let resolver = crate::FilesetPathResolver::from_store(store);
+    let path = resolver.resolve_path(&record.path).map_err(core_error)?;
+    let has_verified_copy = peers.iter().any(|peer| {
+        resolver
+            .resolve_path(&peer.path)
+            .ok()
+            .filter(|peer_path| peer_path != &path)
+            .is_some_and(|peer_path| {
+                dupdupninja_core::hash::files_identical(&path, &peer_path).unwrap_or(false)
+            })
+    });
+    if !has_verified_copy {
+        return Err((
+            StatusCode::CONFLICT,
+            "no other copy of this file could be verified on disk".to_string(),
+        ));
+    }
+
+    let removed = match action {
+        FileAction::Delete => crate::delete_path_permanently(&path),
+        FileAction::Trash => crate::move_path_to_trash(&path),
+    };
+    removed.map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+    store.delete_file_by_id(file_id).map_err(core_error)?;
+    Ok(())
+}
+
+const SNAPSHOT_CACHE_CONTROL: &str = "public, max-age=86400";
+
+fn if_none_match_hits(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|candidate| candidate.trim().trim_start_matches("W/"))
+        .any(|candidate| candidate == "*" || candidate == etag)
+}
+
 async fn start_scan(state: Arc<AppState>, form: ScanForm) -> Result<()> {
     let root = form.root.trim();
     if root.is_empty() {
@@ -396,17 +1141,22 @@ async fn start_scan(state: Arc<AppState>, form: ScanForm) -> Result<()> {
         .map(str::trim)
         .filter(|value| !value.is_empty())
         .map(PathBuf::from)
-        .unwrap_or_else(|| scan_db_path(&root_path));
+        .unwrap_or_else(|| crate::scan_db_path(&root_path));
 
     let cfg = ScanConfig {
         root: root_path.clone(),
         root_kind,
-        hash_files: true,
-        perceptual_hashes: true,
         capture_snapshots: form.capture_snapshots.is_some(),
-        snapshots_per_video: form.snapshots_per_video.unwrap_or(3).clamp(1, 10),
-        snapshot_max_dim: form.snapshot_max_dim.unwrap_or(1024).clamp(128, 4096),
-        concurrent_processing: true,
+        snapshots_per_video: clamp_to(
+            form.snapshots_per_video.unwrap_or(3),
+            ScanConfig::SNAPSHOTS_PER_VIDEO,
+        ),
+        snapshot_max_dim: clamp_to(
+            form.snapshot_max_dim.unwrap_or(1024),
+            ScanConfig::SNAPSHOT_MAX_DIM,
+        ),
+        sort_entries: true,
+        ..Default::default()
     };
 
     let (id, cancel) = {
@@ -420,11 +1170,18 @@ async fn start_scan(state: Arc<AppState>, form: ScanForm) -> Result<()> {
             db_path: db_path.clone(),
             status: JobStatus::Pending,
             progress: None,
+            recent_warnings: VecDeque::new(),
+            warning_count: 0,
+            duplicate_groups: 0,
+            reclaimable_bytes: 0,
+            stats_refreshed_at: None,
             started_at: Instant::now(),
             finished_at: None,
             error: None,
             cancel: cancel.clone(),
         });
+        guard.trim_jobs(state.max_jobs);
+        state.prune_store_pools(&guard.jobs);
         (id, cancel)
     };
 
@@ -434,180 +1191,263 @@ async fn start_scan(state: Arc<AppState>, form: ScanForm) -> Result<()> {
         db_path: db_path.display().to_string(),
     });
 
-    let state_for_task = state.clone();
-    tokio::task::spawn_blocking(move || {
-        update_job(&state_for_task, id, |job| {
-            job.status = JobStatus::Running;
-            job.started_at = Instant::now();
+    {
+        let mut guard = state.inner.lock().await;
+        guard.queue.push_back(QueuedScan {
+            id,
+            cfg,
+            db_path,
+            cancel,
         });
+        schedule_scans(&state, &mut guard);
+    }
 
-        let store = match SqliteScanStore::open(&db_path) {
-            Ok(store) => store,
-            Err(err) => {
-                update_job(&state_for_task, id, |job| {
-                    job.status = JobStatus::Failed;
-                    job.error = Some(format!("Failed to open DB: {err}"));
-                    job.finished_at = Some(Instant::now());
-                });
-                let _ = state_for_task.events_tx.send(ServerEvent::ScanError {
-                    id,
-                    message: "Failed to open DB".to_string(),
-                });
-                return;
-            }
+    Ok(())
+}
+
+/// A scan waiting in `InnerState::queue` for a free slot.
+struct QueuedScan {
+    id: u64,
+    cfg: ScanConfig,
+    db_path: PathBuf,
+    cancel: ScanCancelToken,
+}
+
+/// Marks cancelled queued scans as done, then starts queued scans until
+/// `max_concurrent_scans` are running. Runs whenever a scan is queued, cancelled
+/// or finishes.
+fn schedule_scans(state: &Arc<AppState>, inner: &mut InnerState) {
+    let (cancelled, queued) = inner
+        .queue
+        .drain(..)
+        .partition::<VecDeque<_>, _>(|scan| scan.cancel.is_cancelled());
+    inner.queue = queued;
+    for scan in cancelled {
+        if let Some(job) = inner.jobs.iter_mut().find(|job| job.id == scan.id) {
+            job.status = JobStatus::Cancelled;
+            job.finished_at = Some(Instant::now());
+        }
+        let _ = state
+            .events_tx
+            .send(ServerEvent::ScanCancelled { id: scan.id });
+    }
+
+    while state.max_concurrent_scans == 0 || inner.running_scans < state.max_concurrent_scans {
+        let Some(scan) = inner.queue.pop_front() else {
+            break;
         };
+        inner.running_scans += 1;
+        let state_for_task = Arc::clone(state);
+        let task = tokio::task::spawn_blocking(move || {
+            run_scan_job(&state_for_task, scan);
+            let mut guard = state_for_task.inner.blocking_lock();
+            guard.running_scans -= 1;
+            schedule_scans(&state_for_task, &mut guard);
+        });
+        if let Ok(mut tasks) = state.scan_tasks.lock() {
+            tasks.retain(|task| !task.is_finished());
+            tasks.push(task);
+        }
+    }
+    inner.trim_jobs(state.max_jobs);
+}
 
-        let result = scan_to_sqlite_with_progress(&cfg, &store, Some(&cancel), |progress| {
-            update_job(&state_for_task, id, |job| {
-                job.progress = Some(progress.clone());
+fn run_scan_job(state: &Arc<AppState>, scan: QueuedScan) {
+    let QueuedScan {
+        id,
+        cfg,
+        db_path,
+        cancel,
+    } = scan;
+    set_job_status(state, id, |job| {
+        job.status = JobStatus::Running;
+        job.started_at = Instant::now();
+    });
+
+    let store = match SqliteScanStore::open(&db_path) {
+        Ok(store) => store,
+        Err(err) => {
+            set_job_status(state, id, |job| {
+                job.status = JobStatus::Failed;
+                job.error = Some(format!("Failed to open DB: {err}"));
+                job.finished_at = Some(Instant::now());
             });
-            let _ = state_for_task.events_tx.send(ServerEvent::ScanProgress {
+            let _ = state.events_tx.send(ServerEvent::ScanError {
                 id,
-                progress: ProgressDto::from(progress),
+                message: "Failed to open DB".to_string(),
             });
-        });
+            return;
+        }
+    };
 
-        match result {
-            Ok(result) => {
-                update_job(&state_for_task, id, |job| {
-                    job.status = JobStatus::Completed;
-                    job.finished_at = Some(Instant::now());
-                });
-                let _ = state_for_task.events_tx.send(ServerEvent::ScanDone {
-                    id,
-                    stats: ScanStatsDto::from(&result),
-                });
-                let _ = state_for_task
-                    .events_tx
-                    .send(ServerEvent::MatchesUpdated { id });
+    let on_progress = |progress: &ScanProgress| {
+        let mut refresh_stats = false;
+        update_job(state, id, |job| {
+            if job
+                .stats_refreshed_at
+                .map_or(true, |at| at.elapsed() >= DUPLICATE_STATS_INTERVAL)
+            {
+                job.stats_refreshed_at = Some(Instant::now());
+                refresh_stats = true;
             }
-            Err(Error::Cancelled) => {
-                update_job(&state_for_task, id, |job| {
-                    job.status = JobStatus::Cancelled;
-                    job.finished_at = Some(Instant::now());
-                });
-                let _ = state_for_task
-                    .events_tx
-                    .send(ServerEvent::ScanCancelled { id });
-            }
-            Err(err) => {
-                update_job(&state_for_task, id, |job| {
-                    job.status = JobStatus::Failed;
-                    job.error = Some(err.to_string());
-                    job.finished_at = Some(Instant::now());
-                });
-                let _ = state_for_task.events_tx.send(ServerEvent::ScanError {
-                    id,
-                    message: err.to_string(),
-                });
+            job.progress = Some(progress.clone());
+            if let Some(warning) = &progress.warning {
+                if job.recent_warnings.len() >= RECENT_WARNINGS {
+                    job.recent_warnings.pop_front();
+                }
+                job.recent_warnings.push_back(warning.clone());
+                job.warning_count += 1;
             }
+        });
+        if refresh_stats {
+            let state = state.clone();
+            let db_path = db_path.clone();
+            tokio::task::spawn_blocking(move || refresh_duplicate_stats(&state, id, &db_path));
         }
+        let _ = state.events_tx.send(ServerEvent::ScanProgress {
+            id,
+            progress: ProgressDto::from(progress),
+        });
+    };
+    let result = prescan(&cfg, Some(&cancel), |_| {}).and_then(|totals| {
+        update_job(state, id, |job| {
+            job.progress = Some(ScanProgress {
+                files_seen: 0,
+                files_hashed: 0,
+                files_skipped: 0,
+                bytes_seen: 0,
+                total_files: totals.files,
+                total_bytes: totals.bytes,
+                current_path: PathBuf::new(),
+                current_step: Some("prescan".to_string()),
+                active_tasks: Vec::new(),
+                warning: None,
+                eta_secs: None,
+            });
+        });
+        let _ = state.events_tx.send(ServerEvent::PrescanDone {
+            id,
+            total_files: totals.files,
+            total_bytes: totals.bytes,
+        });
+        scan_to_sqlite_with_progress_and_totals(
+            &cfg,
+            &store,
+            Some(&cancel),
+            Some(totals),
+            on_progress,
+        )
     });
 
-    Ok(())
+    match result {
+        Ok(result) => {
+            refresh_duplicate_stats(state, id, &db_path);
+            set_job_status(state, id, |job| {
+                job.status = JobStatus::Completed;
+                job.finished_at = Some(Instant::now());
+            });
+            let _ = state.events_tx.send(ServerEvent::ScanDone {
+                id,
+                stats: ScanStatsDto::from(&result),
+            });
+            let _ = state.events_tx.send(ServerEvent::MatchesUpdated { id });
+        }
+        Err(Error::Cancelled) => {
+            set_job_status(state, id, |job| {
+                job.status = JobStatus::Cancelled;
+                job.finished_at = Some(Instant::now());
+            });
+            let _ = state.events_tx.send(ServerEvent::ScanCancelled { id });
+        }
+        Err(err) => {
+            let message = match err {
+                Error::DiskFull(_) => {
+                    format!(
+                        "{err}; the fileset was kept as incomplete, free some space and scan again"
+                    )
+                }
+                _ => err.to_string(),
+            };
+            set_job_status(state, id, |job| {
+                job.status = JobStatus::Failed;
+                job.error = Some(message.clone());
+                job.finished_at = Some(Instant::now());
+            });
+            let _ = state.events_tx.send(ServerEvent::ScanError { id, message });
+        }
+    }
+}
+
+/// Reads the duplicate totals through the handler pool, which sees the scan's
+/// committed batches. Errors leave the previous totals in place.
+fn refresh_duplicate_stats(state: &Arc<AppState>, id: u64, db_path: &std::path::Path) {
+    let stats = state.store(db_path).and_then(|store| {
+        Ok::<_, Error>((store.duplicate_group_count()?, store.reclaimable_bytes()?))
+    });
+    if let Ok((groups, reclaimable)) = stats {
+        update_job(state, id, |job| {
+            job.duplicate_groups = groups;
+            job.reclaimable_bytes = reclaimable;
+        });
+    }
 }
 
+/// Applies a progress or stats update, skipped while the state is busy since the
+/// next update replaces it anyway.
 fn update_job<F>(state: &Arc<AppState>, id: u64, f: F)
 where
     F: FnOnce(&mut ScanJob),
 {
     if let Ok(mut guard) = state.inner.try_lock() {
-        if let Some(job) = guard.jobs.iter_mut().find(|job| job.id == id) {
-            f(job);
-        }
-        if guard.jobs.len() > 50 {
-            let excess = guard.jobs.len() - 50;
-            guard.jobs.drain(0..excess);
-        }
+        apply_job_update(state, &mut guard, id, f);
     }
 }
 
-fn group_matches(rows: Vec<FileListRow>) -> Vec<MatchGroupDto> {
-    let mut groups: HashMap<String, Vec<FileDto>> = HashMap::new();
-    for row in rows {
-        let key = if let Some(hash) = row.blake3 {
-            format!("blake3:{}", hex_encode(&hash))
-        } else if let Some(hash) = row.sha256 {
-            format!("sha256:{}", hex_encode(&hash))
-        } else {
-            continue;
-        };
-        groups.entry(key).or_default().push(FileDto {
-            id: row.id,
-            path: row.path.display().to_string(),
-            size_bytes: row.size_bytes,
-            file_type: row.file_type,
-        });
+/// Applies a status change, waiting for the lock so it is never lost. Call only from
+/// blocking tasks.
+fn set_job_status<F>(state: &Arc<AppState>, id: u64, f: F)
+where
+    F: FnOnce(&mut ScanJob),
+{
+    let mut guard = state.inner.blocking_lock();
+    apply_job_update(state, &mut guard, id, f);
+}
+
+fn apply_job_update<F>(state: &Arc<AppState>, inner: &mut InnerState, id: u64, f: F)
+where
+    F: FnOnce(&mut ScanJob),
+{
+    if let Some(job) = inner.jobs.iter_mut().find(|job| job.id == id) {
+        f(job);
     }
-    let mut out: Vec<MatchGroupDto> = groups
-        .into_iter()
-        .filter_map(|(key, files)| {
-            if files.len() < 2 {
-                None
-            } else {
-                Some(MatchGroupDto { key, files })
-            }
-        })
-        .collect();
-    out.sort_by(|a, b| b.files.len().cmp(&a.files.len()));
-    out
-}
-
-fn hex_encode(bytes: &[u8; 32]) -> String {
-    let mut out = String::with_capacity(64);
-    for byte in bytes {
-        out.push_str(&format!("{:02x}", byte));
-    }
-    out
-}
-
-fn scan_db_path(root: &std::path::Path) -> PathBuf {
-    let ts = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_secs();
-    let name = sanitize_fileset_name(root);
-    let file_name = format!("{name}-{ts}.ddn");
-    let mut base = default_fileset_dir();
-    if std::fs::create_dir_all(&base).is_err() {
-        let mut fallback = std::env::temp_dir();
-        fallback.push(file_name);
-        return fallback;
-    }
-    base.push(file_name);
-    base
-}
-
-fn sanitize_fileset_name(root: &std::path::Path) -> String {
-    let raw = root
-        .file_name()
-        .and_then(|name| name.to_str())
-        .unwrap_or("fileset");
-    let mut out = String::with_capacity(raw.len());
-    for ch in raw.chars() {
-        if ch.is_ascii_alphanumeric() || ch == '-' || ch == '_' {
-            out.push(ch.to_ascii_lowercase());
-        } else if ch.is_whitespace() || ch == '.' {
-            out.push('-');
+    inner.trim_jobs(state.max_jobs);
+    state.prune_store_pools(&inner.jobs);
+}
+
+impl From<DuplicateGroup> for MatchGroupDto {
+    fn from(group: DuplicateGroup) -> Self {
+        Self {
+            key: format!("{}:{}", group.algorithm.as_str(), hex_encode(&group.hash)),
+            files: group
+                .files
+                .into_iter()
+                .map(|row| FileDto {
+                    id: row.id,
+                    path: row.path.display().to_string(),
+                    size_bytes: row.size_bytes,
+                    file_type: row.file_type,
+                })
+                .collect(),
         }
     }
-    if out.is_empty() {
-        "fileset".to_string()
-    } else {
-        out
-    }
 }
 
-fn default_fileset_dir() -> PathBuf {
-    let base = dirs::data_dir().unwrap_or_else(std::env::temp_dir);
-    let mut path = base;
-    path.push("dupdupninja");
-    path.push("filesets");
-    path
+fn clamp_to(value: u32, range: std::ops::RangeInclusive<u32>) -> u32 {
+    value.clamp(*range.start(), *range.end())
 }
 
-fn render_ui() -> String {
-    let default_dir = default_fileset_dir();
+fn render_ui(token_required: bool, media_notice: Option<&str>) -> String {
+    let default_dir = crate::default_fileset_dir();
     let html = r##"<!doctype html>
 <html>
 <head>
@@ -625,6 +1465,7 @@ fn render_ui() -> String {
     table {{ border-collapse: collapse; width: 100%; margin-top: 12px; background: #fff; }}
     th, td {{ border: 1px solid #e3e3e3; padding: 6px 8px; text-align: left; font-size: 14px; }}
     .muted {{ color: #555; font-size: 13px; }}
+    .notice {{ border: 1px solid #e5c07b; background: #fff8e1; padding: 8px 12px; border-radius: 8px; }}
     .matches {{ margin-top: 20px; }}
     .group {{ border: 1px solid #ddd; padding: 12px; border-radius: 8px; background: #fff; margin-bottom: 12px; }}
     .file {{ display: flex; align-items: center; gap: 12px; margin: 6px 0; }}
@@ -634,6 +1475,7 @@ fn render_ui() -> String {
 <body>
   <h1>dupdupninja</h1>
   <p class="muted">Live scan status + duplicate groups. Screenshots stream directly from the .ddn database.</p>
+  __MEDIA_NOTICE__
   <fieldset>
     <legend>Start scan</legend>
     <form id="scan-form" method="post" action="/scan">
@@ -659,14 +1501,19 @@ fn render_ui() -> String {
       <label>Fileset DB path (optional)</label>
       <input type="text" name="db_path" placeholder="__DEFAULT_DIR__">
       <label><input type="checkbox" name="capture_snapshots" checked> Capture video snapshots</label>
+      <div id="token-row" hidden>
+        <label>Access token</label>
+        <input type="password" id="token-input" autocomplete="off">
+        <p class="muted">This server was started with a token. Starting and cancelling scans requires it; viewing does not.</p>
+      </div>
       <button type="submit">Start scan</button>
     </form>
   </fieldset>
 
-  <h2>Scans</h2>
+  <h2>Scans <button type="button" id="cancel-all" hidden>Cancel all</button></h2>
   <table id="jobs-table">
     <thead>
-      <tr><th>ID</th><th>Status</th><th>Root</th><th>DB</th><th>Progress</th><th>Actions</th></tr>
+      <tr><th>ID</th><th>Status</th><th>Root</th><th>DB</th><th>Progress</th><th>Duplicates</th><th>Actions</th></tr>
     </thead>
     <tbody></tbody>
   </table>
@@ -677,7 +1524,33 @@ fn render_ui() -> String {
   </div>
 
 <script>
+const tokenRequired = __TOKEN_REQUIRED__;
+const tokenInput = document.querySelector('#token-input');
+if (tokenRequired) {
+  document.querySelector('#token-row').hidden = false;
+  tokenInput.value = localStorage.getItem('dupdupninja-token') || '';
+  tokenInput.addEventListener('change', () => localStorage.setItem('dupdupninja-token', tokenInput.value));
+}
+
+function authHeaders(headers = {}) {
+  if (tokenRequired && tokenInput.value) {
+    headers['Authorization'] = `Bearer ${tokenInput.value}`;
+  }
+  return headers;
+}
+
+async function reportAuthFailure(res) {
+  if (res.status === 401) {
+    alert('Access token missing or invalid.');
+  }
+}
+
 const jobsTable = document.querySelector('#jobs-table tbody');
+const cancelAllButton = document.querySelector('#cancel-all');
+cancelAllButton.addEventListener('click', async () => {
+  const res = await fetch('/api/cancel-all', { method: 'POST', headers: authHeaders() });
+  await reportAuthFailure(res);
+});
 const matchesContainer = document.querySelector('#matches');
 let latestFilesetId = null;
 
@@ -687,7 +1560,39 @@ async function loadJobs() {
   renderJobs(jobs);
 }
 
+function formatBytes(bytes) {
+  const units = ['B', 'KB', 'MB', 'GB', 'TB'];
+  let value = bytes;
+  let unit = 0;
+  while (value >= 1024 && unit < units.length - 1) {
+    value /= 1024;
+    unit += 1;
+  }
+  return unit === 0 ? `${value} B` : `${value.toFixed(1)} ${units[unit]}`;
+}
+
+function formatEta(secs) {
+  const minutes = Math.round(secs / 60);
+  if (minutes < 1) return 'less than a minute remaining';
+  if (minutes === 1) return 'about 1 minute remaining';
+  if (minutes < 60) return `about ${minutes} minutes remaining`;
+  return `about ${Math.floor(minutes / 60)} h ${minutes % 60} min remaining`;
+}
+
+function progressLabel(job) {
+  const progress = job.progress;
+  if (!progress) return job.status === 'running' ? 'counting files…' : '-';
+  let count = progress.total_files
+    ? `${Math.min(100, Math.floor(progress.files_seen * 100 / progress.total_files))}% (${progress.files_seen}/${progress.total_files})`
+    : `${progress.files_seen} files`;
+  if (progress.eta_secs != null) count += `, ${formatEta(progress.eta_secs)}`;
+  return progress.current_path
+    ? `${count} (${progress.current_step || 'scan'}: ${progress.current_path})`
+    : count;
+}
+
 function renderJobs(jobs) {
+  cancelAllButton.hidden = !jobs.some(job => job.status === 'running' || job.status === 'pending');
   jobsTable.innerHTML = '';
   for (const job of jobs) {
     const row = document.createElement('tr');
@@ -696,34 +1601,64 @@ function renderJobs(jobs) {
       <td>${job.status}${job.error ? `: ${job.error}` : ''}</td>
       <td>${job.root}</td>
       <td>${job.db_path}</td>
-      <td>${job.progress ? `${job.progress.files_seen}/${job.progress.total_files} (${job.progress.current_step || 'scan'}: ${job.progress.current_path})` : '-'}</td>
-      <td>${job.status === 'running' ? `<button data-cancel="${job.id}">Cancel</button>` : '-'}</td>
+      <td>${progressLabel(job)}${job.warning_count
+        ? ` <span title="${job.warnings.map(w => `${w.path}: ${w.reason}`).join('\n')}">${job.warning_count} unreadable</span>`
+        : ''}</td>
+      <td>${job.duplicate_groups ? `${job.duplicate_groups} group(s), ${formatBytes(job.reclaimable_bytes)} reclaimable` : '-'}</td>
+      <td>${job.status === 'running' || job.status === 'pending'
+        ? `<button data-cancel="${job.id}">Cancel</button>`
+        : job.status === 'completed'
+          ? `<a href="/api/filesets/${job.id}/export?format=csv">CSV</a> <a href="/api/filesets/${job.id}/export?format=json">JSON</a>`
+          : '-'}</td>
     `;
     jobsTable.appendChild(row);
   }
 
   jobsTable.querySelectorAll('button[data-cancel]').forEach(btn => {
     btn.addEventListener('click', async () => {
-      await fetch(`/cancel/${btn.dataset.cancel}`, { method: 'POST' });
+      const res = await fetch(`/cancel/${btn.dataset.cancel}`, { method: 'POST', headers: authHeaders() });
+      await reportAuthFailure(res);
     });
   });
 }
 
-async function loadMatches(filesetId) {
+const MATCHES_PAGE_SIZE = 50;
+let matchesLoaded = 0;
+
+async function loadMatches(filesetId, append = false) {
   if (!filesetId) return;
-  const res = await fetch(`/api/filesets/${filesetId}/matches`);
+  const offset = append ? matchesLoaded : 0;
+  const res = await fetch(`/api/filesets/${filesetId}/matches?limit=${MATCHES_PAGE_SIZE}&offset=${offset}`);
   if (!res.ok) return;
   const data = await res.json();
-  renderMatches(data.groups, filesetId);
+  if (!append) {
+    matchesContainer.innerHTML = '';
+    matchesLoaded = 0;
+    renderScanConfig(data.scan_config);
+  }
+  renderMatches(data, filesetId);
+}
+
+function renderScanConfig(config) {
+  if (!config) return;
+  const details = document.createElement('details');
+  const summary = document.createElement('summary');
+  summary.textContent = 'Scan settings';
+  const pre = document.createElement('pre');
+  pre.textContent = JSON.stringify(config, null, 2);
+  details.append(summary, pre);
+  matchesContainer.appendChild(details);
 }
 
-function renderMatches(groups, filesetId) {
-  matchesContainer.innerHTML = '';
-  if (!groups.length) {
-    matchesContainer.textContent = 'No duplicates yet.';
+function renderMatches(data, filesetId) {
+  matchesContainer.querySelector('.load-more')?.remove();
+  if (!data.groups.length && matchesLoaded === 0) {
+    const empty = document.createElement('div');
+    empty.textContent = 'No duplicates yet.';
+    matchesContainer.appendChild(empty);
     return;
   }
-  for (const group of groups) {
+  for (const group of data.groups) {
     const wrap = document.createElement('div');
     wrap.className = 'group';
     wrap.innerHTML = `<div class="muted">${group.key}</div>`;
@@ -735,18 +1670,43 @@ function renderMatches(groups, filesetId) {
       img.onerror = () => { img.remove(); };
       const meta = document.createElement('div');
       meta.innerHTML = `<div>${file.path}</div><div class="muted">${file.size_bytes} bytes</div>`;
+      const actions = document.createElement('div');
+      for (const action of ['trash', 'delete']) {
+        const btn = document.createElement('button');
+        btn.textContent = action === 'trash' ? 'Move to trash' : 'Delete';
+        btn.addEventListener('click', () => fileAction(filesetId, file, action));
+        actions.appendChild(btn);
+      }
       fileRow.appendChild(img);
       fileRow.appendChild(meta);
+      fileRow.appendChild(actions);
       wrap.appendChild(fileRow);
     }
     matchesContainer.appendChild(wrap);
   }
+  matchesLoaded += data.groups.length;
+  if (data.has_more) {
+    const more = document.createElement('button');
+    more.className = 'load-more';
+    more.textContent = `Load more (${matchesLoaded} of ${data.total_groups} groups)`;
+    more.addEventListener('click', () => loadMatches(filesetId, true));
+    matchesContainer.appendChild(more);
+  }
+}
+
+async function fileAction(filesetId, file, action) {
+  const verb = action === 'trash' ? 'Move to trash' : 'Permanently delete';
+  if (!confirm(`${verb} ${file.path}?`)) return;
+  const res = await fetch(`/api/filesets/${filesetId}/files/${file.id}/${action}`, { method: 'POST', headers: authHeaders() });
+  await reportAuthFailure(res);
+  if (!res.ok && res.status !== 401) {
+    alert(await res.text());
+  }
 }
 
-const source = new EventSource('/events');
-source.onmessage = (event) => {
+function handleServerEvent(data) {
   try {
-    const payload = JSON.parse(event.data);
+    const payload = JSON.parse(data);
     if (payload.type === 'scan_done' || payload.type === 'matches_updated') {
       latestFilesetId = payload.id;
       loadMatches(latestFilesetId);
@@ -758,14 +1718,44 @@ source.onmessage = (event) => {
   } catch (err) {
     console.error(err);
   }
-};
+}
+
+function connectEventSource() {
+  const source = new EventSource('/events');
+  source.onmessage = (event) => handleServerEvent(event.data);
+}
+
+// Prefer WebSocket: some proxies buffer SSE. Fall back if it never opens.
+function connectEvents() {
+  if (!('WebSocket' in window)) {
+    connectEventSource();
+    return;
+  }
+  const scheme = location.protocol === 'https:' ? 'wss' : 'ws';
+  const socket = new WebSocket(`${scheme}://${location.host}/ws`);
+  let opened = false;
+  socket.onopen = () => { opened = true; loadJobs(); };
+  socket.onmessage = (event) => handleServerEvent(event.data);
+  socket.onclose = () => {
+    if (opened) {
+      setTimeout(connectEvents, 2000);
+    } else {
+      connectEventSource();
+    }
+  };
+}
+
+connectEvents();
 
 const form = document.querySelector('#scan-form');
 form.addEventListener('submit', async (e) => {
   e.preventDefault();
   const data = new URLSearchParams(new FormData(form));
-  await fetch('/scan', { method: 'POST', body: data, headers: { 'Content-Type': 'application/x-www-form-urlencoded' }});
-  form.reset();
+  const res = await fetch('/scan', { method: 'POST', body: data, headers: authHeaders({ 'Content-Type': 'application/x-www-form-urlencoded' })});
+  await reportAuthFailure(res);
+  if (res.ok) {
+    form.reset();
+  }
 });
 
 loadJobs();
@@ -773,6 +1763,16 @@ loadJobs();
 </body>
 </html>"##;
     html.replace("__DEFAULT_DIR__", &default_dir.display().to_string())
+        .replace(
+            "__TOKEN_REQUIRED__",
+            if token_required { "true" } else { "false" },
+        )
+        .replace(
+            "__MEDIA_NOTICE__",
+            &media_notice
+                .map(|notice| format!(r#"<p class="notice">{notice}</p>"#))
+                .unwrap_or_default(),
+        )
 }
 
 fn redirect_home() -> axum::response::Response {
@@ -782,3 +1782,171 @@ fn redirect_home() -> axum::response::Response {
     )
         .into_response()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+    use dupdupninja_core::models::{FileSnapshotRecord, MediaFileRecord, MediaInfo};
+
+    fn if_none_match(value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, HeaderValue::from_static(value));
+        headers
+    }
+
+    #[test]
+    fn mutating_requests_must_come_from_this_host() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::HOST, HeaderValue::from_static("127.0.0.1:8080"));
+        assert!(same_origin_request(&headers));
+        headers.insert(
+            header::ORIGIN,
+            HeaderValue::from_static("http://127.0.0.1:8080"),
+        );
+        headers.insert("sec-fetch-site", HeaderValue::from_static("same-origin"));
+        assert!(same_origin_request(&headers));
+        headers.insert("sec-fetch-site", HeaderValue::from_static("cross-site"));
+        assert!(!same_origin_request(&headers));
+        headers.remove("sec-fetch-site");
+        headers.insert(
+            header::ORIGIN,
+            HeaderValue::from_static("https://evil.example"),
+        );
+        assert!(!same_origin_request(&headers));
+    }
+
+    #[test]
+    fn if_none_match_accepts_exact_weak_listed_and_wildcard_tags() {
+        let etag = "\"abc\"";
+        assert!(if_none_match_hits(&if_none_match("\"abc\""), etag));
+        assert!(if_none_match_hits(&if_none_match("W/\"abc\""), etag));
+        assert!(if_none_match_hits(&if_none_match("\"x\", W/\"abc\""), etag));
+        assert!(if_none_match_hits(&if_none_match("*"), etag));
+        assert!(!if_none_match_hits(&if_none_match("\"abd\""), etag));
+        assert!(!if_none_match_hits(&HeaderMap::new(), etag));
+    }
+
+    /// A state with one completed job whose fileset holds a single snapshot.
+    fn state_with_snapshot(db_path: &std::path::Path) -> (Arc<AppState>, i64) {
+        let store = SqliteScanStore::open(db_path).unwrap();
+        let file_id = store
+            .upsert_file(&MediaFileRecord {
+                file_id: None,
+                path: PathBuf::from("clip.mp4"),
+                size_bytes: 10,
+                modified_at: None,
+                blake3: None,
+                sha256: None,
+                ahash: None,
+                dhash: None,
+                phash: None,
+                ffmpeg_metadata: None,
+                file_type: None,
+                media: MediaInfo::default(),
+            })
+            .unwrap();
+        store
+            .replace_file_snapshots(
+                file_id,
+                &[FileSnapshotRecord {
+                    snapshot_index: 0,
+                    snapshot_count: 1,
+                    at_ms: 0,
+                    duration_ms: None,
+                    ahash: None,
+                    dhash: None,
+                    phash: None,
+                    mime: "image/png".to_string(),
+                    image_avif: vec![1, 2, 3],
+                }],
+            )
+            .unwrap();
+
+        (state_with_job(db_path, JobStatus::Completed), file_id)
+    }
+
+    /// A state holding job 1 on `db_path`.
+    fn state_with_job(db_path: &std::path::Path, status: JobStatus) -> Arc<AppState> {
+        let mut inner = InnerState::new();
+        inner.jobs.push(ScanJob {
+            id: 1,
+            root: PathBuf::new(),
+            db_path: db_path.to_path_buf(),
+            status,
+            progress: None,
+            recent_warnings: VecDeque::new(),
+            warning_count: 0,
+            duplicate_groups: 0,
+            reclaimable_bytes: 0,
+            stats_refreshed_at: None,
+            started_at: Instant::now(),
+            finished_at: None,
+            error: None,
+            cancel: ScanCancelToken::default(),
+        });
+        Arc::new(AppState {
+            inner: Mutex::new(inner),
+            events_tx: broadcast::channel(1).0,
+            token: None,
+            scan_tasks: std::sync::Mutex::new(Vec::new()),
+            shutdown: watch::channel(false).0,
+            max_jobs: 0,
+            max_concurrent_scans: 0,
+            store_pools: std::sync::Mutex::new(HashMap::new()),
+            media_notice: None,
+        })
+    }
+
+    #[test]
+    fn status_changes_wait_for_a_busy_state() {
+        let state = state_with_job(std::path::Path::new("unused.ddn"), JobStatus::Running);
+        let guard = state.inner.blocking_lock();
+        let finisher = {
+            let state = Arc::clone(&state);
+            std::thread::spawn(move || {
+                set_job_status(&state, 1, |job| job.status = JobStatus::Completed);
+            })
+        };
+        std::thread::sleep(Duration::from_millis(50));
+        drop(guard);
+        finisher.join().unwrap();
+        assert_eq!(
+            state.inner.blocking_lock().jobs[0].status,
+            JobStatus::Completed
+        );
+    }
+
+    #[tokio::test]
+    async fn repeated_snapshot_request_with_its_etag_is_not_modified() {
+        let db_path = std::env::temp_dir().join(format!(
+            "dupdupninja-web-snapshot-{}.ddn",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&db_path);
+        let (state, file_id) = state_with_snapshot(&db_path);
+
+        let first = snapshot_handler(
+            State(Arc::clone(&state)),
+            Path((1, file_id, 0)),
+            HeaderMap::new(),
+        )
+        .await
+        .into_response();
+        assert_eq!(first.status(), StatusCode::OK);
+        let etag = first.headers()[header::ETAG].clone();
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, etag.clone());
+        let second = snapshot_handler(State(state), Path((1, file_id, 0)), headers)
+            .await
+            .into_response();
+        assert_eq!(second.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(second.headers()[header::ETAG], etag);
+        assert_eq!(
+            second.headers()[header::CACHE_CONTROL],
+            SNAPSHOT_CACHE_CONTROL
+        );
+        let _ = std::fs::remove_file(&db_path);
+    }
+}
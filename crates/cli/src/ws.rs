@@ -0,0 +1,379 @@
+//! Minimal server-side WebSocket (RFC 6455) support for the web UI's event feed.
+//!
+//! Only what `/ws` needs: the upgrade handshake, unfragmented text frames from the
+//! server, and ping/close handling for frames sent by the client.
+
+use axum::http::{header, HeaderMap};
+use base64::Engine;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+const HANDSHAKE_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+/// Clients only send control frames to this endpoint; anything larger is a protocol abuse.
+const MAX_CLIENT_PAYLOAD: u64 = 64 * 1024;
+/// RFC 6455 caps control frame payloads so they fit the short length form.
+const MAX_CONTROL_PAYLOAD: u64 = 125;
+
+const FIN: u8 = 0x80;
+const RSV_BITS: u8 = 0x70;
+
+const OP_CONTINUATION: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_BINARY: u8 = 0x2;
+const OP_CLOSE: u8 = 0x8;
+const OP_PING: u8 = 0x9;
+const OP_PONG: u8 = 0xA;
+
+/// Returns the `Sec-WebSocket-Accept` value when `headers` form a valid upgrade request.
+pub fn accept_key(headers: &HeaderMap) -> Option<String> {
+    let has_token = |name: header::HeaderName, token: &str| {
+        headers
+            .get_all(name)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|value| value.trim().eq_ignore_ascii_case(token))
+    };
+    if !has_token(header::UPGRADE, "websocket") || !has_token(header::CONNECTION, "upgrade") {
+        return None;
+    }
+    if headers.get(header::SEC_WEBSOCKET_VERSION)?.as_bytes() != b"13" {
+        return None;
+    }
+    let key = headers
+        .get(header::SEC_WEBSOCKET_KEY)?
+        .to_str()
+        .ok()?
+        .trim();
+    let digest = sha1(format!("{key}{HANDSHAKE_GUID}").as_bytes());
+    Some(base64::engine::general_purpose::STANDARD.encode(digest))
+}
+
+/// Browsers let any page open a WebSocket to any host, so an upgrade carrying an
+/// `Origin` is only accepted from a page served by this host. Clients outside a
+/// browser send no `Origin` and are let through, as they are for `/events`.
+pub fn origin_allowed(headers: &HeaderMap) -> bool {
+    let Some(origin) = headers.get(header::ORIGIN) else {
+        return true;
+    };
+    let Some(host) = headers
+        .get(header::HOST)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return false;
+    };
+    let Some(origin_host) = origin.to_str().ok().and_then(|origin| {
+        origin
+            .strip_prefix("http://")
+            .or_else(|| origin.strip_prefix("https://"))
+    }) else {
+        return false;
+    };
+    origin_host
+        .trim_end_matches('/')
+        .eq_ignore_ascii_case(host.trim())
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ClientFrame {
+    Ping(Vec<u8>),
+    Close,
+    /// Data and pong frames; the event feed ignores them.
+    Other,
+}
+
+pub async fn write_text<W: AsyncWrite + Unpin>(writer: &mut W, text: &str) -> std::io::Result<()> {
+    write_frame(writer, OP_TEXT, text.as_bytes()).await
+}
+
+pub async fn write_pong<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    payload: &[u8],
+) -> std::io::Result<()> {
+    write_frame(writer, OP_PONG, payload).await
+}
+
+pub async fn write_close<W: AsyncWrite + Unpin>(writer: &mut W) -> std::io::Result<()> {
+    write_frame(writer, OP_CLOSE, &[]).await
+}
+
+async fn write_frame<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    opcode: u8,
+    payload: &[u8],
+) -> std::io::Result<()> {
+    let mut frame = Vec::with_capacity(payload.len() + 10);
+    frame.push(0x80 | opcode);
+    match payload.len() {
+        len if len < 126 => frame.push(len as u8),
+        len if len <= u16::MAX as usize => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    writer.write_all(&frame).await?;
+    writer.flush().await
+}
+
+/// Reads one client frame. Errors on malformed, unmasked or oversized frames, on
+/// reserved bits (no extension is negotiated), and on fragmented or long control frames.
+pub async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R) -> std::io::Result<ClientFrame> {
+    let mut head = [0u8; 2];
+    reader.read_exact(&mut head).await?;
+    let opcode = head[0] & 0x0F;
+    if head[0] & RSV_BITS != 0 {
+        return Err(protocol_error("reserved bits set"));
+    }
+    if head[1] & 0x80 == 0 {
+        return Err(protocol_error("client frames must be masked"));
+    }
+    let len = match head[1] & 0x7F {
+        126 => {
+            let mut ext = [0u8; 2];
+            reader.read_exact(&mut ext).await?;
+            u64::from(u16::from_be_bytes(ext))
+        }
+        127 => {
+            let mut ext = [0u8; 8];
+            reader.read_exact(&mut ext).await?;
+            u64::from_be_bytes(ext)
+        }
+        len => u64::from(len),
+    };
+    if len > MAX_CLIENT_PAYLOAD {
+        return Err(protocol_error("client frame too large"));
+    }
+    let is_control = opcode & 0x08 != 0;
+    if is_control && (head[0] & FIN == 0 || len > MAX_CONTROL_PAYLOAD) {
+        return Err(protocol_error(
+            "control frames must be final and at most 125 bytes",
+        ));
+    }
+    let mut mask = [0u8; 4];
+    reader.read_exact(&mut mask).await?;
+    let mut payload = vec![0u8; len as usize];
+    reader.read_exact(&mut payload).await?;
+    for (idx, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[idx % 4];
+    }
+
+    match opcode {
+        OP_PING => Ok(ClientFrame::Ping(payload)),
+        OP_CLOSE => Ok(ClientFrame::Close),
+        OP_CONTINUATION | OP_TEXT | OP_BINARY | OP_PONG => Ok(ClientFrame::Other),
+        _ => Err(protocol_error("unknown opcode")),
+    }
+}
+
+fn protocol_error(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// SHA-1 is only used for the handshake's fixed-format accept key, as the RFC requires.
+fn sha1(input: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [
+        0x6745_2301,
+        0xEFCD_AB89,
+        0x98BA_DCFE,
+        0x1032_5476,
+        0xC3D2_E1F0,
+    ];
+    let mut message = input.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((input.len() as u64) * 8).to_be_bytes());
+
+    for chunk in message.chunks_exact(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in chunk.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A82_7999),
+                20..=39 => (b ^ c ^ d, 0x6ED9_EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1B_BCDC),
+                _ => (b ^ c ^ d, 0xCA62_C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (state, value) in h.iter_mut().zip([a, b, c, d, e]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    let mut out = [0u8; 20];
+    for (chunk, word) in out.chunks_exact_mut(4).zip(h) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn upgrade_headers(key: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::UPGRADE, HeaderValue::from_static("websocket"));
+        headers.insert(
+            header::CONNECTION,
+            HeaderValue::from_static("keep-alive, Upgrade"),
+        );
+        headers.insert(
+            header::SEC_WEBSOCKET_VERSION,
+            HeaderValue::from_static("13"),
+        );
+        headers.insert(
+            header::SEC_WEBSOCKET_KEY,
+            HeaderValue::from_str(key).unwrap(),
+        );
+        headers
+    }
+
+    /// A client frame with the given header byte, payload and mask.
+    fn client_frame(head: u8, payload: &[u8], mask: [u8; 4]) -> Vec<u8> {
+        let mut frame = vec![head];
+        match payload.len() {
+            len if len < 126 => frame.push(0x80 | len as u8),
+            len if len <= u16::MAX as usize => {
+                frame.push(0x80 | 126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                frame.push(0x80 | 127);
+                frame.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+        frame.extend_from_slice(&mask);
+        frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+        frame
+    }
+
+    #[test]
+    fn accept_key_matches_rfc_sample() {
+        let headers = upgrade_headers("dGhlIHNhbXBsZSBub25jZQ==");
+        assert_eq!(
+            accept_key(&headers).as_deref(),
+            Some("s3pPLMBiTxaQ9kYGzzhZRbK+xOo=")
+        );
+    }
+
+    #[test]
+    fn accept_key_rejects_non_upgrades() {
+        let mut headers = upgrade_headers("dGhlIHNhbXBsZSBub25jZQ==");
+        headers.insert(header::SEC_WEBSOCKET_VERSION, HeaderValue::from_static("8"));
+        assert_eq!(accept_key(&headers), None);
+        headers.remove(header::SEC_WEBSOCKET_VERSION);
+        assert_eq!(accept_key(&headers), None);
+    }
+
+    #[test]
+    fn sha1_matches_known_digests() {
+        let hex = |digest: [u8; 20]| {
+            digest
+                .iter()
+                .map(|b| format!("{b:02x}"))
+                .collect::<String>()
+        };
+        assert_eq!(hex(sha1(b"")), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        assert_eq!(
+            hex(sha1(&[b'a'; 1000])),
+            "291e9a6c66994949b57ba5e650361e98fc36b1ba"
+        );
+    }
+
+    #[test]
+    fn origin_must_match_host() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::HOST, HeaderValue::from_static("127.0.0.1:8080"));
+        assert!(origin_allowed(&headers));
+        headers.insert(
+            header::ORIGIN,
+            HeaderValue::from_static("http://127.0.0.1:8080"),
+        );
+        assert!(origin_allowed(&headers));
+        headers.insert(
+            header::ORIGIN,
+            HeaderValue::from_static("https://evil.example"),
+        );
+        assert!(!origin_allowed(&headers));
+        headers.insert(header::ORIGIN, HeaderValue::from_static("null"));
+        assert!(!origin_allowed(&headers));
+    }
+
+    #[tokio::test]
+    async fn read_frame_unmasks_client_frames() {
+        let frame = client_frame(0x80 | OP_PING, b"hello", [0x37, 0xfa, 0x21, 0x3d]);
+        let read = read_frame(&mut frame.as_slice()).await.unwrap();
+        assert_eq!(read, ClientFrame::Ping(b"hello".to_vec()));
+
+        let payload = vec![7u8; MAX_CONTROL_PAYLOAD as usize];
+        let frame = client_frame(0x80 | OP_PING, &payload, [1, 2, 3, 4]);
+        let read = read_frame(&mut frame.as_slice()).await.unwrap();
+        assert_eq!(read, ClientFrame::Ping(payload));
+
+        let frame = client_frame(0x80 | OP_CLOSE, &[], [9, 9, 9, 9]);
+        assert_eq!(
+            read_frame(&mut frame.as_slice()).await.unwrap(),
+            ClientFrame::Close
+        );
+    }
+
+    #[tokio::test]
+    async fn read_frame_rejects_unmasked_and_oversized_frames() {
+        let unmasked = [0x80 | OP_TEXT, 2, b'h', b'i'];
+        let err = read_frame(&mut unmasked.as_slice()).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        let mut oversized = vec![0x80 | OP_BINARY, 0x80 | 127];
+        oversized.extend_from_slice(&(MAX_CLIENT_PAYLOAD + 1).to_be_bytes());
+        let err = read_frame(&mut oversized.as_slice()).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn read_frame_enforces_control_frame_rules() {
+        let cases = [
+            client_frame(0x80 | OP_PING, &[7u8; 126], [1, 2, 3, 4]),
+            client_frame(0x80 | OP_PING, &[7u8; 64 * 1024], [1, 2, 3, 4]),
+            client_frame(0x80 | OP_CLOSE, &[0u8; 126], [1, 2, 3, 4]),
+            client_frame(OP_PING, b"part", [1, 2, 3, 4]),
+            client_frame(OP_CLOSE, &[], [1, 2, 3, 4]),
+        ];
+        for frame in cases {
+            let err = read_frame(&mut frame.as_slice()).await.unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[tokio::test]
+    async fn read_frame_rejects_reserved_bits() {
+        for rsv in [0x40, 0x20, 0x10] {
+            let frame = client_frame(0x80 | rsv | OP_TEXT, b"hi", [1, 2, 3, 4]);
+            let err = read_frame(&mut frame.as_slice()).await.unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        }
+    }
+}
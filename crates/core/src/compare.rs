@@ -0,0 +1,52 @@
+use std::collections::HashSet;
+
+use crate::models::FileListRow;
+
+/// Whether removing the files in `selected` (by id) would leave no member of `group`,
+/// a set of identical files. Guards trash and delete actions so at least one copy of
+/// the content survives.
+pub fn would_orphan(selected: &HashSet<i64>, group: &[FileListRow]) -> bool {
+    !group.is_empty() && group.iter().all(|file| selected.contains(&file.id))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn row(id: i64) -> FileListRow {
+        FileListRow {
+            id,
+            path: PathBuf::from(format!("file{id}")),
+            size_bytes: 10,
+            modified_at: None,
+            blake3: Some([1; 32]),
+            sha256: None,
+            ahash: None,
+            dhash: None,
+            phash: None,
+            ffmpeg_metadata: None,
+            file_type: None,
+        }
+    }
+
+    #[test]
+    fn empty_group_is_never_orphaned() {
+        assert!(!would_orphan(&HashSet::from([1, 2]), &[]));
+    }
+
+    #[test]
+    fn fully_selected_group_is_orphaned() {
+        let group = [row(1), row(2), row(3)];
+        assert!(would_orphan(&HashSet::from([1, 2, 3]), &group));
+        assert!(would_orphan(&HashSet::from([1, 2, 3, 4]), &group));
+    }
+
+    #[test]
+    fn partially_selected_group_keeps_a_copy() {
+        let group = [row(1), row(2), row(3)];
+        assert!(!would_orphan(&HashSet::from([1, 2]), &group));
+        assert!(!would_orphan(&HashSet::new(), &group));
+    }
+}
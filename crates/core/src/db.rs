@@ -1,12 +1,97 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use rusqlite::{params, Connection, OptionalExtension};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
 
-use crate::error::Result;
+use crate::error::{Error, Result};
 use crate::models::{
-    DriveMetadata, FileListRow, FileSnapshotRecord, FilesetMetadata, MediaFileRecord, ScanRootKind,
+    ChunkFingerprint, ChunkOverlap, DriveMetadata, DuplicateGroup, FileListRow, FileListSort,
+    FileSnapshotRecord, FileTypeFilter, FileTypeStats, FilesetInfo, FilesetMetadata, HashAlgorithm,
+    MediaFileRecord, MediaInfo, MergeReport, NearDuplicateGroup, ScanCursor, ScanRootKind,
+    SnapshotStorage,
 };
+use crate::store::ScanStore;
+
+/// `hash_counts` keeps one row per content hash, blake3 when present and otherwise
+/// sha256, so duplicate summaries read groups instead of grouping every file. Empty
+/// files are never counted, since every one of them hashes alike. Triggers keep it in
+/// step with `files`; `ensure_hash_counts` backfills filesets from before it existed.
+const HASH_COUNTS_SQL: &str = r#"
+    CREATE TABLE IF NOT EXISTS hash_counts (
+      by_sha256 INTEGER NOT NULL,
+      hash BLOB NOT NULL,
+      count INTEGER NOT NULL,
+      total_bytes INTEGER NOT NULL,
+      PRIMARY KEY (by_sha256, hash)
+    ) WITHOUT ROWID;
+
+    DELETE FROM hash_counts;
+    INSERT INTO hash_counts (by_sha256, hash, count, total_bytes)
+    SELECT blake3 IS NULL, COALESCE(blake3, sha256), COUNT(*), SUM(size_bytes)
+    FROM files
+    WHERE size_bytes > 0 AND COALESCE(blake3, sha256) IS NOT NULL
+    GROUP BY 1, 2;
+
+    CREATE TRIGGER IF NOT EXISTS files_hash_counts_insert AFTER INSERT ON files
+    WHEN NEW.size_bytes > 0 AND COALESCE(NEW.blake3, NEW.sha256) IS NOT NULL
+    BEGIN
+      INSERT INTO hash_counts (by_sha256, hash, count, total_bytes)
+      VALUES (NEW.blake3 IS NULL, COALESCE(NEW.blake3, NEW.sha256), 1, NEW.size_bytes)
+      ON CONFLICT (by_sha256, hash) DO UPDATE SET
+        count = count + 1,
+        total_bytes = total_bytes + excluded.total_bytes;
+    END;
+
+    CREATE TRIGGER IF NOT EXISTS files_hash_counts_delete AFTER DELETE ON files
+    WHEN OLD.size_bytes > 0 AND COALESCE(OLD.blake3, OLD.sha256) IS NOT NULL
+    BEGIN
+      UPDATE hash_counts SET count = count - 1, total_bytes = total_bytes - OLD.size_bytes
+      WHERE by_sha256 = (OLD.blake3 IS NULL) AND hash = COALESCE(OLD.blake3, OLD.sha256);
+      DELETE FROM hash_counts
+      WHERE by_sha256 = (OLD.blake3 IS NULL) AND hash = COALESCE(OLD.blake3, OLD.sha256)
+        AND count <= 0;
+    END;
+
+    CREATE TRIGGER IF NOT EXISTS files_hash_counts_update_old
+    AFTER UPDATE OF size_bytes, blake3, sha256 ON files
+    WHEN OLD.size_bytes > 0 AND COALESCE(OLD.blake3, OLD.sha256) IS NOT NULL
+    BEGIN
+      UPDATE hash_counts SET count = count - 1, total_bytes = total_bytes - OLD.size_bytes
+      WHERE by_sha256 = (OLD.blake3 IS NULL) AND hash = COALESCE(OLD.blake3, OLD.sha256);
+      DELETE FROM hash_counts
+      WHERE by_sha256 = (OLD.blake3 IS NULL) AND hash = COALESCE(OLD.blake3, OLD.sha256)
+        AND count <= 0;
+    END;
+
+    CREATE TRIGGER IF NOT EXISTS files_hash_counts_update_new
+    AFTER UPDATE OF size_bytes, blake3, sha256 ON files
+    WHEN NEW.size_bytes > 0 AND COALESCE(NEW.blake3, NEW.sha256) IS NOT NULL
+    BEGIN
+      INSERT INTO hash_counts (by_sha256, hash, count, total_bytes)
+      VALUES (NEW.blake3 IS NULL, COALESCE(NEW.blake3, NEW.sha256), 1, NEW.size_bytes)
+      ON CONFLICT (by_sha256, hash) DO UPDATE SET
+        count = count + 1,
+        total_bytes = total_bytes + excluded.total_bytes;
+    END;
+"#;
+
+/// `PRAGMA application_id` of every fileset, "DDNF" in ASCII, so tools like `file`
+/// can tell a `.ddn` from any other SQLite database.
+pub const FILESET_APPLICATION_ID: i32 = 0x4444_4e46;
+
+/// `PRAGMA user_version` this build writes. Bumped when a schema change would
+/// break older builds; filesets from before the marker read as version 0.
+pub const FILESET_FORMAT_VERSION: u32 = 1;
+
+/// How many files share `f1`'s content, itself included: by blake3, by sha256 for
+/// files without one, and 1 for unhashed files.
+const COPY_COUNT_SQL: &str = r#"CASE
+    WHEN f1.blake3 IS NOT NULL THEN (SELECT COUNT(*) FROM files f2 WHERE f2.blake3 = f1.blake3)
+    WHEN f1.sha256 IS NOT NULL THEN (SELECT COUNT(*) FROM files f2 WHERE f2.blake3 IS NULL AND f2.sha256 = f1.sha256)
+    ELSE 1
+  END"#;
 
 pub struct SqliteScanStore {
     conn: Connection,
@@ -14,12 +99,52 @@ pub struct SqliteScanStore {
 }
 
 impl SqliteScanStore {
+    /// Opens the fileset at `path`, creating it when the file is missing or empty.
+    /// Fails with `Error::NotAFileset` for other files, SQLite or not.
     pub fn open(path: &Path) -> Result<Self> {
         let conn = Connection::open(path)?;
+        fileset_format(&conn, path)?;
+        let store = Self {
+            conn,
+            has_file_id: false,
+        };
+        store.init_schema()?;
+        let has_file_id = store.files_table_has_id()?;
+        Ok(Self {
+            conn: store.conn,
+            has_file_id,
+        })
+    }
+
+    /// Opens an existing fileset without creating, migrating or marking it, for reads
+    /// that must leave the file as it is. Queries needing columns an older fileset
+    /// lacks fail with `Error::Sqlite`.
+    pub fn open_read_only(path: &Path) -> Result<Self> {
+        let conn = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?;
+        if fileset_format(&conn, path)?.is_none() {
+            return Err(Error::NotAFileset(format!("{} is empty", path.display())));
+        }
         let store = Self {
             conn,
             has_file_id: false,
         };
+        let has_file_id = store.files_table_has_id()?;
+        Ok(Self {
+            conn: store.conn,
+            has_file_id,
+        })
+    }
+
+    /// A store that lives only as long as this handle, for reading against a fileset
+    /// that does not exist yet.
+    pub fn open_in_memory() -> Result<Self> {
+        let store = Self {
+            conn: Connection::open_in_memory()?,
+            has_file_id: false,
+        };
         store.init_schema()?;
         let has_file_id = store.files_table_has_id()?;
         Ok(Self {
@@ -28,9 +153,47 @@ impl SqliteScanStore {
         })
     }
 
+    /// Checks that `path` is a fileset this build can open, without creating or
+    /// changing it.
+    pub fn probe(path: &Path) -> Result<FilesetInfo> {
+        let conn = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?;
+        match fileset_format(&conn, path)? {
+            Some(format_version) => Ok(FilesetInfo { format_version }),
+            None => Err(Error::NotAFileset(format!("{} is empty", path.display()))),
+        }
+    }
+
+    /// A cloneable handle sharing up to `max_conns` connections to `path` across threads.
+    /// Opens one connection up front so schema and open errors surface here.
+    pub fn open_pool(path: &Path, max_conns: usize) -> Result<SqliteStorePool> {
+        let first = Self::open_pooled(path)?;
+        Ok(SqliteStorePool {
+            inner: Arc::new(PoolInner {
+                path: path.to_path_buf(),
+                max_conns: max_conns.max(1),
+                slots: Mutex::new(PoolSlots {
+                    idle: vec![first],
+                    open: 1,
+                }),
+                returned: Condvar::new(),
+            }),
+        })
+    }
+
+    fn open_pooled(path: &Path) -> Result<Self> {
+        let store = Self::open(path)?;
+        // Pooled readers share the file with a scan writer; wait out its lock briefly.
+        store.conn.busy_timeout(POOL_BUSY_TIMEOUT)?;
+        Ok(store)
+    }
+
     fn init_schema(&self) -> Result<()> {
-        self.conn.execute_batch(
-            r#"
+        self.conn
+            .execute_batch(
+                r#"
             PRAGMA foreign_keys = ON;
 
             CREATE TABLE IF NOT EXISTS fileset (
@@ -63,6 +226,12 @@ impl SqliteScanStore {
               phash INTEGER,
               ffmpeg_metadata TEXT,
               file_type TEXT,
+              container TEXT,
+              video_codec TEXT,
+              audio_codec TEXT,
+              width INTEGER,
+              height INTEGER,
+              bit_rate INTEGER,
               UNIQUE(path)
             );
 
@@ -80,15 +249,77 @@ impl SqliteScanStore {
               ahash INTEGER,
               dhash INTEGER,
               phash INTEGER,
+              mime TEXT,
               image_avif BLOB NOT NULL,
+              image_file TEXT,
               PRIMARY KEY (file_id, snapshot_index),
               FOREIGN KEY(file_id) REFERENCES files(id) ON DELETE CASCADE
             ) WITHOUT ROWID;
 
             CREATE INDEX IF NOT EXISTS idx_file_snapshots_file_id ON file_snapshots(file_id);
+
+            CREATE TABLE IF NOT EXISTS file_chunks (
+              file_id INTEGER NOT NULL,
+              chunk_index INTEGER NOT NULL,
+              hash INTEGER NOT NULL,
+              len INTEGER NOT NULL,
+              PRIMARY KEY (file_id, chunk_index),
+              FOREIGN KEY(file_id) REFERENCES files(id) ON DELETE CASCADE
+            ) WITHOUT ROWID;
+
+            CREATE INDEX IF NOT EXISTS idx_file_chunks_hash ON file_chunks(hash);
+
+            CREATE TABLE IF NOT EXISTS scan_cursor (
+              id INTEGER PRIMARY KEY NOT NULL CHECK (id = 1),
+              files_done INTEGER NOT NULL,
+              updated_at_secs INTEGER,
+              last_path TEXT,
+              root_path TEXT,
+              scan_config TEXT
+            );
+
+            CREATE TABLE IF NOT EXISTS ignored_groups (
+              hash BLOB PRIMARY KEY NOT NULL
+            ) WITHOUT ROWID;
             "#,
-        )?;
+            )
+            .map_err(|e| Error::Migration(format!("failed to create tables: {e}")))?;
         self.ensure_hash_columns()?;
+        self.ensure_hash_counts()?;
+        self.mark_fileset_format()?;
+        Ok(())
+    }
+
+    /// Creates `hash_counts` and its triggers, backfilling it from `files`, the first
+    /// time a fileset is opened. Checked outside the write transaction so pooled
+    /// readers opening during a scan do not wait on the writer.
+    fn ensure_hash_counts(&self) -> Result<()> {
+        let has_triggers: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'trigger' AND name LIKE 'files_hash_counts_%'",
+            [],
+            |r| r.get(0),
+        )?;
+        if has_triggers == 4 {
+            return Ok(());
+        }
+        self.conn
+            .execute_batch(&format!("BEGIN IMMEDIATE; {HASH_COUNTS_SQL} COMMIT;"))
+            .map_err(|e| {
+                let _ = self.conn.execute_batch("ROLLBACK");
+                Error::Migration(format!("failed to build duplicate counts: {e}"))
+            })
+    }
+
+    /// Writes the format marker once; pooled readers open alongside a scan writer,
+    /// so an already marked file is left untouched.
+    fn mark_fileset_format(&self) -> Result<()> {
+        let (application_id, user_version) = fileset_marker(&self.conn)?;
+        if application_id != FILESET_APPLICATION_ID || user_version != FILESET_FORMAT_VERSION {
+            self.conn.execute_batch(&format!(
+                "PRAGMA application_id = {FILESET_APPLICATION_ID}; \
+                 PRAGMA user_version = {FILESET_FORMAT_VERSION};"
+            ))?;
+        }
         Ok(())
     }
 
@@ -119,6 +350,18 @@ impl SqliteScanStore {
         self.ensure_column("file_snapshots", "ahash", "INTEGER")?;
         self.ensure_column("file_snapshots", "dhash", "INTEGER")?;
         self.ensure_column("file_snapshots", "phash", "INTEGER")?;
+        self.ensure_column("file_snapshots", "mime", "TEXT")?;
+        self.ensure_column("file_snapshots", "image_file", "TEXT")?;
+        self.ensure_column("files", "container", "TEXT")?;
+        self.ensure_column("files", "video_codec", "TEXT")?;
+        self.ensure_column("files", "audio_codec", "TEXT")?;
+        self.ensure_column("files", "width", "INTEGER")?;
+        self.ensure_column("files", "height", "INTEGER")?;
+        self.ensure_column("files", "bit_rate", "INTEGER")?;
+        self.ensure_column("fileset", "scan_config", "TEXT")?;
+        self.ensure_column("scan_cursor", "last_path", "TEXT")?;
+        self.ensure_column("scan_cursor", "root_path", "TEXT")?;
+        self.ensure_column("scan_cursor", "scan_config", "TEXT")?;
         Ok(())
     }
 
@@ -127,7 +370,9 @@ impl SqliteScanStore {
             return Ok(());
         }
         let sql = format!("ALTER TABLE {table} ADD COLUMN {column} {col_type}");
-        self.conn.execute(&sql, [])?;
+        self.conn
+            .execute(&sql, [])
+            .map_err(|e| Error::Migration(format!("failed to add {table}.{column}: {e}")))?;
         Ok(())
     }
 
@@ -143,6 +388,14 @@ impl SqliteScanStore {
         Ok(false)
     }
 
+    /// The fileset file, or `None` for an in-memory store.
+    pub fn path(&self) -> Option<&Path> {
+        self.conn
+            .path()
+            .filter(|path| !path.is_empty())
+            .map(Path::new)
+    }
+
     pub fn upsert_file(&self, rec: &MediaFileRecord) -> Result<i64> {
         let modified_at_secs = rec.modified_at.map(system_time_to_secs).map(|v| v as i64);
 
@@ -155,8 +408,9 @@ impl SqliteScanStore {
         self.conn.execute(
             r#"
             INSERT INTO files (
-              path, size_bytes, modified_at_secs, blake3, sha256, ahash, dhash, phash, ffmpeg_metadata, file_type
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+              path, size_bytes, modified_at_secs, blake3, sha256, ahash, dhash, phash, ffmpeg_metadata, file_type,
+              container, video_codec, audio_codec, width, height, bit_rate
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)
             ON CONFLICT(path) DO UPDATE SET
               size_bytes=excluded.size_bytes,
               modified_at_secs=excluded.modified_at_secs,
@@ -166,10 +420,16 @@ impl SqliteScanStore {
               dhash=excluded.dhash,
               phash=excluded.phash,
               ffmpeg_metadata=excluded.ffmpeg_metadata,
-              file_type=excluded.file_type
+              file_type=excluded.file_type,
+              container=excluded.container,
+              video_codec=excluded.video_codec,
+              audio_codec=excluded.audio_codec,
+              width=excluded.width,
+              height=excluded.height,
+              bit_rate=excluded.bit_rate
             "#,
             params![
-                rec.path.to_string_lossy(),
+                SqlPath(&rec.path),
                 rec.size_bytes as i64,
                 modified_at_secs,
                 blake3_bytes,
@@ -179,15 +439,19 @@ impl SqliteScanStore {
                 phash,
                 rec.ffmpeg_metadata.as_deref(),
                 rec.file_type.as_deref(),
+                rec.media.container.as_deref(),
+                rec.media.video_codec.as_deref(),
+                rec.media.audio_codec.as_deref(),
+                rec.media.width,
+                rec.media.height,
+                rec.media.bit_rate.map(|v| v as i64),
             ],
         )?;
         let id_col = self.file_id_column();
         let sql = format!("SELECT {id_col} FROM files WHERE path = ?1");
         let file_id = self
             .conn
-            .query_row(&sql, params![rec.path.to_string_lossy()], |r| {
-                r.get::<_, i64>(0)
-            })?;
+            .query_row(&sql, params![SqlPath(&rec.path)], |r| r.get::<_, i64>(0))?;
         Ok(file_id)
     }
 
@@ -216,39 +480,330 @@ impl SqliteScanStore {
         Ok(())
     }
 
+    /// Where a scan that has not completed yet stopped. Cursors written before they
+    /// recorded a path read as `None`, since a bare count cannot be resumed safely.
+    pub fn scan_cursor(&self) -> Result<Option<ScanCursor>> {
+        let cursor = self
+            .conn
+            .query_row(
+                "SELECT files_done, last_path, root_path, scan_config FROM scan_cursor WHERE id = 1",
+                [],
+                |r| {
+                    let last_path: Option<StoredPath> = r.get(1)?;
+                    let root_path: Option<StoredPath> = r.get(2)?;
+                    let scan_config: Option<String> = r.get(3)?;
+                    Ok(match (last_path, root_path, scan_config) {
+                        (Some(last_path), Some(root_path), Some(scan_config)) => Some(ScanCursor {
+                            last_path: last_path.0,
+                            files_done: r.get::<_, i64>(0)?.max(0) as u64,
+                            root_path: root_path.0,
+                            scan_config,
+                        }),
+                        _ => None,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(cursor.flatten())
+    }
+
+    pub fn set_scan_cursor(&self, cursor: &ScanCursor) -> Result<()> {
+        self.conn.execute(
+            r#"
+            INSERT INTO scan_cursor (id, files_done, updated_at_secs, last_path, root_path, scan_config)
+            VALUES (1, ?1, ?2, ?3, ?4, ?5)
+            ON CONFLICT(id) DO UPDATE SET
+              files_done=excluded.files_done,
+              updated_at_secs=excluded.updated_at_secs,
+              last_path=excluded.last_path,
+              root_path=excluded.root_path,
+              scan_config=excluded.scan_config
+            "#,
+            params![
+                cursor.files_done as i64,
+                system_time_to_secs(SystemTime::now()),
+                SqlPath(&cursor.last_path),
+                SqlPath(&cursor.root_path),
+                cursor.scan_config,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn clear_scan_cursor(&self) -> Result<()> {
+        self.conn.execute("DELETE FROM scan_cursor", [])?;
+        Ok(())
+    }
+
     pub fn replace_file_snapshots(
         &self,
         file_id: i64,
         snapshots: &[FileSnapshotRecord],
+    ) -> Result<()> {
+        self.replace_file_snapshots_in(file_id, snapshots, &SnapshotStorage::InDb)
+    }
+
+    /// Like `replace_file_snapshots`, keeping the images wherever `storage` says.
+    pub fn replace_file_snapshots_in(
+        &self,
+        file_id: i64,
+        snapshots: &[FileSnapshotRecord],
+        storage: &SnapshotStorage,
+    ) -> Result<()> {
+        self.delete_snapshots_for_file(file_id)?;
+        for snap in snapshots {
+            match storage {
+                SnapshotStorage::InDb => self.upsert_snapshot(file_id, snap)?,
+                SnapshotStorage::Sidecar { dir } => {
+                    let image_file = self.write_sidecar(
+                        dir,
+                        file_id,
+                        snap.snapshot_index,
+                        &snap.mime,
+                        &snap.image_avif,
+                    )?;
+                    self.upsert_snapshot_row(file_id, snap, &[], Some(&image_file))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Replaces the chunk fingerprints stored for `file_id`; an empty slice clears them.
+    pub fn replace_file_chunks(&self, file_id: i64, chunks: &[ChunkFingerprint]) -> Result<()> {
+        self.conn
+            .prepare_cached("DELETE FROM file_chunks WHERE file_id = ?1")?
+            .execute(params![file_id])?;
+        let mut stmt = self.conn.prepare_cached(
+            "INSERT INTO file_chunks (file_id, chunk_index, hash, len) VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        for (index, chunk) in chunks.iter().enumerate() {
+            stmt.execute(params![file_id, index as i64, chunk.hash as i64, chunk.len])?;
+        }
+        Ok(())
+    }
+
+    /// Pairs of files whose distinct chunks in common add up to at least
+    /// `min_shared_bytes`, most shared first. Only files scanned with
+    /// `ScanConfig::chunk_dedup` have chunks.
+    pub fn files_sharing_chunks(&self, min_shared_bytes: u64) -> Result<Vec<ChunkOverlap>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            WITH chunks AS (SELECT DISTINCT file_id, hash, len FROM file_chunks)
+            SELECT a.file_id, b.file_id, SUM(a.len) AS shared
+            FROM chunks a
+            JOIN chunks b ON b.hash = a.hash AND b.len = a.len AND b.file_id > a.file_id
+            GROUP BY a.file_id, b.file_id
+            HAVING shared >= ?1
+            ORDER BY shared DESC, a.file_id, b.file_id
+            "#,
+        )?;
+        let pairs = stmt.query_map(params![min_shared_bytes.min(i64::MAX as u64) as i64], |r| {
+            Ok((
+                r.get::<_, i64>(0)?,
+                r.get::<_, i64>(1)?,
+                r.get::<_, i64>(2)?,
+            ))
+        })?;
+
+        let id_col = self.file_id_column();
+        let mut row_stmt = self.conn.prepare(&format!(
+            r#"
+            SELECT {id_col} AS id, path, size_bytes, modified_at_secs, blake3, sha256, ahash, dhash, phash, ffmpeg_metadata, file_type
+            FROM files
+            WHERE {id_col} = ?1
+            "#
+        ))?;
+        let mut rows: std::collections::HashMap<i64, FileListRow> =
+            std::collections::HashMap::new();
+        let mut out = Vec::new();
+        for pair in pairs {
+            let (a_id, b_id, shared) = pair?;
+            let mut lookup = |id: i64| -> Result<Option<FileListRow>> {
+                if let Some(row) = rows.get(&id) {
+                    return Ok(Some(row.clone()));
+                }
+                let row = row_stmt
+                    .query_row(params![id], file_list_row_from_sql)
+                    .optional()?;
+                if let Some(row) = &row {
+                    rows.insert(id, row.clone());
+                }
+                Ok(row)
+            };
+            let (Some(a), Some(b)) = (lookup(a_id)?, lookup(b_id)?) else {
+                continue;
+            };
+            let (a, b) = if a.path <= b.path { (a, b) } else { (b, a) };
+            out.push(ChunkOverlap {
+                a,
+                b,
+                shared_bytes: shared.max(0) as u64,
+            });
+        }
+        Ok(out)
+    }
+
+    /// Inserts `snap`, replacing any stored snapshot with the same index for `file_id`.
+    pub fn upsert_snapshot(&self, file_id: i64, snap: &FileSnapshotRecord) -> Result<()> {
+        self.upsert_snapshot_row(file_id, snap, &snap.image_avif, None)
+    }
+
+    fn upsert_snapshot_row(
+        &self,
+        file_id: i64,
+        snap: &FileSnapshotRecord,
+        image: &[u8],
+        image_file: Option<&str>,
     ) -> Result<()> {
         self.conn.execute(
-            r#"DELETE FROM file_snapshots WHERE file_id = ?1"#,
-            params![file_id],
+            r#"
+            INSERT INTO file_snapshots (
+              file_id, snapshot_index, snapshot_count, at_ms, duration_ms, ahash, dhash, phash, mime, image_avif, image_file
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+            ON CONFLICT(file_id, snapshot_index) DO UPDATE SET
+              snapshot_count = excluded.snapshot_count,
+              at_ms = excluded.at_ms,
+              duration_ms = excluded.duration_ms,
+              ahash = excluded.ahash,
+              dhash = excluded.dhash,
+              phash = excluded.phash,
+              mime = excluded.mime,
+              image_avif = excluded.image_avif,
+              image_file = excluded.image_file
+            "#,
+            params![
+                file_id,
+                snap.snapshot_index as i64,
+                snap.snapshot_count as i64,
+                snap.at_ms,
+                snap.duration_ms,
+                snap.ahash.map(|v| v as i64),
+                snap.dhash.map(|v| v as i64),
+                snap.phash.map(|v| v as i64),
+                &snap.mime,
+                image,
+                image_file,
+            ],
         )?;
+        Ok(())
+    }
 
-        for snap in snapshots {
-            self.conn.execute(
-                r#"
-                INSERT INTO file_snapshots (
-                  file_id, snapshot_index, snapshot_count, at_ms, duration_ms, ahash, dhash, phash, image_avif
-                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
-                "#,
-                params![
-                    file_id,
-                    snap.snapshot_index as i64,
-                    snap.snapshot_count as i64,
-                    snap.at_ms,
-                    snap.duration_ms,
-                    snap.ahash.map(|v| v as i64),
-                    snap.dhash.map(|v| v as i64),
-                    snap.phash.map(|v| v as i64),
-                    &snap.image_avif,
-                ],
-            )?;
+    /// Writes one snapshot image under `dir` and returns the reference to store, which
+    /// is relative to the fileset's directory when `dir` is inside it.
+    fn write_sidecar(
+        &self,
+        dir: &Path,
+        file_id: i64,
+        snapshot_index: u32,
+        mime: &str,
+        image: &[u8],
+    ) -> Result<String> {
+        let base = self.sidecar_base();
+        let dir = base.join(dir);
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(format!(
+            "{file_id}_{snapshot_index}.{}",
+            sidecar_extension(mime)
+        ));
+        std::fs::write(&path, image)?;
+        let reference = path.strip_prefix(&base).unwrap_or(&path);
+        Ok(reference.to_string_lossy().into_owned())
+    }
+
+    fn sidecar_base(&self) -> PathBuf {
+        self.path()
+            .and_then(Path::parent)
+            .map(Path::to_path_buf)
+            .unwrap_or_default()
+    }
+
+    /// Deletes the sidecar images of the snapshots matching `condition`; call it before
+    /// deleting the rows. Images that are already gone are ignored.
+    fn remove_sidecars(&self, condition: &str, param: &dyn rusqlite::ToSql) -> Result<()> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT image_file FROM file_snapshots WHERE image_file IS NOT NULL AND {condition}"
+        ))?;
+        let files = stmt
+            .query_map([param], |r| r.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let base = self.sidecar_base();
+        for file in files {
+            let _ = std::fs::remove_file(base.join(file));
         }
         Ok(())
     }
 
+    /// Moves images embedded in the database out to sidecar files under `dir` and
+    /// returns how many moved. `compact` afterwards gives the space back.
+    pub fn move_snapshots_to_sidecars(&self, dir: &Path) -> Result<u64> {
+        let mut stmt = self.conn.prepare(
+            "SELECT file_id, snapshot_index FROM file_snapshots WHERE image_file IS NULL ORDER BY file_id, snapshot_index",
+        )?;
+        let keys = stmt
+            .query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, i64>(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        drop(stmt);
+
+        self.conn.execute_batch("BEGIN IMMEDIATE")?;
+        let moved = (|| -> Result<u64> {
+            let mut moved = 0u64;
+            for (file_id, snapshot_index) in keys {
+                // One image in memory at a time.
+                let (mime, image): (String, Vec<u8>) = self.conn.query_row(
+                    "SELECT COALESCE(mime, 'image/avif'), image_avif FROM file_snapshots WHERE file_id = ?1 AND snapshot_index = ?2",
+                    params![file_id, snapshot_index],
+                    |r| Ok((r.get(0)?, r.get(1)?)),
+                )?;
+                let image_file =
+                    self.write_sidecar(dir, file_id, snapshot_index as u32, &mime, &image)?;
+                self.conn.execute(
+                    "UPDATE file_snapshots SET image_avif = X'', image_file = ?3 WHERE file_id = ?1 AND snapshot_index = ?2",
+                    params![file_id, snapshot_index, image_file],
+                )?;
+                moved += 1;
+            }
+            Ok(moved)
+        })();
+        match moved {
+            Ok(moved) => {
+                self.commit_tx()?;
+                Ok(moved)
+            }
+            Err(err) => {
+                let _ = self.rollback_tx();
+                Err(err)
+            }
+        }
+    }
+
+    /// Returns how many snapshots were removed.
+    pub fn delete_snapshots_for_file(&self, file_id: i64) -> Result<u64> {
+        self.remove_sidecars("file_id = ?1", &file_id)?;
+        let affected = self.conn.execute(
+            "DELETE FROM file_snapshots WHERE file_id = ?1",
+            params![file_id],
+        )?;
+        Ok(affected as u64)
+    }
+
+    /// Whether the recorded scan root is a folder on this machine, so relative file paths
+    /// resolve. An empty root, from a multi-root scan with no common folder, counts as
+    /// present since its paths are absolute.
+    pub fn root_exists(&self) -> Result<bool> {
+        let Some(meta) = self.get_fileset_metadata()? else {
+            return Ok(false);
+        };
+        if meta.root_path.as_os_str().is_empty() {
+            return Ok(true);
+        }
+        let root = match &meta.root_parent_path {
+            Some(parent) if meta.root_path.is_relative() => parent.join(&meta.root_path),
+            _ => meta.root_path,
+        };
+        Ok(root.is_dir())
+    }
+
     pub fn get_fileset_metadata(&self) -> Result<Option<FilesetMetadata>> {
         let row = self
             .conn
@@ -266,8 +821,8 @@ impl SqliteScanStore {
                 |r| {
                     let created_at_secs: Option<i64> = r.get(0)?;
                     let root_kind: Option<String> = r.get(1)?;
-                    let root_path: Option<String> = r.get(2)?;
-                    let root_parent_path: Option<String> = r.get(3)?;
+                    let root_path: Option<StoredPath> = r.get(2)?;
+                    let root_parent_path: Option<StoredPath> = r.get(3)?;
                     let drive_id: Option<String> = r.get(4)?;
                     let drive_label: Option<String> = r.get(5)?;
                     let drive_fs_type: Option<String> = r.get(6)?;
@@ -286,8 +841,8 @@ impl SqliteScanStore {
                             .as_deref()
                             .map(str_to_root_kind)
                             .unwrap_or(ScanRootKind::Folder),
-                        root_path: root_path.map(std::path::PathBuf::from).unwrap_or_default(),
-                        root_parent_path: root_parent_path.map(std::path::PathBuf::from),
+                        root_path: root_path.map(|p| p.0).unwrap_or_default(),
+                        root_parent_path: root_parent_path.map(|p| p.0),
                         drive: DriveMetadata {
                             id: drive_id,
                             label: drive_label,
@@ -309,10 +864,7 @@ impl SqliteScanStore {
 
     pub fn set_fileset_metadata(&self, meta: &FilesetMetadata) -> Result<()> {
         let created_at_secs = system_time_to_secs(meta.created_at);
-        let root_parent = meta
-            .root_parent_path
-            .as_ref()
-            .map(|p| p.to_string_lossy().to_string());
+        let root_parent = meta.root_parent_path.as_deref().map(SqlPath);
         self.conn.execute(
             r#"
             INSERT INTO fileset (
@@ -340,7 +892,7 @@ impl SqliteScanStore {
             params![
                 created_at_secs as i64,
                 root_kind_to_str(meta.root_kind),
-                meta.root_path.to_string_lossy(),
+                SqlPath(&meta.root_path),
                 root_parent,
                 meta.drive.id,
                 meta.drive.label,
@@ -357,6 +909,26 @@ impl SqliteScanStore {
         Ok(())
     }
 
+    /// The `ScanConfig::to_json` of the last scan into this fileset.
+    pub fn get_scan_config(&self) -> Result<Option<String>> {
+        Ok(self
+            .conn
+            .query_row("SELECT scan_config FROM fileset WHERE id = 1", [], |r| {
+                r.get::<_, Option<String>>(0)
+            })
+            .optional()?
+            .flatten())
+    }
+
+    /// Needs the `fileset` row that `set_fileset_metadata` writes.
+    pub fn set_scan_config(&self, json: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE fileset SET scan_config = ?1 WHERE id = 1",
+            params![json],
+        )?;
+        Ok(())
+    }
+
     pub fn count_files(&self) -> Result<u64> {
         let count: i64 = self
             .conn
@@ -364,36 +936,30 @@ impl SqliteScanStore {
         Ok(count.max(0) as u64)
     }
 
-    pub fn list_files(&self, limit: usize, offset: usize) -> Result<Vec<FileListRow>> {
-        let id_col = self.file_id_column();
-        let sql = format!(
+    pub fn total_bytes(&self) -> Result<u64> {
+        let total: i64 =
+            self.conn
+                .query_row("SELECT COALESCE(SUM(size_bytes), 0) FROM files", [], |r| {
+                    r.get(0)
+                })?;
+        Ok(total.max(0) as u64)
+    }
+
+    /// File count and size per detected type, largest count first.
+    pub fn type_breakdown(&self) -> Result<Vec<FileTypeStats>> {
+        let mut stmt = self.conn.prepare(
             r#"
-            SELECT {id_col} AS id, path, size_bytes, modified_at_secs, blake3, sha256, ahash, dhash, phash, ffmpeg_metadata, file_type
+            SELECT file_type, COUNT(*), COALESCE(SUM(size_bytes), 0)
             FROM files
-            ORDER BY path
-            LIMIT ?1 OFFSET ?2
-            "#
-        );
-        let mut stmt = self.conn.prepare(&sql)?;
-        let rows = stmt.query_map(params![limit as i64, offset as i64], |r| {
-            let modified_at_secs: Option<i64> = r.get(3)?;
-            let blake3: Option<Vec<u8>> = r.get(4)?;
-            let sha256: Option<Vec<u8>> = r.get(5)?;
-            let ahash: Option<i64> = r.get(6)?;
-            let dhash: Option<i64> = r.get(7)?;
-            let phash: Option<i64> = r.get(8)?;
-            Ok(FileListRow {
-                id: r.get(0)?,
-                path: Path::new(r.get::<_, String>(1)?.as_str()).to_path_buf(),
-                size_bytes: r.get::<_, i64>(2)? as u64,
-                modified_at: modified_at_secs.map(|v| secs_to_system_time(v.max(0) as u64)),
-                blake3: blob_to_hash(blake3),
-                sha256: blob_to_hash(sha256),
-                ahash: ahash.map(|v| v as u64),
-                dhash: dhash.map(|v| v as u64),
-                phash: phash.map(|v| v as u64),
-                ffmpeg_metadata: r.get(9)?,
-                file_type: r.get(10)?,
+            GROUP BY file_type
+            ORDER BY COUNT(*) DESC, file_type
+            "#,
+        )?;
+        let rows = stmt.query_map([], |r| {
+            Ok(FileTypeStats {
+                file_type: r.get(0)?,
+                files: r.get::<_, i64>(1)?.max(0) as u64,
+                bytes: r.get::<_, i64>(2)?.max(0) as u64,
             })
         })?;
 
@@ -404,56 +970,73 @@ impl SqliteScanStore {
         Ok(out)
     }
 
-    pub fn list_files_with_duplicates(
+    /// Number of exact-duplicate groups, keyed like `list_files_with_duplicates`.
+    pub fn duplicate_group_count(&self) -> Result<u64> {
+        let count: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM hash_counts WHERE count > 1",
+            [],
+            |r| r.get(0),
+        )?;
+        Ok(count.max(0) as u64)
+    }
+
+    /// A page of exact-duplicate groups, largest groups first. Paginates by group so a
+    /// group is never split across pages.
+    pub fn duplicate_groups(
         &self,
+        include_ignored: bool,
         limit: usize,
         offset: usize,
-    ) -> Result<Vec<FileListRow>> {
+    ) -> Result<Vec<DuplicateGroup>> {
+        let ignored = if include_ignored {
+            ""
+        } else {
+            "AND hash NOT IN (SELECT hash FROM ignored_groups)"
+        };
+        let mut stmt = self.conn.prepare(&format!(
+            r#"
+            SELECT by_sha256, hash
+            FROM hash_counts
+            WHERE count > 1 {ignored}
+            ORDER BY count DESC, total_bytes / count DESC, hash
+            LIMIT ?1 OFFSET ?2
+            "#
+        ))?;
+        let keys = stmt.query_map(params![limit as i64, offset as i64], |r| {
+            Ok((r.get::<_, bool>(0)?, r.get::<_, Vec<u8>>(1)?))
+        })?;
+        let mut groups = Vec::new();
+        for key in keys {
+            let (by_sha256, hash) = key?;
+            let Some(hash) = blob_to_hash(Some(hash)) else {
+                continue;
+            };
+            let (algorithm, filter) = if by_sha256 {
+                (HashAlgorithm::Sha256, "blake3 IS NULL AND sha256 = ?1")
+            } else {
+                (HashAlgorithm::Blake3, "blake3 = ?1")
+            };
+            groups.push(DuplicateGroup {
+                algorithm,
+                hash,
+                files: self.files_matching(filter, &hash)?,
+            });
+        }
+        Ok(groups)
+    }
+
+    fn files_matching(&self, filter: &str, hash: &[u8; 32]) -> Result<Vec<FileListRow>> {
         let id_col = self.file_id_column();
         let sql = format!(
             r#"
-            SELECT f1.{id_col} AS id, f1.path, f1.size_bytes, f1.modified_at_secs, f1.blake3, f1.sha256, f1.ahash, f1.dhash, f1.phash, f1.ffmpeg_metadata, f1.file_type
-            FROM files f1
-            WHERE (
-                f1.blake3 IS NOT NULL
-                AND EXISTS (
-                  SELECT 1 FROM files f2
-                  WHERE f2.blake3 = f1.blake3 AND f2.{id_col} != f1.{id_col}
-                )
-              ) OR (
-                f1.blake3 IS NULL
-                AND f1.sha256 IS NOT NULL
-                AND EXISTS (
-                  SELECT 1 FROM files f2
-                  WHERE f2.sha256 = f1.sha256 AND f2.{id_col} != f1.{id_col}
-                )
-              )
-            ORDER BY f1.path
-            LIMIT ?1 OFFSET ?2
+            SELECT {id_col} AS id, path, size_bytes, modified_at_secs, blake3, sha256, ahash, dhash, phash, ffmpeg_metadata, file_type
+            FROM files
+            WHERE {filter}
+            ORDER BY path
             "#
         );
         let mut stmt = self.conn.prepare(&sql)?;
-        let rows = stmt.query_map(params![limit as i64, offset as i64], |r| {
-            let modified_at_secs: Option<i64> = r.get(3)?;
-            let blake3: Option<Vec<u8>> = r.get(4)?;
-            let sha256: Option<Vec<u8>> = r.get(5)?;
-            let ahash: Option<i64> = r.get(6)?;
-            let dhash: Option<i64> = r.get(7)?;
-            let phash: Option<i64> = r.get(8)?;
-            Ok(FileListRow {
-                id: r.get(0)?,
-                path: Path::new(r.get::<_, String>(1)?.as_str()).to_path_buf(),
-                size_bytes: r.get::<_, i64>(2)? as u64,
-                modified_at: modified_at_secs.map(|v| secs_to_system_time(v.max(0) as u64)),
-                blake3: blob_to_hash(blake3),
-                sha256: blob_to_hash(sha256),
-                ahash: ahash.map(|v| v as u64),
-                dhash: dhash.map(|v| v as u64),
-                phash: phash.map(|v| v as u64),
-                ffmpeg_metadata: r.get(9)?,
-                file_type: r.get(10)?,
-            })
-        })?;
+        let rows = stmt.query_map(params![hash.as_slice()], file_list_row_from_sql)?;
 
         let mut out = Vec::new();
         for row in rows {
@@ -462,39 +1045,59 @@ impl SqliteScanStore {
         Ok(out)
     }
 
-    pub fn list_files_with_hashes(&self, limit: usize, offset: usize) -> Result<Vec<FileListRow>> {
+    /// Marks an exact-duplicate group, keyed by its blake3 (or, without one, sha256),
+    /// as reviewed. The mark outlives rescans as long as the hash does.
+    pub fn ignore_group(&self, hash: &[u8; 32]) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR IGNORE INTO ignored_groups (hash) VALUES (?1)",
+            params![hash.as_slice()],
+        )?;
+        Ok(())
+    }
+
+    pub fn unignore_group(&self, hash: &[u8; 32]) -> Result<bool> {
+        let affected = self.conn.execute(
+            "DELETE FROM ignored_groups WHERE hash = ?1",
+            params![hash.as_slice()],
+        )?;
+        Ok(affected > 0)
+    }
+
+    pub fn is_group_ignored(&self, hash: &[u8; 32]) -> Result<bool> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT 1 FROM ignored_groups WHERE hash = ?1",
+                params![hash.as_slice()],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some())
+    }
+
+    /// Bytes freed by keeping one file from each exact-duplicate group.
+    pub fn reclaimable_bytes(&self) -> Result<u64> {
+        let total: i64 = self.conn.query_row(
+            "SELECT COALESCE(SUM(total_bytes - total_bytes / count), 0) FROM hash_counts WHERE count > 1",
+            [],
+            |r| r.get(0),
+        )?;
+        Ok(total.max(0) as u64)
+    }
+
+    /// A page of all files, ordered by path.
+    pub fn list_files(&self, limit: usize, offset: usize) -> Result<Vec<FileListRow>> {
         let id_col = self.file_id_column();
         let sql = format!(
             r#"
             SELECT {id_col} AS id, path, size_bytes, modified_at_secs, blake3, sha256, ahash, dhash, phash, ffmpeg_metadata, file_type
             FROM files
-            WHERE ahash IS NOT NULL OR dhash IS NOT NULL OR phash IS NOT NULL
             ORDER BY path
             LIMIT ?1 OFFSET ?2
             "#
         );
         let mut stmt = self.conn.prepare(&sql)?;
-        let rows = stmt.query_map(params![limit as i64, offset as i64], |r| {
-            let modified_at_secs: Option<i64> = r.get(3)?;
-            let blake3: Option<Vec<u8>> = r.get(4)?;
-            let sha256: Option<Vec<u8>> = r.get(5)?;
-            let ahash: Option<i64> = r.get(6)?;
-            let dhash: Option<i64> = r.get(7)?;
-            let phash: Option<i64> = r.get(8)?;
-            Ok(FileListRow {
-                id: r.get(0)?,
-                path: Path::new(r.get::<_, String>(1)?.as_str()).to_path_buf(),
-                size_bytes: r.get::<_, i64>(2)? as u64,
-                modified_at: modified_at_secs.map(|v| secs_to_system_time(v.max(0) as u64)),
-                blake3: blob_to_hash(blake3),
-                sha256: blob_to_hash(sha256),
-                ahash: ahash.map(|v| v as u64),
-                dhash: dhash.map(|v| v as u64),
-                phash: phash.map(|v| v as u64),
-                ffmpeg_metadata: r.get(9)?,
-                file_type: r.get(10)?,
-            })
-        })?;
+        let rows = stmt.query_map(params![limit as i64, offset as i64], file_list_row_from_sql)?;
 
         let mut out = Vec::new();
         for row in rows {
@@ -503,8 +1106,205 @@ impl SqliteScanStore {
         Ok(out)
     }
 
-    pub fn list_direct_matches_by_blake3(&self, file_id: i64) -> Result<Vec<FileListRow>> {
-        let id_col = self.file_id_column();
+    /// Every file ordered by path, read a page at a time so memory stays flat on
+    /// large filesets.
+    pub fn iter_files(&self) -> impl Iterator<Item = Result<FileListRow>> + '_ {
+        FileRowIter::new(self, false)
+    }
+
+    /// `iter_files` limited to the rows of `list_files_with_duplicates`.
+    pub fn iter_files_with_duplicates(&self) -> impl Iterator<Item = Result<FileListRow>> + '_ {
+        FileRowIter::new(self, true)
+    }
+
+    /// A page of non-empty files that share a blake3 (or, without one, a sha256) with another
+    /// file, ordered by path.
+    pub fn list_files_with_duplicates(
+        &self,
+        include_ignored: bool,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<FileListRow>> {
+        let id_col = self.file_id_column();
+        let sql = format!(
+            r#"
+            SELECT f1.{id_col} AS id, f1.path, f1.size_bytes, f1.modified_at_secs, f1.blake3, f1.sha256, f1.ahash, f1.dhash, f1.phash, f1.ffmpeg_metadata, f1.file_type
+            FROM files f1
+            WHERE {}
+            ORDER BY f1.path
+            LIMIT ?1 OFFSET ?2
+            "#,
+            duplicate_filter_sql(id_col, include_ignored)
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(params![limit as i64, offset as i64], file_list_row_from_sql)?;
+
+        let mut out = Vec::new();
+        for row in rows {
+            out.push(row?);
+        }
+        Ok(out)
+    }
+
+    /// A page of files ordered by path, each with the number of files (itself included)
+    /// sharing its blake3, or its sha256 when it has no blake3. Unhashed files count once.
+    pub fn list_files_with_copy_count(
+        &self,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<(FileListRow, u64)>> {
+        let id_col = self.file_id_column();
+        let sql = format!(
+            r#"
+            SELECT f1.{id_col} AS id, f1.path, f1.size_bytes, f1.modified_at_secs, f1.blake3, f1.sha256, f1.ahash, f1.dhash, f1.phash, f1.ffmpeg_metadata, f1.file_type,
+              {COPY_COUNT_SQL} AS copy_count
+            FROM files f1
+            ORDER BY f1.path
+            LIMIT ?1 OFFSET ?2
+            "#
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(params![limit as i64, offset as i64], |r| {
+            let count: i64 = r.get("copy_count")?;
+            Ok((file_list_row_from_sql(r)?, count.max(1) as u64))
+        })?;
+
+        let mut out = Vec::new();
+        for row in rows {
+            out.push(row?);
+        }
+        Ok(out)
+    }
+
+    pub fn list_files_sorted(
+        &self,
+        sort: FileListSort,
+        duplicates_only: bool,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<FileListRow>> {
+        let id_col = self.file_id_column();
+        let filter = if duplicates_only {
+            format!(
+                r#"
+            WHERE (
+                f1.size_bytes > 0
+                AND f1.blake3 IS NOT NULL
+                AND EXISTS (
+                  SELECT 1 FROM files f2
+                  WHERE f2.blake3 = f1.blake3 AND f2.{id_col} != f1.{id_col}
+                )
+              ) OR (
+                f1.size_bytes > 0
+                AND f1.blake3 IS NULL
+                AND f1.sha256 IS NOT NULL
+                AND EXISTS (
+                  SELECT 1 FROM files f2
+                  WHERE f2.sha256 = f1.sha256 AND f2.{id_col} != f1.{id_col}
+                )
+              )"#
+            )
+        } else {
+            String::new()
+        };
+        // Ties in reclaimable space are broken by hash, so a group is never split by
+        // another group of the same size and pages stay in a stable order.
+        let order = match sort {
+            FileListSort::Path => "f1.path".to_string(),
+            FileListSort::SizeDesc => "f1.size_bytes DESC, f1.path".to_string(),
+            FileListSort::ReclaimableDesc => format!(
+                "f1.size_bytes * (({COPY_COUNT_SQL}) - 1) DESC, COALESCE(f1.blake3, f1.sha256), f1.path"
+            ),
+        };
+        let sql = format!(
+            r#"
+            SELECT f1.{id_col} AS id, f1.path, f1.size_bytes, f1.modified_at_secs, f1.blake3, f1.sha256, f1.ahash, f1.dhash, f1.phash, f1.ffmpeg_metadata, f1.file_type
+            FROM files f1{filter}
+            ORDER BY {order}
+            LIMIT ?1 OFFSET ?2
+            "#
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(params![limit as i64, offset as i64], file_list_row_from_sql)?;
+
+        let mut out = Vec::new();
+        for row in rows {
+            out.push(row?);
+        }
+        Ok(out)
+    }
+
+    /// Files in one MIME family, ordered by path, optionally limited to exact duplicates.
+    pub fn list_files_by_type(
+        &self,
+        filter: FileTypeFilter,
+        duplicates_only: bool,
+        include_ignored: bool,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<FileListRow>> {
+        let id_col = self.file_id_column();
+        let type_clause = match filter.mime_prefix() {
+            Some(prefix) => format!("f1.file_type LIKE '{prefix}%'"),
+            None => {
+                let families: Vec<String> = FileTypeFilter::ALL
+                    .iter()
+                    .filter_map(|family| family.mime_prefix())
+                    .map(|prefix| format!("f1.file_type NOT LIKE '{prefix}%'"))
+                    .collect();
+                format!("(f1.file_type IS NULL OR ({}))", families.join(" AND "))
+            }
+        };
+        let duplicate_clause = if duplicates_only {
+            format!("AND ({})", duplicate_filter_sql(id_col, include_ignored))
+        } else {
+            String::new()
+        };
+        let sql = format!(
+            r#"
+            SELECT f1.{id_col} AS id, f1.path, f1.size_bytes, f1.modified_at_secs, f1.blake3, f1.sha256, f1.ahash, f1.dhash, f1.phash, f1.ffmpeg_metadata, f1.file_type
+            FROM files f1
+            WHERE {type_clause}{duplicate_clause}
+            ORDER BY f1.path
+            LIMIT ?1 OFFSET ?2
+            "#
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(params![limit as i64, offset as i64], file_list_row_from_sql)?;
+
+        let mut out = Vec::new();
+        for row in rows {
+            out.push(row?);
+        }
+        Ok(out)
+    }
+
+    /// A page of files with at least one perceptual hash, ordered by path.
+    pub fn list_files_with_hashes(&self, limit: usize, offset: usize) -> Result<Vec<FileListRow>> {
+        let id_col = self.file_id_column();
+        let sql = format!(
+            r#"
+            SELECT {id_col} AS id, path, size_bytes, modified_at_secs, blake3, sha256, ahash, dhash, phash, ffmpeg_metadata, file_type
+            FROM files
+            WHERE ahash IS NOT NULL OR dhash IS NOT NULL OR phash IS NOT NULL
+            ORDER BY path
+            LIMIT ?1 OFFSET ?2
+            "#
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(params![limit as i64, offset as i64], file_list_row_from_sql)?;
+
+        let mut out = Vec::new();
+        for row in rows {
+            out.push(row?);
+        }
+        Ok(out)
+    }
+
+    /// Non-empty files with the same blake3 as `file_id` (sha256 when it has no blake3).
+    /// Empty when `file_id` does not exist or has no exact hash.
+    pub fn list_direct_matches_by_blake3(&self, file_id: i64) -> Result<Vec<FileListRow>> {
+        let id_col = self.file_id_column();
         let (blake3, sha256): (Option<Vec<u8>>, Option<Vec<u8>>) = match self
             .conn
             .query_row(
@@ -529,32 +1329,12 @@ impl SqliteScanStore {
             r#"
             SELECT {id_col} AS id, path, size_bytes, modified_at_secs, blake3, sha256, ahash, dhash, phash, ffmpeg_metadata, file_type
             FROM files
-            WHERE {hash_col} = ?1 AND {id_col} != ?2
+            WHERE {hash_col} = ?1 AND {id_col} != ?2 AND size_bytes > 0
             ORDER BY path
             "#
         );
         let mut stmt = self.conn.prepare(&sql)?;
-        let rows = stmt.query_map(params![hash, file_id], |r| {
-            let modified_at_secs: Option<i64> = r.get(3)?;
-            let blake3: Option<Vec<u8>> = r.get(4)?;
-            let sha256: Option<Vec<u8>> = r.get(5)?;
-            let ahash: Option<i64> = r.get(6)?;
-            let dhash: Option<i64> = r.get(7)?;
-            let phash: Option<i64> = r.get(8)?;
-            Ok(FileListRow {
-                id: r.get(0)?,
-                path: Path::new(r.get::<_, String>(1)?.as_str()).to_path_buf(),
-                size_bytes: r.get::<_, i64>(2)? as u64,
-                modified_at: modified_at_secs.map(|v| secs_to_system_time(v.max(0) as u64)),
-                blake3: blob_to_hash(blake3),
-                sha256: blob_to_hash(sha256),
-                ahash: ahash.map(|v| v as u64),
-                dhash: dhash.map(|v| v as u64),
-                phash: phash.map(|v| v as u64),
-                ffmpeg_metadata: r.get(9)?,
-                file_type: r.get(10)?,
-            })
-        })?;
+        let rows = stmt.query_map(params![hash, file_id], file_list_row_from_sql)?;
 
         let mut out = Vec::new();
         for row in rows {
@@ -563,11 +1343,13 @@ impl SqliteScanStore {
         Ok(out)
     }
 
+    /// `None` when no row has this id.
     pub fn get_file_by_id(&self, file_id: i64) -> Result<Option<MediaFileRecord>> {
         let id_col = self.file_id_column();
         let sql = format!(
             r#"
-            SELECT path, size_bytes, modified_at_secs, blake3, sha256, ahash, dhash, phash, ffmpeg_metadata, file_type
+            SELECT path, size_bytes, modified_at_secs, blake3, sha256, ahash, dhash, phash, ffmpeg_metadata, file_type,
+                   container, video_codec, audio_codec, width, height, bit_rate
             FROM files
             WHERE {id_col} = ?1
             "#
@@ -583,7 +1365,7 @@ impl SqliteScanStore {
                 let modified_at_secs: Option<i64> = r.get(2)?;
                 Ok(MediaFileRecord {
                     file_id: Some(file_id),
-                    path: Path::new(r.get::<_, String>(0)?.as_str()).to_path_buf(),
+                    path: r.get::<_, StoredPath>(0)?.0,
                     size_bytes: r.get::<_, i64>(1)? as u64,
                     modified_at: modified_at_secs.map(|v| secs_to_system_time(v.max(0) as u64)),
                     blake3: blob_to_hash(blake3),
@@ -593,24 +1375,76 @@ impl SqliteScanStore {
                     phash: phash.map(|v| v as u64),
                     ffmpeg_metadata: r.get(8)?,
                     file_type: r.get(9)?,
+                    media: media_info_from_sql(r, 10)?,
                 })
             })
             .optional()?;
         Ok(row)
     }
 
+    /// Whether a file at stored path `path` with this size and mtime would hash differently
+    /// than its row: true when there is no row, or its size or mtime differ or were never
+    /// recorded.
+    pub fn needs_rehash(
+        &self,
+        path: &Path,
+        size_bytes: u64,
+        modified_at_secs: i64,
+    ) -> Result<bool> {
+        let stored: Option<(i64, Option<i64>)> = self
+            .conn
+            .query_row(
+                "SELECT size_bytes, modified_at_secs FROM files WHERE path = ?1",
+                params![SqlPath(path)],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .optional()?;
+        Ok(match stored {
+            Some((size, Some(mtime))) => size as u64 != size_bytes || mtime != modified_at_secs,
+            _ => true,
+        })
+    }
+
+    /// Indexes `files.path` case-insensitively so `adopt_path_case` stays cheap.
+    pub fn create_path_nocase_index(&self) -> Result<()> {
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_files_path_nocase ON files(path COLLATE NOCASE)",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Renames the row whose path matches `path` up to ASCII case, so the next
+    /// `upsert_file` updates it instead of adding a second row. Does nothing when
+    /// `path` itself is stored. Returns whether a row was renamed.
+    pub fn adopt_path_case(&self, path: &Path) -> Result<bool> {
+        let changed = self.conn.execute(
+            r#"
+            UPDATE files SET path = ?1
+            WHERE rowid = (
+              SELECT rowid FROM files WHERE path = ?1 COLLATE NOCASE AND path <> ?1 LIMIT 1
+            )
+            AND NOT EXISTS (SELECT 1 FROM files WHERE path = ?1)
+            "#,
+            params![SqlPath(path)],
+        )?;
+        Ok(changed > 0)
+    }
+
+    /// Looks up a row by its stored path, which is relative to the scan root.
     pub fn get_file_by_path(&self, path: &Path) -> Result<Option<MediaFileRecord>> {
         let id_col = self.file_id_column();
         let sql = format!(
             r#"
-            SELECT {id_col} AS id, size_bytes, modified_at_secs, blake3, sha256, ahash, dhash, phash, ffmpeg_metadata, file_type
+            SELECT {id_col} AS id, size_bytes, modified_at_secs, blake3, sha256, ahash, dhash, phash, ffmpeg_metadata, file_type,
+                   container, video_codec, audio_codec, width, height, bit_rate
             FROM files
             WHERE path = ?1
             "#
         );
         let row = self
             .conn
-            .query_row(&sql, params![path.to_string_lossy()], |r| {
+            .query_row(&sql, params![SqlPath(path)], |r| {
                 let blake3: Option<Vec<u8>> = r.get(3)?;
                 let sha256: Option<Vec<u8>> = r.get(4)?;
                 let ahash: Option<i64> = r.get(5)?;
@@ -629,23 +1463,25 @@ impl SqliteScanStore {
                     phash: phash.map(|v| v as u64),
                     ffmpeg_metadata: r.get(8)?,
                     file_type: r.get(9)?,
+                    media: media_info_from_sql(r, 10)?,
                 })
             })
             .optional()?;
         Ok(row)
     }
 
+    /// Snapshots for `file_id` in capture order; empty when none were captured.
     pub fn list_file_snapshots(&self, file_id: i64) -> Result<Vec<FileSnapshotRecord>> {
         let mut stmt = self.conn.prepare(
             r#"
-            SELECT snapshot_index, snapshot_count, at_ms, duration_ms, ahash, dhash, phash, image_avif
+            SELECT snapshot_index, snapshot_count, at_ms, duration_ms, ahash, dhash, phash, COALESCE(mime, 'image/avif'), image_avif, image_file
             FROM file_snapshots
             WHERE file_id = ?1
             ORDER BY snapshot_index
             "#,
         )?;
         let rows = stmt.query_map(params![file_id], |r| {
-            Ok(FileSnapshotRecord {
+            let rec = FileSnapshotRecord {
                 snapshot_index: r.get::<_, i64>(0)? as u32,
                 snapshot_count: r.get::<_, i64>(1)? as u32,
                 at_ms: r.get::<_, i64>(2)?,
@@ -653,24 +1489,656 @@ impl SqliteScanStore {
                 ahash: r.get::<_, Option<i64>>(4)?.map(|v| v as u64),
                 dhash: r.get::<_, Option<i64>>(5)?.map(|v| v as u64),
                 phash: r.get::<_, Option<i64>>(6)?.map(|v| v as u64),
-                image_avif: r.get(7)?,
-            })
+                mime: r.get(7)?,
+                image_avif: r.get(8)?,
+            };
+            Ok((rec, r.get::<_, Option<String>>(9)?))
         })?;
 
+        let base = self.sidecar_base();
         let mut out = Vec::new();
         for row in rows {
-            out.push(row?);
+            let (mut rec, image_file) = row?;
+            if let Some(image_file) = image_file {
+                // A sidecar deleted behind our back drops that snapshot, not the whole list.
+                match std::fs::read(base.join(image_file)) {
+                    Ok(image) => rec.image_avif = image,
+                    Err(_) => continue,
+                }
+            }
+            out.push(rec);
         }
         Ok(out)
     }
 
+    /// Copies the files and snapshots of the fileset at `other` into this one, matching
+    /// rows by stored path, in one transaction. Rows whose content differs are reported
+    /// as conflicts and left alone. A fileset without metadata takes `other`'s. `other`
+    /// is opened read-only, so it is never migrated or marked.
+    pub fn merge_from(&self, other: &Path) -> Result<MergeReport> {
+        let other = &SqliteScanStore::open_read_only(other)?;
+        let mut report = MergeReport::default();
+        match (self.get_fileset_metadata()?, other.get_fileset_metadata()?) {
+            (Some(ours), Some(theirs)) if ours.root_path != theirs.root_path => {
+                report.root_mismatch = Some((ours.root_path, theirs.root_path));
+            }
+            (None, Some(theirs)) => self.set_fileset_metadata(&theirs)?,
+            _ => {}
+        }
+        self.conn.execute_batch("BEGIN IMMEDIATE")?;
+        match self.merge_rows(other, &mut report) {
+            Ok(()) => self.commit_tx()?,
+            Err(err) => {
+                let _ = self.rollback_tx();
+                return Err(err);
+            }
+        }
+        Ok(report)
+    }
+
+    fn merge_rows(&self, other: &SqliteScanStore, report: &mut MergeReport) -> Result<()> {
+        for row in other.iter_files() {
+            let row = row?;
+            let Some(theirs) = other.get_file_by_id(row.id)? else {
+                continue;
+            };
+            let Some(mut ours) = self.get_file_by_path(&theirs.path)? else {
+                let file_id = self.upsert_file(&theirs)?;
+                self.replace_file_snapshots(file_id, &other.list_file_snapshots(row.id)?)?;
+                report.added += 1;
+                continue;
+            };
+            if !same_content(&ours, &theirs) {
+                report.conflicts.push(theirs.path);
+                continue;
+            }
+            let mut filled = fill_missing(&mut ours, &theirs);
+            let file_id = if filled {
+                self.upsert_file(&ours)?
+            } else {
+                ours.file_id.unwrap_or_default()
+            };
+            if self.snapshot_count(file_id)? == 0 {
+                let snapshots = other.list_file_snapshots(row.id)?;
+                if !snapshots.is_empty() {
+                    self.replace_file_snapshots(file_id, &snapshots)?;
+                    filled = true;
+                }
+            }
+            if filled {
+                report.updated += 1;
+            } else {
+                report.unchanged += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn snapshot_count(&self, file_id: i64) -> Result<u64> {
+        let count: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM file_snapshots WHERE file_id = ?1",
+            params![file_id],
+            |r| r.get(0),
+        )?;
+        Ok(count as u64)
+    }
+
     pub fn delete_file_by_path(&self, path: &Path) -> Result<bool> {
+        let id_col = self.file_id_column();
+        self.remove_sidecars(
+            &format!("file_id IN (SELECT {id_col} FROM files WHERE path = ?1)"),
+            &SqlPath(path),
+        )?;
+        let affected = self
+            .conn
+            .execute("DELETE FROM files WHERE path = ?1", params![SqlPath(path)])?;
+        Ok(affected > 0)
+    }
+
+    /// Its snapshots go with it through the `file_snapshots` foreign key.
+    pub fn delete_file_by_id(&self, file_id: i64) -> Result<bool> {
+        self.remove_sidecars("file_id = ?1", &file_id)?;
+        let id_col = self.file_id_column();
         let affected = self.conn.execute(
-            "DELETE FROM files WHERE path = ?1",
-            params![path.to_string_lossy()],
+            &format!("DELETE FROM files WHERE {id_col} = ?1"),
+            params![file_id],
         )?;
         Ok(affected > 0)
     }
+
+    /// Other files with the same exact hash as `file_id` (blake3, else sha256).
+    pub fn exact_duplicates_of(&self, file_id: i64) -> Result<Vec<FileListRow>> {
+        let id_col = self.file_id_column();
+        let sql = format!(
+            r#"
+            SELECT f2.{id_col} AS id, f2.path, f2.size_bytes, f2.modified_at_secs, f2.blake3, f2.sha256, f2.ahash, f2.dhash, f2.phash, f2.ffmpeg_metadata, f2.file_type
+            FROM files f1
+            JOIN files f2 ON f2.{id_col} != f1.{id_col} AND (
+                (f1.blake3 IS NOT NULL AND f2.blake3 = f1.blake3)
+                OR (f1.blake3 IS NULL AND f1.sha256 IS NOT NULL AND f2.sha256 = f1.sha256)
+            )
+            WHERE f1.{id_col} = ?1 AND f1.size_bytes > 0
+            ORDER BY f2.path
+            "#
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(params![file_id], file_list_row_from_sql)?;
+
+        let mut out = Vec::new();
+        for row in rows {
+            out.push(row?);
+        }
+        Ok(out)
+    }
+
+    /// Groups non-empty files by pHash: each group's anchor is the first ungrouped file by
+    /// path, and its matches are the later ungrouped files within `max_hamming` bits of it.
+    pub fn list_near_image_duplicates(&self, max_hamming: u32) -> Result<Vec<NearDuplicateGroup>> {
+        let id_col = self.file_id_column();
+        let sql = format!(
+            r#"
+            SELECT {id_col} AS id, path, size_bytes, modified_at_secs, blake3, sha256, ahash, dhash, phash, ffmpeg_metadata, file_type
+            FROM files
+            WHERE phash IS NOT NULL AND size_bytes > 0
+            ORDER BY path
+            "#
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map([], file_list_row_from_sql)?;
+        let mut files = Vec::new();
+        for row in rows {
+            files.push(row?);
+        }
+
+        let hashes: Vec<u64> = files.iter().map(|row| row.phash.unwrap_or(0)).collect();
+        let mut assigned = vec![false; files.len()];
+        let mut groups = Vec::new();
+        for i in 0..files.len() {
+            if assigned[i] {
+                continue;
+            }
+            let members: Vec<usize> = ((i + 1)..files.len())
+                .filter(|&j| !assigned[j] && (hashes[i] ^ hashes[j]).count_ones() <= max_hamming)
+                .collect();
+            if members.is_empty() {
+                continue;
+            }
+            assigned[i] = true;
+            for &j in &members {
+                assigned[j] = true;
+            }
+            groups.push(NearDuplicateGroup {
+                anchor: files[i].clone(),
+                matches: members.into_iter().map(|j| files[j].clone()).collect(),
+            });
+        }
+        Ok(groups)
+    }
+
+    /// Files with no rows in `file_snapshots`, ordered by path.
+    pub fn files_without_snapshots(&self) -> Result<Vec<FileListRow>> {
+        let id_col = self.file_id_column();
+        let sql = format!(
+            r#"
+            SELECT f.{id_col} AS id, f.path, f.size_bytes, f.modified_at_secs, f.blake3, f.sha256, f.ahash, f.dhash, f.phash, f.ffmpeg_metadata, f.file_type
+            FROM files f
+            WHERE NOT EXISTS (SELECT 1 FROM file_snapshots s WHERE s.file_id = f.{id_col})
+            ORDER BY f.path
+            "#
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map([], file_list_row_from_sql)?;
+
+        let mut out = Vec::new();
+        for row in rows {
+            out.push(row?);
+        }
+        Ok(out)
+    }
+
+    pub fn compact(&self) -> Result<()> {
+        self.conn.execute_batch("VACUUM; PRAGMA optimize;")?;
+        Ok(())
+    }
+}
+
+impl ScanStore for SqliteScanStore {
+    fn path(&self) -> Option<&Path> {
+        SqliteScanStore::path(self)
+    }
+
+    fn begin_scan_write_optimized_tx(&self) -> Result<()> {
+        SqliteScanStore::begin_scan_write_optimized_tx(self)
+    }
+
+    fn commit_tx(&self) -> Result<()> {
+        SqliteScanStore::commit_tx(self)
+    }
+
+    fn rollback_tx(&self) -> Result<()> {
+        SqliteScanStore::rollback_tx(self)
+    }
+
+    fn get_fileset_metadata(&self) -> Result<Option<FilesetMetadata>> {
+        SqliteScanStore::get_fileset_metadata(self)
+    }
+
+    fn set_fileset_metadata(&self, meta: &FilesetMetadata) -> Result<()> {
+        SqliteScanStore::set_fileset_metadata(self, meta)
+    }
+
+    fn set_scan_config(&self, json: &str) -> Result<()> {
+        SqliteScanStore::set_scan_config(self, json)
+    }
+
+    fn scan_cursor(&self) -> Result<Option<ScanCursor>> {
+        SqliteScanStore::scan_cursor(self)
+    }
+
+    fn set_scan_cursor(&self, cursor: &ScanCursor) -> Result<()> {
+        SqliteScanStore::set_scan_cursor(self, cursor)
+    }
+
+    fn clear_scan_cursor(&self) -> Result<()> {
+        SqliteScanStore::clear_scan_cursor(self)
+    }
+
+    fn needs_rehash(&self, path: &Path, size_bytes: u64, modified_at_secs: i64) -> Result<bool> {
+        SqliteScanStore::needs_rehash(self, path, size_bytes, modified_at_secs)
+    }
+
+    fn create_path_nocase_index(&self) -> Result<()> {
+        SqliteScanStore::create_path_nocase_index(self)
+    }
+
+    fn adopt_path_case(&self, path: &Path) -> Result<bool> {
+        SqliteScanStore::adopt_path_case(self, path)
+    }
+
+    fn upsert_file(&self, rec: &MediaFileRecord) -> Result<i64> {
+        SqliteScanStore::upsert_file(self, rec)
+    }
+
+    fn replace_file_chunks(&self, file_id: i64, chunks: &[ChunkFingerprint]) -> Result<()> {
+        SqliteScanStore::replace_file_chunks(self, file_id, chunks)
+    }
+
+    fn replace_file_snapshots_in(
+        &self,
+        file_id: i64,
+        snapshots: &[FileSnapshotRecord],
+        storage: &SnapshotStorage,
+    ) -> Result<()> {
+        SqliteScanStore::replace_file_snapshots_in(self, file_id, snapshots, storage)
+    }
+
+    fn duplicate_groups(
+        &self,
+        include_ignored: bool,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<DuplicateGroup>> {
+        SqliteScanStore::duplicate_groups(self, include_ignored, limit, offset)
+    }
+}
+
+fn fileset_marker(conn: &Connection) -> Result<(i32, u32)> {
+    let application_id: i32 = conn.query_row("PRAGMA application_id", [], |r| r.get(0))?;
+    let user_version: i64 = conn.query_row("PRAGMA user_version", [], |r| r.get(0))?;
+    Ok((application_id, user_version.max(0) as u32))
+}
+
+/// The format version of the fileset in `conn`, or `None` for an empty database.
+/// Unmarked databases count as filesets (version 0) when they have its tables.
+fn fileset_format(conn: &Connection, path: &Path) -> Result<Option<u32>> {
+    let not_a_fileset = |reason: &str| Error::NotAFileset(format!("{} {reason}", path.display()));
+    let (application_id, user_version) = match fileset_marker(conn) {
+        Ok(marker) => marker,
+        Err(Error::Sqlite(err))
+            if err.sqlite_error_code() == Some(rusqlite::ErrorCode::NotADatabase) =>
+        {
+            return Err(not_a_fileset("is not a SQLite database"));
+        }
+        Err(err) => return Err(err),
+    };
+    match application_id {
+        FILESET_APPLICATION_ID => {}
+        0 => {
+            let tables: Vec<String> = conn
+                .prepare("SELECT name FROM sqlite_master WHERE type = 'table'")?
+                .query_map([], |r| r.get(0))?
+                .collect::<rusqlite::Result<_>>()?;
+            if tables.is_empty() {
+                return Ok(None);
+            }
+            if !["fileset", "files"]
+                .iter()
+                .all(|name| tables.iter().any(|table| table == name))
+            {
+                return Err(not_a_fileset("is a SQLite database of another application"));
+            }
+        }
+        _ => return Err(not_a_fileset("is a SQLite database of another application")),
+    }
+    if user_version > FILESET_FORMAT_VERSION {
+        return Err(Error::Migration(format!(
+            "{} uses fileset format {user_version}; this build reads up to {FILESET_FORMAT_VERSION}",
+            path.display()
+        )));
+    }
+    Ok(Some(user_version))
+}
+
+fn sidecar_extension(mime: &str) -> &'static str {
+    match mime {
+        "image/jpeg" => "jpg",
+        "image/png" => "png",
+        _ => "avif",
+    }
+}
+
+const POOL_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone)]
+pub struct SqliteStorePool {
+    inner: Arc<PoolInner>,
+}
+
+struct PoolInner {
+    path: PathBuf,
+    max_conns: usize,
+    slots: Mutex<PoolSlots>,
+    returned: Condvar,
+}
+
+struct PoolSlots {
+    idle: Vec<SqliteScanStore>,
+    open: usize,
+}
+
+impl SqliteStorePool {
+    pub fn path(&self) -> &Path {
+        &self.inner.path
+    }
+
+    /// Borrows a connection, opening a new one while under `max_conns` and otherwise
+    /// blocking until another thread returns one.
+    pub fn get(&self) -> Result<PooledStore> {
+        let mut slots = self.inner.slots.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            if let Some(store) = slots.idle.pop() {
+                return Ok(self.pooled(store));
+            }
+            if slots.open < self.inner.max_conns {
+                slots.open += 1;
+                drop(slots);
+                return match SqliteScanStore::open_pooled(&self.inner.path) {
+                    Ok(store) => Ok(self.pooled(store)),
+                    Err(err) => {
+                        let mut slots = self.inner.slots.lock().unwrap_or_else(|e| e.into_inner());
+                        slots.open -= 1;
+                        self.inner.returned.notify_one();
+                        Err(err)
+                    }
+                };
+            }
+            slots = self
+                .inner
+                .returned
+                .wait(slots)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    fn pooled(&self, store: SqliteScanStore) -> PooledStore {
+        PooledStore {
+            store: Some(store),
+            pool: self.inner.clone(),
+        }
+    }
+}
+
+/// A connection borrowed from an `SqliteStorePool`; returned to it on drop.
+pub struct PooledStore {
+    store: Option<SqliteScanStore>,
+    pool: Arc<PoolInner>,
+}
+
+impl std::ops::Deref for PooledStore {
+    type Target = SqliteScanStore;
+
+    fn deref(&self) -> &SqliteScanStore {
+        self.store.as_ref().expect("pooled store used after drop")
+    }
+}
+
+impl Drop for PooledStore {
+    fn drop(&mut self) {
+        if let Some(store) = self.store.take() {
+            let mut slots = self.pool.slots.lock().unwrap_or_else(|e| e.into_inner());
+            slots.idle.push(store);
+            self.pool.returned.notify_one();
+        }
+    }
+}
+
+/// `WHERE` condition on `files f1` for non-empty files with an exact duplicate.
+/// With `include_ignored` false, groups marked by `ignore_group` are left out.
+fn duplicate_filter_sql(id_col: &str, include_ignored: bool) -> String {
+    let ignored = if include_ignored {
+        ""
+    } else {
+        "AND COALESCE(f1.blake3, f1.sha256) NOT IN (SELECT hash FROM ignored_groups)"
+    };
+    format!(
+        r#"((
+            f1.size_bytes > 0
+            AND f1.blake3 IS NOT NULL
+            AND EXISTS (
+              SELECT 1 FROM files f2
+              WHERE f2.blake3 = f1.blake3 AND f2.{id_col} != f1.{id_col}
+            )
+          ) OR (
+            f1.size_bytes > 0
+            AND f1.blake3 IS NULL
+            AND f1.sha256 IS NOT NULL
+            AND EXISTS (
+              SELECT 1 FROM files f2
+              WHERE f2.sha256 = f1.sha256 AND f2.{id_col} != f1.{id_col}
+            )
+          )) {ignored}"#
+    )
+}
+
+const FILE_ITER_PAGE: usize = 1000;
+
+/// Backs `iter_files`. rusqlite rows borrow their statement, so rather than hold one
+/// open query this steps a cached statement keyed on the last path seen. Each page is
+/// an index seek on the unique `path`, unlike `OFFSET`, which rescans skipped rows.
+struct FileRowIter<'a> {
+    store: &'a SqliteScanStore,
+    duplicates_only: bool,
+    after: PathBuf,
+    page: std::vec::IntoIter<FileListRow>,
+    done: bool,
+}
+
+impl<'a> FileRowIter<'a> {
+    fn new(store: &'a SqliteScanStore, duplicates_only: bool) -> Self {
+        Self {
+            store,
+            duplicates_only,
+            after: PathBuf::new(),
+            page: Vec::new().into_iter(),
+            done: false,
+        }
+    }
+
+    fn next_page(&self) -> Result<Vec<FileListRow>> {
+        let id_col = self.store.file_id_column();
+        let filter = if self.duplicates_only {
+            format!("AND ({})", duplicate_filter_sql(id_col, true))
+        } else {
+            String::new()
+        };
+        let sql = format!(
+            r#"
+            SELECT f1.{id_col} AS id, f1.path, f1.size_bytes, f1.modified_at_secs, f1.blake3, f1.sha256, f1.ahash, f1.dhash, f1.phash, f1.ffmpeg_metadata, f1.file_type
+            FROM files f1
+            WHERE f1.path > ?1 {filter}
+            ORDER BY f1.path
+            LIMIT ?2
+            "#
+        );
+        let mut stmt = self.store.conn.prepare_cached(&sql)?;
+        let rows = stmt.query_map(
+            params![SqlPath(&self.after), FILE_ITER_PAGE as i64],
+            file_list_row_from_sql,
+        )?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+}
+
+impl Iterator for FileRowIter<'_> {
+    type Item = Result<FileListRow>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(row) = self.page.next() {
+            return Some(Ok(row));
+        }
+        if self.done {
+            return None;
+        }
+        match self.next_page() {
+            Ok(rows) => {
+                self.done = rows.len() < FILE_ITER_PAGE;
+                if let Some(last) = rows.last() {
+                    self.after = last.path.clone();
+                }
+                self.page = rows.into_iter();
+                self.page.next().map(Ok)
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Maps the `id, path, size_bytes, modified_at_secs, blake3, sha256, ahash, dhash, phash,
+/// ffmpeg_metadata, file_type` column list used by the file listing queries.
+fn file_list_row_from_sql(r: &rusqlite::Row<'_>) -> rusqlite::Result<FileListRow> {
+    let modified_at_secs: Option<i64> = r.get(3)?;
+    let blake3: Option<Vec<u8>> = r.get(4)?;
+    let sha256: Option<Vec<u8>> = r.get(5)?;
+    let ahash: Option<i64> = r.get(6)?;
+    let dhash: Option<i64> = r.get(7)?;
+    let phash: Option<i64> = r.get(8)?;
+    Ok(FileListRow {
+        id: r.get(0)?,
+        path: r.get::<_, StoredPath>(1)?.0,
+        size_bytes: r.get::<_, i64>(2)? as u64,
+        modified_at: modified_at_secs.map(|v| secs_to_system_time(v.max(0) as u64)),
+        blake3: blob_to_hash(blake3),
+        sha256: blob_to_hash(sha256),
+        ahash: ahash.map(|v| v as u64),
+        dhash: dhash.map(|v| v as u64),
+        phash: phash.map(|v| v as u64),
+        ffmpeg_metadata: r.get(9)?,
+        file_type: r.get(10)?,
+    })
+}
+
+/// Maps the `container, video_codec, audio_codec, width, height, bit_rate` columns
+/// starting at index `first`.
+fn media_info_from_sql(r: &rusqlite::Row<'_>, first: usize) -> rusqlite::Result<MediaInfo> {
+    let bit_rate: Option<i64> = r.get(first + 5)?;
+    Ok(MediaInfo {
+        container: r.get(first)?,
+        video_codec: r.get(first + 1)?,
+        audio_codec: r.get(first + 2)?,
+        width: r.get(first + 3)?,
+        height: r.get(first + 4)?,
+        bit_rate: bit_rate.map(|v| v.max(0) as u64),
+    })
+}
+
+/// Whether two rows for the same path describe the same content: equal sizes, and
+/// equal hashes wherever both have one.
+fn same_content(a: &MediaFileRecord, b: &MediaFileRecord) -> bool {
+    if a.size_bytes != b.size_bytes {
+        return false;
+    }
+    if let (Some(x), Some(y)) = (a.blake3, b.blake3) {
+        return x == y;
+    }
+    match (a.sha256, b.sha256) {
+        (Some(x), Some(y)) => x == y,
+        _ => true,
+    }
+}
+
+/// Copies into `ours` whatever `theirs` has and `ours` lacks; true if anything was.
+fn fill_missing(ours: &mut MediaFileRecord, theirs: &MediaFileRecord) -> bool {
+    fn fill<T: Clone>(slot: &mut Option<T>, other: &Option<T>) -> bool {
+        if slot.is_none() && other.is_some() {
+            *slot = other.clone();
+            return true;
+        }
+        false
+    }
+    let mut filled = false;
+    filled |= fill(&mut ours.blake3, &theirs.blake3);
+    filled |= fill(&mut ours.sha256, &theirs.sha256);
+    filled |= fill(&mut ours.ahash, &theirs.ahash);
+    filled |= fill(&mut ours.dhash, &theirs.dhash);
+    filled |= fill(&mut ours.phash, &theirs.phash);
+    filled |= fill(&mut ours.ffmpeg_metadata, &theirs.ffmpeg_metadata);
+    filled |= fill(&mut ours.file_type, &theirs.file_type);
+    if ours.media == MediaInfo::default() && theirs.media != MediaInfo::default() {
+        ours.media = theirs.media.clone();
+        filled = true;
+    }
+    filled
+}
+
+/// Binds a path as TEXT when it is valid UTF-8. Other Unix paths are stored as a BLOB
+/// of their raw bytes, so names that are not UTF-8 can still be reopened and deleted;
+/// `StoredPath` reads either form back.
+struct SqlPath<'a>(&'a Path);
+
+impl rusqlite::ToSql for SqlPath<'_> {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        if let Some(text) = self.0.to_str() {
+            return Ok(ToSqlOutput::from(text));
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            Ok(ToSqlOutput::from(self.0.as_os_str().as_bytes()))
+        }
+        #[cfg(not(unix))]
+        Ok(ToSqlOutput::from(self.0.to_string_lossy().into_owned()))
+    }
+}
+
+struct StoredPath(PathBuf);
+
+impl FromSql for StoredPath {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let bytes = match value {
+            ValueRef::Text(bytes) | ValueRef::Blob(bytes) => bytes,
+            _ => return Err(FromSqlError::InvalidType),
+        };
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            Ok(Self(std::ffi::OsStr::from_bytes(bytes).into()))
+        }
+        #[cfg(not(unix))]
+        Ok(Self(String::from_utf8_lossy(bytes).into_owned().into()))
+    }
 }
 
 fn blob_to_hash(blob: Option<Vec<u8>>) -> Option<[u8; 32]> {
@@ -706,3 +2174,398 @@ fn str_to_root_kind(s: &str) -> ScanRootKind {
         _ => ScanRootKind::Folder,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(path: &str, size_bytes: u64, blake3: Option<[u8; 32]>) -> MediaFileRecord {
+        MediaFileRecord {
+            file_id: None,
+            path: PathBuf::from(path),
+            size_bytes,
+            modified_at: Some(secs_to_system_time(1_700_000_000)),
+            blake3,
+            sha256: None,
+            ahash: None,
+            dhash: None,
+            phash: None,
+            ffmpeg_metadata: None,
+            file_type: None,
+            media: MediaInfo::default(),
+        }
+    }
+
+    fn chunk(hash: u64, len: u32) -> ChunkFingerprint {
+        ChunkFingerprint { hash, len }
+    }
+
+    #[test]
+    fn needs_rehash_compares_size_and_mtime() {
+        let store = SqliteScanStore::open_in_memory().unwrap();
+        store.upsert_file(&record("a", 10, None)).unwrap();
+        store
+            .upsert_file(&MediaFileRecord {
+                modified_at: None,
+                ..record("no-mtime", 10, None)
+            })
+            .unwrap();
+
+        let rehash = |path: &str, size, mtime| store.needs_rehash(Path::new(path), size, mtime);
+        assert!(rehash("missing", 10, 1_700_000_000).unwrap());
+        assert!(!rehash("a", 10, 1_700_000_000).unwrap());
+        assert!(rehash("a", 11, 1_700_000_000).unwrap());
+        assert!(rehash("a", 10, 1_700_000_001).unwrap());
+        assert!(rehash("no-mtime", 10, 1_700_000_000).unwrap());
+    }
+
+    fn paths(rows: &[FileListRow]) -> Vec<String> {
+        rows.iter()
+            .map(|row| row.path.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    /// `a`, `c` and `e` share content; `b` and `d` are unique; `z` is empty.
+    fn listing_store() -> SqliteScanStore {
+        let store = SqliteScanStore::open_in_memory().unwrap();
+        for (path, hash) in [("e", 1), ("d", 4), ("c", 1), ("b", 2), ("a", 1)] {
+            store
+                .upsert_file(&record(path, 10, Some([hash; 32])))
+                .unwrap();
+        }
+        store.upsert_file(&record("z", 0, Some([0; 32]))).unwrap();
+        store
+    }
+
+    #[test]
+    fn list_files_pages_by_path() {
+        let store = listing_store();
+        assert_eq!(paths(&store.list_files(2, 0).unwrap()), ["a", "b"]);
+        assert_eq!(paths(&store.list_files(2, 2).unwrap()), ["c", "d"]);
+        assert_eq!(paths(&store.list_files(2, 4).unwrap()), ["e", "z"]);
+        assert!(store.list_files(2, 6).unwrap().is_empty());
+    }
+
+    #[test]
+    fn list_files_with_duplicates_pages_and_skips_ignored_groups() {
+        let store = listing_store();
+        assert_eq!(
+            paths(&store.list_files_with_duplicates(false, 2, 0).unwrap()),
+            ["a", "c"]
+        );
+        assert_eq!(
+            paths(&store.list_files_with_duplicates(false, 2, 2).unwrap()),
+            ["e"]
+        );
+
+        store.ignore_group(&[1; 32]).unwrap();
+        assert!(store
+            .list_files_with_duplicates(false, 10, 0)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            paths(&store.list_files_with_duplicates(true, 10, 0).unwrap()),
+            ["a", "c", "e"]
+        );
+    }
+
+    #[test]
+    fn lookups_by_id_and_path_agree() {
+        let store = listing_store();
+        let c = store.get_file_by_path(Path::new("c")).unwrap().unwrap();
+        let id = c.file_id.unwrap();
+        assert_eq!(store.get_file_by_id(id).unwrap().unwrap().path, c.path);
+        assert!(store.get_file_by_path(Path::new("nope")).unwrap().is_none());
+        assert!(store.get_file_by_id(10_000).unwrap().is_none());
+
+        assert_eq!(
+            paths(&store.list_direct_matches_by_blake3(id).unwrap()),
+            ["a", "e"]
+        );
+        let b = store.get_file_by_path(Path::new("b")).unwrap().unwrap();
+        assert!(store
+            .list_direct_matches_by_blake3(b.file_id.unwrap())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn file_snapshots_come_back_in_index_order() {
+        let store = listing_store();
+        let id = store
+            .get_file_by_path(Path::new("a"))
+            .unwrap()
+            .unwrap()
+            .file_id
+            .unwrap();
+        let snapshot = |index| FileSnapshotRecord {
+            snapshot_index: index,
+            snapshot_count: 2,
+            at_ms: i64::from(index) * 1000,
+            duration_ms: Some(2000),
+            ahash: None,
+            dhash: None,
+            phash: None,
+            mime: "image/png".to_string(),
+            image_avif: vec![index as u8; 4],
+        };
+        store
+            .replace_file_snapshots(id, &[snapshot(1), snapshot(0)])
+            .unwrap();
+        let indexes: Vec<u32> = store
+            .list_file_snapshots(id)
+            .unwrap()
+            .iter()
+            .map(|snap| snap.snapshot_index)
+            .collect();
+        assert_eq!(indexes, [0, 1]);
+        assert_eq!(store.list_file_snapshots(id).unwrap()[1].image_avif, [1; 4]);
+    }
+
+    #[test]
+    fn files_sharing_chunks_sums_shared_lengths() {
+        let store = SqliteScanStore::open_in_memory().unwrap();
+        let a = store.upsert_file(&record("a", 600, None)).unwrap();
+        let b = store.upsert_file(&record("b", 500, None)).unwrap();
+        let c = store.upsert_file(&record("c", 100, None)).unwrap();
+        store
+            .replace_file_chunks(a, &[chunk(1, 100), chunk(2, 200), chunk(3, 300)])
+            .unwrap();
+        // Chunk 2 twice still counts once, and a hash match with another length is
+        // not a shared chunk.
+        store
+            .replace_file_chunks(b, &[chunk(2, 200), chunk(2, 200), chunk(3, 300)])
+            .unwrap();
+        store.replace_file_chunks(c, &[chunk(1, 99)]).unwrap();
+
+        let overlaps = store.files_sharing_chunks(1).unwrap();
+        assert_eq!(overlaps.len(), 1);
+        assert_eq!(overlaps[0].a.path, PathBuf::from("a"));
+        assert_eq!(overlaps[0].b.path, PathBuf::from("b"));
+        assert_eq!(overlaps[0].shared_bytes, 500);
+        assert!(store.files_sharing_chunks(501).unwrap().is_empty());
+
+        store.replace_file_chunks(b, &[]).unwrap();
+        assert!(store.files_sharing_chunks(1).unwrap().is_empty());
+    }
+
+    /// Checks the trigger-kept `hash_counts` summaries against grouping `files` afresh.
+    fn assert_hash_counts_match_files(store: &SqliteScanStore) {
+        let (groups, reclaimable): (i64, i64) = store
+            .conn
+            .query_row(
+                "SELECT COUNT(*), COALESCE(SUM(total - total / count), 0) FROM ( \
+                   SELECT COUNT(*) AS count, SUM(size_bytes) AS total FROM files \
+                   WHERE size_bytes > 0 AND COALESCE(blake3, sha256) IS NOT NULL \
+                   GROUP BY COALESCE(blake3, sha256) \
+                 ) WHERE count > 1",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(store.duplicate_group_count().unwrap(), groups as u64);
+        assert_eq!(store.reclaimable_bytes().unwrap(), reclaimable as u64);
+    }
+
+    fn sha256_record(path: &str, size_bytes: u64, sha256: [u8; 32]) -> MediaFileRecord {
+        MediaFileRecord {
+            sha256: Some(sha256),
+            ..record(path, size_bytes, None)
+        }
+    }
+
+    /// Inserts, rehashes, a blake3 dropped for sha256, a delete and empty files.
+    fn churn_files(store: &SqliteScanStore) {
+        for path in ["a", "b", "c"] {
+            store.upsert_file(&record(path, 10, Some([1; 32]))).unwrap();
+        }
+        for path in ["d", "e"] {
+            store.upsert_file(&record(path, 20, Some([2; 32]))).unwrap();
+        }
+        store.upsert_file(&sha256_record("g", 7, [7; 32])).unwrap();
+        store
+            .upsert_file(&MediaFileRecord {
+                sha256: Some([7; 32]),
+                ..record("h", 7, Some([4; 32]))
+            })
+            .unwrap();
+
+        store.upsert_file(&record("c", 20, Some([2; 32]))).unwrap();
+        store.upsert_file(&sha256_record("h", 7, [7; 32])).unwrap();
+        assert!(store.delete_file_by_path(Path::new("e")).unwrap());
+        store.upsert_file(&record("y", 0, Some([0; 32]))).unwrap();
+        store.upsert_file(&record("z", 0, Some([0; 32]))).unwrap();
+    }
+
+    #[test]
+    fn hash_counts_follow_inserts_updates_and_deletes() {
+        let store = SqliteScanStore::open_in_memory().unwrap();
+        churn_files(&store);
+
+        // {a, b} by blake3, {c, d} after c's rehash, {g, h} by sha256; y and z are empty.
+        assert_eq!(store.duplicate_group_count().unwrap(), 3);
+        assert_eq!(store.reclaimable_bytes().unwrap(), 10 + 20 + 7);
+        assert_hash_counts_match_files(&store);
+    }
+
+    #[test]
+    fn opening_a_fileset_without_hash_counts_backfills_them() {
+        let dir = crate::test_support::TempDir::new("hash-counts-backfill");
+        let path = dir.path().join("old.ddn");
+        {
+            let store = SqliteScanStore::open(&path).unwrap();
+            store
+                .conn
+                .execute_batch(
+                    "DROP TRIGGER files_hash_counts_insert; DROP TRIGGER files_hash_counts_delete; \
+                     DROP TRIGGER files_hash_counts_update_old; DROP TRIGGER files_hash_counts_update_new; \
+                     DROP TABLE hash_counts;",
+                )
+                .unwrap();
+            // Written with no triggers to count them, as an older build would have.
+            churn_files(&store);
+        }
+
+        let store = SqliteScanStore::open(&path).unwrap();
+        assert_eq!(store.duplicate_group_count().unwrap(), 3);
+        assert_eq!(store.reclaimable_bytes().unwrap(), 10 + 20 + 7);
+        // The triggers are back too, so later writes keep the counts right.
+        store.upsert_file(&record("b", 10, Some([5; 32]))).unwrap();
+        assert_eq!(store.duplicate_group_count().unwrap(), 2);
+        assert_hash_counts_match_files(&store);
+    }
+
+    fn metadata_for_root(root: &str) -> FilesetMetadata {
+        FilesetMetadata {
+            created_at: secs_to_system_time(1_700_000_000),
+            root_kind: ScanRootKind::Folder,
+            root_path: PathBuf::from(root),
+            root_parent_path: None,
+            drive: DriveMetadata {
+                id: None,
+                label: None,
+                fs_type: None,
+            },
+            host_os: String::new(),
+            host_os_version: String::new(),
+            app_version: String::new(),
+            status: String::new(),
+            name: String::new(),
+            description: String::new(),
+            notes: String::new(),
+        }
+    }
+
+    #[test]
+    fn merge_from_adds_fills_and_reports_conflicts() {
+        let dir = crate::test_support::TempDir::new("merge-from");
+        let into = dir.path().join("into.ddn");
+        let from = dir.path().join("from.ddn");
+        let target = SqliteScanStore::open(&into).unwrap();
+        target
+            .set_fileset_metadata(&metadata_for_root("/a"))
+            .unwrap();
+        target
+            .upsert_file(&record("same", 10, Some([1; 32])))
+            .unwrap();
+        target
+            .upsert_file(&record("kept", 10, Some([2; 32])))
+            .unwrap();
+        target
+            .upsert_file(&record("clash", 10, Some([3; 32])))
+            .unwrap();
+        {
+            let source = SqliteScanStore::open(&from).unwrap();
+            source
+                .set_fileset_metadata(&metadata_for_root("/b"))
+                .unwrap();
+            source
+                .upsert_file(&MediaFileRecord {
+                    phash: Some(7),
+                    ..record("same", 10, Some([1; 32]))
+                })
+                .unwrap();
+            source
+                .upsert_file(&record("kept", 10, Some([2; 32])))
+                .unwrap();
+            source
+                .upsert_file(&record("clash", 10, Some([9; 32])))
+                .unwrap();
+            let new_id = source
+                .upsert_file(&record("new", 10, Some([4; 32])))
+                .unwrap();
+            let snapshot = FileSnapshotRecord {
+                snapshot_index: 0,
+                snapshot_count: 1,
+                at_ms: 0,
+                duration_ms: Some(1000),
+                ahash: None,
+                dhash: None,
+                phash: None,
+                mime: "image/png".to_string(),
+                image_avif: vec![1; 4],
+            };
+            source.replace_file_snapshots(new_id, &[snapshot]).unwrap();
+        }
+        let before = schema_snapshot(&from);
+
+        let report = target.merge_from(&from).unwrap();
+        assert_eq!((report.added, report.updated, report.unchanged), (1, 1, 1));
+        assert_eq!(report.conflicts, [PathBuf::from("clash")]);
+        assert_eq!(
+            report.root_mismatch,
+            Some((PathBuf::from("/a"), PathBuf::from("/b")))
+        );
+
+        let get = |path: &str| target.get_file_by_path(Path::new(path)).unwrap().unwrap();
+        assert_eq!(get("same").phash, Some(7));
+        assert_eq!(get("clash").blake3, Some([3; 32]));
+        let new_id = get("new").file_id.unwrap();
+        assert_eq!(
+            target.list_file_snapshots(new_id).unwrap()[0].image_avif,
+            [1; 4]
+        );
+        assert_eq!(schema_snapshot(&from), before);
+    }
+
+    /// Schema objects and format markers, to check a fileset was left alone.
+    fn schema_snapshot(path: &Path) -> (Vec<String>, (i32, u32)) {
+        let conn = Connection::open(path).unwrap();
+        let names = conn
+            .prepare("SELECT name FROM sqlite_master ORDER BY name")
+            .unwrap()
+            .query_map([], |r| r.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        (names, fileset_marker(&conn).unwrap())
+    }
+
+    #[test]
+    fn read_only_open_leaves_an_older_fileset_untouched() {
+        let dir = crate::test_support::TempDir::new("read-only-open");
+        let path = dir.path().join("old.ddn");
+        {
+            let store = SqliteScanStore::open(&path).unwrap();
+            store.upsert_file(&record("a", 10, Some([1; 32]))).unwrap();
+            // What a fileset from before hash counts and format markers looks like.
+            store
+                .conn
+                .execute_batch(
+                    "DROP TRIGGER files_hash_counts_insert; DROP TRIGGER files_hash_counts_delete; \
+                     DROP TRIGGER files_hash_counts_update_old; DROP TRIGGER files_hash_counts_update_new; \
+                     DROP TABLE hash_counts; PRAGMA application_id = 0; PRAGMA user_version = 0;",
+                )
+                .unwrap();
+        }
+        let before = schema_snapshot(&path);
+
+        let store = SqliteScanStore::open_read_only(&path).unwrap();
+        assert!(!store
+            .needs_rehash(Path::new("a"), 10, 1_700_000_000)
+            .unwrap());
+        assert!(store.upsert_file(&record("b", 10, None)).is_err());
+        drop(store);
+        assert_eq!(schema_snapshot(&path), before);
+    }
+}
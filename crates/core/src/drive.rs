@@ -6,17 +6,60 @@ use crate::models::DriveMetadata;
 pub fn probe_for_path(path: &Path) -> Result<DriveMetadata> {
     #[cfg(target_os = "linux")]
     {
-        return linux::probe_for_path(path);
+        linux::probe_for_path(path)
     }
 
-    #[cfg(not(target_os = "linux"))]
+    #[cfg(target_os = "macos")]
+    {
+        macos::probe_for_path(path)
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        windows::probe_for_path(path)
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
     {
         let _ = path;
-        return Ok(DriveMetadata {
+        Ok(DriveMetadata {
             id: None,
             label: None,
             fs_type: None,
-        });
+        })
+    }
+}
+
+/// Bytes an unprivileged process can still write on the filesystem holding `path`.
+pub fn available_space(path: &Path) -> Option<u64> {
+    #[cfg(unix)]
+    {
+        use std::ffi::CString;
+        use std::os::unix::ffi::OsStrExt;
+
+        let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+        let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+        // SAFETY: `c_path` is NUL-terminated and `stat` is only read after statvfs succeeds.
+        let stat = unsafe {
+            if libc::statvfs(c_path.as_ptr(), stat.as_mut_ptr()) != 0 {
+                return None;
+            }
+            stat.assume_init()
+        };
+        // The field widths differ between platforms.
+        #[allow(clippy::unnecessary_cast)]
+        Some((stat.f_bavail as u64).saturating_mul(stat.f_frsize as u64))
+    }
+
+    #[cfg(windows)]
+    {
+        windows::available_space(path)
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = path;
+        None
     }
 }
 
@@ -134,3 +177,256 @@ mod linux {
         ))
     }
 }
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+
+    use crate::error::Result;
+    use crate::models::DriveMetadata;
+
+    /// `mount` prints the same `f_mntfromname`/`f_fstypename` pairs that `statfs`
+    /// returns, and `diskutil` supplies the volume UUID and name for local disks.
+    pub fn probe_for_path(path: &Path) -> Result<DriveMetadata> {
+        let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let Some(mount) = best_mount_for_path(&canonical) else {
+            return Ok(DriveMetadata {
+                id: None,
+                label: None,
+                fs_type: None,
+            });
+        };
+
+        let (uuid, name) = if mount.mount_source.starts_with("/dev/") {
+            diskutil_volume_info(&mount.mount_source)
+        } else {
+            (None, None)
+        };
+        let label = name.or_else(|| {
+            mount
+                .mount_point
+                .strip_prefix("/Volumes")
+                .ok()
+                .and_then(|rest| rest.file_name())
+                .map(|name| name.to_string_lossy().to_string())
+        });
+
+        Ok(DriveMetadata {
+            id: uuid,
+            label,
+            fs_type: Some(mount.fs_type),
+        })
+    }
+
+    #[derive(Debug, Clone)]
+    struct MountInfo {
+        mount_point: PathBuf,
+        fs_type: String,
+        mount_source: String,
+    }
+
+    fn best_mount_for_path(path: &Path) -> Option<MountInfo> {
+        let output = Command::new("/sbin/mount").output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let text = String::from_utf8_lossy(&output.stdout);
+        text.lines()
+            .filter_map(parse_mount_line)
+            .filter(|mount| path.starts_with(&mount.mount_point))
+            .max_by_key(|mount| mount.mount_point.as_os_str().len())
+    }
+
+    /// Parses `<source> on <mount point> (<fstype>, <options>...)`.
+    fn parse_mount_line(line: &str) -> Option<MountInfo> {
+        let (source, rest) = line.split_once(" on ")?;
+        let (mount_point, details) = rest.rsplit_once(" (")?;
+        let fs_type = details
+            .trim_end_matches(')')
+            .split(',')
+            .next()?
+            .trim()
+            .to_string();
+        if fs_type.is_empty() {
+            return None;
+        }
+        Some(MountInfo {
+            mount_point: PathBuf::from(mount_point),
+            fs_type,
+            mount_source: source.to_string(),
+        })
+    }
+
+    fn diskutil_volume_info(device: &str) -> (Option<String>, Option<String>) {
+        let Ok(output) = Command::new("/usr/sbin/diskutil")
+            .args(["info", "-plist", device])
+            .output()
+        else {
+            return (None, None);
+        };
+        if !output.status.success() {
+            return (None, None);
+        }
+        let plist = String::from_utf8_lossy(&output.stdout);
+        (
+            plist_string(&plist, "VolumeUUID"),
+            plist_string(&plist, "VolumeName"),
+        )
+    }
+
+    /// Reads a top-level `<key>name</key><string>value</string>` pair.
+    fn plist_string(plist: &str, key: &str) -> Option<String> {
+        let marker = format!("<key>{key}</key>");
+        let after_key = &plist[plist.find(&marker)? + marker.len()..];
+        let value = after_key.trim_start().strip_prefix("<string>")?;
+        let value = &value[..value.find("</string>")?];
+        let value = value
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&amp;", "&");
+        if value.is_empty() {
+            None
+        } else {
+            Some(value)
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod windows {
+    use std::ffi::OsStr;
+    use std::os::windows::ffi::OsStrExt;
+    use std::path::Path;
+    use std::ptr;
+
+    use windows_sys::Win32::Storage::FileSystem::{
+        GetDiskFreeSpaceExW, GetVolumeInformationW, GetVolumeNameForVolumeMountPointW,
+        GetVolumePathNameW,
+    };
+
+    use crate::error::Result;
+    use crate::models::DriveMetadata;
+
+    /// Large enough for any volume path, label or `\\?\Volume{GUID}\` name.
+    const BUF_LEN: usize = 1024;
+
+    pub fn probe_for_path(path: &Path) -> Result<DriveMetadata> {
+        let Some(root) = volume_path_for(path) else {
+            return Ok(DriveMetadata {
+                id: None,
+                label: None,
+                fs_type: None,
+            });
+        };
+        let root_w = to_wide(OsStr::new(&root));
+
+        let mut label = vec![0u16; BUF_LEN];
+        let mut fs_name = vec![0u16; BUF_LEN];
+        let mut serial = 0u32;
+        // SAFETY: `root_w` is NUL-terminated and both buffers are `BUF_LEN` elements long.
+        let info_ok = unsafe {
+            GetVolumeInformationW(
+                root_w.as_ptr(),
+                label.as_mut_ptr(),
+                BUF_LEN as u32,
+                &mut serial,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                fs_name.as_mut_ptr(),
+                BUF_LEN as u32,
+            )
+        } != 0;
+
+        let mut volume_name = vec![0u16; BUF_LEN];
+        // SAFETY: `root_w` is a NUL-terminated mount point with a trailing backslash and
+        // `volume_name` is `BUF_LEN` elements long.
+        let guid_ok = unsafe {
+            GetVolumeNameForVolumeMountPointW(
+                root_w.as_ptr(),
+                volume_name.as_mut_ptr(),
+                BUF_LEN as u32,
+            )
+        } != 0;
+
+        // Network shares have no volume GUID; their serial number is the next best id.
+        let id = if guid_ok {
+            non_empty(from_wide(&volume_name)).map(|name| volume_guid(&name))
+        } else if info_ok && serial != 0 {
+            Some(format!("{:04X}-{:04X}", serial >> 16, serial & 0xFFFF))
+        } else {
+            None
+        };
+        let (label, fs_type) = if info_ok {
+            (non_empty(from_wide(&label)), non_empty(from_wide(&fs_name)))
+        } else {
+            (None, None)
+        };
+
+        Ok(DriveMetadata { id, label, fs_type })
+    }
+
+    pub fn available_space(path: &Path) -> Option<u64> {
+        let root_w = to_wide(OsStr::new(&volume_path_for(path)?));
+        let mut available = 0u64;
+        // SAFETY: `root_w` is NUL-terminated; the two totals we do not need may be null.
+        let ok = unsafe {
+            GetDiskFreeSpaceExW(
+                root_w.as_ptr(),
+                &mut available,
+                ptr::null_mut(),
+                ptr::null_mut(),
+            )
+        };
+        (ok != 0).then_some(available)
+    }
+
+    fn volume_path_for(path: &Path) -> Option<String> {
+        let path_w = to_wide(path.as_os_str());
+        let mut root = vec![0u16; BUF_LEN];
+        // SAFETY: `path_w` is NUL-terminated and `root` is `BUF_LEN` elements long.
+        let ok = unsafe { GetVolumePathNameW(path_w.as_ptr(), root.as_mut_ptr(), BUF_LEN as u32) };
+        if ok == 0 {
+            return None;
+        }
+        non_empty(from_wide(&root))
+    }
+
+    /// `\\?\Volume{GUID}\` -> `GUID`.
+    fn volume_guid(volume_name: &str) -> String {
+        volume_name
+            .split_once('{')
+            .and_then(|(_, rest)| rest.split_once('}'))
+            .map(|(guid, _)| guid.to_string())
+            .unwrap_or_else(|| volume_name.to_string())
+    }
+
+    fn to_wide(value: &OsStr) -> Vec<u16> {
+        value.encode_wide().chain(std::iter::once(0)).collect()
+    }
+
+    fn from_wide(buf: &[u16]) -> String {
+        let len = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
+        String::from_utf16_lossy(&buf[..len])
+    }
+
+    fn non_empty(value: String) -> Option<String> {
+        if value.is_empty() {
+            None
+        } else {
+            Some(value)
+        }
+    }
+}
+
+#[cfg(all(test, target_os = "macos"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn probing_root_reports_a_filesystem_type() {
+        let meta = probe_for_path(Path::new("/")).unwrap();
+        assert!(meta.fs_type.is_some(), "{meta:?}");
+    }
+}
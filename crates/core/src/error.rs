@@ -5,14 +5,77 @@ pub type Result<T> = std::result::Result<T, Error>;
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("I/O error: {0}")]
-    Io(#[from] std::io::Error),
+    Io(#[source] std::io::Error),
 
     #[error("SQLite error: {0}")]
-    Sqlite(#[from] rusqlite::Error),
+    Sqlite(#[source] rusqlite::Error),
+
+    /// Creating or upgrading the fileset schema failed.
+    #[error("schema migration failed: {0}")]
+    Migration(String),
+
+    #[error("not found: {0}")]
+    NotFound(String),
+
+    /// The file is not a SQLite database, or one without the fileset `application_id`.
+    #[error("not a dupdupninja fileset: {0}")]
+    NotAFileset(String),
 
     #[error("invalid argument: {0}")]
     InvalidArgument(String),
 
+    #[error("ffmpeg error: {0}")]
+    Ffmpeg(String),
+
+    #[error("trash error: {0}")]
+    Trash(String),
+
     #[error("scan cancelled")]
     Cancelled,
+
+    /// A write failed because its disk ran out of space. Converted from I/O and
+    /// SQLite errors, so `?` on writes surfaces it without extra mapping.
+    #[error("disk full: {0}")]
+    DiskFull(String),
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        if is_disk_full(&err) {
+            Error::DiskFull(err.to_string())
+        } else {
+            Error::Io(err)
+        }
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(err: rusqlite::Error) -> Self {
+        if err.sqlite_error_code() == Some(rusqlite::ErrorCode::DiskFull) {
+            Error::DiskFull(err.to_string())
+        } else {
+            Error::Sqlite(err)
+        }
+    }
+}
+
+// `io::ErrorKind::StorageFull` is newer than the crate's minimum Rust version.
+fn is_disk_full(err: &std::io::Error) -> bool {
+    #[cfg(unix)]
+    {
+        err.raw_os_error() == Some(libc::ENOSPC)
+    }
+
+    #[cfg(windows)]
+    {
+        use windows_sys::Win32::Foundation::{ERROR_DISK_FULL, ERROR_HANDLE_DISK_FULL};
+        let code = err.raw_os_error().map(|code| code as u32);
+        code == Some(ERROR_DISK_FULL) || code == Some(ERROR_HANDLE_DISK_FULL)
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = err;
+        false
+    }
 }
@@ -0,0 +1,80 @@
+use std::io::Write;
+
+use serde_json::json;
+
+use crate::db::SqliteScanStore;
+use crate::format::hex_encode;
+use crate::models::FileListRow;
+use crate::Result;
+
+/// Writes the fileset's rows as CSV with a header line, streaming them from the store.
+pub fn export_csv<W: Write>(
+    store: &SqliteScanStore,
+    out: &mut W,
+    duplicates_only: bool,
+) -> Result<()> {
+    writeln!(out, "id,path,size_bytes,file_type,blake3,sha256")?;
+    for row in export_rows(store, duplicates_only) {
+        let row = row?;
+        writeln!(
+            out,
+            "{},{},{},{},{},{}",
+            row.id,
+            csv_field(&row.path.to_string_lossy()),
+            row.size_bytes,
+            csv_field(row.file_type.as_deref().unwrap_or("")),
+            row.blake3.as_ref().map(hex_encode).unwrap_or_default(),
+            row.sha256.as_ref().map(hex_encode).unwrap_or_default(),
+        )?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Rows written between flushes by `export_jsonl`.
+const JSONL_FLUSH_EVERY: usize = 1000;
+
+/// Writes one JSON object per file per line, with the same fields as `export_csv`.
+/// Missing hashes and types are `null`.
+pub fn export_jsonl<W: Write>(
+    store: &SqliteScanStore,
+    out: &mut W,
+    duplicates_only: bool,
+) -> Result<()> {
+    for (index, row) in export_rows(store, duplicates_only).enumerate() {
+        let row = row?;
+        let line = json!({
+            "id": row.id,
+            "path": row.path.to_string_lossy(),
+            "size_bytes": row.size_bytes,
+            "file_type": row.file_type,
+            "blake3": row.blake3.as_ref().map(hex_encode),
+            "sha256": row.sha256.as_ref().map(hex_encode),
+        });
+        writeln!(out, "{line}")?;
+        if (index + 1) % JSONL_FLUSH_EVERY == 0 {
+            out.flush()?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn export_rows(
+    store: &SqliteScanStore,
+    duplicates_only: bool,
+) -> Box<dyn Iterator<Item = Result<FileListRow>> + '_> {
+    if duplicates_only {
+        Box::new(store.iter_files_with_duplicates())
+    } else {
+        Box::new(store.iter_files())
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
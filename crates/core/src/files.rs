@@ -0,0 +1,210 @@
+use std::ffi::OsStr;
+use std::path::Path;
+use std::process::Command;
+
+use crate::error::{Error, Result};
+
+/// Creates `dir` if needed and checks that a file can be created in it. An existing
+/// read-only folder or mount only fails at the first write otherwise.
+pub fn ensure_writable_dir(dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let probe = dir.join(format!(".dupdupninja-write-test-{}", std::process::id()));
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)?;
+    let _ = std::fs::remove_file(&probe);
+    Ok(())
+}
+
+/// Moves `path` to the platform trash (freedesktop Trash, Finder Trash or Recycle Bin).
+pub fn move_to_trash(path: &Path) -> Result<()> {
+    if std::fs::symlink_metadata(path).is_err() {
+        return Err(Error::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("{} does not exist", path.display()),
+        )));
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        if try_status_command("gio", &[OsStr::new("trash"), path.as_os_str()])? {
+            return Ok(());
+        }
+        for kioclient in ["kioclient5", "kioclient"] {
+            if try_status_command(
+                kioclient,
+                &[OsStr::new("move"), path.as_os_str(), OsStr::new("trash:/")],
+            )? {
+                return Ok(());
+            }
+        }
+        if try_status_command("trash-put", &[path.as_os_str()])? {
+            return Ok(());
+        }
+        if try_status_command("gvfs-trash", &[path.as_os_str()])? {
+            return Ok(());
+        }
+        return freedesktop::move_to_home_trash(path).map_err(|err| {
+            Error::Trash(format!(
+                "no trash command found (tried gio/kioclient5/kioclient/trash-put/gvfs-trash) and the home trash failed: {err}"
+            ))
+        });
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let escaped = path
+            .to_string_lossy()
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"");
+        let script = format!("tell application \"Finder\" to delete POSIX file \"{escaped}\"");
+        if try_status_command("osascript", &[OsStr::new("-e"), OsStr::new(&script)])? {
+            return Ok(());
+        }
+        return Err(Error::Trash(
+            "osascript failed to move file to trash".to_string(),
+        ));
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        let escaped = path.to_string_lossy().replace('\'', "''");
+        let script = format!(
+            "Add-Type -AssemblyName Microsoft.VisualBasic; [Microsoft.VisualBasic.FileIO.FileSystem]::DeleteFile('{escaped}', 'OnlyErrorDialogs', 'SendToRecycleBin')"
+        );
+        let args = [
+            OsStr::new("-NoProfile"),
+            OsStr::new("-NonInteractive"),
+            OsStr::new("-Command"),
+            OsStr::new(&script),
+        ];
+        if try_status_command("powershell", &args)? || try_status_command("pwsh", &args)? {
+            return Ok(());
+        }
+        return Err(Error::Trash(
+            "failed to move file to Recycle Bin via PowerShell".to_string(),
+        ));
+    }
+
+    #[allow(unreachable_code)]
+    Err(Error::Trash(
+        "trash is not implemented for this OS".to_string(),
+    ))
+}
+
+fn try_status_command(program: &str, args: &[&OsStr]) -> Result<bool> {
+    match Command::new(program).args(args).status() {
+        Ok(status) => Ok(status.success()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(err) => Err(Error::Io(err)),
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod freedesktop {
+    use std::fs::{self, OpenOptions};
+    use std::io::{self, Write};
+    use std::path::{Path, PathBuf};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    /// Home trash from the freedesktop.org Trash spec. Only works when `path` is on the
+    /// same filesystem as `$XDG_DATA_HOME`, since the file is renamed into place.
+    pub fn move_to_home_trash(path: &Path) -> io::Result<()> {
+        let absolute = fs::canonicalize(path.parent().unwrap_or(Path::new(".")))?
+            .join(path.file_name().unwrap_or_default());
+        let trash = home_trash_dir()?;
+        let files_dir = trash.join("files");
+        let info_dir = trash.join("info");
+        fs::create_dir_all(&files_dir)?;
+        fs::create_dir_all(&info_dir)?;
+
+        let base = absolute
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "file".to_string());
+        for attempt in 0u32.. {
+            let name = if attempt == 0 {
+                base.clone()
+            } else {
+                format!("{base}.{attempt}")
+            };
+            let info_path = info_dir.join(format!("{name}.trashinfo"));
+            let mut info = match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&info_path)
+            {
+                Ok(file) => file,
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(err),
+            };
+            let target = files_dir.join(&name);
+            if target.symlink_metadata().is_ok() {
+                drop(info);
+                let _ = fs::remove_file(&info_path);
+                continue;
+            }
+            let written = write!(
+                info,
+                "[Trash Info]\nPath={}\nDeletionDate={}\n",
+                percent_encode(&absolute),
+                deletion_date()
+            );
+            if let Err(err) = written.and_then(|_| fs::rename(&absolute, &target)) {
+                let _ = fs::remove_file(&info_path);
+                return Err(err);
+            }
+            return Ok(());
+        }
+        unreachable!("trash name attempts exhausted")
+    }
+
+    fn home_trash_dir() -> io::Result<PathBuf> {
+        if let Some(data) = std::env::var_os("XDG_DATA_HOME").filter(|v| !v.is_empty()) {
+            return Ok(PathBuf::from(data).join("Trash"));
+        }
+        std::env::var_os("HOME")
+            .filter(|v| !v.is_empty())
+            .map(|home| PathBuf::from(home).join(".local/share/Trash"))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "HOME is not set"))
+    }
+
+    fn percent_encode(path: &Path) -> String {
+        use std::os::unix::ffi::OsStrExt;
+        let mut out = String::new();
+        for &byte in path.as_os_str().as_bytes() {
+            if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+                out.push(byte as char);
+            } else {
+                out.push_str(&format!("%{byte:02X}"));
+            }
+        }
+        out
+    }
+
+    /// `YYYY-MM-DDThh:mm:ss` in UTC; the spec asks for local time, which std cannot provide.
+    fn deletion_date() -> String {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        let (days, rem) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+        // Civil-from-days, Howard Hinnant's algorithm.
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + i64::from(month <= 2);
+        format!(
+            "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}",
+            rem / 3600,
+            rem % 3600 / 60,
+            rem % 60
+        )
+    }
+}
@@ -0,0 +1,39 @@
+/// Formats a byte count with binary units, e.g. `1.50 MB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const KB: f64 = 1024.0;
+    const MB: f64 = KB * 1024.0;
+    const GB: f64 = MB * 1024.0;
+    const TB: f64 = GB * 1024.0;
+    let b = bytes as f64;
+    if b >= TB {
+        format!("{:.2} TB", b / TB)
+    } else if b >= GB {
+        format!("{:.2} GB", b / GB)
+    } else if b >= MB {
+        format!("{:.2} MB", b / MB)
+    } else if b >= KB {
+        format!("{:.2} KB", b / KB)
+    } else {
+        format!("{bytes} B")
+    }
+}
+
+/// Phrases a remaining time loosely, e.g. `about 12 minutes remaining`.
+pub fn format_eta(secs: u64) -> String {
+    let minutes = (secs + 30) / 60;
+    match minutes {
+        0 => "less than a minute remaining".to_string(),
+        1 => "about 1 minute remaining".to_string(),
+        2..=59 => format!("about {minutes} minutes remaining"),
+        _ => format!("about {} h {} min remaining", minutes / 60, minutes % 60),
+    }
+}
+
+/// Lowercase hex for a 32-byte digest.
+pub fn hex_encode(bytes: &[u8; 32]) -> String {
+    let mut out = String::with_capacity(64);
+    for byte in bytes {
+        out.push_str(&format!("{:02x}", byte));
+    }
+    out
+}
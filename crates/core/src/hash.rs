@@ -2,13 +2,46 @@ use std::fs::File;
 use std::io::{BufReader, Read};
 use std::path::Path;
 
-use crate::error::Result;
+use crate::error::{Error, Result};
+use crate::models::{ChunkFingerprint, HashAlgorithm};
+use crate::scan::ScanCancelToken;
+use image_hasher::{HashAlg, HasherConfig};
 use sha2::{Digest, Sha256};
 
-pub fn blake3_file(path: &Path) -> Result<[u8; 32]> {
+/// How much the read loops below read between checks of their cancel token.
+const CANCEL_CHECK_BYTES: usize = 8 * 1024 * 1024;
+
+/// Lets a read loop give up with `Error::Cancelled` partway through a large file.
+struct CancelCheck<'a> {
+    cancel: Option<&'a ScanCancelToken>,
+    unchecked_bytes: usize,
+}
+
+impl<'a> CancelCheck<'a> {
+    fn new(cancel: Option<&'a ScanCancelToken>) -> Self {
+        Self {
+            cancel,
+            unchecked_bytes: 0,
+        }
+    }
+
+    fn after_read(&mut self, read: usize) -> Result<()> {
+        self.unchecked_bytes += read;
+        if self.unchecked_bytes >= CANCEL_CHECK_BYTES {
+            self.unchecked_bytes = 0;
+            if self.cancel.is_some_and(ScanCancelToken::is_cancelled) {
+                return Err(Error::Cancelled);
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn blake3_file(path: &Path, cancel: Option<&ScanCancelToken>) -> Result<[u8; 32]> {
     let file = File::open(path)?;
     let mut reader = BufReader::new(file);
     let mut hasher = blake3::Hasher::new();
+    let mut cancel = CancelCheck::new(cancel);
 
     let mut buf = [0u8; 1024 * 128];
     loop {
@@ -17,15 +50,17 @@ pub fn blake3_file(path: &Path) -> Result<[u8; 32]> {
             break;
         }
         hasher.update(&buf[..read]);
+        cancel.after_read(read)?;
     }
 
     Ok(*hasher.finalize().as_bytes())
 }
 
-pub fn sha256_file(path: &Path) -> Result<[u8; 32]> {
+pub fn sha256_file(path: &Path, cancel: Option<&ScanCancelToken>) -> Result<[u8; 32]> {
     let file = File::open(path)?;
     let mut reader = BufReader::new(file);
     let mut hasher = Sha256::new();
+    let mut cancel = CancelCheck::new(cancel);
 
     let mut buf = [0u8; 1024 * 128];
     loop {
@@ -34,6 +69,7 @@ pub fn sha256_file(path: &Path) -> Result<[u8; 32]> {
             break;
         }
         hasher.update(&buf[..read]);
+        cancel.after_read(read)?;
     }
 
     let digest = hasher.finalize();
@@ -41,3 +77,307 @@ pub fn sha256_file(path: &Path) -> Result<[u8; 32]> {
     out.copy_from_slice(&digest);
     Ok(out)
 }
+
+/// Digests from `hash_file_multi`; algorithms that were not requested stay `None`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Hashes {
+    pub blake3: Option<[u8; 32]>,
+    pub sha256: Option<[u8; 32]>,
+}
+
+/// Hashes a file with every algorithm in `algos` from one read, feeding each chunk to
+/// all of the hashers. Matches `blake3_file` and `sha256_file` byte-for-byte. Fails
+/// with `Error::Cancelled` within a few MiB of `cancel` firing.
+pub fn hash_file_multi(
+    path: &Path,
+    algos: &[HashAlgorithm],
+    cancel: Option<&ScanCancelToken>,
+) -> Result<Hashes> {
+    let file = File::open(path)?;
+    let mut reader = BufReader::new(file);
+    let mut cancel = CancelCheck::new(cancel);
+    let mut blake3 = algos
+        .contains(&HashAlgorithm::Blake3)
+        .then(blake3::Hasher::new);
+    let mut sha256 = algos.contains(&HashAlgorithm::Sha256).then(Sha256::new);
+
+    let mut buf = [0u8; 1024 * 128];
+    loop {
+        let read = reader.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        if let Some(hasher) = blake3.as_mut() {
+            hasher.update(&buf[..read]);
+        }
+        if let Some(hasher) = sha256.as_mut() {
+            hasher.update(&buf[..read]);
+        }
+        cancel.after_read(read)?;
+    }
+
+    Ok(Hashes {
+        blake3: blake3.map(|hasher| *hasher.finalize().as_bytes()),
+        sha256: sha256.map(|hasher| {
+            let mut out = [0u8; 32];
+            out.copy_from_slice(&hasher.finalize());
+            out
+        }),
+    })
+}
+
+/// Compares two files byte-for-byte. Used before destructive dedupe actions so a
+/// stale or colliding hash never causes distinct content to be removed.
+pub fn files_identical(a: &Path, b: &Path) -> Result<bool> {
+    let file_a = File::open(a)?;
+    let file_b = File::open(b)?;
+    if file_a.metadata()?.len() != file_b.metadata()?.len() {
+        return Ok(false);
+    }
+    let mut reader_a = BufReader::new(file_a);
+    let mut reader_b = BufReader::new(file_b);
+
+    let mut buf_a = [0u8; 1024 * 64];
+    let mut buf_b = [0u8; 1024 * 64];
+    loop {
+        let read = read_full(&mut reader_a, &mut buf_a)?;
+        if read_full(&mut reader_b, &mut buf_b[..read.max(1)])? != read {
+            return Ok(false);
+        }
+        if read == 0 {
+            return Ok(true);
+        }
+        if buf_a[..read] != buf_b[..read] {
+            return Ok(false);
+        }
+    }
+}
+
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        let read = reader.read(&mut buf[filled..])?;
+        if read == 0 {
+            break;
+        }
+        filled += read;
+    }
+    Ok(filled)
+}
+
+/// Chunks are at least `CHUNK_MIN_LEN` and at most `CHUNK_MAX_LEN` bytes; past the
+/// minimum a boundary falls wherever the rolling hash has its top `CHUNK_MASK` bits
+/// clear, which averages about 1 MiB more.
+const CHUNK_MIN_LEN: usize = 256 * 1024;
+const CHUNK_MAX_LEN: usize = 4 * 1024 * 1024;
+const CHUNK_MASK: u64 = !(u64::MAX >> 20);
+
+/// Splits a file into content-defined chunks with a gear rolling hash, so an insertion
+/// or edit only changes the chunks around it and the rest line up with the original.
+/// Each chunk is identified by the first 8 bytes of its BLAKE3 hash.
+pub fn chunk_fingerprints(
+    path: &Path,
+    cancel: Option<&ScanCancelToken>,
+) -> Result<Vec<ChunkFingerprint>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut cancel = CancelCheck::new(cancel);
+    let mut chunks = Vec::new();
+    let mut hasher = blake3::Hasher::new();
+    let mut rolling = 0_u64;
+    let mut len = 0_usize;
+
+    let mut buf = vec![0u8; 1024 * 128];
+    loop {
+        let read = reader.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        let mut start = 0;
+        for (i, &byte) in buf[..read].iter().enumerate() {
+            rolling = (rolling << 1).wrapping_add(GEAR[byte as usize]);
+            len += 1;
+            if len >= CHUNK_MAX_LEN || (len >= CHUNK_MIN_LEN && rolling & CHUNK_MASK == 0) {
+                hasher.update(&buf[start..=i]);
+                chunks.push(finish_chunk(&mut hasher, len));
+                start = i + 1;
+                rolling = 0;
+                len = 0;
+            }
+        }
+        hasher.update(&buf[start..read]);
+        cancel.after_read(read)?;
+    }
+    if len > 0 {
+        chunks.push(finish_chunk(&mut hasher, len));
+    }
+    Ok(chunks)
+}
+
+fn finish_chunk(hasher: &mut blake3::Hasher, len: usize) -> ChunkFingerprint {
+    let digest = hasher.finalize();
+    hasher.reset();
+    let mut prefix = [0u8; 8];
+    prefix.copy_from_slice(&digest.as_bytes()[..8]);
+    ChunkFingerprint {
+        hash: u64::from_le_bytes(prefix),
+        len: len as u32,
+    }
+}
+
+/// Per-byte values for the gear hash, from a fixed splitmix64 sequence so chunk
+/// boundaries stay the same across builds.
+const GEAR: [u64; 256] = gear_table();
+
+const fn gear_table() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    let mut state = 0x6475_7064_7570_6e6a_u64;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+}
+
+/// aHash, dHash and pHash of a decoded image.
+pub(crate) fn perceptual_hashes(image: &image::DynamicImage) -> Option<(u64, u64, u64)> {
+    let ahash = hash_image_with_alg(image, HashAlg::Mean, false)?;
+    let dhash = hash_image_with_alg(image, HashAlg::Gradient, false)?;
+    let phash = hash_image_with_alg(image, HashAlg::Mean, true)?;
+    Some((ahash, dhash, phash))
+}
+
+fn hash_image_with_alg(image: &image::DynamicImage, alg: HashAlg, use_dct: bool) -> Option<u64> {
+    let mut config = HasherConfig::new().hash_alg(alg);
+    if use_dct {
+        config = config.preproc_dct();
+    }
+    let hasher = config.to_hasher();
+    let hash = hasher.hash_image(image);
+    hash_to_u64(&hash)
+}
+
+fn hash_to_u64(hash: &image_hasher::ImageHash) -> Option<u64> {
+    let bytes = hash.as_bytes();
+    if bytes.len() != 8 {
+        return None;
+    }
+    let arr: [u8; 8] = bytes.try_into().ok()?;
+    Some(u64::from_be_bytes(arr))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{pseudo_random_bytes, TempDir};
+
+    #[test]
+    fn hash_file_multi_matches_single_hashers() {
+        let dir = TempDir::new("hash-multi");
+        // Empty, inside one 128 KiB read buffer, and across several reads.
+        for len in [0, 1000, 128 * 1024, 300_000] {
+            let path = dir.write(format!("f{len}"), &pseudo_random_bytes(len, len as u64));
+            let both =
+                hash_file_multi(&path, &[HashAlgorithm::Blake3, HashAlgorithm::Sha256], None)
+                    .unwrap();
+            assert_eq!(
+                both.blake3,
+                Some(blake3_file(&path, None).unwrap()),
+                "{len}"
+            );
+            assert_eq!(
+                both.sha256,
+                Some(sha256_file(&path, None).unwrap()),
+                "{len}"
+            );
+
+            let only_sha = hash_file_multi(&path, &[HashAlgorithm::Sha256], None).unwrap();
+            assert_eq!(only_sha.blake3, None);
+            assert_eq!(only_sha.sha256, both.sha256);
+        }
+    }
+
+    #[test]
+    fn reads_stop_soon_after_cancel() {
+        let dir = TempDir::new("hash-cancel");
+        let path = dir.write("big", &pseudo_random_bytes(3 * CANCEL_CHECK_BYTES, 7));
+        let token = ScanCancelToken::new();
+        let remote = token.clone();
+        std::thread::spawn(move || remote.cancel()).join().unwrap();
+
+        let started = std::time::Instant::now();
+        let algos = [HashAlgorithm::Blake3, HashAlgorithm::Sha256];
+        assert!(matches!(
+            hash_file_multi(&path, &algos, Some(&token)),
+            Err(Error::Cancelled)
+        ));
+        assert!(matches!(
+            blake3_file(&path, Some(&token)),
+            Err(Error::Cancelled)
+        ));
+        assert!(matches!(
+            sha256_file(&path, Some(&token)),
+            Err(Error::Cancelled)
+        ));
+        assert!(matches!(
+            chunk_fingerprints(&path, Some(&token)),
+            Err(Error::Cancelled)
+        ));
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+
+        assert!(hash_file_multi(&path, &algos, Some(&ScanCancelToken::new())).is_ok());
+    }
+
+    fn chunk_lens(chunks: &[ChunkFingerprint]) -> Vec<usize> {
+        chunks.iter().map(|chunk| chunk.len as usize).collect()
+    }
+
+    #[test]
+    fn chunks_respect_length_bounds_and_cover_the_file() {
+        let dir = TempDir::new("chunk-bounds");
+        for (name, data) in [
+            ("random", pseudo_random_bytes(20 * 1024 * 1024, 1)),
+            ("small", pseudo_random_bytes(1000, 2)),
+        ] {
+            let path = dir.write(name, &data);
+            let lens = chunk_lens(&chunk_fingerprints(&path, None).unwrap());
+            assert_eq!(lens.iter().sum::<usize>(), data.len(), "{name}");
+            let (last, rest) = lens.split_last().unwrap();
+            assert!(*last > 0 && *last <= CHUNK_MAX_LEN, "{name}");
+            for &len in rest {
+                assert!(
+                    (CHUNK_MIN_LEN..=CHUNK_MAX_LEN).contains(&len),
+                    "{name}: {len}"
+                );
+            }
+        }
+
+        // A run of one byte value never brings the rolling hash to a boundary of its
+        // own, so every chunk is cut at the maximum length.
+        let zeros =
+            chunk_lens(&chunk_fingerprints(&dir.write("z", &vec![0u8; 9 << 20]), None).unwrap());
+        assert_eq!(zeros, [CHUNK_MAX_LEN, CHUNK_MAX_LEN, 1 << 20]);
+        assert!(chunk_fingerprints(&dir.write("empty", &[]), None)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn chunk_boundaries_survive_a_prefix_insertion() {
+        let dir = TempDir::new("chunk-shift");
+        let original = pseudo_random_bytes(16 * 1024 * 1024, 3);
+        let mut shifted = pseudo_random_bytes(1234, 4);
+        shifted.extend_from_slice(&original);
+
+        let a = chunk_fingerprints(&dir.write("a", &original), None).unwrap();
+        let b = chunk_fingerprints(&dir.write("b", &shifted), None).unwrap();
+        assert!(a.len() > 4, "test data should span several chunks");
+        // Only the chunk the insertion lands in may differ; the rest realign.
+        assert_eq!(a[1..], b[b.len() - (a.len() - 1)..]);
+        assert_ne!(a[0], b[0]);
+    }
+}
@@ -1,9 +1,16 @@
+pub mod compare;
 pub mod db;
 pub mod drive;
 pub mod error;
+pub mod export;
+pub mod files;
+pub mod format;
 pub mod hash;
 pub mod models;
 pub mod scan;
+pub mod store;
+#[cfg(test)]
+mod test_support;
 pub mod video;
 
 pub use crate::error::{Error, Result};
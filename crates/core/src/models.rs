@@ -7,6 +7,13 @@ pub enum ScanRootKind {
     Drive,
 }
 
+/// What `SqliteScanStore::probe` found in a fileset file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FilesetInfo {
+    /// The file's `user_version`; 0 for filesets written before the format was marked.
+    pub format_version: u32,
+}
+
 #[derive(Debug, Clone)]
 pub struct DriveMetadata {
     pub id: Option<String>,
@@ -27,6 +34,31 @@ pub struct MediaFileRecord {
     pub phash: Option<u64>,
     pub ffmpeg_metadata: Option<String>,
     pub file_type: Option<String>,
+    pub media: MediaInfo,
+}
+
+/// Stream details parsed out of ffprobe output, kept even when the raw JSON is not.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MediaInfo {
+    /// ffprobe `format_name`, e.g. `mov,mp4,m4a,3gp,3g2,mj2`.
+    pub container: Option<String>,
+    pub video_codec: Option<String>,
+    pub audio_codec: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Overall bitrate in bits per second.
+    pub bit_rate: Option<u64>,
+}
+
+/// Where snapshot images are kept.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum SnapshotStorage {
+    /// As BLOBs in `file_snapshots`.
+    #[default]
+    InDb,
+    /// As `<file id>_<index>.<ext>` files under `dir`, resolved against the fileset
+    /// file's directory when relative. The database keeps only a reference to each file.
+    Sidecar { dir: PathBuf },
 }
 
 #[derive(Debug, Clone)]
@@ -38,9 +70,18 @@ pub struct FileSnapshotRecord {
     pub ahash: Option<u64>,
     pub dhash: Option<u64>,
     pub phash: Option<u64>,
+    /// MIME type of `image_avif`; `image/avif` for snapshots stored before it was recorded.
+    pub mime: String,
     pub image_avif: Vec<u8>,
 }
 
+/// One content-defined chunk of a file; see `hash::chunk_fingerprints`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkFingerprint {
+    pub hash: u64,
+    pub len: u32,
+}
+
 #[derive(Debug, Clone)]
 pub struct FileListRow {
     pub id: i64,
@@ -56,6 +97,114 @@ pub struct FileListRow {
     pub file_type: Option<String>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Blake3,
+    Sha256,
+}
+
+impl HashAlgorithm {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Blake3 => "blake3",
+            Self::Sha256 => "sha256",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DuplicateGroup {
+    pub algorithm: HashAlgorithm,
+    pub hash: [u8; 32],
+    pub files: Vec<FileListRow>,
+}
+
+/// Files whose pHash is within a Hamming distance of `anchor`'s.
+#[derive(Debug, Clone)]
+pub struct NearDuplicateGroup {
+    pub anchor: FileListRow,
+    pub matches: Vec<FileListRow>,
+}
+
+/// Two files with `shared_bytes` of chunk content in common, `a` ordered before `b` by path.
+#[derive(Debug, Clone)]
+pub struct ChunkOverlap {
+    pub a: FileListRow,
+    pub b: FileListRow,
+    pub shared_bytes: u64,
+}
+
+/// What `SqliteScanStore::merge_from` did with the other fileset's rows.
+#[derive(Debug, Clone, Default)]
+pub struct MergeReport {
+    /// Paths new to the target, copied with their snapshots.
+    pub added: u64,
+    /// Paths with the same content in both whose missing hashes, metadata or
+    /// snapshots were filled in from the other fileset.
+    pub updated: u64,
+    /// Paths with the same content in both and nothing to add.
+    pub unchanged: u64,
+    /// Paths stored in both with a different size or hash; the target's row is kept.
+    pub conflicts: Vec<PathBuf>,
+    /// The target's and the other fileset's root paths when they differ, in which
+    /// case equal relative paths may not name the same file.
+    pub root_mismatch: Option<(PathBuf, PathBuf)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileTypeStats {
+    /// `None` for files whose type could not be detected.
+    pub file_type: Option<String>,
+    pub files: u64,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FileListSort {
+    #[default]
+    Path,
+    /// Largest files first, ties broken by path.
+    SizeDesc,
+    /// Most space freed by removing the other copies first: size times the number
+    /// of other files with the same content. Copies of one file stay together.
+    ReclaimableDesc,
+}
+
+/// Coarse MIME family for narrowing file listings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FileTypeFilter {
+    Image,
+    Video,
+    Audio,
+    /// Undetected types and anything outside the families above.
+    Other,
+}
+
+impl FileTypeFilter {
+    pub const ALL: [FileTypeFilter; 4] = [Self::Image, Self::Video, Self::Audio, Self::Other];
+
+    pub fn mime_prefix(self) -> Option<&'static str> {
+        match self {
+            Self::Image => Some("image/"),
+            Self::Video => Some("video/"),
+            Self::Audio => Some("audio/"),
+            Self::Other => None,
+        }
+    }
+
+    pub fn of(file_type: Option<&str>) -> Self {
+        file_type
+            .and_then(|mime| {
+                Self::ALL.into_iter().find(|filter| {
+                    filter
+                        .mime_prefix()
+                        .is_some_and(|prefix| mime.starts_with(prefix))
+                })
+            })
+            .unwrap_or(Self::Other)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct FilesetMetadata {
     pub created_at: SystemTime,
@@ -77,9 +226,54 @@ pub struct ScanStats {
     pub files_seen: u64,
     pub files_hashed: u64,
     pub files_skipped: u64,
+    /// The part of `files_skipped` that still failed after retrying a transient
+    /// I/O error `ScanConfig::io_retries` times.
+    pub files_failed_after_retries: u64,
+    /// Files left out by scan filters, such as empty files.
+    pub files_filtered: u64,
+    pub bytes_seen: u64,
+    /// Dry runs only: files whose stored row already matches their size and mtime,
+    /// which includes every file a `resume` would skip.
+    pub files_reused: u64,
+    /// Dry runs only: files that are new or changed since their stored row.
+    pub files_to_hash: u64,
+    pub bytes_to_hash: u64,
+}
+
+/// Where an unfinished scan stopped. Only a scan of the same root with the same
+/// settings may resume from it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanCursor {
+    /// Stored path of the last file written; paths up to it, in walk order, are done.
+    pub last_path: PathBuf,
+    /// Walk entries processed when the cursor was written.
+    pub files_done: u64,
+    pub root_path: PathBuf,
+    /// `ScanConfig::to_json` of the scan, with `resume` cleared.
+    pub scan_config: String,
+}
+
+/// A file the scan could not read, and why.
+#[derive(Debug, Clone)]
+pub struct ScanWarning {
+    pub path: PathBuf,
+    pub reason: String,
 }
 
 #[derive(Debug, Clone)]
 pub struct ScanResult {
     pub stats: ScanStats,
+    /// One entry per unreadable file, in the order they were hit.
+    pub warnings: Vec<ScanWarning>,
+}
+
+impl ScanResult {
+    /// "12 files unreadable", or `None` when every file was read.
+    pub fn warnings_summary(&self) -> Option<String> {
+        match self.warnings.len() {
+            0 => None,
+            1 => Some("1 file unreadable".to_string()),
+            n => Some(format!("{n} files unreadable")),
+        }
+    }
 }
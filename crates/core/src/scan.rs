@@ -1,62 +1,276 @@
+use std::borrow::Cow;
 use std::collections::BTreeMap;
-use std::convert::TryInto;
-use std::io::Read;
+use std::ops::RangeInclusive;
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
 use std::sync::{
     atomic::{AtomicBool, Ordering},
-    mpsc, Arc,
+    mpsc, Arc, Condvar, Mutex,
 };
 use std::thread;
 use std::time::{Duration, Instant, SystemTime};
 
-use image_hasher::{HashAlg, HasherConfig};
 use rayon::prelude::*;
+use serde_json::json;
 use walkdir::WalkDir;
 
 use crate::db::SqliteScanStore;
 use crate::drive;
 use crate::error::{Error, Result};
-use crate::hash::{blake3_file, sha256_file};
+use crate::hash::{chunk_fingerprints, hash_file_multi, perceptual_hashes};
 use crate::models::{
-    DriveMetadata, FileSnapshotRecord, FilesetMetadata, MediaFileRecord, ScanResult, ScanRootKind,
-    ScanStats,
+    ChunkFingerprint, DriveMetadata, FileSnapshotRecord, FilesetMetadata, HashAlgorithm,
+    MediaFileRecord, MediaInfo, ScanCursor, ScanResult, ScanRootKind, ScanStats, ScanWarning,
+    SnapshotStorage,
 };
-use serde_json::Value;
-use wait_timeout::ChildExt;
+use crate::store::ScanStore;
+use crate::video::{self, SnapshotFormat};
 
 #[derive(Debug, Clone)]
 pub struct ScanConfig {
+    /// The folder to scan when `roots` is empty.
     pub root: PathBuf,
+    /// Several folders scanned into one fileset. Paths are stored relative to
+    /// their deepest common ancestor, which is recorded as the fileset root.
+    pub roots: Vec<PathBuf>,
     pub root_kind: ScanRootKind,
     pub hash_files: bool,
     pub perceptual_hashes: bool,
+    /// Video frames, plus one preview for HEIC and camera RAW images, which the UI
+    /// cannot decode itself.
     pub capture_snapshots: bool,
+    /// Sniff each file's MIME type into `file_type`. Off, type checks fall back to
+    /// the file extension and `file_type` stays NULL.
+    pub detect_file_type: bool,
+    /// Run ffprobe on media files for `ffmpeg_metadata` and the codec columns.
+    /// Video snapshots need the duration it reports, so none are taken without it.
+    pub extract_metadata: bool,
     pub snapshots_per_video: u32,
     pub snapshot_max_dim: u32,
+    pub snapshot_storage: SnapshotStorage,
     pub concurrent_processing: bool,
+    /// Worker threads for concurrent processing; 0 uses the rayon default.
+    pub threads: usize,
+    /// Glob patterns matched against root-relative paths (and bare file names).
+    pub exclude: Vec<String>,
+    /// Directories to leave out whole, typically mount points. Matched after
+    /// canonicalizing, so `/mnt/backup` is skipped however the root was spelled.
+    pub exclude_mounts: Vec<PathBuf>,
+    /// Do not descend into directories on another filesystem than their root, so
+    /// scanning `/` skips `/proc`, `/sys`, network shares and other drives.
+    pub stay_on_filesystem: bool,
+    pub min_size_bytes: u64,
+    /// Skip the files an interrupted scan of the same root, with the same settings,
+    /// already stored. Requires `sort_entries`.
+    pub resume: bool,
+    /// Walk roots and each directory in file-name order so row order and resume
+    /// cursors are stable.
+    pub sort_entries: bool,
+    /// Skip zero-byte files; they all hash alike and would form one large duplicate group.
+    pub ignore_empty_files: bool,
+    /// Extra attempts at reading a file's metadata, hashes or chunks after a
+    /// transient I/O error, as flaky USB drives produce. Missing files and
+    /// permission errors are never retried.
+    pub io_retries: u32,
+    /// Most ffprobe subprocesses one scan runs at once; 0 is treated as 1.
+    pub ffprobe_concurrency: usize,
+    /// Keep the full ffprobe JSON in `ffmpeg_metadata`. The parsed `MediaInfo`
+    /// columns are stored either way.
+    pub store_raw_ffprobe: bool,
+    /// Let `prescan_for_scan` keep the walked entries for `scan_with_prescan`, so the
+    /// tree is walked once. Holds every file path in memory between the two phases.
+    pub single_pass: bool,
+    /// Also store content-defined chunk fingerprints of large files, so
+    /// `SqliteScanStore::files_sharing_chunks` can find partial overlaps. Reads each
+    /// of those files once more.
+    pub chunk_dedup: bool,
+    /// Walk with the `ignore` crate, skipping what `.gitignore`, `.ignore` and the
+    /// global git excludes leave out, then apply `exclude` on top.
+    pub use_gitignore: bool,
+    /// Store canonical absolute file paths instead of paths relative to `base_root`.
+    /// Actions keep working wherever the fileset DB is opened, but moving or
+    /// remounting the scanned folder breaks them; relative paths follow a moved root
+    /// once `root_path` is updated.
+    pub store_absolute_paths: bool,
+    /// Treat stored paths that differ only in ASCII case as the same file, as
+    /// Windows and macOS volumes do: a re-scan that sees `photo.jpg` updates the
+    /// `Photo.JPG` row, renaming it, instead of inserting a second one.
+    pub case_insensitive_paths: bool,
+    /// Walk and filter as usual, but only count what would be processed: no hashing,
+    /// no ffprobe and no writes to the store. See the dry-run fields of `ScanStats`.
+    pub dry_run: bool,
 }
 
-impl ScanConfig {
-    pub fn for_folder(root: impl Into<PathBuf>) -> Self {
+impl Default for ScanConfig {
+    fn default() -> Self {
         Self {
-            root: root.into(),
+            root: PathBuf::new(),
+            roots: Vec::new(),
             root_kind: ScanRootKind::Folder,
             hash_files: true,
             perceptual_hashes: true,
             capture_snapshots: true,
+            detect_file_type: true,
+            extract_metadata: true,
             snapshots_per_video: 3,
             snapshot_max_dim: 1024,
+            snapshot_storage: SnapshotStorage::InDb,
             concurrent_processing: true,
+            threads: 0,
+            exclude: Vec::new(),
+            exclude_mounts: Vec::new(),
+            stay_on_filesystem: false,
+            min_size_bytes: 0,
+            resume: false,
+            sort_entries: false,
+            ignore_empty_files: true,
+            io_retries: 2,
+            ffprobe_concurrency: 4,
+            store_raw_ffprobe: true,
+            single_pass: false,
+            chunk_dedup: false,
+            use_gitignore: false,
+            store_absolute_paths: false,
+            case_insensitive_paths: false,
+            dry_run: false,
+        }
+    }
+}
+
+impl ScanConfig {
+    /// Accepted `snapshots_per_video` values.
+    pub const SNAPSHOTS_PER_VIDEO: RangeInclusive<u32> = 1..=10;
+    /// Accepted `snapshot_max_dim` values, in pixels.
+    pub const SNAPSHOT_MAX_DIM: RangeInclusive<u32> = 128..=4096;
+
+    pub fn for_folder(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Fails with `Error::InvalidArgument` on settings outside their accepted range.
+    /// Every scan entry point checks this before touching the filesystem.
+    pub fn validate(&self) -> Result<()> {
+        let checks = [
+            (
+                "snapshots_per_video",
+                self.snapshots_per_video,
+                Self::SNAPSHOTS_PER_VIDEO,
+            ),
+            (
+                "snapshot_max_dim",
+                self.snapshot_max_dim,
+                Self::SNAPSHOT_MAX_DIM,
+            ),
+        ];
+        for (name, value, range) in checks {
+            if !range.contains(&value) {
+                return Err(Error::InvalidArgument(format!(
+                    "{name} must be between {} and {}, got {value}",
+                    range.start(),
+                    range.end()
+                )));
+            }
+        }
+        if self.resume && !self.sort_entries {
+            return Err(Error::InvalidArgument(
+                "resume needs sort_entries, so the walk order is the same on every run".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The settings that shape a scan's results, as JSON. The fileset already records
+    /// the base root, so `roots` are given relative to it.
+    pub fn to_json(&self) -> String {
+        let base = self.base_root();
+        let roots: Vec<String> = self
+            .scan_roots()
+            .iter()
+            .map(|root| match root.strip_prefix(&base) {
+                Ok(rel) if rel.as_os_str().is_empty() => ".".to_string(),
+                Ok(rel) => rel.display().to_string(),
+                Err(_) => root.display().to_string(),
+            })
+            .collect();
+        let snapshot_storage = match &self.snapshot_storage {
+            SnapshotStorage::InDb => json!("in_db"),
+            SnapshotStorage::Sidecar { dir } => json!({ "sidecar": dir.display().to_string() }),
+        };
+        json!({
+            "roots": roots,
+            "root_kind": match self.root_kind {
+                ScanRootKind::Folder => "folder",
+                ScanRootKind::Drive => "drive",
+            },
+            "hash_files": self.hash_files,
+            "perceptual_hashes": self.perceptual_hashes,
+            "capture_snapshots": self.capture_snapshots,
+            "detect_file_type": self.detect_file_type,
+            "extract_metadata": self.extract_metadata,
+            "snapshots_per_video": self.snapshots_per_video,
+            "snapshot_max_dim": self.snapshot_max_dim,
+            "snapshot_storage": snapshot_storage,
+            "concurrent_processing": self.concurrent_processing,
+            "threads": self.threads,
+            "exclude": self.exclude,
+            "exclude_mounts": self
+                .exclude_mounts
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>(),
+            "stay_on_filesystem": self.stay_on_filesystem,
+            "min_size_bytes": self.min_size_bytes,
+            "resume": self.resume,
+            "sort_entries": self.sort_entries,
+            "ignore_empty_files": self.ignore_empty_files,
+            "io_retries": self.io_retries,
+            "ffprobe_concurrency": self.ffprobe_concurrency,
+            "store_raw_ffprobe": self.store_raw_ffprobe,
+            "single_pass": self.single_pass,
+            "chunk_dedup": self.chunk_dedup,
+            "use_gitignore": self.use_gitignore,
+            "store_absolute_paths": self.store_absolute_paths,
+            "case_insensitive_paths": self.case_insensitive_paths,
+            "media_support": video::MEDIA_SUPPORT,
+        })
+        .to_string()
+    }
+
+    /// The folders a scan walks: `roots`, or just `root` when that is empty.
+    pub fn scan_roots(&self) -> &[PathBuf] {
+        if self.roots.is_empty() {
+            std::slice::from_ref(&self.root)
+        } else {
+            &self.roots
+        }
+    }
+
+    /// The path stored paths are relative to. Empty when the roots share no
+    /// ancestor (different Windows drives), in which case paths stay absolute.
+    pub fn base_root(&self) -> Cow<'_, Path> {
+        let roots = self.scan_roots();
+        if let [root] = roots {
+            return Cow::Borrowed(root.as_path());
+        }
+        let mut base = roots[0].clone();
+        for root in &roots[1..] {
+            while !root.starts_with(&base) {
+                if !base.pop() {
+                    return Cow::Owned(PathBuf::new());
+                }
+            }
         }
+        Cow::Owned(base)
     }
 }
 
-pub fn scan_to_sqlite(config: &ScanConfig, store: &SqliteScanStore) -> Result<ScanResult> {
+pub fn scan_to_sqlite(config: &ScanConfig, store: &dyn ScanStore) -> Result<ScanResult> {
     scan_to_sqlite_with_progress(config, store, None, |_| {})
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct ScanCancelToken {
     cancelled: Arc<AtomicBool>,
 }
@@ -75,6 +289,11 @@ impl ScanCancelToken {
     pub fn is_cancelled(&self) -> bool {
         self.cancelled.load(Ordering::SeqCst)
     }
+
+    /// The underlying flag, for wiring the token into signal handlers.
+    pub fn shared_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.cancelled)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -94,11 +313,76 @@ pub struct ScanProgress {
     pub current_path: PathBuf,
     pub current_step: Option<String>,
     pub active_tasks: Vec<ActiveScanTask>,
+    /// Set on the update for a file that was just skipped as unreadable.
+    pub warning: Option<ScanWarning>,
+    /// Seconds left at the recent byte throughput; `None` until there is enough
+    /// data to tell, or without a byte total.
+    pub eta_secs: Option<u64>,
+}
+
+/// Smooths bytes per second with an exponential moving average, since file sizes
+/// vary too much for a per-file rate to be steady.
+struct EtaEstimator {
+    started: Instant,
+    last_sample: Instant,
+    last_bytes: u64,
+    bytes_per_sec: Option<f64>,
+}
+
+impl EtaEstimator {
+    const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+    /// No estimate before this much scanning; the first seconds are mostly warm-up.
+    const WARMUP: Duration = Duration::from_secs(3);
+    /// Weight of the newest sample.
+    const SMOOTHING: f64 = 0.2;
+
+    fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            started: now,
+            last_sample: now,
+            last_bytes: 0,
+            bytes_per_sec: None,
+        }
+    }
+
+    fn update(&mut self, bytes_seen: u64, total_bytes: u64) -> Option<u64> {
+        let elapsed = self.last_sample.elapsed();
+        if elapsed >= Self::SAMPLE_INTERVAL {
+            let sample = bytes_seen.saturating_sub(self.last_bytes) as f64 / elapsed.as_secs_f64();
+            self.bytes_per_sec = Some(match self.bytes_per_sec {
+                Some(rate) => rate + Self::SMOOTHING * (sample - rate),
+                None => sample,
+            });
+            self.last_sample = Instant::now();
+            self.last_bytes = bytes_seen;
+        }
+        if total_bytes == 0 || self.started.elapsed() < Self::WARMUP {
+            return None;
+        }
+        let rate = self.bytes_per_sec.filter(|rate| *rate > 0.0)?;
+        Some((total_bytes.saturating_sub(bytes_seen) as f64 / rate).ceil() as u64)
+    }
+}
+
+/// Wraps a progress callback so its updates carry `eta_secs`.
+fn with_eta<F>(mut on_progress: F) -> impl FnMut(&ScanProgress)
+where
+    F: FnMut(&ScanProgress),
+{
+    let mut eta = EtaEstimator::new();
+    move |progress| {
+        let eta_secs = eta.update(progress.bytes_seen, progress.total_bytes);
+        on_progress(&ScanProgress {
+            eta_secs,
+            ..progress.clone()
+        });
+    }
 }
 
 pub fn scan_to_sqlite_with_progress<F>(
     config: &ScanConfig,
-    store: &SqliteScanStore,
+    store: &dyn ScanStore,
     cancel: Option<&ScanCancelToken>,
     on_progress: F,
 ) -> Result<ScanResult>
@@ -112,16 +396,60 @@ where
 pub struct ScanTotals {
     pub files: u64,
     pub bytes: u64,
+    /// Files with a video extension, for the snapshot free-space check.
+    pub video_files: u64,
 }
 
 pub fn scan_to_sqlite_with_progress_and_totals<F>(
     config: &ScanConfig,
-    store: &SqliteScanStore,
+    store: &dyn ScanStore,
     cancel: Option<&ScanCancelToken>,
     totals: Option<ScanTotals>,
-    mut on_progress: F,
+    on_progress: F,
+) -> Result<ScanResult>
+where
+    F: FnMut(&ScanProgress),
+{
+    let items = walk_root(config).filter_map(|entry| classify_entry(config, entry));
+    scan_walk_items(config, store, cancel, totals, items, on_progress)
+}
+
+/// Scans after `prescan_for_scan`, reusing its walk when `ScanConfig::single_pass`
+/// was set and walking the tree again otherwise.
+pub fn scan_with_prescan<F>(
+    config: &ScanConfig,
+    store: &dyn ScanStore,
+    cancel: Option<&ScanCancelToken>,
+    prescanned: PrescannedWalk,
+    on_progress: F,
+) -> Result<ScanResult>
+where
+    F: FnMut(&ScanProgress),
+{
+    let totals = Some(prescanned.totals);
+    match prescanned.items {
+        Some(items) => scan_walk_items(
+            config,
+            store,
+            cancel,
+            totals,
+            items.into_iter(),
+            on_progress,
+        ),
+        None => scan_to_sqlite_with_progress_and_totals(config, store, cancel, totals, on_progress),
+    }
+}
+
+fn scan_walk_items<I, F>(
+    config: &ScanConfig,
+    store: &dyn ScanStore,
+    cancel: Option<&ScanCancelToken>,
+    totals: Option<ScanTotals>,
+    items: I,
+    on_progress: F,
 ) -> Result<ScanResult>
 where
+    I: Iterator<Item = WalkItem>,
     F: FnMut(&ScanProgress),
 {
     const SCAN_BATCH_FILES: usize = 32;
@@ -129,34 +457,38 @@ where
     const FLUSH_EVERY_FILES: u64 = 2_000;
     const FLUSH_EVERY_ELAPSED: Duration = Duration::from_secs(5);
 
-    if !config.root.exists() {
-        return Err(Error::InvalidArgument(format!(
-            "root does not exist: {}",
-            config.root.to_string_lossy()
-        )));
+    config.validate()?;
+    check_roots_exist(config)?;
+    if config.dry_run {
+        return dry_run_walk_items(config, store, cancel, totals, items, on_progress);
+    }
+    let mut on_progress = with_eta(on_progress);
+    if let Some(totals) = totals.filter(|_| cfg!(feature = "media") && config.capture_snapshots) {
+        check_snapshot_space(store, config, totals.video_files)?;
     }
 
-    let drive = drive::probe_for_path(&config.root).unwrap_or(DriveMetadata {
+    let base = config.base_root();
+    let drive = drive::probe_for_path(&config.scan_roots()[0]).unwrap_or(DriveMetadata {
         id: None,
         label: None,
         fs_type: None,
     });
     let root_parent_path = if config.root_kind == ScanRootKind::Folder {
-        config.root.parent().map(|p| p.to_path_buf())
+        base.parent().map(|p| p.to_path_buf())
     } else {
         None
     };
     let fileset_meta = FilesetMetadata {
         created_at: SystemTime::now(),
         root_kind: config.root_kind,
-        root_path: config.root.clone(),
+        root_path: base.to_path_buf(),
         root_parent_path,
         drive,
         host_os: std::env::consts::OS.to_string(),
         host_os_version: host_os_version(),
         app_version: env!("CARGO_PKG_VERSION").to_string(),
         status: String::new(),
-        name: fileset_name_from_root(&config.root),
+        name: fileset_name(config),
         description: String::new(),
         notes: String::new(),
     };
@@ -164,15 +496,24 @@ where
 
     let scan_result = (|| -> Result<ScanResult> {
         store.set_fileset_metadata(&fileset_meta)?;
+        store.set_scan_config(&config.to_json())?;
+        if config.case_insensitive_paths {
+            store.create_path_nocase_index()?;
+        }
 
         let mut stats = ScanStats::default();
+        let mut warnings = Vec::new();
         let mut bytes_seen = 0u64;
         let mut files_since_flush = 0u64;
         let mut last_flush = Instant::now();
-        let totals = totals.unwrap_or_default();
+        let mut totals = totals.unwrap_or_default();
         let mut batch = Vec::with_capacity(SCAN_BATCH_FILES);
         let mut last_batch_flush = Instant::now();
-        for entry in WalkDir::new(&config.root).follow_links(false).into_iter() {
+        let pool = worker_pool(config)?;
+        let probe_slots = ProbeSlots::new(config.ffprobe_concurrency);
+        let resume_after = resume_cursor(config, store)?;
+        let cursor_config = cursor_config_json(config);
+        for item in items {
             if let Some(cancel) = cancel {
                 if cancel.is_cancelled() {
                     update_fileset_status(store, config, "incomplete");
@@ -181,34 +522,60 @@ where
                 }
             }
 
-            let entry = match entry {
-                Ok(v) => v,
-                Err(_) => {
+            let candidate = match item {
+                WalkItem::File { candidate, .. } => candidate,
+                WalkItem::Filtered => {
+                    stats.files_filtered += 1;
+                    continue;
+                }
+                WalkItem::Unreadable(err) => {
                     stats.files_skipped += 1;
+                    let warning = ScanWarning {
+                        path: err.path.unwrap_or_default(),
+                        reason: err.reason,
+                    };
+                    on_progress(&ScanProgress {
+                        files_seen: stats.files_seen,
+                        files_hashed: stats.files_hashed,
+                        files_skipped: stats.files_skipped,
+                        bytes_seen,
+                        total_files: totals.files,
+                        total_bytes: totals.bytes,
+                        current_path: warning.path.clone(),
+                        current_step: Some("walk".to_string()),
+                        active_tasks: Vec::new(),
+                        warning: Some(warning.clone()),
+                        eta_secs: None,
+                    });
+                    warnings.push(warning);
                     continue;
                 }
             };
 
-            if !entry.file_type().is_file() {
+            stats.files_seen += 1;
+            // Files created after a separate prescan walk would push the fraction past 100%.
+            if totals.files > 0 && stats.files_seen > totals.files {
+                totals.files = stats.files_seen;
+            }
+            if is_resumed_past(config, store, resume_after.as_deref(), &candidate.path)? {
                 continue;
             }
-
-            stats.files_seen += 1;
-            batch.push(ScanCandidate {
-                path: entry.path().to_path_buf(),
-                is_symlink: entry.file_type().is_symlink(),
-            });
+            batch.push(candidate);
 
             if batch.len() >= SCAN_BATCH_FILES
                 || (!batch.is_empty() && last_batch_flush.elapsed() >= SCAN_PROGRESS_TICK)
             {
+                let last_path = stored_path(config, &batch[batch.len() - 1].path);
                 flush_scan_batch(
                     config,
                     store,
                     cancel,
+                    pool.as_ref(),
+                    &probe_slots,
                     &totals,
                     &mut on_progress,
                     &mut stats,
+                    &mut warnings,
                     &mut bytes_seen,
                     &mut files_since_flush,
                     &mut last_flush,
@@ -216,6 +583,12 @@ where
                     FLUSH_EVERY_FILES,
                     FLUSH_EVERY_ELAPSED,
                 )?;
+                store.set_scan_cursor(&ScanCursor {
+                    last_path,
+                    files_done: stats.files_seen,
+                    root_path: base.to_path_buf(),
+                    scan_config: cursor_config.clone(),
+                })?;
                 last_batch_flush = Instant::now();
             }
         }
@@ -224,9 +597,12 @@ where
                 config,
                 store,
                 cancel,
+                pool.as_ref(),
+                &probe_slots,
                 &totals,
                 &mut on_progress,
                 &mut stats,
+                &mut warnings,
                 &mut bytes_seen,
                 &mut files_since_flush,
                 &mut last_flush,
@@ -236,8 +612,10 @@ where
             )?;
         }
 
+        store.clear_scan_cursor()?;
         update_fileset_status(store, config, "completed");
-        Ok(ScanResult { stats })
+        stats.bytes_seen = bytes_seen;
+        Ok(ScanResult { stats, warnings })
     })();
 
     match scan_result {
@@ -247,39 +625,318 @@ where
         }
         Err(err) => {
             let _ = store.rollback_tx();
+            if matches!(err, Error::DiskFull(_)) {
+                // Rows committed so far stay, and the scan cursor lets `resume` continue.
+                update_fileset_status(store, config, "incomplete");
+            }
             Err(err)
         }
     }
 }
 
+/// The stored path a `resume` scan continues after, when the store holds a cursor
+/// from a scan of the same root with the same settings.
+fn resume_cursor(config: &ScanConfig, store: &dyn ScanStore) -> Result<Option<PathBuf>> {
+    if !config.resume {
+        return Ok(None);
+    }
+    Ok(store.scan_cursor()?.and_then(|cursor| {
+        (cursor.root_path == config.base_root() && cursor.scan_config == cursor_config_json(config))
+            .then_some(cursor.last_path)
+    }))
+}
+
+/// The settings a cursor must match; `resume` itself differs between the runs.
+fn cursor_config_json(config: &ScanConfig) -> String {
+    ScanConfig {
+        resume: false,
+        ..config.clone()
+    }
+    .to_json()
+}
+
+/// Sorted walks visit stored paths in `Path` order, so a file up to the cursor was
+/// written by the interrupted scan, unless it appeared or changed since.
+fn is_resumed_past(
+    config: &ScanConfig,
+    store: &dyn ScanStore,
+    cursor: Option<&Path>,
+    path: &Path,
+) -> Result<bool> {
+    if !cursor.is_some_and(|last| stored_path(config, path).as_path() <= last) {
+        return Ok(false);
+    }
+    match std::fs::metadata(path) {
+        Ok(md) => is_stored_unchanged(config, store, path, &md),
+        Err(_) => Ok(false),
+    }
+}
+
+/// Whether `path`'s stored row matches its size and mtime.
+fn is_stored_unchanged(
+    config: &ScanConfig,
+    store: &dyn ScanStore,
+    path: &Path,
+    md: &std::fs::Metadata,
+) -> Result<bool> {
+    let Ok(modified) = md.modified() else {
+        return Ok(false);
+    };
+    // Stored mtimes are whole seconds since the epoch, as `upsert_file` writes them.
+    let modified_at_secs = modified
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64);
+    Ok(!store.needs_rehash(&stored_path(config, path), md.len(), modified_at_secs)?)
+}
+
+/// `scan_walk_items` for `ScanConfig::dry_run`: reads metadata and the stored rows only.
+fn dry_run_walk_items<I, F>(
+    config: &ScanConfig,
+    store: &dyn ScanStore,
+    cancel: Option<&ScanCancelToken>,
+    totals: Option<ScanTotals>,
+    items: I,
+    mut on_progress: F,
+) -> Result<ScanResult>
+where
+    I: Iterator<Item = WalkItem>,
+    F: FnMut(&ScanProgress),
+{
+    const DRY_RUN_PROGRESS_TICK: Duration = Duration::from_millis(500);
+
+    let totals = totals.unwrap_or_default();
+    let mut stats = ScanStats::default();
+    let mut warnings = Vec::new();
+    let mut last_progress = Instant::now();
+    for item in items {
+        if cancel.is_some_and(ScanCancelToken::is_cancelled) {
+            return Err(Error::Cancelled);
+        }
+
+        let candidate = match item {
+            WalkItem::File { candidate, .. } => candidate,
+            WalkItem::Filtered => {
+                stats.files_filtered += 1;
+                continue;
+            }
+            WalkItem::Unreadable(err) => {
+                stats.files_skipped += 1;
+                warnings.push(ScanWarning {
+                    path: err.path.unwrap_or_default(),
+                    reason: err.reason,
+                });
+                continue;
+            }
+        };
+        let md = match std::fs::metadata(&candidate.path) {
+            Ok(v) => v,
+            Err(err) => {
+                stats.files_skipped += 1;
+                warnings.push(ScanWarning {
+                    path: candidate.path,
+                    reason: format!("metadata: {err}"),
+                });
+                continue;
+            }
+        };
+
+        stats.files_seen += 1;
+        stats.bytes_seen = stats.bytes_seen.saturating_add(md.len());
+        let reused = is_stored_unchanged(config, store, &candidate.path, &md)?;
+        if reused {
+            stats.files_reused += 1;
+        } else {
+            stats.files_to_hash += 1;
+            stats.bytes_to_hash = stats.bytes_to_hash.saturating_add(md.len());
+        }
+
+        if last_progress.elapsed() >= DRY_RUN_PROGRESS_TICK {
+            on_progress(&ScanProgress {
+                files_seen: stats.files_seen,
+                files_hashed: 0,
+                files_skipped: stats.files_skipped,
+                bytes_seen: stats.bytes_seen,
+                total_files: totals.files,
+                total_bytes: totals.bytes,
+                current_path: candidate.path,
+                current_step: Some("dry run".to_string()),
+                active_tasks: Vec::new(),
+                warning: None,
+                eta_secs: None,
+            });
+            last_progress = Instant::now();
+        }
+    }
+    Ok(ScanResult { stats, warnings })
+}
+
+/// Rough size of one stored snapshot: AVIF frames at the scan's quality come in
+/// well under a byte per eight pixels.
+fn estimated_snapshot_bytes(max_dim: u32) -> u64 {
+    u64::from(max_dim).pow(2) / 8
+}
+
+/// Fails with `Error::DiskFull` up front when the fileset's disk cannot hold the
+/// snapshots of `video_files` videos.
+fn check_snapshot_space(
+    store: &dyn ScanStore,
+    config: &ScanConfig,
+    video_files: u64,
+) -> Result<()> {
+    if video_files == 0 {
+        return Ok(());
+    }
+    let Some(db_path) = store.path() else {
+        return Ok(());
+    };
+    let target = match &config.snapshot_storage {
+        SnapshotStorage::InDb => db_path.to_path_buf(),
+        SnapshotStorage::Sidecar { dir } => db_path.parent().unwrap_or(Path::new("")).join(dir),
+    };
+    // The sidecar directory may not exist yet.
+    let Some(available) = target
+        .ancestors()
+        .find(|path| path.exists())
+        .and_then(drive::available_space)
+    else {
+        return Ok(());
+    };
+    let needed = video_files
+        .saturating_mul(u64::from(config.snapshots_per_video))
+        .saturating_mul(estimated_snapshot_bytes(config.snapshot_max_dim));
+    if needed > available {
+        return Err(Error::DiskFull(format!(
+            "snapshots for {video_files} videos need about {} MiB, {} MiB free",
+            needed >> 20,
+            available >> 20
+        )));
+    }
+    Ok(())
+}
+
+/// Writes a processed file. A failed snapshot write only loses that file's
+/// snapshots, unless the disk is full.
+fn store_processed_item(
+    store: &dyn ScanStore,
+    config: &ScanConfig,
+    rec: &MediaFileRecord,
+    snapshots: Option<Vec<FileSnapshotRecord>>,
+    chunks: Option<Vec<ChunkFingerprint>>,
+) -> Result<()> {
+    if config.case_insensitive_paths {
+        store.adopt_path_case(&rec.path)?;
+    }
+    let file_id = store.upsert_file(rec)?;
+    // Cleared when the file was not chunked, so an edited file keeps no stale chunks.
+    store.replace_file_chunks(file_id, chunks.as_deref().unwrap_or_default())?;
+    if let Some(snaps) = snapshots {
+        if let Err(err @ Error::DiskFull(_)) =
+            store.replace_file_snapshots_in(file_id, &snaps, &config.snapshot_storage)
+        {
+            return Err(err);
+        }
+    }
+    Ok(())
+}
+
 #[derive(Clone)]
 struct ScanCandidate {
     path: PathBuf,
     is_symlink: bool,
 }
 
+/// A walk entry after the scan's filters; prescan and scan classify entries the same
+/// way so their file counts agree.
+enum WalkItem {
+    Unreadable(WalkError),
+    /// Left out by `ignore_empty_files`; counted in `ScanStats::files_filtered`.
+    Filtered,
+    File {
+        candidate: ScanCandidate,
+        size_bytes: u64,
+    },
+}
+
+/// `None` for directories, non-regular files and files under `min_size_bytes`.
+fn classify_entry(
+    config: &ScanConfig,
+    entry: std::result::Result<WalkEntry, WalkError>,
+) -> Option<WalkItem> {
+    let entry = match entry {
+        Ok(v) => v,
+        Err(err) => return Some(WalkItem::Unreadable(err)),
+    };
+    if !entry.file_type.is_file() {
+        return None;
+    }
+    let size_bytes = std::fs::symlink_metadata(&entry.path)
+        .ok()
+        .map(|md| md.len());
+    if size_bytes.is_some_and(|len| len < config.min_size_bytes) {
+        return None;
+    }
+    if config.ignore_empty_files && size_bytes == Some(0) {
+        return Some(WalkItem::Filtered);
+    }
+    Some(WalkItem::File {
+        candidate: ScanCandidate {
+            is_symlink: entry.file_type.is_symlink(),
+            path: entry.path,
+        },
+        size_bytes: size_bytes.unwrap_or(0),
+    })
+}
+
 struct ProcessedScanItem {
     path: PathBuf,
     rec: Option<MediaFileRecord>,
     snapshots: Option<Vec<FileSnapshotRecord>>,
+    chunks: Option<Vec<ChunkFingerprint>>,
     bytes_seen: u64,
     files_hashed_inc: u64,
     files_skipped_inc: u64,
+    /// Set when the skip came after every `io_retries` attempt failed.
+    files_failed_after_retries_inc: u64,
+    warning: Option<ScanWarning>,
+}
+
+impl ProcessedScanItem {
+    /// A file abandoned because the scan was cancelled; nothing of it is stored.
+    fn cancelled(path: PathBuf) -> Self {
+        Self {
+            path,
+            rec: None,
+            snapshots: None,
+            chunks: None,
+            bytes_seen: 0,
+            files_hashed_inc: 0,
+            files_skipped_inc: 0,
+            files_failed_after_retries_inc: 0,
+            warning: None,
+        }
+    }
 }
 
 enum WorkerUpdate {
-    Stage { path: PathBuf, step: &'static str },
-    Done(ProcessedScanItem),
+    Stage {
+        path: PathBuf,
+        step: &'static str,
+    },
+    /// A finished candidate and its index in the batch.
+    Done(usize, Box<ProcessedScanItem>),
 }
 
 #[allow(clippy::too_many_arguments)]
 fn flush_scan_batch<F>(
     config: &ScanConfig,
-    store: &SqliteScanStore,
+    store: &dyn ScanStore,
     cancel: Option<&ScanCancelToken>,
+    pool: Option<&Arc<rayon::ThreadPool>>,
+    probe_slots: &Arc<ProbeSlots>,
     totals: &ScanTotals,
     on_progress: &mut F,
     stats: &mut ScanStats,
+    warnings: &mut Vec<ScanWarning>,
     bytes_seen: &mut u64,
     files_since_flush: &mut u64,
     last_flush: &mut Instant,
@@ -300,22 +957,45 @@ where
         let mut active_tasks: BTreeMap<PathBuf, String> = BTreeMap::new();
         let mut completed = 0usize;
         let target = candidates.len();
+        // Workers finish out of order; rows are written in walk order so ids stay repeatable.
+        let mut finished: BTreeMap<usize, Box<ProcessedScanItem>> = BTreeMap::new();
+        let mut next_to_store = 0usize;
         let (tx, rx) = mpsc::channel::<WorkerUpdate>();
         let mut cancelled = false;
+        // A write error stops persisting; the batch already handed to workers still drains.
+        let mut failed = None;
         let mut last_heartbeat = Instant::now();
 
         let cfg = config.clone();
+        let pool = pool.cloned();
+        let probe_slots = Arc::clone(probe_slots);
+        let worker_cancel = cancel.cloned();
         let handle = thread::spawn(move || {
-            candidates.into_par_iter().for_each(|candidate| {
-                let tx_item = tx.clone();
-                let item = process_scan_candidate(&cfg, candidate, |path, step| {
-                    let _ = tx_item.send(WorkerUpdate::Stage {
-                        path: path.to_path_buf(),
-                        step,
+            let run = || {
+                candidates
+                    .into_par_iter()
+                    .enumerate()
+                    .for_each(|(index, candidate)| {
+                        let tx_item = tx.clone();
+                        let item = process_scan_candidate(
+                            &cfg,
+                            &probe_slots,
+                            worker_cancel.as_ref(),
+                            candidate,
+                            |path, step| {
+                                let _ = tx_item.send(WorkerUpdate::Stage {
+                                    path: path.to_path_buf(),
+                                    step,
+                                });
+                            },
+                        );
+                        let _ = tx_item.send(WorkerUpdate::Done(index, Box::new(item)));
                     });
-                });
-                let _ = tx_item.send(WorkerUpdate::Done(item));
-            });
+            };
+            match pool {
+                Some(pool) => pool.install(run),
+                None => run(),
+            }
         });
 
         while completed < target {
@@ -344,6 +1024,8 @@ where
                             current_path: path,
                             current_step: step,
                             active_tasks: active_task_list(&active_tasks),
+                            warning: None,
+                            eta_secs: None,
                         });
                         last_heartbeat = Instant::now();
                     }
@@ -365,77 +1047,102 @@ where
                         current_path: path,
                         current_step: Some(step.to_string()),
                         active_tasks: active_task_list(&active_tasks),
+                        warning: None,
+                        eta_secs: None,
                     });
                     last_heartbeat = Instant::now();
                 }
-                WorkerUpdate::Done(item) => {
+                WorkerUpdate::Done(index, item) => {
                     completed = completed.saturating_add(1);
                     active_tasks.remove(&item.path);
+                    finished.insert(index, item);
 
-                    if cancelled {
-                        continue;
-                    }
-
-                    *bytes_seen = bytes_seen.saturating_add(item.bytes_seen);
-                    stats.files_hashed = stats.files_hashed.saturating_add(item.files_hashed_inc);
-                    stats.files_skipped =
-                        stats.files_skipped.saturating_add(item.files_skipped_inc);
+                    while let Some(item) = finished.remove(&next_to_store) {
+                        next_to_store += 1;
+                        if cancelled || failed.is_some() {
+                            continue;
+                        }
 
-                    if let Some(mut rec) = item.rec {
-                        let file_id = store.upsert_file(&rec)?;
-                        rec.file_id = Some(file_id);
-                        if let Some(snaps) = item.snapshots {
-                            let _ = store.replace_file_snapshots(file_id, &snaps);
+                        *bytes_seen = bytes_seen.saturating_add(item.bytes_seen);
+                        stats.files_hashed =
+                            stats.files_hashed.saturating_add(item.files_hashed_inc);
+                        stats.files_skipped =
+                            stats.files_skipped.saturating_add(item.files_skipped_inc);
+                        stats.files_failed_after_retries = stats
+                            .files_failed_after_retries
+                            .saturating_add(item.files_failed_after_retries_inc);
+
+                        if let Some(rec) = &item.rec {
+                            if let Err(err) = store_processed_item(
+                                store,
+                                config,
+                                rec,
+                                item.snapshots,
+                                item.chunks,
+                            ) {
+                                failed = Some(err);
+                                continue;
+                            }
                         }
-                    }
 
-                    on_progress(&ScanProgress {
-                        files_seen: stats.files_seen,
-                        files_hashed: stats.files_hashed,
-                        files_skipped: stats.files_skipped,
-                        bytes_seen: *bytes_seen,
-                        total_files: totals.files,
-                        total_bytes: totals.bytes,
-                        current_path: item.path.clone(),
-                        current_step: Some("done".to_string()),
-                        active_tasks: active_task_list(&active_tasks),
-                    });
-                    last_heartbeat = Instant::now();
+                        on_progress(&ScanProgress {
+                            files_seen: stats.files_seen,
+                            files_hashed: stats.files_hashed,
+                            files_skipped: stats.files_skipped,
+                            bytes_seen: *bytes_seen,
+                            total_files: totals.files,
+                            total_bytes: totals.bytes,
+                            current_path: item.path.clone(),
+                            current_step: Some("done".to_string()),
+                            active_tasks: active_task_list(&active_tasks),
+                            warning: item.warning.clone(),
+                            eta_secs: None,
+                        });
+                        warnings.extend(item.warning);
+                        last_heartbeat = Instant::now();
 
-                    *files_since_flush = files_since_flush.saturating_add(1);
-                    if *files_since_flush >= flush_every_files
-                        || last_flush.elapsed() >= flush_every_elapsed
-                    {
-                        store.commit_tx()?;
-                        store.begin_scan_write_optimized_tx()?;
-                        *files_since_flush = 0;
-                        *last_flush = Instant::now();
+                        *files_since_flush = files_since_flush.saturating_add(1);
+                        if *files_since_flush >= flush_every_files
+                            || last_flush.elapsed() >= flush_every_elapsed
+                        {
+                            store.commit_tx()?;
+                            store.begin_scan_write_optimized_tx()?;
+                            *files_since_flush = 0;
+                            *last_flush = Instant::now();
+                        }
                     }
                 }
             }
         }
         let _ = handle.join();
 
-        if cancelled {
+        if let Some(err) = failed {
+            return Err(err);
+        }
+        // A file abandoned mid-hash may be the batch's last, after the loop's final check.
+        if cancelled || cancel.is_some_and(ScanCancelToken::is_cancelled) {
             update_fileset_status(store, config, "incomplete");
             store.commit_tx()?;
             return Err(Error::Cancelled);
         }
     } else {
         for candidate in candidates {
-            let item = process_scan_candidate(config, candidate, |path, step| {
-                on_progress(&ScanProgress {
-                    files_seen: stats.files_seen,
-                    files_hashed: stats.files_hashed,
-                    files_skipped: stats.files_skipped,
-                    bytes_seen: *bytes_seen,
-                    total_files: totals.files,
-                    total_bytes: totals.bytes,
-                    current_path: path.to_path_buf(),
-                    current_step: Some(step.to_string()),
-                    active_tasks: Vec::new(),
+            let item =
+                process_scan_candidate(config, probe_slots, cancel, candidate, |path, step| {
+                    on_progress(&ScanProgress {
+                        files_seen: stats.files_seen,
+                        files_hashed: stats.files_hashed,
+                        files_skipped: stats.files_skipped,
+                        bytes_seen: *bytes_seen,
+                        total_files: totals.files,
+                        total_bytes: totals.bytes,
+                        current_path: path.to_path_buf(),
+                        current_step: Some(step.to_string()),
+                        active_tasks: Vec::new(),
+                        warning: None,
+                        eta_secs: None,
+                    });
                 });
-            });
 
             if let Some(cancel) = cancel {
                 if cancel.is_cancelled() {
@@ -448,13 +1155,12 @@ where
             *bytes_seen = bytes_seen.saturating_add(item.bytes_seen);
             stats.files_hashed = stats.files_hashed.saturating_add(item.files_hashed_inc);
             stats.files_skipped = stats.files_skipped.saturating_add(item.files_skipped_inc);
+            stats.files_failed_after_retries = stats
+                .files_failed_after_retries
+                .saturating_add(item.files_failed_after_retries_inc);
 
-            if let Some(mut rec) = item.rec {
-                let file_id = store.upsert_file(&rec)?;
-                rec.file_id = Some(file_id);
-                if let Some(snaps) = item.snapshots {
-                    let _ = store.replace_file_snapshots(file_id, &snaps);
-                }
+            if let Some(rec) = &item.rec {
+                store_processed_item(store, config, rec, item.snapshots, item.chunks)?;
             }
 
             on_progress(&ScanProgress {
@@ -467,7 +1173,10 @@ where
                 current_path: item.path.clone(),
                 current_step: Some("done".to_string()),
                 active_tasks: Vec::new(),
+                warning: item.warning.clone(),
+                eta_secs: None,
             });
+            warnings.extend(item.warning);
 
             *files_since_flush = files_since_flush.saturating_add(1);
             if *files_since_flush >= flush_every_files
@@ -494,8 +1203,14 @@ fn active_task_list(active: &BTreeMap<PathBuf, String>) -> Vec<ActiveScanTask> {
         .collect()
 }
 
+/// `chunk_dedup` skips smaller files: they make only a few chunks, and exact hashes
+/// already cover whole-file duplicates.
+const CHUNK_DEDUP_MIN_BYTES: u64 = 8 * 1024 * 1024;
+
 fn process_scan_candidate<F>(
     config: &ScanConfig,
+    probe_slots: &Arc<ProbeSlots>,
+    cancel: Option<&ScanCancelToken>,
     candidate: ScanCandidate,
     mut on_stage: F,
 ) -> ProcessedScanItem
@@ -503,26 +1218,38 @@ where
     F: FnMut(&Path, &'static str),
 {
     let path = candidate.path;
+    if cancel.is_some_and(ScanCancelToken::is_cancelled) {
+        return ProcessedScanItem::cancelled(path);
+    }
     on_stage(&path, "metadata");
-    let md = match std::fs::metadata(&path) {
+    let (md, retries) = with_io_retries(config.io_retries, cancel, is_transient_io_error, || {
+        std::fs::metadata(&path)
+    });
+    let md = match md {
         Ok(v) => v,
-        Err(_) => {
+        Err(err) => {
+            let warning = ScanWarning {
+                path: path.clone(),
+                reason: format!("metadata: {err}{}", retries_suffix(retries)),
+            };
             return ProcessedScanItem {
                 path,
                 rec: None,
                 snapshots: None,
+                chunks: None,
                 bytes_seen: 0,
                 files_hashed_inc: 0,
                 files_skipped_inc: 1,
+                files_failed_after_retries_inc: u64::from(retries > 0),
+                warning: Some(warning),
             };
         }
     };
 
     let linked_file = candidate.is_symlink || is_hardlinked_file(&md);
-    on_stage(&path, "file type");
     let mut rec = MediaFileRecord {
         file_id: None,
-        path: relative_to_root(&config.root, &path).unwrap_or(path.clone()),
+        path: stored_path(config, &path),
         size_bytes: md.len(),
         modified_at: md.modified().ok(),
         blake3: None,
@@ -532,16 +1259,31 @@ where
         phash: None,
         ffmpeg_metadata: None,
         file_type: None,
+        media: MediaInfo::default(),
     };
 
-    rec.file_type = match infer::get_from_path(&path) {
-        Ok(Some(kind)) => Some(kind.mime_type().to_string()),
-        Ok(None) => None,
-        Err(_) => None,
-    };
-    if is_ffprobe_candidate(&path, rec.file_type.as_deref()) {
+    if config.detect_file_type {
+        on_stage(&path, "file type");
+        rec.file_type = match infer::get_from_path(&path) {
+            Ok(Some(kind)) => Some(kind.mime_type().to_string()),
+            Ok(None) => None,
+            Err(_) => None,
+        };
+    }
+    let mut duration_ms = None;
+    if cfg!(feature = "media")
+        && config.extract_metadata
+        && is_ffprobe_candidate(&path, rec.file_type.as_deref())
+    {
         on_stage(&path, "ffprobe metadata");
-        rec.ffmpeg_metadata = ffprobe_metadata(&path);
+        let probe = ffprobe_metadata(&path, probe_slots, cancel);
+        if let Some(json) = probe.as_deref() {
+            rec.media = video::media_info(json);
+            duration_ms = video::duration_ms(json);
+        }
+        if config.store_raw_ffprobe {
+            rec.ffmpeg_metadata = probe;
+        }
     }
 
     if config.perceptual_hashes && !linked_file && is_image_file(&path, rec.file_type.as_deref()) {
@@ -555,32 +1297,58 @@ where
 
     let mut files_hashed_inc = 0_u64;
     let mut files_skipped_inc = 0_u64;
+    let mut files_failed_after_retries_inc = 0_u64;
+    let mut warning = None;
     if config.hash_files && !linked_file {
-        on_stage(&path, "blake3");
-        match blake3_file(&path) {
-            Ok(hash) => {
-                rec.blake3 = Some(hash);
+        on_stage(&path, "blake3/sha256");
+        let (hashes, retries) =
+            with_io_retries(config.io_retries, cancel, is_transient_scan_error, || {
+                hash_file_multi(
+                    &path,
+                    &[HashAlgorithm::Blake3, HashAlgorithm::Sha256],
+                    cancel,
+                )
+            });
+        match hashes {
+            Ok(hashes) => {
+                rec.blake3 = hashes.blake3;
+                rec.sha256 = hashes.sha256;
+                files_hashed_inc = files_hashed_inc.saturating_add(1);
             }
-            Err(_) => {
+            Err(Error::Cancelled) => return ProcessedScanItem::cancelled(path),
+            Err(err) => {
                 files_skipped_inc = files_skipped_inc.saturating_add(1);
+                files_failed_after_retries_inc = u64::from(retries > 0);
+                warning = Some(ScanWarning {
+                    path: path.clone(),
+                    reason: format!("blake3/sha256: {err}{}", retries_suffix(retries)),
+                });
             }
         }
-        on_stage(&path, "sha256");
-        match sha256_file(&path) {
-            Ok(hash) => {
-                rec.sha256 = Some(hash);
-                files_hashed_inc = files_hashed_inc.saturating_add(1);
-            }
-            Err(_) => {
-                files_skipped_inc = files_skipped_inc.saturating_add(1);
+    }
+
+    let mut chunks = None;
+    if config.chunk_dedup && !linked_file && md.len() >= CHUNK_DEDUP_MIN_BYTES {
+        on_stage(&path, "chunks");
+        let (fingerprints, retries) =
+            with_io_retries(config.io_retries, cancel, is_transient_scan_error, || {
+                chunk_fingerprints(&path, cancel)
+            });
+        match fingerprints {
+            Ok(fingerprints) => chunks = Some(fingerprints),
+            Err(Error::Cancelled) => return ProcessedScanItem::cancelled(path),
+            Err(err) => {
+                warning.get_or_insert_with(|| ScanWarning {
+                    path: path.clone(),
+                    reason: format!("chunks: {err}{}", retries_suffix(retries)),
+                });
             }
         }
     }
 
     let mut snapshots = None;
-    if config.capture_snapshots && config.snapshots_per_video > 0 {
+    if cfg!(feature = "media") && config.capture_snapshots && config.snapshots_per_video > 0 {
         let is_video = is_video_file(&path, rec.file_type.as_deref());
-        let duration_ms = rec.ffmpeg_metadata.as_deref().and_then(ffprobe_duration_ms);
         if is_video && duration_ms.is_some() {
             on_stage(&path, "video snapshots");
             snapshots = video_snapshots_for_file(
@@ -590,6 +1358,10 @@ where
                 config.snapshot_max_dim,
                 Duration::from_secs(30),
             );
+        } else if needs_still_preview(&path, rec.file_type.as_deref()) {
+            on_stage(&path, "image preview");
+            snapshots =
+                still_preview_for_file(&path, config.snapshot_max_dim, Duration::from_secs(30));
         }
     }
 
@@ -597,9 +1369,73 @@ where
         path,
         rec: Some(rec),
         snapshots,
+        chunks,
         bytes_seen: md.len(),
         files_hashed_inc,
         files_skipped_inc,
+        files_failed_after_retries_inc,
+        warning,
+    }
+}
+
+/// Runs `read`, then up to `retries` more times while it fails with an error
+/// `is_transient` accepts, backing off 50 ms, 100 ms, 200 ms and so on between
+/// attempts. Returns the last result and how many retries were made.
+fn with_io_retries<T, E>(
+    retries: u32,
+    cancel: Option<&ScanCancelToken>,
+    is_transient: impl Fn(&E) -> bool,
+    mut read: impl FnMut() -> std::result::Result<T, E>,
+) -> (std::result::Result<T, E>, u32) {
+    const FIRST_BACKOFF: Duration = Duration::from_millis(50);
+
+    let mut attempt = 0;
+    loop {
+        let result = read();
+        match &result {
+            Err(err)
+                if attempt < retries
+                    && is_transient(err)
+                    && !cancel.is_some_and(ScanCancelToken::is_cancelled) =>
+            {
+                thread::sleep(FIRST_BACKOFF * 2u32.saturating_pow(attempt.min(6)));
+                attempt += 1;
+            }
+            _ => return (result, attempt),
+        }
+    }
+}
+
+/// Errors a second read may not hit: interrupted or timed-out calls, and on Unix
+/// the generic `EIO` a failing USB drive or network mount reports.
+fn is_transient_io_error(err: &std::io::Error) -> bool {
+    use std::io::ErrorKind;
+
+    if matches!(
+        err.kind(),
+        ErrorKind::Interrupted | ErrorKind::TimedOut | ErrorKind::WouldBlock
+    ) {
+        return true;
+    }
+    #[cfg(unix)]
+    {
+        err.raw_os_error() == Some(libc::EIO)
+    }
+    #[cfg(not(unix))]
+    {
+        false
+    }
+}
+
+fn is_transient_scan_error(err: &Error) -> bool {
+    matches!(err, Error::Io(io) if is_transient_io_error(io))
+}
+
+fn retries_suffix(retries: u32) -> String {
+    match retries {
+        0 => String::new(),
+        1 => " (after 1 retry)".to_string(),
+        n => format!(" (after {n} retries)"),
     }
 }
 
@@ -607,7 +1443,7 @@ fn is_hardlinked_file(md: &std::fs::Metadata) -> bool {
     #[cfg(unix)]
     {
         use std::os::unix::fs::MetadataExt;
-        return md.nlink() > 1;
+        md.nlink() > 1
     }
     #[cfg(windows)]
     {
@@ -622,83 +1458,78 @@ fn effective_concurrency_enabled(config: &ScanConfig) -> bool {
     }
     // Set DUPDUPNINJA_SCAN_SINGLE_THREADED=1 to force single-thread mode.
     match std::env::var("DUPDUPNINJA_SCAN_SINGLE_THREADED") {
-        Ok(v) => v == "1" || v.eq_ignore_ascii_case("true") || v.eq_ignore_ascii_case("yes"),
+        Ok(v) => !(v == "1" || v.eq_ignore_ascii_case("true") || v.eq_ignore_ascii_case("yes")),
         Err(_) => true,
     }
 }
 
-fn ffprobe_metadata(path: &Path) -> Option<String> {
-    let (tx, rx) = mpsc::channel();
-    let path = path.to_path_buf();
-    thread::spawn(move || {
-        let result = std::panic::catch_unwind(|| ffprobe_metadata_inner(&path))
-            .ok()
-            .flatten();
-        let _ = tx.send(result);
-    });
-
-    rx.recv_timeout(Duration::from_secs(30)).ok().flatten()
+/// Counting semaphore that bounds the ffprobe subprocesses of one scan, so a folder
+/// of thousands of clips cannot fork thousands of them at once.
+struct ProbeSlots {
+    free: Mutex<usize>,
+    released: Condvar,
 }
 
-fn ffprobe_metadata_inner(path: &Path) -> Option<String> {
-    let mut child = Command::new("ffprobe")
-        .arg("-v")
-        .arg("error")
-        .arg("-print_format")
-        .arg("json")
-        .arg("-show_format")
-        .arg("-show_streams")
-        .arg("--")
-        .arg(path)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .ok()?;
+/// Held for the lifetime of one ffprobe run; dropping it frees the slot.
+struct ProbeSlot(Arc<ProbeSlots>);
+
+impl ProbeSlots {
+    fn new(limit: usize) -> Arc<Self> {
+        Arc::new(Self {
+            free: Mutex::new(limit.max(1)),
+            released: Condvar::new(),
+        })
+    }
 
-    let mut stdout = child.stdout.take()?;
-    let mut stderr = child.stderr.take()?;
-    let timeout = Duration::from_secs(30);
-
-    match child.wait_timeout(timeout).ok()? {
-        Some(status) => {
-            let mut out = Vec::new();
-            let mut err = Vec::new();
-            let _ = stdout.read_to_end(&mut out);
-            let _ = stderr.read_to_end(&mut err);
-            if !status.success() {
+    /// Blocks until a slot is free; `None` once `cancel` fires.
+    fn acquire(self: &Arc<Self>, cancel: Option<&ScanCancelToken>) -> Option<ProbeSlot> {
+        let mut free = self.free.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            if cancel.is_some_and(ScanCancelToken::is_cancelled) {
                 return None;
             }
-            let text = String::from_utf8(out).ok()?;
-            let trimmed = text.trim();
-            if trimmed.is_empty() {
-                None
-            } else {
-                Some(trimmed.to_string())
+            if *free > 0 {
+                *free -= 1;
+                return Some(ProbeSlot(Arc::clone(self)));
             }
-        }
-        None => {
-            let _ = child.kill();
-            let _ = child.wait();
-            None
+            free = self
+                .released
+                .wait_timeout(free, Duration::from_millis(100))
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
         }
     }
 }
 
-fn ffprobe_duration_ms(json: &str) -> Option<i64> {
-    let v: Value = serde_json::from_str(json).ok()?;
-    let duration_secs = v
-        .get("format")
-        .and_then(|f| f.get("duration"))
-        .and_then(|d| {
-            if let Some(s) = d.as_str() {
-                s.parse::<f64>().ok()
-            } else {
-                d.as_f64()
-            }
-        })
-        .filter(|d| d.is_finite() && *d > 0.0);
+impl Drop for ProbeSlot {
+    fn drop(&mut self) {
+        let mut free = self.0.free.lock().unwrap_or_else(|e| e.into_inner());
+        *free += 1;
+        self.0.released.notify_one();
+    }
+}
 
-    duration_secs.map(|d| (d * 1000.0).round() as i64)
+fn ffprobe_metadata(
+    path: &Path,
+    probe_slots: &Arc<ProbeSlots>,
+    cancel: Option<&ScanCancelToken>,
+) -> Option<String> {
+    let slot = probe_slots.acquire(cancel)?;
+    let (tx, rx) = mpsc::channel();
+    let path = path.to_path_buf();
+    let cancel = cancel.cloned();
+    // The slot moves into the thread so it stays taken until ffprobe has exited,
+    // even if this side stops waiting first.
+    thread::spawn(move || {
+        let _slot = slot;
+        let cancelled = || cancel.as_ref().is_some_and(ScanCancelToken::is_cancelled);
+        let result = std::panic::catch_unwind(|| video::probe_metadata_until(&path, cancelled))
+            .ok()
+            .and_then(|result| result.ok());
+        let _ = tx.send(result);
+    });
+
+    rx.recv_timeout(Duration::from_secs(30)).ok().flatten()
 }
 
 fn is_video_file(path: &Path, file_type: Option<&str>) -> bool {
@@ -780,159 +1611,220 @@ fn video_snapshots_for_file(
     timeout: Duration,
 ) -> Option<Vec<FileSnapshotRecord>> {
     let duration_ms = duration_ms?;
-    let (tx, rx) = mpsc::channel();
     let path = path.to_path_buf();
+    let inner_timeout = timeout.saturating_sub(Duration::from_secs(2));
+    capture_off_thread(timeout, move || {
+        video::capture_snapshots_for_duration(
+            &path,
+            duration_ms,
+            snapshots_per_video,
+            snapshot_max_dim,
+            SnapshotFormat::Avif,
+            inner_timeout,
+        )
+    })
+}
 
+fn still_preview_for_file(
+    path: &Path,
+    snapshot_max_dim: u32,
+    timeout: Duration,
+) -> Option<Vec<FileSnapshotRecord>> {
+    let path = path.to_path_buf();
     let inner_timeout = timeout.saturating_sub(Duration::from_secs(2));
+    capture_off_thread(timeout, move || {
+        video::capture_still_preview_until(
+            &path,
+            snapshot_max_dim,
+            SnapshotFormat::Avif,
+            inner_timeout,
+        )
+        .map(|snapshot| vec![snapshot])
+    })
+}
+
+/// Runs an ffmpeg capture on its own thread so a hung or panicking capture only
+/// costs this file its snapshots.
+fn capture_off_thread<F>(timeout: Duration, capture: F) -> Option<Vec<FileSnapshotRecord>>
+where
+    F: FnOnce() -> Result<Vec<FileSnapshotRecord>> + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel();
     thread::spawn(move || {
-        let result = std::panic::catch_unwind(|| {
-            video_snapshots_for_file_inner(
-                &path,
-                duration_ms,
-                snapshots_per_video,
-                snapshot_max_dim,
-                inner_timeout,
-            )
-        })
-        .ok()
-        .flatten();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(capture))
+            .ok()
+            .and_then(|result| result.ok());
         let _ = tx.send(result);
     });
 
     rx.recv_timeout(timeout).ok().flatten()
 }
 
-fn video_snapshots_for_file_inner(
-    path: &Path,
-    duration_ms: i64,
-    snapshots_per_video: u32,
-    snapshot_max_dim: u32,
-    timeout: Duration,
-) -> Option<Vec<FileSnapshotRecord>> {
-    if snapshots_per_video == 0 || duration_ms <= 0 {
-        return Some(Vec::new());
+/// HEIC/HEIF and camera RAW images: GTK and the `image` crate cannot decode them, so
+/// scans store an ffmpeg-rendered preview for them like a video snapshot.
+fn needs_still_preview(path: &Path, file_type: Option<&str>) -> bool {
+    if matches!(
+        file_type,
+        Some("image/heif" | "image/heic" | "image/x-canon-cr2")
+    ) {
+        return true;
     }
+    let Some(ext) = path.extension().and_then(|e| e.to_str()) else {
+        return false;
+    };
+    matches!(
+        ext.to_ascii_lowercase().as_str(),
+        "heic"
+            | "heif"
+            | "hif"
+            | "dng"
+            | "cr2"
+            | "cr3"
+            | "crw"
+            | "nef"
+            | "nrw"
+            | "arw"
+            | "srf"
+            | "sr2"
+            | "orf"
+            | "rw2"
+            | "raf"
+            | "pef"
+            | "srw"
+            | "x3f"
+            | "3fr"
+            | "erf"
+            | "kdc"
+            | "mrw"
+            | "iiq"
+            | "rwl"
+    )
+}
 
-    let deadline = Instant::now() + timeout;
-    let duration_secs = (duration_ms as f64) / 1000.0;
-
-    let mut snaps = Vec::with_capacity(snapshots_per_video as usize);
-    for idx in 0..snapshots_per_video {
-        let remaining = deadline.saturating_duration_since(Instant::now());
-        if remaining < Duration::from_millis(250) {
-            break;
+/// Captures snapshots for video files in `store` that have none, and previews for
+/// HEIC and RAW images, e.g. after a scan with `capture_snapshots` off. Relative paths are resolved against `root`; only
+/// `snapshots_per_video` and `snapshot_max_dim` are read from `config`.
+pub fn regenerate_snapshots<F>(
+    store: &SqliteScanStore,
+    root: &Path,
+    config: &ScanConfig,
+    cancel: Option<&ScanCancelToken>,
+    on_progress: F,
+) -> Result<ScanResult>
+where
+    F: FnMut(&ScanProgress),
+{
+    config.validate()?;
+    let mut on_progress = with_eta(on_progress);
+    if !cfg!(feature = "media") {
+        return Err(Error::Ffmpeg(
+            "snapshots need a build with the `media` feature".to_string(),
+        ));
+    }
+    let videos: Vec<_> = store
+        .files_without_snapshots()?
+        .into_iter()
+        .filter(|row| {
+            is_video_file(&row.path, row.file_type.as_deref())
+                || needs_still_preview(&row.path, row.file_type.as_deref())
+        })
+        .collect();
+    let total_files = videos.len() as u64;
+    let total_bytes = videos.iter().map(|row| row.size_bytes).sum();
+    check_snapshot_space(store, config, total_files)?;
+
+    let mut stats = ScanStats::default();
+    let mut warnings = Vec::new();
+    let mut bytes_seen = 0u64;
+    for row in videos {
+        if let Some(cancel) = cancel {
+            if cancel.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
         }
 
-        let pos = ((idx + 1) as f64) / ((snapshots_per_video + 1) as f64);
-        let mut at_secs = duration_secs * pos;
-        if duration_secs > 2.0 {
-            at_secs = at_secs.clamp(0.5, duration_secs - 0.5);
+        let path = if row.path.is_absolute() {
+            row.path.clone()
         } else {
-            at_secs = at_secs.clamp(0.0, duration_secs.max(0.0));
-        }
-
-        let per_snapshot_timeout = remaining.min(Duration::from_secs(10));
-        let image_avif =
-            match ffmpeg_snapshot_avif_inner(path, at_secs, snapshot_max_dim, per_snapshot_timeout)
-            {
-                Some(bytes) => bytes,
-                None => continue,
-            };
-
-        let (ahash, dhash, phash) = image_hashes_from_avif(&image_avif)
-            .map(|(a, d, p)| (Some(a), Some(d), Some(p)))
-            .unwrap_or((None, None, None));
-
-        snaps.push(FileSnapshotRecord {
-            snapshot_index: idx,
-            snapshot_count: snapshots_per_video,
-            at_ms: (at_secs * 1000.0).round() as i64,
-            duration_ms: Some(duration_ms),
-            ahash,
-            dhash,
-            phash,
-            image_avif,
+            root.join(&row.path)
+        };
+        let still = !is_video_file(&row.path, row.file_type.as_deref());
+        let step = if still {
+            "image preview"
+        } else {
+            "video snapshots"
+        };
+        on_progress(&ScanProgress {
+            files_seen: stats.files_seen,
+            files_hashed: stats.files_hashed,
+            files_skipped: stats.files_skipped,
+            bytes_seen,
+            total_files,
+            total_bytes,
+            current_path: path.clone(),
+            current_step: Some(step.to_string()),
+            active_tasks: Vec::new(),
+            warning: None,
+            eta_secs: None,
         });
-    }
 
-    Some(snaps)
-}
-
-fn ffmpeg_snapshot_avif_inner(
-    path: &Path,
-    at_secs: f64,
-    snapshot_max_dim: u32,
-    timeout: Duration,
-) -> Option<Vec<u8>> {
-    let ts = format!("{at_secs:.3}");
-    let mut out_path = std::env::temp_dir();
-    let unique = format!(
-        "dupdupninja-snapshot-{}-{}.avif",
-        std::process::id(),
-        SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .ok()?
-            .as_nanos()
-    );
-    out_path.push(unique);
-
-    let max_dim = snapshot_max_dim.max(1);
-    let scale_filter = format!(
-        "scale='min(iw,{0})':'min(ih,{0})':force_original_aspect_ratio=decrease,scale=trunc(iw/2)*2:trunc(ih/2)*2",
-        max_dim
-    );
-
-    let mut child = Command::new("ffmpeg")
-        .arg("-hide_banner")
-        .arg("-loglevel")
-        .arg("error")
-        .arg("-nostdin")
-        .arg("-ss")
-        .arg(ts)
-        .arg("-i")
-        .arg(path)
-        .arg("-map")
-        .arg("0:v:0")
-        .arg("-frames:v")
-        .arg("1")
-        .arg("-an")
-        .arg("-sn")
-        .arg("-dn")
-        .arg("-vf")
-        .arg(scale_filter)
-        .arg("-pix_fmt")
-        .arg("yuv420p")
-        .arg("-c:v")
-        .arg("libaom-av1")
-        .arg("-still-picture")
-        .arg("1")
-        .arg("-crf")
-        .arg("35")
-        .arg("-b:v")
-        .arg("0")
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .arg(&out_path)
-        .spawn()
-        .ok()?;
-
-    match child.wait_timeout(timeout).ok()? {
-        Some(status) => {
-            if !status.success() {
-                let _ = std::fs::remove_file(&out_path);
-                return None;
+        stats.files_seen += 1;
+        bytes_seen = bytes_seen.saturating_add(row.size_bytes);
+        let captured = if still {
+            video::capture_still_preview(&path, config.snapshot_max_dim, SnapshotFormat::Avif)
+                .map(|snapshot| vec![snapshot])
+        } else {
+            video::capture_snapshots(
+                &path,
+                config.snapshots_per_video,
+                config.snapshot_max_dim,
+                SnapshotFormat::Avif,
+            )
+        };
+        match captured {
+            Ok(snaps) if !snaps.is_empty() => {
+                store.replace_file_snapshots_in(row.id, &snaps, &config.snapshot_storage)?;
+                stats.files_hashed += 1;
+            }
+            result => {
+                stats.files_skipped += 1;
+                let reason = match result {
+                    Err(err) => err.to_string(),
+                    Ok(_) => "no frames captured".to_string(),
+                };
+                let warning = ScanWarning { path, reason };
+                on_progress(&ScanProgress {
+                    files_seen: stats.files_seen,
+                    files_hashed: stats.files_hashed,
+                    files_skipped: stats.files_skipped,
+                    bytes_seen,
+                    total_files,
+                    total_bytes,
+                    current_path: warning.path.clone(),
+                    current_step: Some(step.to_string()),
+                    active_tasks: Vec::new(),
+                    warning: Some(warning.clone()),
+                    eta_secs: None,
+                });
+                warnings.push(warning);
             }
-            let bytes = std::fs::read(&out_path).ok()?;
-            let _ = std::fs::remove_file(&out_path);
-            Some(bytes)
-        }
-        None => {
-            let _ = child.kill();
-            let _ = child.wait();
-            let _ = std::fs::remove_file(&out_path);
-            None
         }
     }
+
+    on_progress(&ScanProgress {
+        files_seen: stats.files_seen,
+        files_hashed: stats.files_hashed,
+        files_skipped: stats.files_skipped,
+        bytes_seen,
+        total_files,
+        total_bytes,
+        current_path: PathBuf::new(),
+        current_step: Some("done".to_string()),
+        active_tasks: Vec::new(),
+        warning: None,
+        eta_secs: None,
+    });
+    Ok(ScanResult { stats, warnings })
 }
 
 #[derive(Debug, Clone)]
@@ -946,71 +1838,338 @@ pub struct PrescanProgress {
 pub fn prescan<F>(
     config: &ScanConfig,
     cancel: Option<&ScanCancelToken>,
-    mut on_progress: F,
+    on_progress: F,
 ) -> Result<ScanTotals>
 where
     F: FnMut(&PrescanProgress),
 {
-    if !config.root.exists() {
-        return Err(Error::InvalidArgument(format!(
-            "root does not exist: {}",
-            config.root.to_string_lossy()
-        )));
-    }
+    prescan_walk(config, cancel, false, on_progress).map(|(totals, _)| totals)
+}
+
+/// Totals from `prescan_for_scan`, plus the walked entries when `single_pass` is set.
+pub struct PrescannedWalk {
+    pub totals: ScanTotals,
+    items: Option<Vec<WalkItem>>,
+}
+
+/// Like `prescan`, for handing to `scan_with_prescan`.
+pub fn prescan_for_scan<F>(
+    config: &ScanConfig,
+    cancel: Option<&ScanCancelToken>,
+    on_progress: F,
+) -> Result<PrescannedWalk>
+where
+    F: FnMut(&PrescanProgress),
+{
+    let (totals, items) = prescan_walk(config, cancel, config.single_pass, on_progress)?;
+    Ok(PrescannedWalk { totals, items })
+}
+
+fn prescan_walk<F>(
+    config: &ScanConfig,
+    cancel: Option<&ScanCancelToken>,
+    keep_items: bool,
+    mut on_progress: F,
+) -> Result<(ScanTotals, Option<Vec<WalkItem>>)>
+where
+    F: FnMut(&PrescanProgress),
+{
+    config.validate()?;
+    check_roots_exist(config)?;
 
     let mut files = 0u64;
     let mut bytes = 0u64;
+    let mut video_files = 0u64;
     let mut dirs = 0u64;
+    let mut items = keep_items.then(Vec::new);
 
-    for entry in WalkDir::new(&config.root).follow_links(false).into_iter() {
+    for entry in walk_root(config) {
         if let Some(cancel) = cancel {
             if cancel.is_cancelled() {
                 return Err(Error::Cancelled);
             }
         }
 
-        let entry = match entry {
-            Ok(v) => v,
-            Err(_) => {
-                continue;
-            }
-        };
-
-        if entry.file_type().is_dir() {
+        if let Some(dir) = entry.as_ref().ok().filter(|e| e.file_type.is_dir()) {
             dirs += 1;
             on_progress(&PrescanProgress {
                 files_seen: files,
                 bytes_seen: bytes,
                 dirs_seen: dirs,
-                current_path: entry.path().to_path_buf(),
+                current_path: dir.path.clone(),
             });
             continue;
         }
 
-        if !entry.file_type().is_file() {
+        let Some(item) = classify_entry(config, entry) else {
             continue;
+        };
+        if let WalkItem::File {
+            candidate,
+            size_bytes,
+        } = &item
+        {
+            files += 1;
+            bytes = bytes.saturating_add(*size_bytes);
+            if is_video_file(&candidate.path, None) {
+                video_files += 1;
+            }
+            on_progress(&PrescanProgress {
+                files_seen: files,
+                bytes_seen: bytes,
+                dirs_seen: dirs,
+                current_path: candidate.path.clone(),
+            });
+        }
+        if let Some(items) = items.as_mut() {
+            items.push(item);
+        }
+    }
+
+    Ok((
+        ScanTotals {
+            files,
+            bytes,
+            video_files,
+        },
+        items,
+    ))
+}
+
+fn check_roots_exist(config: &ScanConfig) -> Result<()> {
+    match config.scan_roots().iter().find(|root| !root.exists()) {
+        Some(root) => Err(Error::NotFound(format!(
+            "root does not exist: {}",
+            root.to_string_lossy()
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Scan roots minus any that sit inside (or repeat) another, so no file is walked twice.
+fn walked_roots(config: &ScanConfig) -> impl Iterator<Item = &PathBuf> + '_ {
+    let roots = config.scan_roots();
+    let mut walked: Vec<&PathBuf> = roots
+        .iter()
+        .enumerate()
+        .filter(move |(idx, root)| {
+            !roots.iter().enumerate().any(|(other_idx, other)| {
+                other_idx != *idx
+                    && root.starts_with(other)
+                    && (other.as_path() != root.as_path() || other_idx < *idx)
+            })
+        })
+        .map(|(_, root)| root)
+        .collect();
+    // Sorted walks then visit every stored path in order, across roots too.
+    if config.sort_entries {
+        walked.sort();
+    }
+    walked.into_iter()
+}
+
+/// A walk entry from either walker, so prescan and scan classify them the same way.
+struct WalkEntry {
+    path: PathBuf,
+    file_type: std::fs::FileType,
+}
+
+/// A directory or file the walker could not read.
+struct WalkError {
+    path: Option<PathBuf>,
+    reason: String,
+}
+
+type WalkResult = std::result::Result<WalkEntry, WalkError>;
+
+fn walk_root(config: &ScanConfig) -> Box<dyn Iterator<Item = WalkResult> + '_> {
+    if config.use_gitignore {
+        Box::new(walked_roots(config).flat_map(move |root| walk_gitignore_root(config, root)))
+    } else {
+        Box::new(walked_roots(config).flat_map(move |root| walk_plain_root(config, root)))
+    }
+}
+
+fn walk_plain_root<'a>(
+    config: &'a ScanConfig,
+    root: &'a Path,
+) -> impl Iterator<Item = WalkResult> + 'a {
+    let walk = WalkDir::new(root)
+        .follow_links(false)
+        .same_file_system(config.stay_on_filesystem);
+    let walk = if config.sort_entries {
+        walk.sort_by_file_name()
+    } else {
+        walk
+    };
+    let mounts = excluded_mounts_under(config, root);
+    walk.into_iter()
+        .filter_entry(move |entry| {
+            entry.depth() == 0
+                || !(is_excluded(&config.exclude, root, entry.path())
+                    || mounts.iter().any(|mount| mount == entry.path()))
+        })
+        .map(|entry| match entry {
+            Ok(entry) => Ok(WalkEntry {
+                file_type: entry.file_type(),
+                path: entry.into_path(),
+            }),
+            Err(err) => Err(WalkError {
+                path: err.path().map(Path::to_path_buf),
+                reason: err.to_string(),
+            }),
+        })
+}
+
+/// Like `walk_plain_root`, but through `ignore::WalkBuilder`. Hidden files are still
+/// walked, and `.gitignore` files apply outside git repositories too.
+fn walk_gitignore_root(config: &ScanConfig, root: &Path) -> impl Iterator<Item = WalkResult> {
+    let mut builder = ignore::WalkBuilder::new(root);
+    builder
+        .follow_links(false)
+        .hidden(false)
+        .require_git(false)
+        .same_file_system(config.stay_on_filesystem);
+    if config.sort_entries {
+        builder.sort_by_file_name(|a, b| a.cmp(b));
+    }
+    let exclude = config.exclude.clone();
+    let mounts = excluded_mounts_under(config, root);
+    let filter_root = root.to_path_buf();
+    builder.filter_entry(move |entry| {
+        entry.depth() == 0
+            || !(is_excluded(&exclude, &filter_root, entry.path())
+                || mounts.iter().any(|mount| mount == entry.path()))
+    });
+    builder.build().filter_map(|entry| match entry {
+        Ok(entry) => {
+            // Only stdin entries lack a file type.
+            let file_type = entry.file_type()?;
+            Some(Ok(WalkEntry {
+                file_type,
+                path: entry.into_path(),
+            }))
         }
+        Err(err) => Some(Err(WalkError {
+            path: ignore_error_path(&err),
+            reason: err.to_string(),
+        })),
+    })
+}
 
-        files += 1;
-        if let Ok(md) = entry.metadata() {
-            bytes = bytes.saturating_add(md.len());
+fn ignore_error_path(err: &ignore::Error) -> Option<PathBuf> {
+    match err {
+        ignore::Error::WithPath { path, .. } => Some(path.clone()),
+        ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => {
+            ignore_error_path(err)
         }
+        ignore::Error::Partial(errs) => errs.iter().find_map(ignore_error_path),
+        _ => None,
+    }
+}
 
-        on_progress(&PrescanProgress {
-            files_seen: files,
-            bytes_seen: bytes,
-            dirs_seen: dirs,
-            current_path: entry.path().to_path_buf(),
-        });
+/// `exclude_mounts` inside `root`, spelled the way the walk of `root` yields them.
+/// Links are not followed during the walk, so joining a path relative to the
+/// canonical root back onto `root` gives the walked path.
+fn excluded_mounts_under(config: &ScanConfig, root: &Path) -> Vec<PathBuf> {
+    if config.exclude_mounts.is_empty() {
+        return Vec::new();
     }
+    let Ok(canonical_root) = root.canonicalize() else {
+        return Vec::new();
+    };
+    config
+        .exclude_mounts
+        .iter()
+        .filter_map(|mount| mount.canonicalize().ok())
+        .filter_map(|mount| {
+            mount
+                .strip_prefix(&canonical_root)
+                .ok()
+                .filter(|rel| !rel.as_os_str().is_empty())
+                .map(|rel| root.join(rel))
+        })
+        .collect()
+}
 
-    Ok(ScanTotals { files, bytes })
+fn is_excluded(exclude: &[String], root: &Path, path: &Path) -> bool {
+    if exclude.is_empty() {
+        return false;
+    }
+    let rel = relative_to_root(root, path)
+        .map(|p| p.to_string_lossy().replace('\\', "/"))
+        .unwrap_or_default();
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    exclude.iter().any(|pattern| {
+        let pattern = pattern.trim_end_matches('/');
+        if pattern.contains('/') {
+            glob_match(pattern.trim_start_matches('/').as_bytes(), rel.as_bytes())
+        } else {
+            glob_match(pattern.as_bytes(), name.as_bytes())
+        }
+    })
+}
+
+// `*` and `?` stay within one path segment; `**` spans segments.
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some(b'*') if pattern.get(1) == Some(&b'*') => {
+            let rest = pattern[2..].strip_prefix(b"/").unwrap_or(&pattern[2..]);
+            (0..=text.len()).any(|i| glob_match(rest, &text[i..]))
+        }
+        Some(b'*') => {
+            let rest = &pattern[1..];
+            for i in 0..=text.len() {
+                if glob_match(rest, &text[i..]) {
+                    return true;
+                }
+                if text.get(i) == Some(&b'/') {
+                    break;
+                }
+            }
+            false
+        }
+        Some(b'?') => {
+            matches!(text.first(), Some(c) if *c != b'/') && glob_match(&pattern[1..], &text[1..])
+        }
+        Some(c) => text.first() == Some(c) && glob_match(&pattern[1..], &text[1..]),
+    }
+}
+
+fn worker_pool(config: &ScanConfig) -> Result<Option<Arc<rayon::ThreadPool>>> {
+    if config.threads == 0 || !effective_concurrency_enabled(config) {
+        return Ok(None);
+    }
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(config.threads)
+        .build()
+        .map(|pool| Some(Arc::new(pool)))
+        .map_err(|e| Error::InvalidArgument(format!("failed to start worker pool: {e}")))
+}
+
+/// `path` as written to the fileset; see `ScanConfig::store_absolute_paths`.
+fn stored_path(config: &ScanConfig, path: &Path) -> PathBuf {
+    if config.store_absolute_paths {
+        return std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    }
+    relative_to_root(&config.base_root(), path).unwrap_or_else(|| path.to_path_buf())
 }
 
 fn relative_to_root(root: &Path, path: &Path) -> Option<PathBuf> {
     path.strip_prefix(root).ok().map(|p| p.to_path_buf())
 }
 
+fn fileset_name(config: &ScanConfig) -> String {
+    walked_roots(config)
+        .map(|root| fileset_name_from_root(root))
+        .collect::<Vec<_>>()
+        .join(" + ")
+}
+
 fn fileset_name_from_root(root: &Path) -> String {
     root.file_name()
         .and_then(|name| name.to_str())
@@ -1034,40 +2193,16 @@ fn is_image_file(path: &Path, file_type: Option<&str>) -> bool {
     )
 }
 
+/// Decodes by content rather than extension, so extensionless or misnamed copies
+/// hash like their originals.
 fn image_hashes_from_path(path: &Path) -> Option<(u64, u64, u64)> {
-    let image = image::open(path).ok()?;
-    image_hashes_from_image(&image)
-}
-
-fn image_hashes_from_avif(bytes: &[u8]) -> Option<(u64, u64, u64)> {
-    let image = image::load_from_memory_with_format(bytes, image::ImageFormat::Avif).ok()?;
-    image_hashes_from_image(&image)
-}
-
-fn image_hashes_from_image(image: &image::DynamicImage) -> Option<(u64, u64, u64)> {
-    let ahash = hash_image_with_alg(image, HashAlg::Mean, false)?;
-    let dhash = hash_image_with_alg(image, HashAlg::Gradient, false)?;
-    let phash = hash_image_with_alg(image, HashAlg::Mean, true)?;
-    Some((ahash, dhash, phash))
-}
-
-fn hash_image_with_alg(image: &image::DynamicImage, alg: HashAlg, use_dct: bool) -> Option<u64> {
-    let mut config = HasherConfig::new().hash_alg(alg);
-    if use_dct {
-        config = config.preproc_dct();
-    }
-    let hasher = config.to_hasher();
-    let hash = hasher.hash_image(image);
-    hash_to_u64(&hash)
-}
-
-fn hash_to_u64(hash: &image_hasher::ImageHash) -> Option<u64> {
-    let bytes = hash.as_bytes();
-    if bytes.len() != 8 {
-        return None;
-    }
-    let arr: [u8; 8] = bytes.try_into().ok()?;
-    Some(u64::from_be_bytes(arr))
+    let image = image::io::Reader::open(path)
+        .ok()?
+        .with_guessed_format()
+        .ok()?
+        .decode()
+        .ok()?;
+    perceptual_hashes(&image)
 }
 
 fn host_os_version() -> String {
@@ -1085,7 +2220,7 @@ fn host_os_version() -> String {
     String::new()
 }
 
-fn update_fileset_status(store: &SqliteScanStore, config: &ScanConfig, status: &str) {
+fn update_fileset_status(store: &dyn ScanStore, config: &ScanConfig, status: &str) {
     let meta = store
         .get_fileset_metadata()
         .ok()
@@ -1093,9 +2228,9 @@ fn update_fileset_status(store: &SqliteScanStore, config: &ScanConfig, status: &
         .unwrap_or_else(|| FilesetMetadata {
             created_at: SystemTime::now(),
             root_kind: config.root_kind,
-            root_path: config.root.clone(),
+            root_path: config.base_root().to_path_buf(),
             root_parent_path: if config.root_kind == ScanRootKind::Folder {
-                config.root.parent().map(|p| p.to_path_buf())
+                config.base_root().parent().map(|p| p.to_path_buf())
             } else {
                 None
             },
@@ -1108,7 +2243,7 @@ fn update_fileset_status(store: &SqliteScanStore, config: &ScanConfig, status: &
             host_os_version: host_os_version(),
             app_version: env!("CARGO_PKG_VERSION").to_string(),
             status: String::new(),
-            name: fileset_name_from_root(&config.root),
+            name: fileset_name(config),
             description: String::new(),
             notes: String::new(),
         });
@@ -1116,3 +2251,257 @@ fn update_fileset_status(store: &SqliteScanStore, config: &ScanConfig, status: &
     updated.status = status.to_string();
     let _ = store.set_fileset_metadata(&updated);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MemoryScanStore;
+    use crate::test_support::{pseudo_random_bytes, TempDir};
+
+    fn config(root: &Path) -> ScanConfig {
+        ScanConfig {
+            root: root.to_path_buf(),
+            capture_snapshots: false,
+            extract_metadata: false,
+            sort_entries: true,
+            ..ScanConfig::default()
+        }
+    }
+
+    #[test]
+    fn sorted_scans_write_rows_in_the_same_order() {
+        let tree = TempDir::new("sorted-tree");
+        for i in 0..40u64 {
+            let dir = ["", "b/", "a/c/", "a/"][i as usize % 4];
+            // Every third file repeats an earlier one's bytes, so groups form too.
+            let seed = if i % 3 == 0 { i / 3 } else { 100 + i };
+            tree.write(
+                format!("{dir}file-{i:02}.bin"),
+                &pseudo_random_bytes(4096 + i as usize * 37, seed),
+            );
+        }
+        let dbs = TempDir::new("sorted-dbs");
+
+        let scan = |name: &str| {
+            let store = SqliteScanStore::open(&dbs.path().join(name)).unwrap();
+            // Several workers, so rows would land in finishing order without reordering.
+            let config = ScanConfig {
+                threads: 4,
+                ..config(tree.path())
+            };
+            let result = scan_to_sqlite(&config, &store).unwrap();
+            let rows = store.list_files(100, 0).unwrap();
+            let mut by_id: Vec<_> = rows.into_iter().map(|r| (r.id, r.path)).collect();
+            by_id.sort();
+            (by_id, format!("{:?}", result.stats))
+        };
+        let (first_rows, first_stats) = scan("first.ddn");
+        let (second_rows, second_stats) = scan("second.ddn");
+
+        assert_eq!(first_rows.len(), 40);
+        assert_eq!(first_rows, second_rows);
+        assert_eq!(first_stats, second_stats);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_paths_round_trip() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let name = Path::new(OsStr::from_bytes(b"bad\xff\xfe.bin"));
+        let tree = TempDir::new("non-utf8-tree");
+        tree.write(name, &pseudo_random_bytes(1024, 7));
+        let dbs = TempDir::new("non-utf8-dbs");
+        let store = SqliteScanStore::open(&dbs.path().join("fileset.ddn")).unwrap();
+        scan_to_sqlite(&config(tree.path()), &store).unwrap();
+
+        let listed = store.list_files(10, 0).unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].path, name);
+        let iterated: Vec<_> = store.iter_files().map(|row| row.unwrap().path).collect();
+        assert_eq!(iterated, [name]);
+        let record = store.get_file_by_path(name).unwrap().unwrap();
+        assert_eq!(record.path, name);
+        assert!(store.delete_file_by_path(name).unwrap());
+        assert!(store.get_file_by_path(name).unwrap().is_none());
+    }
+
+    /// Scans `Photo.JPG`, renames it to `photo.jpg` and scans again into `store`.
+    fn rescan_after_case_rename(store: &dyn ScanStore, name: &str) {
+        let tree = TempDir::new(name);
+        tree.write("Photo.JPG", &pseudo_random_bytes(2048, 11));
+        let config = ScanConfig {
+            case_insensitive_paths: true,
+            ..config(tree.path())
+        };
+        scan_to_sqlite(&config, store).unwrap();
+        std::fs::rename(tree.path().join("Photo.JPG"), tree.path().join("photo.jpg")).unwrap();
+        scan_to_sqlite(&config, store).unwrap();
+    }
+
+    #[test]
+    fn case_only_rename_keeps_one_row() {
+        let dbs = TempDir::new("case-rename-dbs");
+        let store = SqliteScanStore::open(&dbs.path().join("fileset.ddn")).unwrap();
+        rescan_after_case_rename(&store, "case-rename-tree");
+        let rows = store.list_files(10, 0).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].path, Path::new("photo.jpg"));
+    }
+
+    #[test]
+    fn memory_store_adopts_case_only_rename() {
+        let store = MemoryScanStore::new();
+        rescan_after_case_rename(&store, "case-rename-memory-tree");
+        let files = store.files();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, Path::new("photo.jpg"));
+        assert!(!store.adopt_path_case(Path::new("photo.jpg")).unwrap());
+        assert!(!store.adopt_path_case(Path::new("other.jpg")).unwrap());
+    }
+
+    #[test]
+    fn extensionless_image_copy_groups_with_the_original() {
+        let image = image::RgbImage::from_fn(64, 64, |x, y| {
+            image::Rgb([(x * 4) as u8, (y * 4) as u8, ((x + y) * 2) as u8])
+        });
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgb8(image)
+            .write_to(
+                &mut std::io::Cursor::new(&mut png),
+                image::ImageOutputFormat::Png,
+            )
+            .unwrap();
+        let tree = TempDir::new("extensionless-tree");
+        tree.write("orig.png", &png);
+        tree.write("copy", &png);
+        let dbs = TempDir::new("extensionless-dbs");
+        let store = SqliteScanStore::open(&dbs.path().join("fileset.ddn")).unwrap();
+        scan_to_sqlite(&config(tree.path()), &store).unwrap();
+
+        let rows = store.list_files(10, 0).unwrap();
+        assert_eq!(rows.len(), 2);
+        for row in &rows {
+            assert_eq!(
+                row.file_type.as_deref(),
+                Some("image/png"),
+                "{:?}",
+                row.path
+            );
+            assert!(row.phash.is_some(), "{:?}", row.path);
+        }
+        let groups = store.duplicate_groups(false, 10, 0).unwrap();
+        assert_eq!(groups.len(), 1);
+        let mut grouped: Vec<_> = groups[0].files.iter().map(|f| f.path.clone()).collect();
+        grouped.sort();
+        assert_eq!(grouped, [Path::new("copy"), Path::new("orig.png")]);
+    }
+
+    #[test]
+    fn probe_slots_bound_holders_and_give_up_on_cancel() {
+        use std::sync::atomic::AtomicUsize;
+
+        let slots = ProbeSlots::new(2);
+        let held = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let workers: Vec<_> = (0..8)
+            .map(|_| {
+                let (slots, held, peak) =
+                    (Arc::clone(&slots), Arc::clone(&held), Arc::clone(&peak));
+                thread::spawn(move || {
+                    for _ in 0..5 {
+                        let slot = slots.acquire(None).unwrap();
+                        let now = held.fetch_add(1, Ordering::SeqCst) + 1;
+                        peak.fetch_max(now, Ordering::SeqCst);
+                        thread::sleep(Duration::from_millis(2));
+                        held.fetch_sub(1, Ordering::SeqCst);
+                        drop(slot);
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        assert!((1..=2).contains(&peak.load(Ordering::SeqCst)));
+
+        let _first = slots.acquire(None).unwrap();
+        let _second = slots.acquire(None).unwrap();
+        let cancel = ScanCancelToken::new();
+        let waiter = {
+            let (slots, cancel) = (Arc::clone(&slots), cancel.clone());
+            thread::spawn(move || {
+                let started = Instant::now();
+                let slot = slots.acquire(Some(&cancel));
+                (slot.is_none(), started.elapsed())
+            })
+        };
+        thread::sleep(Duration::from_millis(50));
+        cancel.cancel();
+        let (gave_up, waited) = waiter.join().unwrap();
+        assert!(gave_up);
+        assert!(waited < Duration::from_secs(1), "{waited:?}");
+    }
+
+    #[test]
+    fn resume_requires_sorted_walks() {
+        let config = ScanConfig {
+            resume: true,
+            sort_entries: false,
+            ..ScanConfig::default()
+        };
+        assert!(matches!(config.validate(), Err(Error::InvalidArgument(_))));
+    }
+
+    /// Scans a five-file tree, edits `b.bin`, adds `a2.bin` and resumes after `c.bin`
+    /// with `cursor_config` recorded. Returns how many files the resumed scan hashed.
+    fn resumed_scan_hashes(name: &str, cursor_config: impl Fn(&ScanConfig) -> String) -> u64 {
+        let tree = TempDir::new(name);
+        for (i, file) in ["a.bin", "b.bin", "c.bin", "d.bin", "e.bin"]
+            .iter()
+            .enumerate()
+        {
+            tree.write(file, &pseudo_random_bytes(512, i as u64));
+        }
+        let store = MemoryScanStore::new();
+        let config = config(tree.path());
+        scan_to_sqlite(&config, &store).unwrap();
+
+        tree.write("b.bin", &pseudo_random_bytes(700, 20));
+        tree.write("a2.bin", &pseudo_random_bytes(512, 21));
+        let resume = ScanConfig {
+            resume: true,
+            ..config
+        };
+        store
+            .set_scan_cursor(&ScanCursor {
+                last_path: PathBuf::from("c.bin"),
+                files_done: 3,
+                root_path: tree.path().to_path_buf(),
+                scan_config: cursor_config(&resume),
+            })
+            .unwrap();
+        let result = scan_to_sqlite(&resume, &store).unwrap();
+        assert_eq!(store.files().len(), 6);
+        assert!(store.scan_cursor().unwrap().is_none());
+        result.stats.files_hashed
+    }
+
+    #[test]
+    fn resume_skips_only_unchanged_files_up_to_the_cursor() {
+        // a.bin and c.bin are skipped; the edited b.bin and the new a2.bin are not.
+        assert_eq!(resumed_scan_hashes("resume-tree", cursor_config_json), 4);
+    }
+
+    #[test]
+    fn resume_ignores_a_cursor_from_other_settings() {
+        let other = |config: &ScanConfig| {
+            cursor_config_json(&ScanConfig {
+                min_size_bytes: 1,
+                ..config.clone()
+            })
+        };
+        assert_eq!(resumed_scan_hashes("resume-other-tree", other), 6);
+    }
+}
@@ -0,0 +1,302 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+use crate::models::{
+    ChunkFingerprint, DuplicateGroup, FileListRow, FileSnapshotRecord, FilesetMetadata,
+    HashAlgorithm, MediaFileRecord, ScanCursor, SnapshotStorage,
+};
+use crate::Result;
+
+/// What the scan engine writes through. `SqliteScanStore` keeps a `.ddn` fileset;
+/// `MemoryScanStore` keeps everything in memory for embedders that only want the
+/// results.
+pub trait ScanStore {
+    /// The fileset file, or `None` when the store is not on disk.
+    fn path(&self) -> Option<&Path>;
+
+    /// Starts the write transaction a scan runs in. Stores without transactions
+    /// can leave the default.
+    fn begin_scan_write_optimized_tx(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn commit_tx(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn rollback_tx(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn get_fileset_metadata(&self) -> Result<Option<FilesetMetadata>>;
+
+    fn set_fileset_metadata(&self, meta: &FilesetMetadata) -> Result<()>;
+
+    fn set_scan_config(&self, json: &str) -> Result<()>;
+
+    /// Where a scan that has not completed yet stopped.
+    fn scan_cursor(&self) -> Result<Option<ScanCursor>>;
+
+    fn set_scan_cursor(&self, cursor: &ScanCursor) -> Result<()>;
+
+    fn clear_scan_cursor(&self) -> Result<()>;
+
+    /// Whether a file at stored path `path` with this size and mtime (whole seconds
+    /// since the epoch) would hash differently than its stored record.
+    fn needs_rehash(&self, path: &Path, size_bytes: u64, modified_at_secs: i64) -> Result<bool>;
+
+    /// Prepares for `adopt_path_case` calls; only stores that index paths need it.
+    fn create_path_nocase_index(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Renames the record whose path matches `path` up to ASCII case, unless `path`
+    /// itself is stored. Returns whether a record was renamed.
+    fn adopt_path_case(&self, path: &Path) -> Result<bool>;
+
+    /// Inserts or updates the record for `rec.path` and returns its file id.
+    fn upsert_file(&self, rec: &MediaFileRecord) -> Result<i64>;
+
+    /// Replaces the chunk fingerprints of `file_id`; an empty slice clears them.
+    fn replace_file_chunks(&self, file_id: i64, chunks: &[ChunkFingerprint]) -> Result<()>;
+
+    fn replace_file_snapshots_in(
+        &self,
+        file_id: i64,
+        snapshots: &[FileSnapshotRecord],
+        storage: &SnapshotStorage,
+    ) -> Result<()>;
+
+    /// A page of exact-duplicate groups, largest groups first.
+    fn duplicate_groups(
+        &self,
+        include_ignored: bool,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<DuplicateGroup>>;
+}
+
+/// A `ScanStore` that keeps records in memory and is dropped with them. Snapshots
+/// stay in memory whatever `SnapshotStorage` says, and no group is ever ignored.
+#[derive(Debug, Default)]
+pub struct MemoryScanStore {
+    state: Mutex<MemoryState>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryState {
+    /// A record's file id is its index plus one; records are never removed.
+    files: Vec<MediaFileRecord>,
+    by_path: HashMap<PathBuf, usize>,
+    chunks: HashMap<i64, Vec<ChunkFingerprint>>,
+    snapshots: HashMap<i64, Vec<FileSnapshotRecord>>,
+    fileset: Option<FilesetMetadata>,
+    scan_config: Option<String>,
+    scan_cursor: Option<ScanCursor>,
+}
+
+impl MemoryScanStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every stored record, in the order they were first stored.
+    pub fn files(&self) -> Vec<MediaFileRecord> {
+        self.lock().files.clone()
+    }
+
+    pub fn file_snapshots(&self, file_id: i64) -> Vec<FileSnapshotRecord> {
+        self.lock()
+            .snapshots
+            .get(&file_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn file_chunks(&self, file_id: i64) -> Vec<ChunkFingerprint> {
+        self.lock()
+            .chunks
+            .get(&file_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// The settings JSON the last scan recorded.
+    pub fn scan_config(&self) -> Option<String> {
+        self.lock().scan_config.clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MemoryState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl ScanStore for MemoryScanStore {
+    fn path(&self) -> Option<&Path> {
+        None
+    }
+
+    fn get_fileset_metadata(&self) -> Result<Option<FilesetMetadata>> {
+        Ok(self.lock().fileset.clone())
+    }
+
+    fn set_fileset_metadata(&self, meta: &FilesetMetadata) -> Result<()> {
+        self.lock().fileset = Some(meta.clone());
+        Ok(())
+    }
+
+    fn set_scan_config(&self, json: &str) -> Result<()> {
+        self.lock().scan_config = Some(json.to_string());
+        Ok(())
+    }
+
+    fn scan_cursor(&self) -> Result<Option<ScanCursor>> {
+        Ok(self.lock().scan_cursor.clone())
+    }
+
+    fn set_scan_cursor(&self, cursor: &ScanCursor) -> Result<()> {
+        self.lock().scan_cursor = Some(cursor.clone());
+        Ok(())
+    }
+
+    fn clear_scan_cursor(&self) -> Result<()> {
+        self.lock().scan_cursor = None;
+        Ok(())
+    }
+
+    fn needs_rehash(&self, path: &Path, size_bytes: u64, modified_at_secs: i64) -> Result<bool> {
+        let state = self.lock();
+        let Some(rec) = state.by_path.get(path).map(|&idx| &state.files[idx]) else {
+            return Ok(true);
+        };
+        let stored_secs = rec.modified_at.map(|t| {
+            t.duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs() as i64)
+        });
+        Ok(rec.size_bytes != size_bytes || stored_secs != Some(modified_at_secs))
+    }
+
+    fn adopt_path_case(&self, path: &Path) -> Result<bool> {
+        let mut state = self.lock();
+        if state.by_path.contains_key(path) {
+            return Ok(false);
+        }
+        let wanted = path.to_string_lossy();
+        let Some(old) = state
+            .by_path
+            .keys()
+            .find(|stored| stored.to_string_lossy().eq_ignore_ascii_case(&wanted))
+            .cloned()
+        else {
+            return Ok(false);
+        };
+        let idx = state.by_path.remove(&old).unwrap_or_default();
+        state.files[idx].path = path.to_path_buf();
+        state.by_path.insert(path.to_path_buf(), idx);
+        Ok(true)
+    }
+
+    fn upsert_file(&self, rec: &MediaFileRecord) -> Result<i64> {
+        let mut state = self.lock();
+        let idx = match state.by_path.get(&rec.path) {
+            Some(&idx) => idx,
+            None => {
+                let idx = state.files.len();
+                state.files.push(rec.clone());
+                state.by_path.insert(rec.path.clone(), idx);
+                idx
+            }
+        };
+        let file_id = idx as i64 + 1;
+        state.files[idx] = MediaFileRecord {
+            file_id: Some(file_id),
+            ..rec.clone()
+        };
+        Ok(file_id)
+    }
+
+    fn replace_file_chunks(&self, file_id: i64, chunks: &[ChunkFingerprint]) -> Result<()> {
+        let mut state = self.lock();
+        if chunks.is_empty() {
+            state.chunks.remove(&file_id);
+        } else {
+            state.chunks.insert(file_id, chunks.to_vec());
+        }
+        Ok(())
+    }
+
+    fn replace_file_snapshots_in(
+        &self,
+        file_id: i64,
+        snapshots: &[FileSnapshotRecord],
+        _storage: &SnapshotStorage,
+    ) -> Result<()> {
+        self.lock().snapshots.insert(file_id, snapshots.to_vec());
+        Ok(())
+    }
+
+    /// Grouped and ordered like `SqliteScanStore::duplicate_groups`.
+    fn duplicate_groups(
+        &self,
+        _include_ignored: bool,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<DuplicateGroup>> {
+        let state = self.lock();
+        let mut groups: HashMap<(bool, [u8; 32]), Vec<&MediaFileRecord>> = HashMap::new();
+        for rec in state.files.iter().filter(|rec| rec.size_bytes > 0) {
+            let key = match (rec.blake3, rec.sha256) {
+                (Some(hash), _) => (false, hash),
+                (None, Some(hash)) => (true, hash),
+                (None, None) => continue,
+            };
+            groups.entry(key).or_default().push(rec);
+        }
+        let mut groups: Vec<_> = groups
+            .into_iter()
+            .filter(|(_, files)| files.len() > 1)
+            .collect();
+        let max_size = |files: &[&MediaFileRecord]| files.iter().map(|f| f.size_bytes).max();
+        groups.sort_by(|(a_key, a), (b_key, b)| {
+            b.len()
+                .cmp(&a.len())
+                .then_with(|| max_size(b).cmp(&max_size(a)))
+                .then_with(|| a_key.1.cmp(&b_key.1))
+        });
+        Ok(groups
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .map(|((by_sha256, hash), mut files)| {
+                files.sort_by(|a, b| a.path.cmp(&b.path));
+                DuplicateGroup {
+                    algorithm: if by_sha256 {
+                        HashAlgorithm::Sha256
+                    } else {
+                        HashAlgorithm::Blake3
+                    },
+                    hash,
+                    files: files.into_iter().map(file_list_row).collect(),
+                }
+            })
+            .collect())
+    }
+}
+
+fn file_list_row(rec: &MediaFileRecord) -> FileListRow {
+    FileListRow {
+        id: rec.file_id.unwrap_or_default(),
+        path: rec.path.clone(),
+        size_bytes: rec.size_bytes,
+        modified_at: rec.modified_at,
+        blake3: rec.blake3,
+        sha256: rec.sha256,
+        ahash: rec.ahash,
+        dhash: rec.dhash,
+        phash: rec.phash,
+        ffmpeg_metadata: rec.ffmpeg_metadata.clone(),
+        file_type: rec.file_type.clone(),
+    }
+}
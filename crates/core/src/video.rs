@@ -1,6 +1,84 @@
+#[cfg(feature = "media")]
+use std::io::Read;
 use std::path::Path;
+#[cfg(feature = "media")]
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 
-use crate::error::Result;
+use serde_json::Value;
+#[cfg(feature = "media")]
+use wait_timeout::ChildExt;
+
+use crate::error::{Error, Result};
+use crate::hash::perceptual_hashes;
+use crate::models::{FileSnapshotRecord, MediaInfo};
+
+/// Whether this build can run ffprobe and ffmpeg, i.e. has the `media` feature.
+pub const MEDIA_SUPPORT: bool = cfg!(feature = "media");
+
+/// Upper bound for one `capture_snapshots` call; each frame gets at most 10s of it.
+const CAPTURE_TIMEOUT: Duration = Duration::from_secs(30);
+#[cfg(feature = "media")]
+const PROBE_TIMEOUT: Duration = Duration::from_secs(30);
+/// How often a running ffprobe checks whether its caller gave up.
+#[cfg(feature = "media")]
+const PROBE_POLL: Duration = Duration::from_millis(100);
+#[cfg(feature = "media")]
+const VERSION_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Whether `ffprobe -version` runs, checked once per process. Without ffprobe
+/// scans store no media metadata.
+pub fn ffprobe_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    *AVAILABLE.get_or_init(|| tool_runs("ffprobe"))
+}
+
+/// Whether `ffmpeg -version` runs, checked once per process. Without ffmpeg
+/// scans capture no video snapshots.
+pub fn ffmpeg_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    *AVAILABLE.get_or_init(|| tool_runs("ffmpeg"))
+}
+
+/// A line for the UIs to show when media features will not work, `None` when they do.
+pub fn media_tools_notice() -> Option<&'static str> {
+    if !MEDIA_SUPPORT {
+        return Some("Built without media support: metadata and snapshots are disabled");
+    }
+    match (ffprobe_available(), ffmpeg_available()) {
+        (true, true) => None,
+        (false, false) => Some("ffmpeg not found: metadata and snapshots are disabled"),
+        (false, true) => Some("ffprobe not found: media metadata is disabled"),
+        (true, false) => Some("ffmpeg not found: video snapshots are disabled"),
+    }
+}
+
+#[cfg(feature = "media")]
+fn tool_runs(program: &str) -> bool {
+    let Ok(mut child) = Command::new(program)
+        .arg("-version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+    else {
+        return false;
+    };
+    match child.wait_timeout(VERSION_CHECK_TIMEOUT) {
+        Ok(Some(status)) => status.success(),
+        _ => {
+            let _ = child.kill();
+            let _ = child.wait();
+            false
+        }
+    }
+}
+
+#[cfg(not(feature = "media"))]
+fn tool_runs(_program: &str) -> bool {
+    false
+}
 
 #[derive(Debug, Clone)]
 pub struct VideoSignature {
@@ -14,3 +92,431 @@ pub struct VideoSignature {
 pub trait VideoAnalyzer {
     fn signature(&self, path: &Path) -> Result<VideoSignature>;
 }
+
+/// Image encoding for captured frames. Scans store AVIF.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SnapshotFormat {
+    #[default]
+    Avif,
+    Jpeg,
+    Png,
+}
+
+impl SnapshotFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Avif => "avif",
+            Self::Jpeg => "jpg",
+            Self::Png => "png",
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Self::Avif => "image/avif",
+            Self::Jpeg => "image/jpeg",
+            Self::Png => "image/png",
+        }
+    }
+
+    #[cfg(feature = "media")]
+    fn encoder_args(self) -> &'static [&'static str] {
+        match self {
+            Self::Avif => &[
+                "-pix_fmt",
+                "yuv420p",
+                "-c:v",
+                "libaom-av1",
+                "-still-picture",
+                "1",
+                "-crf",
+                "35",
+                "-b:v",
+                "0",
+            ],
+            Self::Jpeg => &["-pix_fmt", "yuvj420p", "-c:v", "mjpeg", "-q:v", "3"],
+            Self::Png => &["-pix_fmt", "rgb24", "-c:v", "png"],
+        }
+    }
+
+    fn image_format(self) -> image::ImageFormat {
+        match self {
+            Self::Avif => image::ImageFormat::Avif,
+            Self::Jpeg => image::ImageFormat::Jpeg,
+            Self::Png => image::ImageFormat::Png,
+        }
+    }
+}
+
+/// Extracts `count` evenly spaced frames from a video, scaled to fit within `max_dim`.
+///
+/// The `image_avif` field of each record holds the bytes in `format`, named by `mime`.
+pub fn capture_snapshots(
+    path: &Path,
+    count: u32,
+    max_dim: u32,
+    format: SnapshotFormat,
+) -> Result<Vec<FileSnapshotRecord>> {
+    let metadata = probe_metadata(path)?;
+    let duration_ms = duration_ms(&metadata)
+        .ok_or_else(|| Error::Ffmpeg(format!("no duration for {}", path.display())))?;
+    capture_snapshots_for_duration(path, duration_ms, count, max_dim, format, CAPTURE_TIMEOUT)
+}
+
+/// Like `capture_snapshots` for callers that already know the duration.
+pub(crate) fn capture_snapshots_for_duration(
+    path: &Path,
+    duration_ms: i64,
+    count: u32,
+    max_dim: u32,
+    format: SnapshotFormat,
+    timeout: Duration,
+) -> Result<Vec<FileSnapshotRecord>> {
+    if count == 0 || duration_ms <= 0 {
+        return Ok(Vec::new());
+    }
+
+    let deadline = Instant::now() + timeout;
+    let duration_secs = (duration_ms as f64) / 1000.0;
+
+    let mut snaps = Vec::with_capacity(count as usize);
+    let mut last_err = None;
+    for idx in 0..count {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining < Duration::from_millis(250) {
+            break;
+        }
+
+        let pos = ((idx + 1) as f64) / ((count + 1) as f64);
+        let mut at_secs = duration_secs * pos;
+        if duration_secs > 2.0 {
+            at_secs = at_secs.clamp(0.5, duration_secs - 0.5);
+        } else {
+            at_secs = at_secs.clamp(0.0, duration_secs.max(0.0));
+        }
+
+        let per_snapshot_timeout = remaining.min(Duration::from_secs(10));
+        let image = match capture_frame(path, Some(at_secs), max_dim, format, per_snapshot_timeout)
+        {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                last_err = Some(err);
+                continue;
+            }
+        };
+
+        let (ahash, dhash, phash) =
+            image::load_from_memory_with_format(&image, format.image_format())
+                .ok()
+                .and_then(|decoded| perceptual_hashes(&decoded))
+                .map(|(a, d, p)| (Some(a), Some(d), Some(p)))
+                .unwrap_or((None, None, None));
+
+        snaps.push(FileSnapshotRecord {
+            snapshot_index: idx,
+            snapshot_count: count,
+            at_ms: (at_secs * 1000.0).round() as i64,
+            duration_ms: Some(duration_ms),
+            ahash,
+            dhash,
+            phash,
+            mime: format.mime_type().to_string(),
+            image_avif: image,
+        });
+    }
+
+    match last_err {
+        Some(err) if snaps.is_empty() => Err(err),
+        _ => Ok(snaps),
+    }
+}
+
+/// Renders a single preview of a still image the UI cannot decode itself, such as
+/// HEIC or a camera RAW, scaled to fit within `max_dim`. Which formats work depends
+/// on the decoders of the installed ffmpeg.
+pub fn capture_still_preview(
+    path: &Path,
+    max_dim: u32,
+    format: SnapshotFormat,
+) -> Result<FileSnapshotRecord> {
+    capture_still_preview_until(path, max_dim, format, CAPTURE_TIMEOUT)
+}
+
+pub(crate) fn capture_still_preview_until(
+    path: &Path,
+    max_dim: u32,
+    format: SnapshotFormat,
+    timeout: Duration,
+) -> Result<FileSnapshotRecord> {
+    let image = capture_frame(
+        path,
+        None,
+        max_dim,
+        format,
+        timeout.min(Duration::from_secs(10)),
+    )?;
+    let (ahash, dhash, phash) = image::load_from_memory_with_format(&image, format.image_format())
+        .ok()
+        .and_then(|decoded| perceptual_hashes(&decoded))
+        .map(|(a, d, p)| (Some(a), Some(d), Some(p)))
+        .unwrap_or((None, None, None));
+    Ok(FileSnapshotRecord {
+        snapshot_index: 0,
+        snapshot_count: 1,
+        at_ms: 0,
+        duration_ms: None,
+        ahash,
+        dhash,
+        phash,
+        mime: format.mime_type().to_string(),
+        image_avif: image,
+    })
+}
+
+/// Encodes the frame at `at_secs`, or with `None` the still image `path` holds. Stills
+/// keep ffmpeg's default stream choice so tiled HEIF images come out whole.
+#[cfg(feature = "media")]
+fn capture_frame(
+    path: &Path,
+    at_secs: Option<f64>,
+    max_dim: u32,
+    format: SnapshotFormat,
+    timeout: Duration,
+) -> Result<Vec<u8>> {
+    let what = match at_secs {
+        Some(at_secs) => format!("snapshot at {at_secs:.3}s"),
+        None => "still preview".to_string(),
+    };
+    let mut out_path = std::env::temp_dir();
+    let unique = format!(
+        "dupdupninja-snapshot-{}-{}.{}",
+        std::process::id(),
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos(),
+        format.extension()
+    );
+    out_path.push(unique);
+
+    let max_dim = max_dim.max(1);
+    let scale_filter = format!(
+        "scale='min(iw,{0})':'min(ih,{0})':force_original_aspect_ratio=decrease,scale=trunc(iw/2)*2:trunc(ih/2)*2",
+        max_dim
+    );
+
+    let mut command = Command::new("ffmpeg");
+    command
+        .arg("-hide_banner")
+        .arg("-loglevel")
+        .arg("error")
+        .arg("-nostdin");
+    if let Some(at_secs) = at_secs {
+        command.arg("-ss").arg(format!("{at_secs:.3}"));
+    }
+    command.arg("-i").arg(path);
+    if at_secs.is_some() {
+        command.arg("-map").arg("0:v:0");
+    }
+    let mut child = command
+        .arg("-frames:v")
+        .arg("1")
+        .arg("-an")
+        .arg("-sn")
+        .arg("-dn")
+        .arg("-vf")
+        .arg(scale_filter)
+        .args(format.encoder_args())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .arg(&out_path)
+        .spawn()
+        .map_err(|e| Error::Ffmpeg(format!("failed to run ffmpeg: {e}")))?;
+
+    match child.wait_timeout(timeout)? {
+        Some(status) => {
+            if !status.success() {
+                let _ = std::fs::remove_file(&out_path);
+                return Err(Error::Ffmpeg(format!("ffmpeg {what} exited with {status}")));
+            }
+            let bytes = std::fs::read(&out_path);
+            let _ = std::fs::remove_file(&out_path);
+            Ok(bytes?)
+        }
+        None => {
+            let _ = child.kill();
+            let _ = child.wait();
+            let _ = std::fs::remove_file(&out_path);
+            Err(Error::Ffmpeg(format!("ffmpeg {what} timed out")))
+        }
+    }
+}
+
+#[cfg(not(feature = "media"))]
+fn capture_frame(
+    _path: &Path,
+    _at_secs: Option<f64>,
+    _max_dim: u32,
+    _format: SnapshotFormat,
+    _timeout: Duration,
+) -> Result<Vec<u8>> {
+    Err(media_disabled())
+}
+
+/// `ffprobe -show_format -show_streams` output as JSON.
+pub fn probe_metadata(path: &Path) -> Result<String> {
+    probe_metadata_until(path, || false)
+}
+
+/// Like `probe_metadata`, but kills ffprobe and returns `Error::Cancelled` once
+/// `cancelled` returns true.
+#[cfg(feature = "media")]
+pub(crate) fn probe_metadata_until(path: &Path, cancelled: impl Fn() -> bool) -> Result<String> {
+    let mut child = Command::new("ffprobe")
+        .arg("-v")
+        .arg("error")
+        .arg("-print_format")
+        .arg("json")
+        .arg("-show_format")
+        .arg("-show_streams")
+        .arg("--")
+        .arg(path)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| Error::Ffmpeg(format!("failed to run ffprobe: {e}")))?;
+
+    let (Some(stdout), Some(stderr)) = (child.stdout.take(), child.stderr.take()) else {
+        return Err(Error::Ffmpeg("ffprobe output is not piped".to_string()));
+    };
+    // Read both pipes while waiting: ffprobe blocks once either fills its buffer, which
+    // large stream lists do, and would then only ever end by timing out.
+    let stdout = drain_pipe(stdout);
+    let stderr = drain_pipe(stderr);
+
+    let deadline = Instant::now() + PROBE_TIMEOUT;
+    let finished = loop {
+        if let Some(status) = child.wait_timeout(PROBE_POLL)? {
+            break Some(status);
+        }
+        if cancelled() {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(Error::Cancelled);
+        }
+        if Instant::now() >= deadline {
+            break None;
+        }
+    };
+
+    match finished {
+        Some(status) => {
+            let out = stdout.join().unwrap_or_default();
+            let err = stderr.join().unwrap_or_default();
+            if !status.success() {
+                return Err(Error::Ffmpeg(format!(
+                    "ffprobe exited with {status}: {}",
+                    String::from_utf8_lossy(&err).trim()
+                )));
+            }
+            let text = String::from_utf8(out)
+                .map_err(|_| Error::Ffmpeg("ffprobe output is not UTF-8".to_string()))?;
+            let trimmed = text.trim();
+            if trimmed.is_empty() {
+                Err(Error::Ffmpeg("ffprobe produced no output".to_string()))
+            } else {
+                Ok(trimmed.to_string())
+            }
+        }
+        None => {
+            let _ = child.kill();
+            let _ = child.wait();
+            Err(Error::Ffmpeg(format!(
+                "ffprobe timed out after {}s",
+                PROBE_TIMEOUT.as_secs()
+            )))
+        }
+    }
+}
+
+/// Reads `pipe` to its end on a helper thread.
+#[cfg(feature = "media")]
+fn drain_pipe(mut pipe: impl Read + Send + 'static) -> std::thread::JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = pipe.read_to_end(&mut buf);
+        buf
+    })
+}
+
+#[cfg(not(feature = "media"))]
+pub(crate) fn probe_metadata_until(_path: &Path, _cancelled: impl Fn() -> bool) -> Result<String> {
+    Err(media_disabled())
+}
+
+#[cfg(not(feature = "media"))]
+fn media_disabled() -> Error {
+    Error::Ffmpeg("built without the `media` feature".to_string())
+}
+
+/// Container and first audio/video stream details from `probe_metadata` output.
+/// Cover art attached to audio files is not counted as a video stream.
+pub fn media_info(metadata_json: &str) -> MediaInfo {
+    let Ok(v) = serde_json::from_str::<Value>(metadata_json) else {
+        return MediaInfo::default();
+    };
+    let format = v.get("format");
+    let streams = v
+        .get("streams")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let stream_of = |kind: &str| {
+        streams.iter().find(|s| {
+            s.get("codec_type").and_then(Value::as_str) == Some(kind)
+                && s.pointer("/disposition/attached_pic")
+                    .and_then(Value::as_i64)
+                    != Some(1)
+        })
+    };
+    let video = stream_of("video");
+    let audio = stream_of("audio");
+    let text = |v: Option<&Value>, key: &str| {
+        v.and_then(|v| v.get(key))
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    };
+    let dim = |key: &str| {
+        video
+            .and_then(|v| v.get(key))
+            .and_then(Value::as_u64)
+            .and_then(|d| u32::try_from(d).ok())
+    };
+
+    MediaInfo {
+        container: text(format, "format_name"),
+        video_codec: text(video, "codec_name"),
+        audio_codec: text(audio, "codec_name"),
+        width: dim("width"),
+        height: dim("height"),
+        bit_rate: text(format, "bit_rate").and_then(|b| b.parse().ok()),
+    }
+}
+
+/// Container duration from `probe_metadata` output.
+pub fn duration_ms(metadata_json: &str) -> Option<i64> {
+    let v: Value = serde_json::from_str(metadata_json).ok()?;
+    let duration_secs = v
+        .get("format")
+        .and_then(|f| f.get("duration"))
+        .and_then(|d| {
+            if let Some(s) = d.as_str() {
+                s.parse::<f64>().ok()
+            } else {
+                d.as_f64()
+            }
+        })
+        .filter(|d| d.is_finite() && *d > 0.0);
+
+    duration_secs.map(|d| (d * 1000.0).round() as i64)
+}
@@ -1,8 +1,21 @@
 use std::env;
+use std::process::Command;
 
 fn main() {
     // Keep in sync with FFI_ABI_MAJOR in src/lib.rs and DUPDUPNINJA_FFI_ABI_MAJOR in the header.
-    const ABI_MAJOR: &str = "1";
+    const ABI_MAJOR: &str = "3";
+
+    // Reported by dupdupninja_ffi_build_string; builds outside a git checkout say "unknown".
+    let git_hash = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|hash| hash.trim().to_string())
+        .filter(|hash| !hash.is_empty())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=DUPDUPNINJA_GIT_HASH={git_hash}");
 
     let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
     if target_os == "linux" {
@@ -9,12 +9,22 @@
 extern "C" {
 #endif
 
+#if defined(__GNUC__) || defined(__clang__)
+#define DUPDUP_DEPRECATED(msg) __attribute__((deprecated(msg)))
+#elif defined(_MSC_VER)
+#define DUPDUP_DEPRECATED(msg) __declspec(deprecated(msg))
+#else
+#define DUPDUP_DEPRECATED(msg)
+#endif
+
 typedef struct DupdupEngine DupdupEngine;
 typedef struct DupdupCancelToken DupdupCancelToken;
+typedef struct DupdupStore DupdupStore;
+typedef struct DupdupScanHandle DupdupScanHandle;
 
 enum {
-  DUPDUPNINJA_FFI_ABI_MAJOR = 1,
-  DUPDUPNINJA_FFI_ABI_MINOR = 3,
+  DUPDUPNINJA_FFI_ABI_MAJOR = 3,
+  DUPDUPNINJA_FFI_ABI_MINOR = 0,
   DUPDUPNINJA_FFI_ABI_PATCH = 0,
 };
 
@@ -29,6 +39,10 @@ typedef enum DupdupStatus {
   DUPDUP_STATUS_ERROR = 1,
   DUPDUP_STATUS_INVALID_ARGUMENT = 2,
   DUPDUP_STATUS_NULL_POINTER = 3,
+  DUPDUP_STATUS_CANCELLED = 4,
+  DUPDUP_STATUS_NOT_FOUND = 5,
+  DUPDUP_STATUS_IO = 6,
+  DUPDUP_STATUS_RUNNING = 7,
 } DupdupStatus;
 
 typedef struct DupdupProgress {
@@ -44,11 +58,17 @@ typedef struct DupdupProgress {
 
 typedef void (*DupdupProgressCallback)(const DupdupProgress* progress, void* user_data);
 
+// Deprecated: use DupdupTotals, which holds the same counts.
 typedef struct DupdupPrescanTotals {
   uint64_t total_files;
   uint64_t total_bytes;
 } DupdupPrescanTotals;
 
+typedef struct DupdupTotals {
+  uint64_t files;
+  uint64_t bytes;
+} DupdupTotals;
+
 typedef struct DupdupPrescanProgress {
   uint64_t files_seen;
   uint64_t bytes_seen;
@@ -65,6 +85,14 @@ typedef struct DupdupScanOptions {
   uint8_t concurrent_processing;
 } DupdupScanOptions;
 
+// min_interval_ms coalesces progress callbacks to at most one per interval (0 = every
+// update). The last update of a scan is always delivered. Zero totals mean unknown.
+typedef struct DupdupScanConfig {
+  DupdupScanOptions options;
+  DupdupTotals totals;
+  uint32_t min_interval_ms;
+} DupdupScanConfig;
+
 typedef struct DupdupFilesetRow {
   int64_t id;
   char* path;
@@ -106,6 +134,26 @@ typedef struct DupdupFilesetMetadataView {
   char* status;
 } DupdupFilesetMetadataView;
 
+typedef enum DupdupRootKind {
+  DUPDUP_ROOT_KIND_FOLDER = 0,
+  DUPDUP_ROOT_KIND_DRIVE = 1,
+} DupdupRootKind;
+
+typedef struct DupdupMetadata {
+  char* name;
+  char* description;
+  char* notes;
+  char* status;
+  char* root_path;
+  // dupdupninja_store_set_metadata leaves created_at_secs and root_kind alone
+  // unless their has_* flag is set.
+  uint8_t has_created_at;
+  int64_t created_at_secs;
+  uint8_t has_root_kind;
+  // A DupdupRootKind value; set_metadata rejects others with InvalidArgument.
+  uint32_t root_kind;
+} DupdupMetadata;
+
 typedef struct DupdupSnapshotInfo {
   uint32_t snapshot_index;
   uint32_t snapshot_count;
@@ -126,6 +174,17 @@ DupdupNinjaVersion dupdupninja_ffi_version(void);
 // Returns the ABI major version used for compatibility checks.
 uint32_t dupdupninja_ffi_abi_major(void);
 
+enum {
+  DUPDUP_FEATURE_MEDIA = 1u << 0,           // ffprobe metadata and ffmpeg video snapshots
+  DUPDUP_FEATURE_PERCEPTUAL_HASH = 1u << 1, // ahash/dhash/phash of images
+};
+
+// Returns the DUPDUP_FEATURE_* bits compiled into this library.
+uint32_t dupdupninja_ffi_features(void);
+
+// Returns a static, nul-terminated "dupdupninja-ffi <version> (<git hash>)" string.
+const char* dupdupninja_ffi_build_string(void);
+
 DupdupEngine* dupdupninja_engine_new(void);
 void dupdupninja_engine_free(DupdupEngine* engine);
 
@@ -137,6 +196,10 @@ void dupdupninja_cancel_token_cancel(DupdupCancelToken* token);
 // The pointer becomes invalid after the next dupdupninja call on the same thread.
 const char* dupdupninja_last_error_message(void);
 
+// Returns the category of the last error on this thread (DUPDUP_STATUS_OK if the last
+// call succeeded). Same lifetime rules as dupdupninja_last_error_message.
+DupdupStatus dupdupninja_last_error_code(void);
+
 DupdupStatus dupdupninja_scan_folder_to_sqlite(
   DupdupEngine* engine,
   const char* root_path,
@@ -144,16 +207,31 @@ DupdupStatus dupdupninja_scan_folder_to_sqlite(
 );
 
 // Progress callback is invoked from the scanning thread. current_path is only valid
-// for the duration of the callback.
+// for the duration of the callback. totals may be NULL; pass the result of
+// dupdupninja_prescan to get populated total_files/total_bytes in progress.
 DupdupStatus dupdupninja_scan_folder_to_sqlite_with_progress(
   DupdupEngine* engine,
   const char* root_path,
   const char* db_path,
   DupdupCancelToken* cancel_token,
+  const DupdupTotals* totals,
   DupdupProgressCallback progress_cb,
   void* user_data
 );
 
+// Counts files/bytes under root_path. Returns DUPDUP_STATUS_CANCELLED if the
+// cancel token fired before the walk finished. progress_cb may be NULL; it runs on
+// the calling thread and current_path is only valid for the duration of the callback.
+DupdupStatus dupdupninja_prescan(
+  DupdupEngine* engine,
+  const char* root_path,
+  DupdupCancelToken* cancel_token,
+  DupdupPrescanCallback progress_cb,
+  void* user_data,
+  DupdupTotals* out_totals
+);
+
+DUPDUP_DEPRECATED("use dupdupninja_prescan")
 DupdupStatus dupdupninja_prescan_folder(
   const char* root_path,
   DupdupCancelToken* cancel_token,
@@ -162,6 +240,7 @@ DupdupStatus dupdupninja_prescan_folder(
   DupdupPrescanTotals* out_totals
 );
 
+DUPDUP_DEPRECATED("use dupdupninja_scan_folder_to_sqlite_with_progress")
 DupdupStatus dupdupninja_scan_folder_to_sqlite_with_progress_and_totals(
   DupdupEngine* engine,
   const char* root_path,
@@ -185,6 +264,41 @@ DupdupStatus dupdupninja_scan_folder_to_sqlite_with_progress_totals_and_options(
   void* user_data
 );
 
+// config may be NULL to use default options with no totals and no throttling.
+DupdupStatus dupdupninja_scan_folder_to_sqlite_with_config(
+  DupdupEngine* engine,
+  const char* root_path,
+  const char* db_path,
+  DupdupCancelToken* cancel_token,
+  const DupdupScanConfig* config,
+  DupdupProgressCallback progress_cb,
+  void* user_data
+);
+
+// Starts a scan on a background thread and returns immediately. Returns NULL on failure.
+// config may be NULL (min_interval_ms is ignored; poll at whatever rate suits the caller).
+DupdupScanHandle* dupdupninja_scan_start(
+  DupdupEngine* engine,
+  const char* root_path,
+  const char* db_path,
+  const DupdupScanConfig* config
+);
+
+// Copies the latest progress into out_progress (may be NULL) and returns
+// DUPDUP_STATUS_RUNNING while the scan is in flight, DUPDUP_STATUS_OK when it finished,
+// or the failure status. current_path/current_step stay valid until the next poll or free.
+DupdupStatus dupdupninja_scan_poll(DupdupScanHandle* handle, DupdupProgress* out_progress);
+
+// Blocks until the scan finishes and returns its final status.
+DupdupStatus dupdupninja_scan_join(DupdupScanHandle* handle);
+
+// Poll, join and cancel may be called from different threads at once, e.g. cancel from
+// a UI thread while another thread waits in join. Free must come after all of them.
+void dupdupninja_scan_handle_cancel(DupdupScanHandle* handle);
+
+// Cancels and joins a still-running scan before releasing the handle.
+void dupdupninja_scan_handle_free(DupdupScanHandle* handle);
+
 DupdupStatus dupdupninja_fileset_list_rows(
   const char* db_path,
   uint8_t duplicates_only,
@@ -238,11 +352,36 @@ DupdupStatus dupdupninja_fileset_set_metadata(
 
 void dupdupninja_fileset_metadata_free(DupdupFilesetMetadataView* meta);
 
+// Opens a fileset database. Returns NULL on failure (see dupdupninja_last_error_message).
+DupdupStore* dupdupninja_store_open(const char* db_path);
+void dupdupninja_store_free(DupdupStore* store);
+
+// Strings in out_meta are owned by the library; release them with dupdupninja_metadata_free.
+DupdupStatus dupdupninja_store_get_metadata(DupdupStore* store, DupdupMetadata* out_meta);
+
+// NULL string fields keep their stored value. Strings must be valid UTF-8.
+DupdupStatus dupdupninja_store_set_metadata(DupdupStore* store, const DupdupMetadata* meta);
+
+void dupdupninja_metadata_free(DupdupMetadata* meta);
+
+// Sets *out_needs_rehash to 1 when file_path (relative to the scan root) has no row or its
+// stored size or modification time differ from the given values, else 0.
+DupdupStatus dupdupninja_store_needs_rehash(
+  DupdupStore* store,
+  const char* file_path,
+  uint64_t size_bytes,
+  int64_t modified_at_secs,
+  uint8_t* out_needs_rehash
+);
+
 DupdupStatus dupdupninja_fileset_delete_file_by_path(
   const char* db_path,
   const char* file_path
 );
 
+// Moves file_path to the platform trash. Does not touch any fileset database.
+DupdupStatus dupdupninja_move_to_trash(const char* file_path);
+
 DupdupStatus dupdupninja_fileset_list_snapshots_by_path(
   const char* db_path,
   const char* file_path,
@@ -1,35 +1,61 @@
 #![allow(unsafe_code)]
+#![allow(clippy::missing_safety_doc)]
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::BTreeMap;
 use std::ffi::{CStr, CString};
 use std::os::raw::c_char;
 use std::path::PathBuf;
 use std::slice;
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
 use dupdupninja_core::db::SqliteScanStore;
 use dupdupninja_core::models::{DriveMetadata, FilesetMetadata, ScanRootKind};
 use dupdupninja_core::scan::{
-    prescan, scan_to_sqlite, scan_to_sqlite_with_progress, scan_to_sqlite_with_progress_and_totals,
-    PrescanProgress, ScanCancelToken, ScanConfig, ScanTotals,
+    prescan, scan_to_sqlite, scan_to_sqlite_with_progress_and_totals, PrescanProgress,
+    ScanCancelToken, ScanConfig, ScanProgress, ScanTotals,
 };
+use dupdupninja_core::Error;
 
 thread_local! {
     static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+    static LAST_ERROR_CODE: Cell<DupdupStatus> = const { Cell::new(DupdupStatus::Ok) };
 }
 
-fn set_last_error(msg: impl Into<String>) {
+fn set_last_error(code: DupdupStatus, msg: impl Into<String>) {
     let msg = msg.into();
     let cmsg = CString::new(msg).unwrap_or_else(|_| CString::new("error").unwrap());
     LAST_ERROR.with(|slot| {
         *slot.borrow_mut() = Some(cmsg);
     });
+    LAST_ERROR_CODE.with(|slot| slot.set(code));
+}
+
+fn set_core_error(err: Error) -> DupdupStatus {
+    let code = status_for_error(&err);
+    set_last_error(code, err.to_string());
+    code
+}
+
+fn status_for_error(err: &Error) -> DupdupStatus {
+    match err {
+        Error::Io(e) if e.kind() == std::io::ErrorKind::NotFound => DupdupStatus::NotFound,
+        Error::Io(_) => DupdupStatus::Io,
+        Error::NotFound(_) => DupdupStatus::NotFound,
+        Error::Sqlite(_) | Error::Migration(_) | Error::Ffmpeg(_) => DupdupStatus::Error,
+        Error::InvalidArgument(_) | Error::NotAFileset(_) => DupdupStatus::InvalidArgument,
+        Error::Trash(_) | Error::DiskFull(_) => DupdupStatus::Io,
+        Error::Cancelled => DupdupStatus::Cancelled,
+    }
 }
 
 fn ok_last_error() {
     LAST_ERROR.with(|slot| {
         *slot.borrow_mut() = None;
     });
+    LAST_ERROR_CODE.with(|slot| slot.set(DupdupStatus::Ok));
 }
 
 #[repr(C)]
@@ -39,6 +65,32 @@ pub struct DupdupEngine {
 
 struct Engine;
 
+#[repr(C)]
+pub struct DupdupStore {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+pub struct DupdupScanHandle {
+    _private: [u8; 0],
+}
+
+// Shared by reference between threads: a UI thread may poll or cancel while another
+// blocks in join, so everything mutable sits behind a mutex.
+struct ScanHandle {
+    cancel: ScanCancelToken,
+    shared: Arc<Mutex<ScanHandleShared>>,
+    thread: Mutex<Option<JoinHandle<()>>>,
+    /// Backing for the last poll's `current_path` and `current_step`.
+    polled: Mutex<(CString, Option<CString>)>,
+}
+
+#[derive(Default)]
+struct ScanHandleShared {
+    progress: Option<ScanProgress>,
+    outcome: Option<(DupdupStatus, String)>,
+}
+
 #[repr(C)]
 pub struct DupdupCancelToken {
     token: ScanCancelToken,
@@ -59,12 +111,20 @@ pub struct DupdupProgress {
 pub type DupdupProgressCallback =
     Option<extern "C" fn(progress: *const DupdupProgress, user_data: *mut libc::c_void)>;
 
+#[deprecated(note = "use DupdupTotals")]
 #[repr(C)]
 pub struct DupdupPrescanTotals {
     pub total_files: u64,
     pub total_bytes: u64,
 }
 
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DupdupTotals {
+    pub files: u64,
+    pub bytes: u64,
+}
+
 #[repr(C)]
 pub struct DupdupPrescanProgress {
     pub files_seen: u64,
@@ -85,6 +145,14 @@ pub struct DupdupScanOptions {
     pub concurrent_processing: bool,
 }
 
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct DupdupScanConfig {
+    pub options: DupdupScanOptions,
+    pub totals: DupdupTotals,
+    pub min_interval_ms: u32,
+}
+
 #[repr(C)]
 pub struct DupdupFilesetRow {
     pub id: i64,
@@ -131,6 +199,29 @@ pub struct DupdupFilesetMetadataView {
     pub status: *mut c_char,
 }
 
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DupdupRootKind {
+    Folder = 0,
+    Drive = 1,
+}
+
+#[repr(C)]
+pub struct DupdupMetadata {
+    pub name: *mut c_char,
+    pub description: *mut c_char,
+    pub notes: *mut c_char,
+    pub status: *mut c_char,
+    pub root_path: *mut c_char,
+    /// `dupdupninja_store_set_metadata` leaves `created_at_secs` and `root_kind`
+    /// alone unless their `has_*` flag is set.
+    pub has_created_at: u8,
+    pub created_at_secs: i64,
+    pub has_root_kind: u8,
+    /// A `DupdupRootKind` value.
+    pub root_kind: u32,
+}
+
 #[repr(C)]
 pub struct DupdupSnapshotInfo {
     pub snapshot_index: u32,
@@ -146,10 +237,22 @@ pub struct DupdupSnapshotInfo {
     pub phash: u64,
 }
 
-const FFI_ABI_MAJOR: u32 = 1;
-const FFI_ABI_MINOR: u32 = 3;
+const FFI_ABI_MAJOR: u32 = 3;
+const FFI_ABI_MINOR: u32 = 0;
 const FFI_ABI_PATCH: u32 = 0;
 
+/// Bits of `dupdupninja_ffi_features`.
+pub const DUPDUP_FEATURE_MEDIA: u32 = 1 << 0;
+pub const DUPDUP_FEATURE_PERCEPTUAL_HASH: u32 = 1 << 1;
+
+const BUILD_STRING: &str = concat!(
+    "dupdupninja-ffi ",
+    env!("CARGO_PKG_VERSION"),
+    " (",
+    env!("DUPDUPNINJA_GIT_HASH"),
+    ")\0"
+);
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct DupdupVersion {
@@ -165,6 +268,10 @@ pub enum DupdupStatus {
     Error = 1,
     InvalidArgument = 2,
     NullPointer = 3,
+    Cancelled = 4,
+    NotFound = 5,
+    Io = 6,
+    Running = 7,
 }
 
 #[no_mangle]
@@ -188,6 +295,20 @@ pub extern "C" fn dupdupninja_ffi_abi_major() -> u32 {
     FFI_ABI_MAJOR
 }
 
+#[no_mangle]
+pub extern "C" fn dupdupninja_ffi_features() -> u32 {
+    let mut features = DUPDUP_FEATURE_PERCEPTUAL_HASH;
+    if dupdupninja_core::video::MEDIA_SUPPORT {
+        features |= DUPDUP_FEATURE_MEDIA;
+    }
+    features
+}
+
+#[no_mangle]
+pub extern "C" fn dupdupninja_ffi_build_string() -> *const c_char {
+    BUILD_STRING.as_ptr().cast()
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dupdupninja_engine_free(engine: *mut DupdupEngine) {
     ok_last_error();
@@ -232,6 +353,11 @@ pub extern "C" fn dupdupninja_last_error_message() -> *const c_char {
     })
 }
 
+#[no_mangle]
+pub extern "C" fn dupdupninja_last_error_code() -> DupdupStatus {
+    LAST_ERROR_CODE.with(|slot| slot.get())
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dupdupninja_scan_folder_to_sqlite(
     engine: *mut DupdupEngine,
@@ -241,49 +367,43 @@ pub unsafe extern "C" fn dupdupninja_scan_folder_to_sqlite(
     ok_last_error();
 
     if engine.is_null() {
-        set_last_error("engine is null");
+        set_last_error(DupdupStatus::NullPointer, "engine is null");
         return DupdupStatus::NullPointer;
     }
     if root_path.is_null() {
-        set_last_error("root_path is null");
+        set_last_error(DupdupStatus::NullPointer, "root_path is null");
         return DupdupStatus::NullPointer;
     }
     if db_path.is_null() {
-        set_last_error("db_path is null");
+        set_last_error(DupdupStatus::NullPointer, "db_path is null");
         return DupdupStatus::NullPointer;
     }
 
     let root_path = match c_path(root_path) {
         Ok(v) => v,
         Err(e) => {
-            set_last_error(e);
+            set_last_error(DupdupStatus::InvalidArgument, e);
             return DupdupStatus::InvalidArgument;
         }
     };
     let db_path = match c_path(db_path) {
         Ok(v) => v,
         Err(e) => {
-            set_last_error(e);
+            set_last_error(DupdupStatus::InvalidArgument, e);
             return DupdupStatus::InvalidArgument;
         }
     };
 
     let store = match SqliteScanStore::open(&db_path) {
         Ok(v) => v,
-        Err(e) => {
-            set_last_error(e.to_string());
-            return DupdupStatus::Error;
-        }
+        Err(e) => return set_core_error(e),
     };
 
     let cfg = scan_config_from_options(root_path, default_scan_options(), true);
 
     match scan_to_sqlite(&cfg, &store) {
         Ok(_) => DupdupStatus::Ok,
-        Err(e) => {
-            set_last_error(e.to_string());
-            DupdupStatus::Error
-        }
+        Err(e) => set_core_error(e),
     }
 }
 
@@ -293,45 +413,43 @@ pub unsafe extern "C" fn dupdupninja_scan_folder_to_sqlite_with_progress(
     root_path: *const c_char,
     db_path: *const c_char,
     cancel_token: *mut DupdupCancelToken,
+    totals: *const DupdupTotals,
     progress_cb: DupdupProgressCallback,
     user_data: *mut libc::c_void,
 ) -> DupdupStatus {
     ok_last_error();
 
     if engine.is_null() {
-        set_last_error("engine is null");
+        set_last_error(DupdupStatus::NullPointer, "engine is null");
         return DupdupStatus::NullPointer;
     }
     if root_path.is_null() {
-        set_last_error("root_path is null");
+        set_last_error(DupdupStatus::NullPointer, "root_path is null");
         return DupdupStatus::NullPointer;
     }
     if db_path.is_null() {
-        set_last_error("db_path is null");
+        set_last_error(DupdupStatus::NullPointer, "db_path is null");
         return DupdupStatus::NullPointer;
     }
 
     let root_path = match c_path(root_path) {
         Ok(v) => v,
         Err(e) => {
-            set_last_error(e);
+            set_last_error(DupdupStatus::InvalidArgument, e);
             return DupdupStatus::InvalidArgument;
         }
     };
     let db_path = match c_path(db_path) {
         Ok(v) => v,
         Err(e) => {
-            set_last_error(e);
+            set_last_error(DupdupStatus::InvalidArgument, e);
             return DupdupStatus::InvalidArgument;
         }
     };
 
     let store = match SqliteScanStore::open(&db_path) {
         Ok(v) => v,
-        Err(e) => {
-            set_last_error(e.to_string());
-            return DupdupStatus::Error;
-        }
+        Err(e) => return set_core_error(e),
     };
 
     let cfg = scan_config_from_options(root_path, default_scan_options(), true);
@@ -342,40 +460,47 @@ pub unsafe extern "C" fn dupdupninja_scan_folder_to_sqlite_with_progress(
         Some(&(*cancel_token).token)
     };
 
-    let result = scan_to_sqlite_with_progress(&cfg, &store, cancel_ref, |progress| {
-        if let Some(cb) = progress_cb {
-            let path = progress.current_path.to_string_lossy();
-            let c_path = CString::new(path.as_ref()).unwrap_or_else(|_| CString::new("").unwrap());
-            let c_step = progress
-                .current_step
-                .as_deref()
-                .and_then(|step| CString::new(step).ok());
-            let payload = DupdupProgress {
-                files_seen: progress.files_seen,
-                files_hashed: progress.files_hashed,
-                files_skipped: progress.files_skipped,
-                bytes_seen: progress.bytes_seen,
-                total_files: progress.total_files,
-                total_bytes: progress.total_bytes,
-                current_path: c_path.as_ptr(),
-                current_step: c_step
-                    .as_ref()
-                    .map(|s| s.as_ptr())
-                    .unwrap_or(std::ptr::null()),
-            };
-            cb(&payload, user_data);
-        }
-    });
+    let totals = if totals.is_null() {
+        None
+    } else {
+        Some(ScanTotals {
+            files: (*totals).files,
+            bytes: (*totals).bytes,
+            ..Default::default()
+        })
+    };
+
+    let result =
+        scan_to_sqlite_with_progress_and_totals(&cfg, &store, cancel_ref, totals, |progress| {
+            emit_progress(progress_cb, user_data, progress)
+        });
 
     match result {
         Ok(_) => DupdupStatus::Ok,
-        Err(e) => {
-            set_last_error(e.to_string());
-            DupdupStatus::Error
-        }
+        Err(e) => set_core_error(e),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dupdupninja_prescan(
+    engine: *mut DupdupEngine,
+    root_path: *const c_char,
+    cancel_token: *mut DupdupCancelToken,
+    progress_cb: DupdupPrescanCallback,
+    user_data: *mut libc::c_void,
+    out_totals: *mut DupdupTotals,
+) -> DupdupStatus {
+    ok_last_error();
+
+    if engine.is_null() {
+        set_last_error(DupdupStatus::NullPointer, "engine is null");
+        return DupdupStatus::NullPointer;
     }
+    prescan_to_totals(root_path, cancel_token, progress_cb, user_data, out_totals)
 }
 
+#[deprecated(note = "use dupdupninja_prescan")]
+#[allow(deprecated)]
 #[no_mangle]
 pub unsafe extern "C" fn dupdupninja_prescan_folder(
     root_path: *const c_char,
@@ -386,19 +511,41 @@ pub unsafe extern "C" fn dupdupninja_prescan_folder(
 ) -> DupdupStatus {
     ok_last_error();
 
+    if out_totals.is_null() {
+        set_last_error(DupdupStatus::NullPointer, "out_totals is null");
+        return DupdupStatus::NullPointer;
+    }
+    let mut totals = DupdupTotals::default();
+    let status = prescan_to_totals(root_path, cancel_token, progress_cb, user_data, &mut totals);
+    if status == DupdupStatus::Ok {
+        (*out_totals) = DupdupPrescanTotals {
+            total_files: totals.files,
+            total_bytes: totals.bytes,
+        };
+    }
+    status
+}
+
+unsafe fn prescan_to_totals(
+    root_path: *const c_char,
+    cancel_token: *mut DupdupCancelToken,
+    progress_cb: DupdupPrescanCallback,
+    user_data: *mut libc::c_void,
+    out_totals: *mut DupdupTotals,
+) -> DupdupStatus {
     if root_path.is_null() {
-        set_last_error("root_path is null");
+        set_last_error(DupdupStatus::NullPointer, "root_path is null");
         return DupdupStatus::NullPointer;
     }
     if out_totals.is_null() {
-        set_last_error("out_totals is null");
+        set_last_error(DupdupStatus::NullPointer, "out_totals is null");
         return DupdupStatus::NullPointer;
     }
 
     let root_path = match c_path(root_path) {
         Ok(v) => v,
         Err(e) => {
-            set_last_error(e);
+            set_last_error(DupdupStatus::InvalidArgument, e);
             return DupdupStatus::InvalidArgument;
         }
     };
@@ -427,19 +574,17 @@ pub unsafe extern "C" fn dupdupninja_prescan_folder(
 
     match result {
         Ok(totals) => {
-            (*out_totals) = DupdupPrescanTotals {
-                total_files: totals.files,
-                total_bytes: totals.bytes,
+            (*out_totals) = DupdupTotals {
+                files: totals.files,
+                bytes: totals.bytes,
             };
             DupdupStatus::Ok
         }
-        Err(e) => {
-            set_last_error(e.to_string());
-            DupdupStatus::Error
-        }
+        Err(e) => set_core_error(e),
     }
 }
 
+#[deprecated(note = "use dupdupninja_scan_folder_to_sqlite_with_progress")]
 #[no_mangle]
 pub unsafe extern "C" fn dupdupninja_scan_folder_to_sqlite_with_progress_and_totals(
     engine: *mut DupdupEngine,
@@ -450,46 +595,74 @@ pub unsafe extern "C" fn dupdupninja_scan_folder_to_sqlite_with_progress_and_tot
     total_bytes: u64,
     progress_cb: DupdupProgressCallback,
     user_data: *mut libc::c_void,
+) -> DupdupStatus {
+    let totals = DupdupTotals {
+        files: total_files,
+        bytes: total_bytes,
+    };
+    dupdupninja_scan_folder_to_sqlite_with_progress(
+        engine,
+        root_path,
+        db_path,
+        cancel_token,
+        &totals,
+        progress_cb,
+        user_data,
+    )
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dupdupninja_scan_folder_to_sqlite_with_progress_totals_and_options(
+    engine: *mut DupdupEngine,
+    root_path: *const c_char,
+    db_path: *const c_char,
+    cancel_token: *mut DupdupCancelToken,
+    total_files: u64,
+    total_bytes: u64,
+    options: *const DupdupScanOptions,
+    progress_cb: DupdupProgressCallback,
+    user_data: *mut libc::c_void,
 ) -> DupdupStatus {
     ok_last_error();
 
     if engine.is_null() {
-        set_last_error("engine is null");
+        set_last_error(DupdupStatus::NullPointer, "engine is null");
         return DupdupStatus::NullPointer;
     }
     if root_path.is_null() {
-        set_last_error("root_path is null");
+        set_last_error(DupdupStatus::NullPointer, "root_path is null");
         return DupdupStatus::NullPointer;
     }
     if db_path.is_null() {
-        set_last_error("db_path is null");
+        set_last_error(DupdupStatus::NullPointer, "db_path is null");
+        return DupdupStatus::NullPointer;
+    }
+    if options.is_null() {
+        set_last_error(DupdupStatus::NullPointer, "options is null");
         return DupdupStatus::NullPointer;
     }
 
     let root_path = match c_path(root_path) {
         Ok(v) => v,
         Err(e) => {
-            set_last_error(e);
+            set_last_error(DupdupStatus::InvalidArgument, e);
             return DupdupStatus::InvalidArgument;
         }
     };
     let db_path = match c_path(db_path) {
         Ok(v) => v,
         Err(e) => {
-            set_last_error(e);
+            set_last_error(DupdupStatus::InvalidArgument, e);
             return DupdupStatus::InvalidArgument;
         }
     };
 
     let store = match SqliteScanStore::open(&db_path) {
         Ok(v) => v,
-        Err(e) => {
-            set_last_error(e.to_string());
-            return DupdupStatus::Error;
-        }
+        Err(e) => return set_core_error(e),
     };
 
-    let cfg = scan_config_from_options(root_path, default_scan_options(), true);
+    let cfg = scan_config_from_options(root_path, *options, true);
 
     let cancel_ref = if cancel_token.is_null() {
         None
@@ -500,6 +673,7 @@ pub unsafe extern "C" fn dupdupninja_scan_folder_to_sqlite_with_progress_and_tot
     let totals = ScanTotals {
         files: total_files,
         bytes: total_bytes,
+        ..Default::default()
     };
 
     let result = scan_to_sqlite_with_progress_and_totals(
@@ -507,97 +681,66 @@ pub unsafe extern "C" fn dupdupninja_scan_folder_to_sqlite_with_progress_and_tot
         &store,
         cancel_ref,
         Some(totals),
-        |progress| {
-            if let Some(cb) = progress_cb {
-                let path = progress.current_path.to_string_lossy();
-                let c_path =
-                    CString::new(path.as_ref()).unwrap_or_else(|_| CString::new("").unwrap());
-                let c_step = progress
-                    .current_step
-                    .as_deref()
-                    .and_then(|step| CString::new(step).ok());
-                let payload = DupdupProgress {
-                    files_seen: progress.files_seen,
-                    files_hashed: progress.files_hashed,
-                    files_skipped: progress.files_skipped,
-                    bytes_seen: progress.bytes_seen,
-                    total_files: progress.total_files,
-                    total_bytes: progress.total_bytes,
-                    current_path: c_path.as_ptr(),
-                    current_step: c_step
-                        .as_ref()
-                        .map(|s| s.as_ptr())
-                        .unwrap_or(std::ptr::null()),
-                };
-                cb(&payload, user_data);
-            }
-        },
+        |progress| emit_progress(progress_cb, user_data, progress),
     );
 
     match result {
         Ok(_) => DupdupStatus::Ok,
-        Err(e) => {
-            set_last_error(e.to_string());
-            DupdupStatus::Error
-        }
+        Err(e) => set_core_error(e),
     }
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn dupdupninja_scan_folder_to_sqlite_with_progress_totals_and_options(
+pub unsafe extern "C" fn dupdupninja_scan_folder_to_sqlite_with_config(
     engine: *mut DupdupEngine,
     root_path: *const c_char,
     db_path: *const c_char,
     cancel_token: *mut DupdupCancelToken,
-    total_files: u64,
-    total_bytes: u64,
-    options: *const DupdupScanOptions,
+    config: *const DupdupScanConfig,
     progress_cb: DupdupProgressCallback,
     user_data: *mut libc::c_void,
 ) -> DupdupStatus {
     ok_last_error();
 
     if engine.is_null() {
-        set_last_error("engine is null");
+        set_last_error(DupdupStatus::NullPointer, "engine is null");
         return DupdupStatus::NullPointer;
     }
     if root_path.is_null() {
-        set_last_error("root_path is null");
+        set_last_error(DupdupStatus::NullPointer, "root_path is null");
         return DupdupStatus::NullPointer;
     }
     if db_path.is_null() {
-        set_last_error("db_path is null");
-        return DupdupStatus::NullPointer;
-    }
-    if options.is_null() {
-        set_last_error("options is null");
+        set_last_error(DupdupStatus::NullPointer, "db_path is null");
         return DupdupStatus::NullPointer;
     }
 
     let root_path = match c_path(root_path) {
         Ok(v) => v,
         Err(e) => {
-            set_last_error(e);
+            set_last_error(DupdupStatus::InvalidArgument, e);
             return DupdupStatus::InvalidArgument;
         }
     };
     let db_path = match c_path(db_path) {
         Ok(v) => v,
         Err(e) => {
-            set_last_error(e);
+            set_last_error(DupdupStatus::InvalidArgument, e);
             return DupdupStatus::InvalidArgument;
         }
     };
 
     let store = match SqliteScanStore::open(&db_path) {
         Ok(v) => v,
-        Err(e) => {
-            set_last_error(e.to_string());
-            return DupdupStatus::Error;
-        }
+        Err(e) => return set_core_error(e),
     };
 
-    let cfg = scan_config_from_options(root_path, *options, true);
+    let config = if config.is_null() {
+        default_scan_config()
+    } else {
+        *config
+    };
+    let cfg = scan_config_from_options(root_path, config.options, true);
 
     let cancel_ref = if cancel_token.is_null() {
         None
@@ -606,48 +749,247 @@ pub unsafe extern "C" fn dupdupninja_scan_folder_to_sqlite_with_progress_totals_
     };
 
     let totals = ScanTotals {
-        files: total_files,
-        bytes: total_bytes,
+        files: config.totals.files,
+        bytes: config.totals.bytes,
+        ..Default::default()
     };
 
+    let mut throttle = ProgressThrottle::new(config.min_interval_ms);
     let result = scan_to_sqlite_with_progress_and_totals(
         &cfg,
         &store,
         cancel_ref,
         Some(totals),
         |progress| {
-            if let Some(cb) = progress_cb {
-                let path = progress.current_path.to_string_lossy();
-                let c_path =
-                    CString::new(path.as_ref()).unwrap_or_else(|_| CString::new("").unwrap());
-                let c_step = progress
-                    .current_step
-                    .as_deref()
-                    .and_then(|step| CString::new(step).ok());
-                let payload = DupdupProgress {
-                    files_seen: progress.files_seen,
-                    files_hashed: progress.files_hashed,
-                    files_skipped: progress.files_skipped,
-                    bytes_seen: progress.bytes_seen,
-                    total_files: progress.total_files,
-                    total_bytes: progress.total_bytes,
-                    current_path: c_path.as_ptr(),
-                    current_step: c_step
-                        .as_ref()
-                        .map(|s| s.as_ptr())
-                        .unwrap_or(std::ptr::null()),
-                };
-                cb(&payload, user_data);
+            if throttle.should_emit(progress) {
+                emit_progress(progress_cb, user_data, progress);
             }
         },
     );
+    if let Some(last) = throttle.take_pending() {
+        emit_progress(progress_cb, user_data, &last);
+    }
 
     match result {
         Ok(_) => DupdupStatus::Ok,
+        Err(e) => set_core_error(e),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dupdupninja_scan_start(
+    engine: *mut DupdupEngine,
+    root_path: *const c_char,
+    db_path: *const c_char,
+    config: *const DupdupScanConfig,
+) -> *mut DupdupScanHandle {
+    ok_last_error();
+
+    if engine.is_null() {
+        set_last_error(DupdupStatus::NullPointer, "engine is null");
+        return std::ptr::null_mut();
+    }
+    if root_path.is_null() {
+        set_last_error(DupdupStatus::NullPointer, "root_path is null");
+        return std::ptr::null_mut();
+    }
+    if db_path.is_null() {
+        set_last_error(DupdupStatus::NullPointer, "db_path is null");
+        return std::ptr::null_mut();
+    }
+
+    let root_path = match c_path(root_path) {
+        Ok(v) => v,
+        Err(e) => {
+            set_last_error(DupdupStatus::InvalidArgument, e);
+            return std::ptr::null_mut();
+        }
+    };
+    let db_path = match c_path(db_path) {
+        Ok(v) => v,
+        Err(e) => {
+            set_last_error(DupdupStatus::InvalidArgument, e);
+            return std::ptr::null_mut();
+        }
+    };
+
+    let store = match SqliteScanStore::open(&db_path) {
+        Ok(v) => v,
+        Err(e) => {
+            set_core_error(e);
+            return std::ptr::null_mut();
+        }
+    };
+
+    let config = if config.is_null() {
+        default_scan_config()
+    } else {
+        *config
+    };
+    let cfg = scan_config_from_options(root_path, config.options, true);
+    let totals = ScanTotals {
+        files: config.totals.files,
+        bytes: config.totals.bytes,
+        ..Default::default()
+    };
+
+    let cancel = ScanCancelToken::new();
+    let shared = Arc::new(Mutex::new(ScanHandleShared::default()));
+    let worker_cancel = cancel.clone();
+    let worker_shared = shared.clone();
+    let spawned = std::thread::Builder::new()
+        .name("dupdupninja-scan".to_string())
+        .spawn(move || {
+            // A panic must still leave an outcome, or poll would report running forever.
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                scan_to_sqlite_with_progress_and_totals(
+                    &cfg,
+                    &store,
+                    Some(&worker_cancel),
+                    Some(totals),
+                    |progress| {
+                        if let Ok(mut shared) = worker_shared.lock() {
+                            shared.progress = Some(progress.clone());
+                        }
+                    },
+                )
+            }));
+            let outcome = match result {
+                Ok(Ok(_)) => (DupdupStatus::Ok, String::new()),
+                Ok(Err(e)) => (status_for_error(&e), e.to_string()),
+                Err(_) => (DupdupStatus::Error, "scan thread panicked".to_string()),
+            };
+            worker_shared
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .outcome = Some(outcome);
+        });
+    let thread = match spawned {
+        Ok(v) => v,
         Err(e) => {
-            set_last_error(e.to_string());
-            DupdupStatus::Error
+            set_last_error(DupdupStatus::Io, e.to_string());
+            return std::ptr::null_mut();
+        }
+    };
+
+    let handle = ScanHandle {
+        cancel,
+        shared,
+        thread: Mutex::new(Some(thread)),
+        polled: Mutex::new((CString::default(), None)),
+    };
+    Box::into_raw(Box::new(handle)) as *mut DupdupScanHandle
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dupdupninja_scan_poll(
+    handle: *mut DupdupScanHandle,
+    out_progress: *mut DupdupProgress,
+) -> DupdupStatus {
+    ok_last_error();
+    if handle.is_null() {
+        set_last_error(DupdupStatus::NullPointer, "handle is null");
+        return DupdupStatus::NullPointer;
+    }
+
+    let handle = &*(handle as *const ScanHandle);
+    let (progress, outcome) = match handle.shared.lock() {
+        Ok(shared) => (shared.progress.clone(), shared.outcome.clone()),
+        Err(_) => {
+            set_last_error(DupdupStatus::Error, "scan state is poisoned");
+            return DupdupStatus::Error;
+        }
+    };
+
+    if !out_progress.is_null() {
+        let mut payload = DupdupProgress {
+            files_seen: 0,
+            files_hashed: 0,
+            files_skipped: 0,
+            bytes_seen: 0,
+            total_files: 0,
+            total_bytes: 0,
+            current_path: std::ptr::null(),
+            current_step: std::ptr::null(),
+        };
+        if let Some(progress) = progress {
+            let path = progress.current_path.to_string_lossy();
+            let mut polled = handle.polled.lock().unwrap_or_else(|e| e.into_inner());
+            polled.0 = CString::new(path.as_ref()).unwrap_or_else(|_| CString::new("").unwrap());
+            polled.1 = progress
+                .current_step
+                .as_deref()
+                .and_then(|step| CString::new(step).ok());
+            payload = DupdupProgress {
+                files_seen: progress.files_seen,
+                files_hashed: progress.files_hashed,
+                files_skipped: progress.files_skipped,
+                bytes_seen: progress.bytes_seen,
+                total_files: progress.total_files,
+                total_bytes: progress.total_bytes,
+                current_path: polled.0.as_ptr(),
+                current_step: polled
+                    .1
+                    .as_ref()
+                    .map(|s| s.as_ptr())
+                    .unwrap_or(std::ptr::null()),
+            };
         }
+        *out_progress = payload;
+    }
+
+    scan_outcome_status(outcome)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dupdupninja_scan_join(handle: *mut DupdupScanHandle) -> DupdupStatus {
+    ok_last_error();
+    if handle.is_null() {
+        set_last_error(DupdupStatus::NullPointer, "handle is null");
+        return DupdupStatus::NullPointer;
+    }
+
+    let handle = &*(handle as *const ScanHandle);
+    {
+        // Held while joining, so a second join waits for the outcome too.
+        let mut thread = handle.thread.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(thread) = thread.take() {
+            let _ = thread.join();
+        }
+    }
+    let outcome = match handle.shared.lock() {
+        Ok(shared) => shared.outcome.clone(),
+        Err(_) => {
+            set_last_error(DupdupStatus::Error, "scan state is poisoned");
+            return DupdupStatus::Error;
+        }
+    };
+    scan_outcome_status(outcome)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dupdupninja_scan_handle_cancel(handle: *mut DupdupScanHandle) {
+    ok_last_error();
+    if handle.is_null() {
+        return;
+    }
+    (*(handle as *const ScanHandle)).cancel.cancel();
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dupdupninja_scan_handle_free(handle: *mut DupdupScanHandle) {
+    ok_last_error();
+    if handle.is_null() {
+        return;
+    }
+    let handle = Box::from_raw(handle as *mut ScanHandle);
+    let thread = handle
+        .thread
+        .into_inner()
+        .unwrap_or_else(|e| e.into_inner());
+    if let Some(thread) = thread {
+        handle.cancel.cancel();
+        let _ = thread.join();
     }
 }
 
@@ -663,45 +1005,39 @@ pub unsafe extern "C" fn dupdupninja_fileset_list_rows(
     ok_last_error();
 
     if db_path.is_null() {
-        set_last_error("db_path is null");
+        set_last_error(DupdupStatus::NullPointer, "db_path is null");
         return DupdupStatus::NullPointer;
     }
     if out_rows.is_null() {
-        set_last_error("out_rows is null");
+        set_last_error(DupdupStatus::NullPointer, "out_rows is null");
         return DupdupStatus::NullPointer;
     }
     if out_len.is_null() {
-        set_last_error("out_len is null");
+        set_last_error(DupdupStatus::NullPointer, "out_len is null");
         return DupdupStatus::NullPointer;
     }
 
     let db_path = match c_path(db_path) {
         Ok(v) => v,
         Err(e) => {
-            set_last_error(e);
+            set_last_error(DupdupStatus::InvalidArgument, e);
             return DupdupStatus::InvalidArgument;
         }
     };
     let store = match SqliteScanStore::open(&db_path) {
         Ok(v) => v,
-        Err(e) => {
-            set_last_error(e.to_string());
-            return DupdupStatus::Error;
-        }
+        Err(e) => return set_core_error(e),
     };
 
     let limit = (limit.min(10_000)) as usize;
     let offset = (offset.min(10_000_000)) as usize;
     let rows = match if duplicates_only {
-        store.list_files_with_duplicates(limit, offset)
+        store.list_files_with_duplicates(true, limit, offset)
     } else {
         store.list_files(limit, offset)
     } {
         Ok(v) => v,
-        Err(e) => {
-            set_last_error(e.to_string());
-            return DupdupStatus::Error;
-        }
+        Err(e) => return set_core_error(e),
     };
 
     let mut out = Vec::with_capacity(rows.len());
@@ -745,49 +1081,43 @@ pub unsafe extern "C" fn dupdupninja_fileset_list_similar_groups(
     ok_last_error();
 
     if db_path.is_null() {
-        set_last_error("db_path is null");
+        set_last_error(DupdupStatus::NullPointer, "db_path is null");
         return DupdupStatus::NullPointer;
     }
     if out_groups.is_null() {
-        set_last_error("out_groups is null");
+        set_last_error(DupdupStatus::NullPointer, "out_groups is null");
         return DupdupStatus::NullPointer;
     }
     if out_groups_len.is_null() {
-        set_last_error("out_groups_len is null");
+        set_last_error(DupdupStatus::NullPointer, "out_groups_len is null");
         return DupdupStatus::NullPointer;
     }
     if out_rows.is_null() {
-        set_last_error("out_rows is null");
+        set_last_error(DupdupStatus::NullPointer, "out_rows is null");
         return DupdupStatus::NullPointer;
     }
     if out_rows_len.is_null() {
-        set_last_error("out_rows_len is null");
+        set_last_error(DupdupStatus::NullPointer, "out_rows_len is null");
         return DupdupStatus::NullPointer;
     }
 
     let db_path = match c_path(db_path) {
         Ok(v) => v,
         Err(e) => {
-            set_last_error(e);
+            set_last_error(DupdupStatus::InvalidArgument, e);
             return DupdupStatus::InvalidArgument;
         }
     };
     let store = match SqliteScanStore::open(&db_path) {
         Ok(v) => v,
-        Err(e) => {
-            set_last_error(e.to_string());
-            return DupdupStatus::Error;
-        }
+        Err(e) => return set_core_error(e),
     };
 
     let limit = (limit.min(2_000)) as usize;
     let offset = (offset.min(10_000_000)) as usize;
     let rows = match store.list_files_with_hashes(limit, offset) {
         Ok(v) => v,
-        Err(e) => {
-            set_last_error(e.to_string());
-            return DupdupStatus::Error;
-        }
+        Err(e) => return set_core_error(e),
     };
 
     let phash_threshold = phash_max_distance.clamp(1, 32) as u32;
@@ -949,49 +1279,43 @@ pub unsafe extern "C" fn dupdupninja_fileset_list_exact_groups(
     ok_last_error();
 
     if db_path.is_null() {
-        set_last_error("db_path is null");
+        set_last_error(DupdupStatus::NullPointer, "db_path is null");
         return DupdupStatus::NullPointer;
     }
     if out_groups.is_null() {
-        set_last_error("out_groups is null");
+        set_last_error(DupdupStatus::NullPointer, "out_groups is null");
         return DupdupStatus::NullPointer;
     }
     if out_groups_len.is_null() {
-        set_last_error("out_groups_len is null");
+        set_last_error(DupdupStatus::NullPointer, "out_groups_len is null");
         return DupdupStatus::NullPointer;
     }
     if out_rows.is_null() {
-        set_last_error("out_rows is null");
+        set_last_error(DupdupStatus::NullPointer, "out_rows is null");
         return DupdupStatus::NullPointer;
     }
     if out_rows_len.is_null() {
-        set_last_error("out_rows_len is null");
+        set_last_error(DupdupStatus::NullPointer, "out_rows_len is null");
         return DupdupStatus::NullPointer;
     }
 
     let db_path = match c_path(db_path) {
         Ok(v) => v,
         Err(e) => {
-            set_last_error(e);
+            set_last_error(DupdupStatus::InvalidArgument, e);
             return DupdupStatus::InvalidArgument;
         }
     };
     let store = match SqliteScanStore::open(&db_path) {
         Ok(v) => v,
-        Err(e) => {
-            set_last_error(e.to_string());
-            return DupdupStatus::Error;
-        }
+        Err(e) => return set_core_error(e),
     };
 
     let limit = (limit.min(10_000)) as usize;
     let offset = (offset.min(10_000_000)) as usize;
-    let rows = match store.list_files_with_duplicates(limit, offset) {
+    let rows = match store.list_files_with_duplicates(true, limit, offset) {
         Ok(v) => v,
-        Err(e) => {
-            set_last_error(e.to_string());
-            return DupdupStatus::Error;
-        }
+        Err(e) => return set_core_error(e),
     };
 
     let mut grouped: BTreeMap<String, Vec<dupdupninja_core::models::FileListRow>> = BTreeMap::new();
@@ -1096,53 +1420,30 @@ pub unsafe extern "C" fn dupdupninja_fileset_get_metadata(
 ) -> DupdupStatus {
     ok_last_error();
     if db_path.is_null() {
-        set_last_error("db_path is null");
+        set_last_error(DupdupStatus::NullPointer, "db_path is null");
         return DupdupStatus::NullPointer;
     }
     if out_meta.is_null() {
-        set_last_error("out_meta is null");
+        set_last_error(DupdupStatus::NullPointer, "out_meta is null");
         return DupdupStatus::NullPointer;
     }
 
     let db_path = match c_path(db_path) {
         Ok(v) => v,
         Err(e) => {
-            set_last_error(e);
+            set_last_error(DupdupStatus::InvalidArgument, e);
             return DupdupStatus::InvalidArgument;
         }
     };
     let store = match SqliteScanStore::open(&db_path) {
         Ok(v) => v,
-        Err(e) => {
-            set_last_error(e.to_string());
-            return DupdupStatus::Error;
-        }
+        Err(e) => return set_core_error(e),
     };
 
     let meta = match store.get_fileset_metadata() {
         Ok(Some(v)) => v,
-        Ok(None) => FilesetMetadata {
-            created_at: std::time::SystemTime::now(),
-            root_kind: ScanRootKind::Folder,
-            root_path: PathBuf::new(),
-            root_parent_path: None,
-            drive: DriveMetadata {
-                id: None,
-                label: None,
-                fs_type: None,
-            },
-            host_os: String::new(),
-            host_os_version: String::new(),
-            app_version: String::new(),
-            status: String::new(),
-            name: String::new(),
-            description: String::new(),
-            notes: String::new(),
-        },
-        Err(e) => {
-            set_last_error(e.to_string());
-            return DupdupStatus::Error;
-        }
+        Ok(None) => empty_fileset_metadata(),
+        Err(e) => return set_core_error(e),
     };
 
     *out_meta = DupdupFilesetMetadataView {
@@ -1164,48 +1465,25 @@ pub unsafe extern "C" fn dupdupninja_fileset_set_metadata(
 ) -> DupdupStatus {
     ok_last_error();
     if db_path.is_null() {
-        set_last_error("db_path is null");
+        set_last_error(DupdupStatus::NullPointer, "db_path is null");
         return DupdupStatus::NullPointer;
     }
 
     let db_path = match c_path(db_path) {
         Ok(v) => v,
         Err(e) => {
-            set_last_error(e);
+            set_last_error(DupdupStatus::InvalidArgument, e);
             return DupdupStatus::InvalidArgument;
         }
     };
     let store = match SqliteScanStore::open(&db_path) {
         Ok(v) => v,
-        Err(e) => {
-            set_last_error(e.to_string());
-            return DupdupStatus::Error;
-        }
+        Err(e) => return set_core_error(e),
     };
     let mut meta = match store.get_fileset_metadata() {
         Ok(Some(v)) => v,
-        Ok(None) => FilesetMetadata {
-            created_at: std::time::SystemTime::now(),
-            root_kind: ScanRootKind::Folder,
-            root_path: PathBuf::new(),
-            root_parent_path: None,
-            drive: DriveMetadata {
-                id: None,
-                label: None,
-                fs_type: None,
-            },
-            host_os: String::new(),
-            host_os_version: String::new(),
-            app_version: String::new(),
-            status: String::new(),
-            name: String::new(),
-            description: String::new(),
-            notes: String::new(),
-        },
-        Err(e) => {
-            set_last_error(e.to_string());
-            return DupdupStatus::Error;
-        }
+        Ok(None) => empty_fileset_metadata(),
+        Err(e) => return set_core_error(e),
     };
 
     meta.name = c_string_opt(name).unwrap_or_default();
@@ -1215,15 +1493,181 @@ pub unsafe extern "C" fn dupdupninja_fileset_set_metadata(
 
     match store.set_fileset_metadata(&meta) {
         Ok(()) => DupdupStatus::Ok,
+        Err(e) => set_core_error(e),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dupdupninja_fileset_metadata_free(meta: *mut DupdupFilesetMetadataView) {
+    ok_last_error();
+    if meta.is_null() {
+        return;
+    }
+    free_owned_c_string((*meta).name);
+    (*meta).name = std::ptr::null_mut();
+    free_owned_c_string((*meta).description);
+    (*meta).description = std::ptr::null_mut();
+    free_owned_c_string((*meta).notes);
+    (*meta).notes = std::ptr::null_mut();
+    free_owned_c_string((*meta).status);
+    (*meta).status = std::ptr::null_mut();
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dupdupninja_store_open(db_path: *const c_char) -> *mut DupdupStore {
+    ok_last_error();
+    if db_path.is_null() {
+        set_last_error(DupdupStatus::NullPointer, "db_path is null");
+        return std::ptr::null_mut();
+    }
+
+    let db_path = match c_path(db_path) {
+        Ok(v) => v,
         Err(e) => {
-            set_last_error(e.to_string());
-            DupdupStatus::Error
+            set_last_error(DupdupStatus::InvalidArgument, e);
+            return std::ptr::null_mut();
+        }
+    };
+    match SqliteScanStore::open(&db_path) {
+        Ok(store) => Box::into_raw(Box::new(store)) as *mut DupdupStore,
+        Err(e) => {
+            set_core_error(e);
+            std::ptr::null_mut()
         }
     }
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn dupdupninja_fileset_metadata_free(meta: *mut DupdupFilesetMetadataView) {
+pub unsafe extern "C" fn dupdupninja_store_free(store: *mut DupdupStore) {
+    ok_last_error();
+    if store.is_null() {
+        return;
+    }
+    drop(Box::from_raw(store as *mut SqliteScanStore));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dupdupninja_store_get_metadata(
+    store: *mut DupdupStore,
+    out_meta: *mut DupdupMetadata,
+) -> DupdupStatus {
+    ok_last_error();
+    if store.is_null() {
+        set_last_error(DupdupStatus::NullPointer, "store is null");
+        return DupdupStatus::NullPointer;
+    }
+    if out_meta.is_null() {
+        set_last_error(DupdupStatus::NullPointer, "out_meta is null");
+        return DupdupStatus::NullPointer;
+    }
+
+    let store = &*(store as *const SqliteScanStore);
+    let meta = match store.get_fileset_metadata() {
+        Ok(Some(v)) => v,
+        Ok(None) => empty_fileset_metadata(),
+        Err(e) => return set_core_error(e),
+    };
+
+    let created_at_secs = meta
+        .created_at
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    *out_meta = DupdupMetadata {
+        name: string_to_c_owned(&meta.name),
+        description: string_to_c_owned(&meta.description),
+        notes: string_to_c_owned(&meta.notes),
+        status: string_to_c_owned(&meta.status),
+        root_path: string_to_c_owned(meta.root_path.to_string_lossy().as_ref()),
+        has_created_at: 1,
+        created_at_secs,
+        has_root_kind: 1,
+        root_kind: match meta.root_kind {
+            ScanRootKind::Folder => DupdupRootKind::Folder as u32,
+            ScanRootKind::Drive => DupdupRootKind::Drive as u32,
+        },
+    };
+    DupdupStatus::Ok
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dupdupninja_store_set_metadata(
+    store: *mut DupdupStore,
+    meta: *const DupdupMetadata,
+) -> DupdupStatus {
+    ok_last_error();
+    if store.is_null() {
+        set_last_error(DupdupStatus::NullPointer, "store is null");
+        return DupdupStatus::NullPointer;
+    }
+    if meta.is_null() {
+        set_last_error(DupdupStatus::NullPointer, "meta is null");
+        return DupdupStatus::NullPointer;
+    }
+
+    let store = &*(store as *const SqliteScanStore);
+    let input = &*meta;
+    let mut current = match store.get_fileset_metadata() {
+        Ok(Some(v)) => v,
+        Ok(None) => empty_fileset_metadata(),
+        Err(e) => return set_core_error(e),
+    };
+
+    // Null string fields keep their stored value; invalid UTF-8 is rejected.
+    let fields: [(&str, *const c_char, &mut String); 4] = [
+        ("name", input.name, &mut current.name),
+        ("description", input.description, &mut current.description),
+        ("notes", input.notes, &mut current.notes),
+        ("status", input.status, &mut current.status),
+    ];
+    for (label, ptr, slot) in fields {
+        match c_string_checked(ptr) {
+            Ok(Some(v)) => *slot = v,
+            Ok(None) => {}
+            Err(e) => {
+                set_last_error(DupdupStatus::InvalidArgument, format!("{label}: {e}"));
+                return DupdupStatus::InvalidArgument;
+            }
+        }
+    }
+    match c_string_checked(input.root_path) {
+        Ok(Some(v)) => current.root_path = PathBuf::from(v),
+        Ok(None) => {}
+        Err(e) => {
+            set_last_error(DupdupStatus::InvalidArgument, format!("root_path: {e}"));
+            return DupdupStatus::InvalidArgument;
+        }
+    }
+    if input.has_created_at != 0 {
+        if input.created_at_secs < 0 {
+            set_last_error(DupdupStatus::InvalidArgument, "created_at_secs is negative");
+            return DupdupStatus::InvalidArgument;
+        }
+        current.created_at =
+            std::time::UNIX_EPOCH + std::time::Duration::from_secs(input.created_at_secs as u64);
+    }
+    if input.has_root_kind != 0 {
+        current.root_kind = match input.root_kind {
+            0 => ScanRootKind::Folder,
+            1 => ScanRootKind::Drive,
+            other => {
+                set_last_error(
+                    DupdupStatus::InvalidArgument,
+                    format!("root_kind {other} is not a DupdupRootKind"),
+                );
+                return DupdupStatus::InvalidArgument;
+            }
+        };
+    }
+
+    match store.set_fileset_metadata(&current) {
+        Ok(()) => DupdupStatus::Ok,
+        Err(e) => set_core_error(e),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dupdupninja_metadata_free(meta: *mut DupdupMetadata) {
     ok_last_error();
     if meta.is_null() {
         return;
@@ -1236,6 +1680,47 @@ pub unsafe extern "C" fn dupdupninja_fileset_metadata_free(meta: *mut DupdupFile
     (*meta).notes = std::ptr::null_mut();
     free_owned_c_string((*meta).status);
     (*meta).status = std::ptr::null_mut();
+    free_owned_c_string((*meta).root_path);
+    (*meta).root_path = std::ptr::null_mut();
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dupdupninja_store_needs_rehash(
+    store: *mut DupdupStore,
+    file_path: *const c_char,
+    size_bytes: u64,
+    modified_at_secs: i64,
+    out_needs_rehash: *mut u8,
+) -> DupdupStatus {
+    ok_last_error();
+    if store.is_null() {
+        set_last_error(DupdupStatus::NullPointer, "store is null");
+        return DupdupStatus::NullPointer;
+    }
+    if file_path.is_null() {
+        set_last_error(DupdupStatus::NullPointer, "file_path is null");
+        return DupdupStatus::NullPointer;
+    }
+    if out_needs_rehash.is_null() {
+        set_last_error(DupdupStatus::NullPointer, "out_needs_rehash is null");
+        return DupdupStatus::NullPointer;
+    }
+
+    let file_path = match c_path(file_path) {
+        Ok(v) => v,
+        Err(e) => {
+            set_last_error(DupdupStatus::InvalidArgument, e);
+            return DupdupStatus::InvalidArgument;
+        }
+    };
+    let store = &*(store as *const SqliteScanStore);
+    match store.needs_rehash(&file_path, size_bytes, modified_at_secs) {
+        Ok(needs) => {
+            *out_needs_rehash = u8::from(needs);
+            DupdupStatus::Ok
+        }
+        Err(e) => set_core_error(e),
+    }
 }
 
 #[no_mangle]
@@ -1245,42 +1730,56 @@ pub unsafe extern "C" fn dupdupninja_fileset_delete_file_by_path(
 ) -> DupdupStatus {
     ok_last_error();
     if db_path.is_null() {
-        set_last_error("db_path is null");
+        set_last_error(DupdupStatus::NullPointer, "db_path is null");
         return DupdupStatus::NullPointer;
     }
     if file_path.is_null() {
-        set_last_error("file_path is null");
+        set_last_error(DupdupStatus::NullPointer, "file_path is null");
         return DupdupStatus::NullPointer;
     }
 
     let db_path = match c_path(db_path) {
         Ok(v) => v,
         Err(e) => {
-            set_last_error(e);
+            set_last_error(DupdupStatus::InvalidArgument, e);
             return DupdupStatus::InvalidArgument;
         }
     };
     let file_path = match c_path(file_path) {
         Ok(v) => v,
         Err(e) => {
-            set_last_error(e);
+            set_last_error(DupdupStatus::InvalidArgument, e);
             return DupdupStatus::InvalidArgument;
         }
     };
 
     let store = match SqliteScanStore::open(&db_path) {
         Ok(v) => v,
-        Err(e) => {
-            set_last_error(e.to_string());
-            return DupdupStatus::Error;
-        }
+        Err(e) => return set_core_error(e),
     };
     match store.delete_file_by_path(&file_path) {
         Ok(_) => DupdupStatus::Ok,
+        Err(e) => set_core_error(e),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dupdupninja_move_to_trash(file_path: *const c_char) -> DupdupStatus {
+    ok_last_error();
+    if file_path.is_null() {
+        set_last_error(DupdupStatus::NullPointer, "file_path is null");
+        return DupdupStatus::NullPointer;
+    }
+    let file_path = match c_path(file_path) {
+        Ok(v) => v,
         Err(e) => {
-            set_last_error(e.to_string());
-            DupdupStatus::Error
+            set_last_error(DupdupStatus::InvalidArgument, e);
+            return DupdupStatus::InvalidArgument;
         }
+    };
+    match dupdupninja_core::files::move_to_trash(&file_path) {
+        Ok(()) => DupdupStatus::Ok,
+        Err(e) => set_core_error(e),
     }
 }
 
@@ -1293,51 +1792,45 @@ pub unsafe extern "C" fn dupdupninja_fileset_list_snapshots_by_path(
 ) -> DupdupStatus {
     ok_last_error();
     if db_path.is_null() {
-        set_last_error("db_path is null");
+        set_last_error(DupdupStatus::NullPointer, "db_path is null");
         return DupdupStatus::NullPointer;
     }
     if file_path.is_null() {
-        set_last_error("file_path is null");
+        set_last_error(DupdupStatus::NullPointer, "file_path is null");
         return DupdupStatus::NullPointer;
     }
     if out_rows.is_null() {
-        set_last_error("out_rows is null");
+        set_last_error(DupdupStatus::NullPointer, "out_rows is null");
         return DupdupStatus::NullPointer;
     }
     if out_len.is_null() {
-        set_last_error("out_len is null");
+        set_last_error(DupdupStatus::NullPointer, "out_len is null");
         return DupdupStatus::NullPointer;
     }
 
     let db_path = match c_path(db_path) {
         Ok(v) => v,
         Err(e) => {
-            set_last_error(e);
+            set_last_error(DupdupStatus::InvalidArgument, e);
             return DupdupStatus::InvalidArgument;
         }
     };
     let file_path = match c_path(file_path) {
         Ok(v) => v,
         Err(e) => {
-            set_last_error(e);
+            set_last_error(DupdupStatus::InvalidArgument, e);
             return DupdupStatus::InvalidArgument;
         }
     };
 
     let store = match SqliteScanStore::open(&db_path) {
         Ok(v) => v,
-        Err(e) => {
-            set_last_error(e.to_string());
-            return DupdupStatus::Error;
-        }
+        Err(e) => return set_core_error(e),
     };
 
     let file = match store.get_file_by_path(&file_path) {
         Ok(v) => v,
-        Err(e) => {
-            set_last_error(e.to_string());
-            return DupdupStatus::Error;
-        }
+        Err(e) => return set_core_error(e),
     };
     let Some(file) = file else {
         *out_rows = std::ptr::null_mut();
@@ -1352,10 +1845,7 @@ pub unsafe extern "C" fn dupdupninja_fileset_list_snapshots_by_path(
 
     let snapshots = match store.list_file_snapshots(file_id) {
         Ok(v) => v,
-        Err(e) => {
-            set_last_error(e.to_string());
-            return DupdupStatus::Error;
-        }
+        Err(e) => return set_core_error(e),
     };
 
     if snapshots.is_empty() {
@@ -1418,6 +1908,37 @@ unsafe fn c_string_opt(ptr: *const c_char) -> Option<String> {
     CStr::from_ptr(ptr).to_str().ok().map(str::to_owned)
 }
 
+unsafe fn c_string_checked(ptr: *const c_char) -> Result<Option<String>, String> {
+    if ptr.is_null() {
+        return Ok(None);
+    }
+    CStr::from_ptr(ptr)
+        .to_str()
+        .map(|s| Some(s.to_owned()))
+        .map_err(|_| "string is not valid UTF-8".to_string())
+}
+
+fn empty_fileset_metadata() -> FilesetMetadata {
+    FilesetMetadata {
+        created_at: std::time::SystemTime::now(),
+        root_kind: ScanRootKind::Folder,
+        root_path: PathBuf::new(),
+        root_parent_path: None,
+        drive: DriveMetadata {
+            id: None,
+            label: None,
+            fs_type: None,
+        },
+        host_os: String::new(),
+        host_os_version: String::new(),
+        app_version: String::new(),
+        status: String::new(),
+        name: String::new(),
+        description: String::new(),
+        notes: String::new(),
+    }
+}
+
 fn default_scan_options() -> DupdupScanOptions {
     DupdupScanOptions {
         capture_snapshots: true,
@@ -1427,6 +1948,14 @@ fn default_scan_options() -> DupdupScanOptions {
     }
 }
 
+fn default_scan_config() -> DupdupScanConfig {
+    DupdupScanConfig {
+        options: default_scan_options(),
+        totals: DupdupTotals::default(),
+        min_interval_ms: 0,
+    }
+}
+
 fn scan_config_from_options(
     root: PathBuf,
     options: DupdupScanOptions,
@@ -1434,13 +1963,98 @@ fn scan_config_from_options(
 ) -> ScanConfig {
     ScanConfig {
         root,
-        root_kind: ScanRootKind::Folder,
         hash_files,
-        perceptual_hashes: true,
         capture_snapshots: options.capture_snapshots,
-        snapshots_per_video: options.snapshots_per_video.clamp(1, 10),
-        snapshot_max_dim: options.snapshot_max_dim.clamp(128, 4096),
+        snapshots_per_video: options.snapshots_per_video.clamp(
+            *ScanConfig::SNAPSHOTS_PER_VIDEO.start(),
+            *ScanConfig::SNAPSHOTS_PER_VIDEO.end(),
+        ),
+        snapshot_max_dim: options.snapshot_max_dim.clamp(
+            *ScanConfig::SNAPSHOT_MAX_DIM.start(),
+            *ScanConfig::SNAPSHOT_MAX_DIM.end(),
+        ),
         concurrent_processing: options.concurrent_processing,
+        ..Default::default()
+    }
+}
+
+fn scan_outcome_status(outcome: Option<(DupdupStatus, String)>) -> DupdupStatus {
+    match outcome {
+        None => DupdupStatus::Running,
+        Some((DupdupStatus::Ok, _)) => DupdupStatus::Ok,
+        Some((status, msg)) => {
+            set_last_error(status, msg);
+            status
+        }
+    }
+}
+
+unsafe fn emit_progress(
+    progress_cb: DupdupProgressCallback,
+    user_data: *mut libc::c_void,
+    progress: &ScanProgress,
+) {
+    let Some(cb) = progress_cb else {
+        return;
+    };
+    let path = progress.current_path.to_string_lossy();
+    let c_path = CString::new(path.as_ref()).unwrap_or_else(|_| CString::new("").unwrap());
+    let c_step = progress
+        .current_step
+        .as_deref()
+        .and_then(|step| CString::new(step).ok());
+    let payload = DupdupProgress {
+        files_seen: progress.files_seen,
+        files_hashed: progress.files_hashed,
+        files_skipped: progress.files_skipped,
+        bytes_seen: progress.bytes_seen,
+        total_files: progress.total_files,
+        total_bytes: progress.total_bytes,
+        current_path: c_path.as_ptr(),
+        current_step: c_step
+            .as_ref()
+            .map(|s| s.as_ptr())
+            .unwrap_or(std::ptr::null()),
+    };
+    cb(&payload, user_data);
+}
+
+// Coalesces progress so callers see at most one update per interval. The most recent
+// suppressed update is kept so it can be delivered once the scan returns.
+struct ProgressThrottle {
+    min_interval: Duration,
+    last_emit: Option<Instant>,
+    pending: Option<ScanProgress>,
+}
+
+impl ProgressThrottle {
+    fn new(min_interval_ms: u32) -> Self {
+        Self {
+            min_interval: Duration::from_millis(u64::from(min_interval_ms)),
+            last_emit: None,
+            pending: None,
+        }
+    }
+
+    // `files_seen` counts walked files, so it reaches the total a whole batch before the
+    // scan ends; the final update is the `take_pending` flush instead.
+    fn should_emit(&mut self, progress: &ScanProgress) -> bool {
+        let due = self
+            .last_emit
+            .map(|at| at.elapsed() >= self.min_interval)
+            .unwrap_or(true);
+        if due {
+            self.last_emit = Some(Instant::now());
+            self.pending = None;
+            true
+        } else {
+            self.pending = Some(progress.clone());
+            false
+        }
+    }
+
+    fn take_pending(&mut self) -> Option<ScanProgress> {
+        self.pending.take()
     }
 }
 
@@ -1489,7 +2103,7 @@ fn hamming64(a: u64, b: u64) -> u32 {
 
 fn similar_confidence_percent(phash_distance: u32) -> f32 {
     let similarity = ((64_u32.saturating_sub(phash_distance)) as f32 / 64.0) * 100.0;
-    similarity.min(99.99).max(0.0)
+    similarity.clamp(0.0, 99.99)
 }
 
 fn string_to_c_owned(text: &str) -> *mut c_char {
@@ -1504,3 +2118,214 @@ unsafe fn free_owned_c_string(ptr: *mut c_char) {
     }
     let _ = CString::from_raw(ptr);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_fileset(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("dupdupninja-ffi-{name}-{}.ddn", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn empty_metadata() -> DupdupMetadata {
+        DupdupMetadata {
+            name: std::ptr::null_mut(),
+            description: std::ptr::null_mut(),
+            notes: std::ptr::null_mut(),
+            status: std::ptr::null_mut(),
+            root_path: std::ptr::null_mut(),
+            has_created_at: 0,
+            created_at_secs: 0,
+            has_root_kind: 0,
+            root_kind: 0,
+        }
+    }
+
+    #[test]
+    fn set_metadata_keeps_unflagged_fields_and_rejects_bad_root_kind() {
+        let path = temp_fileset("metadata");
+        let c_path = CString::new(path.to_str().unwrap()).unwrap();
+        unsafe {
+            let store = dupdupninja_store_open(c_path.as_ptr());
+            assert!(!store.is_null());
+
+            let mut meta = empty_metadata();
+            meta.has_created_at = 1;
+            meta.created_at_secs = 1_700_000_000;
+            meta.has_root_kind = 1;
+            meta.root_kind = DupdupRootKind::Drive as u32;
+            assert_eq!(
+                dupdupninja_store_set_metadata(store, &meta),
+                DupdupStatus::Ok
+            );
+
+            let name = CString::new("renamed").unwrap();
+            let mut rename = empty_metadata();
+            rename.name = name.as_ptr() as *mut c_char;
+            assert_eq!(
+                dupdupninja_store_set_metadata(store, &rename),
+                DupdupStatus::Ok
+            );
+
+            let mut bad = empty_metadata();
+            bad.has_root_kind = 1;
+            bad.root_kind = 7;
+            assert_eq!(
+                dupdupninja_store_set_metadata(store, &bad),
+                DupdupStatus::InvalidArgument
+            );
+            assert_eq!(dupdupninja_last_error_code(), DupdupStatus::InvalidArgument);
+
+            let mut out = empty_metadata();
+            assert_eq!(
+                dupdupninja_store_get_metadata(store, &mut out),
+                DupdupStatus::Ok
+            );
+            assert_eq!(CStr::from_ptr(out.name).to_str().unwrap(), "renamed");
+            assert_eq!(out.created_at_secs, 1_700_000_000);
+            assert_eq!(out.root_kind, DupdupRootKind::Drive as u32);
+            dupdupninja_metadata_free(&mut out);
+            dupdupninja_store_free(store);
+        }
+        let _ = std::fs::remove_file(&path);
+    }
+
+    extern "C" fn record_files_seen(progress: *const DupdupProgress, user_data: *mut libc::c_void) {
+        unsafe {
+            (*(user_data as *mut Vec<u64>)).push((*progress).files_seen);
+        }
+    }
+
+    #[test]
+    fn throttled_scan_drops_intermediate_updates_but_delivers_the_last() {
+        let root =
+            std::env::temp_dir().join(format!("dupdupninja-ffi-throttle-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        for i in 0..40u32 {
+            std::fs::write(
+                root.join(format!("file-{i:02}.bin")),
+                i.to_le_bytes().repeat(64),
+            )
+            .unwrap();
+        }
+        let path = temp_fileset("throttle");
+        let c_root = CString::new(root.to_str().unwrap()).unwrap();
+        let c_path = CString::new(path.to_str().unwrap()).unwrap();
+        let mut config = default_scan_config();
+        config.options.capture_snapshots = false;
+        config.totals = DupdupTotals {
+            files: 40,
+            bytes: 40 * 256,
+        };
+        config.min_interval_ms = 60_000;
+
+        let mut seen: Vec<u64> = Vec::new();
+        unsafe {
+            let engine = dupdupninja_engine_new();
+            let status = dupdupninja_scan_folder_to_sqlite_with_config(
+                engine,
+                c_root.as_ptr(),
+                c_path.as_ptr(),
+                std::ptr::null_mut(),
+                &config,
+                Some(record_files_seen),
+                &mut seen as *mut Vec<u64> as *mut libc::c_void,
+            );
+            assert_eq!(status, DupdupStatus::Ok);
+            dupdupninja_engine_free(engine);
+        }
+
+        // The first update goes out at once; the rest wait for the after-scan flush.
+        assert_eq!(seen.len(), 2, "{seen:?}");
+        assert_eq!(seen.last(), Some(&40));
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    fn scan_handle_root(name: &str, files: u32) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("dupdupninja-ffi-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        for i in 0..files {
+            std::fs::write(root.join(format!("file-{i:03}.bin")), i.to_le_bytes()).unwrap();
+        }
+        root
+    }
+
+    struct SendHandle(*mut DupdupScanHandle);
+    unsafe impl Send for SendHandle {}
+
+    #[test]
+    fn scan_handle_polls_while_another_thread_joins() {
+        let root = scan_handle_root("handle-join", 12);
+        let path = temp_fileset("handle-join");
+        let c_root = CString::new(root.to_str().unwrap()).unwrap();
+        let c_path = CString::new(path.to_str().unwrap()).unwrap();
+        let mut config = default_scan_config();
+        config.options.capture_snapshots = false;
+
+        unsafe {
+            let engine = dupdupninja_engine_new();
+            let handle = dupdupninja_scan_start(engine, c_root.as_ptr(), c_path.as_ptr(), &config);
+            assert!(!handle.is_null());
+
+            let joiner = SendHandle(handle);
+            let joiner = std::thread::spawn(move || {
+                let joiner = joiner;
+                dupdupninja_scan_join(joiner.0)
+            });
+            let mut progress = std::mem::zeroed::<DupdupProgress>();
+            let status = loop {
+                match dupdupninja_scan_poll(handle, &mut progress) {
+                    DupdupStatus::Running => std::thread::sleep(Duration::from_millis(5)),
+                    status => break status,
+                }
+            };
+            assert_eq!(status, DupdupStatus::Ok);
+            assert_eq!(joiner.join().unwrap(), DupdupStatus::Ok);
+            // Joining a finished scan again just reports its outcome.
+            assert_eq!(dupdupninja_scan_join(handle), DupdupStatus::Ok);
+            assert_eq!(
+                dupdupninja_scan_poll(handle, &mut progress),
+                DupdupStatus::Ok
+            );
+            assert_eq!(progress.files_seen, 12);
+
+            dupdupninja_scan_handle_free(handle);
+            dupdupninja_engine_free(engine);
+        }
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn cancelled_scan_handle_joins_as_cancelled() {
+        let root = scan_handle_root("handle-cancel", 400);
+        let path = temp_fileset("handle-cancel");
+        let c_root = CString::new(root.to_str().unwrap()).unwrap();
+        let c_path = CString::new(path.to_str().unwrap()).unwrap();
+        let mut config = default_scan_config();
+        config.options.capture_snapshots = false;
+
+        unsafe {
+            let engine = dupdupninja_engine_new();
+            let handle = dupdupninja_scan_start(engine, c_root.as_ptr(), c_path.as_ptr(), &config);
+            assert!(!handle.is_null());
+            dupdupninja_scan_handle_cancel(handle);
+            assert_eq!(dupdupninja_scan_join(handle), DupdupStatus::Cancelled);
+            assert_eq!(
+                dupdupninja_scan_poll(handle, std::ptr::null_mut()),
+                DupdupStatus::Cancelled
+            );
+            dupdupninja_scan_handle_free(handle);
+            dupdupninja_engine_free(engine);
+        }
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
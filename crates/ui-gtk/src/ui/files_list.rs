@@ -1,30 +1,111 @@
 use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::time::SystemTime;
 
 use adw::prelude::*;
 use gtk::glib::prelude::Cast;
 use gtk::prelude::GtkWindowExt;
 use gtk4 as gtk;
 
-use dupdupninja_core::models::{FileListRow, FileSnapshotRecord};
+use dupdupninja_core::compare::would_orphan;
+use dupdupninja_core::format::format_bytes;
+use dupdupninja_core::models::{FileListRow, FileListSort, FileSnapshotRecord, FileTypeFilter};
 use dupdupninja_core::MediaFileRecord;
 use image::ImageFormat;
 
 use crate::ui::state::{FileActionButtons, MatchRootData, SelectedFile, UiState};
 
+/// Group label used for byte-identical matches; similar matches are never auto-selected.
+pub(crate) const EXACT_MATCHES_LABEL: &str = "Exact matches";
+
+/// Orders for the top-level rows; clicking a column header sorts within the list.
+const ROOT_SORT_CHOICES: [(FileListSort, &str); 2] = [
+    (FileListSort::SizeDesc, "Largest files first"),
+    (FileListSort::ReclaimableDesc, "By reclaimable space"),
+];
+
+pub(crate) struct FilesFilterBar {
+    pub(crate) container: gtk::Box,
+    pub(crate) filter: gtk::CustomFilter,
+}
+
 pub(crate) struct FileActionBar {
     pub(crate) label: gtk::Label,
     pub(crate) buttons: FileActionButtons,
+    pub(crate) type_filter: TypeFilterControl,
     pub(crate) container: gtk::Box,
 }
 
+/// File type dropdown whose choices follow the types present in the loaded fileset.
 #[derive(Clone)]
+pub(crate) struct TypeFilterControl {
+    dropdown: gtk::DropDown,
+    options: Rc<RefCell<Vec<Option<FileTypeFilter>>>>,
+    syncing: Rc<Cell<bool>>,
+}
+
+impl TypeFilterControl {
+    fn new() -> Self {
+        let control = Self {
+            dropdown: gtk::DropDown::from_strings(&[type_filter_label(None)]),
+            options: Rc::new(RefCell::new(vec![None])),
+            syncing: Rc::new(Cell::new(false)),
+        };
+        control
+            .dropdown
+            .set_tooltip_text(Some("Show only one kind of file"));
+        control
+    }
+
+    /// Rebuilds the choices, keeping `current` selectable even if no file has that type.
+    pub(crate) fn set_available(
+        &self,
+        available: &[FileTypeFilter],
+        current: Option<FileTypeFilter>,
+    ) {
+        let mut options = vec![None];
+        options.extend(
+            FileTypeFilter::ALL
+                .into_iter()
+                .filter(|filter| available.contains(filter) || current == Some(*filter))
+                .map(Some),
+        );
+        let labels: Vec<&str> = options
+            .iter()
+            .map(|option| type_filter_label(*option))
+            .collect();
+        let selected = options
+            .iter()
+            .position(|option| *option == current)
+            .unwrap_or(0);
+        self.syncing.set(true);
+        self.dropdown
+            .set_model(Some(&gtk::StringList::new(&labels)));
+        self.dropdown.set_selected(selected as u32);
+        *self.options.borrow_mut() = options;
+        self.syncing.set(false);
+    }
+}
+
+fn type_filter_label(filter: Option<FileTypeFilter>) -> &'static str {
+    match filter {
+        None => "All types",
+        Some(FileTypeFilter::Image) => "Images",
+        Some(FileTypeFilter::Video) => "Videos",
+        Some(FileTypeFilter::Audio) => "Audio",
+        Some(FileTypeFilter::Other) => "Other",
+    }
+}
+
+#[derive(Clone, PartialEq)]
 pub(crate) struct RowItem {
     pub(crate) kind: RowKind,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub(crate) enum RowKind {
     File {
         file: FileRow,
@@ -37,11 +118,12 @@ pub(crate) enum RowKind {
     MatchItem(FileRow),
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub(crate) struct FileRow {
     pub(crate) id: i64,
     path: PathBuf,
     size_bytes: u64,
+    modified_at: Option<SystemTime>,
     blake3: Option<[u8; 32]>,
     sha256: Option<[u8; 32]>,
     file_type: Option<String>,
@@ -98,6 +180,19 @@ impl RowItem {
         }
     }
 
+    /// How many files share this root's content, itself included. Only root rows have one.
+    fn copy_count(&self) -> Option<u64> {
+        let RowKind::File { groups, .. } = &self.kind else {
+            return None;
+        };
+        let exact = groups
+            .iter()
+            .find(|(label, _)| label.starts_with(EXACT_MATCHES_LABEL))
+            .map(|(_, matches)| matches.len() as u64)
+            .unwrap_or(0);
+        Some(exact + 1)
+    }
+
     fn is_group(&self) -> bool {
         matches!(self.kind, RowKind::MatchGroup { .. })
     }
@@ -107,12 +202,38 @@ impl RowItem {
     }
 }
 
+impl RowItem {
+    /// Case-insensitive substring match against the file and its matches, so a
+    /// search for either copy keeps the whole group visible.
+    fn matches_query(&self, query: &str, full_path: bool) -> bool {
+        let hit = |file: &FileRow| {
+            let text = if full_path {
+                file.path.to_string_lossy()
+            } else {
+                file.path
+                    .file_name()
+                    .map(|name| name.to_string_lossy())
+                    .unwrap_or_default()
+            };
+            text.to_lowercase().contains(query)
+        };
+        match &self.kind {
+            RowKind::File { file, groups } => {
+                hit(file) || groups.iter().any(|(_, matches)| matches.iter().any(&hit))
+            }
+            RowKind::MatchGroup { matches, .. } => matches.iter().any(&hit),
+            RowKind::MatchItem(file) => hit(file),
+        }
+    }
+}
+
 impl From<FileListRow> for FileRow {
     fn from(row: FileListRow) -> Self {
         Self {
             id: row.id,
             path: row.path,
             size_bytes: row.size_bytes,
+            modified_at: row.modified_at,
             blake3: row.blake3,
             sha256: row.sha256,
             file_type: row.file_type,
@@ -123,6 +244,7 @@ impl From<FileListRow> for FileRow {
 pub(crate) fn build_files_column_view(
     selection: &gtk::NoSelection,
     ui_state: Rc<RefCell<Option<UiState>>>,
+    files_db_path: Rc<RefCell<Option<PathBuf>>>,
 ) -> gtk::ColumnView {
     let column_view = gtk::ColumnView::new(Some(selection.clone()));
     column_view.set_hexpand(true);
@@ -239,6 +361,9 @@ pub(crate) fn build_files_column_view(
     check_column.set_resizable(false);
     column_view.append_column(&check_column);
 
+    let preview_column = make_thumbnail_column(files_db_path);
+    column_view.append_column(&preview_column);
+
     let name_factory = gtk::SignalListItemFactory::new();
     name_factory.connect_setup(|_, item| {
         let expander = gtk::TreeExpander::new();
@@ -272,6 +397,13 @@ pub(crate) fn build_files_column_view(
             .item()
             .and_then(|o| o.downcast::<gtk::glib::BoxedAnyObject>().ok())
             .and_then(|o| o.try_borrow::<RowItem>().ok().map(|r| r.clone()));
+        set_row_path(
+            &expander,
+            row_item
+                .as_ref()
+                .and_then(RowItem::file_ref)
+                .map(|f| f.path.as_path()),
+        );
         if let Some(row_item) = row_item {
             label.set_text(&row_item.label());
             if row_item.is_group() {
@@ -287,6 +419,9 @@ pub(crate) fn build_files_column_view(
     let name_column = gtk::ColumnViewColumn::new(Some("Filename"), Some(name_factory));
     name_column.set_resizable(true);
     name_column.set_expand(true);
+    name_column.set_sorter(Some(&row_sorter(|a, b| {
+        a.label().to_lowercase().cmp(&b.label().to_lowercase())
+    })));
     column_view.append_column(&name_column);
 
     let size_column = make_text_column("Size", move |row| {
@@ -294,6 +429,11 @@ pub(crate) fn build_files_column_view(
             .map(|f| format_bytes(f.size_bytes))
             .unwrap_or_default()
     });
+    let copies_column = make_text_column("Copies", move |row| {
+        row.copy_count()
+            .map(|count| count.to_string())
+            .unwrap_or_default()
+    });
     let type_column = make_text_column("File Type", move |row| {
         row.file_ref()
             .and_then(|f| f.file_type.clone())
@@ -311,17 +451,29 @@ pub(crate) fn build_files_column_view(
             .map(hash_to_hex)
             .unwrap_or_default()
     });
+    size_column.set_sorter(Some(&file_sorter(|a, b| a.size_bytes.cmp(&b.size_bytes))));
+    copies_column.set_sorter(Some(&row_sorter(|a, b| {
+        a.copy_count().cmp(&b.copy_count())
+    })));
+    type_column.set_sorter(Some(&file_sorter(|a, b| a.file_type.cmp(&b.file_type))));
+    blake3_column.set_sorter(Some(&file_sorter(|a, b| a.blake3.cmp(&b.blake3))));
+    sha256_column.set_sorter(Some(&file_sorter(|a, b| a.sha256.cmp(&b.sha256))));
 
     column_view.append_column(&size_column);
+    column_view.append_column(&copies_column);
     column_view.append_column(&type_column);
     column_view.append_column(&blake3_column);
     column_view.append_column(&sha256_column);
 
+    let row_menu = build_row_context_menu(&column_view, ui_state.clone());
     attach_column_menu(
         &column_view,
+        row_menu,
         &[
+            ("Preview", preview_column),
             ("Filename", name_column),
             ("Size", size_column),
+            ("Copies", copies_column),
             ("File Type", type_column),
             ("Blake3", blake3_column),
             ("SHA-256", sha256_column),
@@ -331,6 +483,35 @@ pub(crate) fn build_files_column_view(
     column_view
 }
 
+/// Compares the `RowItem`s behind two list items. The view's `TreeListRowSorter`
+/// hands us the tree's items, so each level (roots, groups, matches) sorts on its own.
+fn row_sorter<F>(compare: F) -> gtk::CustomSorter
+where
+    F: Fn(&RowItem, &RowItem) -> Ordering + 'static,
+{
+    gtk::CustomSorter::new(move |a, b| {
+        let item = |obj: &gtk::glib::Object| {
+            obj.downcast_ref::<gtk::glib::BoxedAnyObject>()
+                .and_then(|o| o.try_borrow::<RowItem>().ok().map(|r| r.clone()))
+        };
+        match (item(a), item(b)) {
+            (Some(a), Some(b)) => compare(&a, &b).into(),
+            _ => gtk::Ordering::Equal,
+        }
+    })
+}
+
+/// Like `row_sorter`, but group rows (which have no file) keep their order.
+fn file_sorter<F>(compare: F) -> gtk::CustomSorter
+where
+    F: Fn(&FileRow, &FileRow) -> Ordering + 'static,
+{
+    row_sorter(move |a, b| match (a.file_ref(), b.file_ref()) {
+        (Some(a), Some(b)) => compare(a, b),
+        _ => Ordering::Equal,
+    })
+}
+
 fn find_parent_file_path(row: &gtk::TreeListRow) -> Option<PathBuf> {
     let mut current = row.parent();
     while let Some(parent) = current {
@@ -348,6 +529,157 @@ fn find_parent_file_path(row: &gtk::TreeListRow) -> Option<PathBuf> {
     None
 }
 
+const THUMBNAIL_SIZE: i32 = 48;
+
+/// First-snapshot textures keyed by file id, for the fileset they were read from.
+#[derive(Default)]
+struct ThumbnailCache {
+    db_path: Option<PathBuf>,
+    store: Option<dupdupninja_core::db::SqliteScanStore>,
+    textures: HashMap<i64, Option<gtk::gdk::Texture>>,
+}
+
+impl ThumbnailCache {
+    fn texture(&mut self, db_path: &Path, file_id: i64) -> Option<gtk::gdk::Texture> {
+        if self.db_path.as_deref() != Some(db_path) {
+            self.db_path = Some(db_path.to_path_buf());
+            self.store = dupdupninja_core::db::SqliteScanStore::open(db_path).ok();
+            self.textures.clear();
+        }
+        let store = self.store.as_ref();
+        self.textures
+            .entry(file_id)
+            .or_insert_with(|| {
+                let snapshots = store.map(|store| load_snapshots(store, Some(file_id)))?;
+                let first = snapshots.iter().min_by_key(|snap| snap.snapshot_index)?;
+                decode_avif_texture(&first.image_avif)
+            })
+            .clone()
+    }
+}
+
+/// Decoded pixels the snapshot cache keeps, about 128 MiB of RGBA.
+const SNAPSHOT_CACHE_MAX_PIXELS: u64 = 32 * 1024 * 1024;
+
+/// Decoded snapshot textures keyed by (file id, snapshot index) for one fileset. Past
+/// `SNAPSHOT_CACHE_MAX_PIXELS` the least recently used are dropped.
+#[derive(Default)]
+pub(crate) struct SnapshotTextureCache {
+    db_path: Option<PathBuf>,
+    /// Texture and the tick it was last used at.
+    textures: HashMap<(i64, u32), (gtk::gdk::Texture, u64)>,
+    tick: u64,
+    pixels: u64,
+}
+
+impl SnapshotTextureCache {
+    fn texture<F>(
+        &mut self,
+        db_path: &Path,
+        key: (i64, u32),
+        decode: F,
+    ) -> Option<gtk::gdk::Texture>
+    where
+        F: FnOnce() -> Option<gtk::gdk::Texture>,
+    {
+        if self.db_path.as_deref() != Some(db_path) {
+            *self = Self {
+                db_path: Some(db_path.to_path_buf()),
+                ..Self::default()
+            };
+        }
+        self.tick += 1;
+        if let Some((texture, used)) = self.textures.get_mut(&key) {
+            *used = self.tick;
+            return Some(texture.clone());
+        }
+
+        let texture = decode()?;
+        let pixels = texture_pixels(&texture);
+        while self.pixels + pixels > SNAPSHOT_CACHE_MAX_PIXELS {
+            let Some(oldest) = self
+                .textures
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(key, _)| *key)
+            else {
+                break;
+            };
+            if let Some((evicted, _)) = self.textures.remove(&oldest) {
+                self.pixels -= texture_pixels(&evicted);
+            }
+        }
+        self.pixels += pixels;
+        self.textures.insert(key, (texture.clone(), self.tick));
+        Some(texture)
+    }
+}
+
+fn texture_pixels(texture: &gtk::gdk::Texture) -> u64 {
+    u64::from(texture.width().unsigned_abs()) * u64::from(texture.height().unsigned_abs())
+}
+
+fn make_thumbnail_column(files_db_path: Rc<RefCell<Option<PathBuf>>>) -> gtk::ColumnViewColumn {
+    let cache = Rc::new(RefCell::new(ThumbnailCache::default()));
+    let factory = gtk::SignalListItemFactory::new();
+    factory.connect_setup(|_, item| {
+        let image = gtk::Image::new();
+        image.set_pixel_size(THUMBNAIL_SIZE);
+        item.downcast_ref::<gtk::ListItem>()
+            .unwrap()
+            .set_child(Some(&image));
+    });
+    factory.connect_bind(move |_, item| {
+        let list_item = item.downcast_ref::<gtk::ListItem>().unwrap();
+        let image = list_item
+            .child()
+            .and_then(|c| c.downcast::<gtk::Image>().ok())
+            .unwrap();
+        let row_item: Option<RowItem> = list_item
+            .item()
+            .and_then(|o| o.downcast::<gtk::TreeListRow>().ok())
+            .and_then(|row| row.item())
+            .and_then(|o| o.downcast::<gtk::glib::BoxedAnyObject>().ok())
+            .and_then(|o| o.try_borrow::<RowItem>().ok().map(|r| r.clone()));
+        let Some(file) = row_item.as_ref().and_then(RowItem::file_ref) else {
+            set_row_path(&image, None);
+            image.clear();
+            return;
+        };
+        set_row_path(&image, Some(&file.path));
+
+        // Snapshots only exist for media, so skip the lookup for everything else.
+        let is_media = matches!(
+            file.file_type.as_deref(),
+            Some(mime) if mime.starts_with("video/") || mime.starts_with("image/")
+        );
+        let texture = if is_media {
+            let db_path = files_db_path.borrow().clone();
+            db_path.and_then(|db_path| cache.borrow_mut().texture(&db_path, file.id))
+        } else {
+            None
+        };
+        match texture {
+            Some(texture) => image.set_paintable(Some(&texture)),
+            None => image.set_icon_name(Some(generic_icon_name(file.file_type.as_deref()))),
+        }
+    });
+
+    let column = gtk::ColumnViewColumn::new(Some("Preview"), Some(factory));
+    column.set_fixed_width(THUMBNAIL_SIZE + 16);
+    column.set_resizable(false);
+    column
+}
+
+fn generic_icon_name(file_type: Option<&str>) -> &'static str {
+    match file_type {
+        Some(mime) if mime.starts_with("video/") => "video-x-generic",
+        Some(mime) if mime.starts_with("image/") => "image-x-generic",
+        Some(mime) if mime.starts_with("audio/") => "audio-x-generic",
+        _ => "text-x-generic",
+    }
+}
+
 fn make_text_column<F>(title: &str, value: F) -> gtk::ColumnViewColumn
 where
     F: Fn(&RowItem) -> String + 'static,
@@ -375,6 +707,13 @@ where
             .and_then(|o| o.downcast::<gtk::glib::BoxedAnyObject>().ok())
             .and_then(|o| o.try_borrow::<RowItem>().ok().map(|r| r.clone()));
 
+        set_row_path(
+            &label,
+            row_item
+                .as_ref()
+                .and_then(RowItem::file_ref)
+                .map(|f| f.path.as_path()),
+        );
         if let Some(row_item) = row_item {
             label.set_text(&value(&row_item));
         } else {
@@ -387,7 +726,319 @@ where
     column
 }
 
-fn attach_column_menu(column_view: &gtk::ColumnView, columns: &[(&str, gtk::ColumnViewColumn)]) {
+const ROW_PATH_KEY: &str = "ddn-row-path";
+
+/// Tags a cell widget with its row's relative path so a right-click can find the file.
+fn set_row_path(widget: &impl IsA<gtk::Widget>, path: Option<&Path>) {
+    unsafe {
+        match path {
+            Some(path) => widget.set_data(ROW_PATH_KEY, path.to_path_buf()),
+            None => drop(widget.steal_data::<PathBuf>(ROW_PATH_KEY)),
+        }
+    }
+}
+
+fn row_path_at(column_view: &gtk::ColumnView, x: f64, y: f64) -> Option<PathBuf> {
+    let mut widget = column_view.pick(x, y, gtk::PickFlags::DEFAULT);
+    while let Some(current) = widget {
+        if current == *column_view.upcast_ref::<gtk::Widget>() {
+            break;
+        }
+        let path = unsafe {
+            current
+                .data::<PathBuf>(ROW_PATH_KEY)
+                .map(|p| p.as_ref().clone())
+        };
+        if path.is_some() {
+            return path;
+        }
+        widget = current.parent();
+    }
+    None
+}
+
+struct RowContextMenu {
+    popover: gtk::Popover,
+    target: Rc<RefCell<Option<PathBuf>>>,
+    ignore_group: gtk::Button,
+    ui_state: Rc<RefCell<Option<UiState>>>,
+}
+
+impl RowContextMenu {
+    /// Points the menu at `rel_path`, labelling the ignore item for that file's group.
+    fn set_target(&self, rel_path: PathBuf) {
+        let group = group_ignore_state(&self.ui_state, &rel_path);
+        self.ignore_group.set_sensitive(group.is_some());
+        self.ignore_group
+            .set_label(if matches!(group, Some((_, true))) {
+                "Stop ignoring this group"
+            } else {
+                "Ignore this group"
+            });
+        *self.target.borrow_mut() = Some(rel_path);
+    }
+}
+
+fn build_row_context_menu(
+    column_view: &gtk::ColumnView,
+    ui_state: Rc<RefCell<Option<UiState>>>,
+) -> RowContextMenu {
+    let popover = gtk::Popover::new();
+    popover.set_autohide(true);
+    popover.set_has_arrow(false);
+    popover.set_parent(column_view);
+    let target: Rc<RefCell<Option<PathBuf>>> = Rc::new(RefCell::new(None));
+
+    let content = gtk::Box::new(gtk::Orientation::Vertical, 4);
+    content.set_margin_top(6);
+    content.set_margin_bottom(6);
+    content.set_margin_start(6);
+    content.set_margin_end(6);
+
+    let open_file = gtk::Button::with_label("Open file");
+    let open_folder = gtk::Button::with_label("Open containing folder");
+    for (button, reveal) in [(&open_file, false), (&open_folder, true)] {
+        button.add_css_class("flat");
+        let popover = popover.clone();
+        let target = target.clone();
+        let ui_state = ui_state.clone();
+        button.connect_clicked(move |_| {
+            popover.popdown();
+            let Some(rel_path) = target.borrow().clone() else {
+                return;
+            };
+            let Some(path) = resolve_active_path(&ui_state, &rel_path) else {
+                update_status(
+                    &ui_state,
+                    Err("Fileset root path is missing. Please rescan.".to_string()),
+                );
+                return;
+            };
+            let result = if reveal {
+                show_in_file_manager(&path)
+            } else {
+                launch_default(&path)
+            };
+            if let Err(err) = result {
+                update_status(
+                    &ui_state,
+                    Err(format!("Failed to open {}: {err}", path.display())),
+                );
+            }
+        });
+        content.append(button);
+    }
+
+    let ignore_group = gtk::Button::with_label("Ignore this group");
+    ignore_group.add_css_class("flat");
+    {
+        let popover = popover.clone();
+        let target = target.clone();
+        let ui_state = ui_state.clone();
+        ignore_group.connect_clicked(move |_| {
+            popover.popdown();
+            let Some(rel_path) = target.borrow().clone() else {
+                return;
+            };
+            if let Some((hash, ignored)) = group_ignore_state(&ui_state, &rel_path) {
+                set_group_ignored(&ui_state, &hash, !ignored);
+            }
+        });
+    }
+    content.append(&ignore_group);
+    popover.set_child(Some(&content));
+
+    RowContextMenu {
+        popover,
+        target,
+        ignore_group,
+        ui_state,
+    }
+}
+
+/// The exact-match hash of the file at `rel_path` and whether its group is ignored.
+/// `None` when the file has no exact duplicates.
+fn group_ignore_state(
+    ui_state: &Rc<RefCell<Option<UiState>>>,
+    rel_path: &Path,
+) -> Option<([u8; 32], bool)> {
+    let state_ref = ui_state.borrow();
+    let state = state_ref.as_ref()?;
+    let active_id = state.active_fileset_id?;
+    let entry = state.filesets.iter().find(|entry| entry.id == active_id)?;
+    let store = entry.store().ok()?;
+    let file = store.get_file_by_path(rel_path).ok()??;
+    let hash = file.blake3.or(file.sha256)?;
+    if store.exact_duplicates_of(file.file_id?).ok()?.is_empty() {
+        return None;
+    }
+    Some((hash, store.is_group_ignored(&hash).ok()?))
+}
+
+fn set_group_ignored(ui_state: &Rc<RefCell<Option<UiState>>>, hash: &[u8; 32], ignore: bool) {
+    let result = {
+        let state_ref = ui_state.borrow();
+        let Some(state) = state_ref.as_ref() else {
+            return;
+        };
+        let Some(entry) = state
+            .active_fileset_id
+            .and_then(|active_id| state.filesets.iter().find(|entry| entry.id == active_id))
+        else {
+            return;
+        };
+        entry.store().and_then(|store| {
+            if ignore {
+                store.ignore_group(hash)
+            } else {
+                store.unignore_group(hash).map(drop)
+            }
+        })
+    };
+    let failed = result.is_err();
+    update_status(
+        ui_state,
+        result
+            .map(|()| {
+                if ignore {
+                    "Ignored group; turn on \"Show ignored\" to see it again".to_string()
+                } else {
+                    "Group is no longer ignored".to_string()
+                }
+            })
+            .map_err(|err| format!("Failed to update ignored groups: {err}")),
+    );
+    if failed {
+        return;
+    }
+
+    let mut state = ui_state.borrow_mut();
+    let Some(state) = state.as_mut() else {
+        return;
+    };
+    if let Some(active_id) = state.active_fileset_id {
+        if let Some(entry) = state.filesets.iter().find(|entry| entry.id == active_id) {
+            let db_path = entry.db_path.clone();
+            crate::ui::load_fileset_rows(state, &db_path);
+        }
+    }
+}
+
+fn resolve_active_path(
+    ui_state: &Rc<RefCell<Option<UiState>>>,
+    rel_path: &Path,
+) -> Option<PathBuf> {
+    let state_ref = ui_state.borrow();
+    let state = state_ref.as_ref()?;
+    let active_id = state.active_fileset_id?;
+    let entry = state.filesets.iter().find(|entry| entry.id == active_id)?;
+    stored_file_path(&resolve_root_path(&entry.metadata), rel_path)
+}
+
+/// Where a stored file path points now. Absolute paths, from scans with
+/// `store_absolute_paths`, are used as is; relative ones need the fileset root.
+pub(crate) fn stored_file_path(root: &Path, stored: &Path) -> Option<PathBuf> {
+    if stored.is_absolute() {
+        Some(stored.to_path_buf())
+    } else if root.as_os_str().is_empty() {
+        None
+    } else {
+        Some(root.join(stored))
+    }
+}
+
+fn launch_default(path: &Path) -> std::result::Result<(), String> {
+    let uri = gtk::gio::File::for_path(path).uri();
+    gtk::gio::AppInfo::launch_default_for_uri(&uri, None::<&gtk::gio::AppLaunchContext>)
+        .map_err(|err| err.to_string())
+}
+
+/// Checks an external compare command template as entered in Settings.
+pub(crate) fn validate_external_command(template: &str) -> std::result::Result<(), String> {
+    let mut words = template.split_whitespace();
+    let Some(program) = words.next() else {
+        return Err("The command is empty".to_string());
+    };
+    if program.contains("%parent") || program.contains("%match") {
+        return Err("The command must start with a program, not a placeholder".to_string());
+    }
+    for placeholder in ["%parent", "%match"] {
+        if !template.contains(placeholder) {
+            return Err(format!("The command needs a {placeholder} placeholder"));
+        }
+    }
+    if gtk::glib::find_program_in_path(program).is_none() {
+        return Err(format!("Program not found: {program}"));
+    }
+    Ok(())
+}
+
+/// Splits the template on whitespace and fills in the two paths, so paths with
+/// spaces stay single arguments.
+fn external_command_argv(template: &str, parent: &Path, other: &Path) -> Vec<std::ffi::OsString> {
+    template
+        .split_whitespace()
+        .map(|word| match word {
+            "%parent" => parent.as_os_str().to_os_string(),
+            "%match" => other.as_os_str().to_os_string(),
+            _ => word
+                .replace("%parent", &parent.to_string_lossy())
+                .replace("%match", &other.to_string_lossy())
+                .into(),
+        })
+        .collect()
+}
+
+fn open_in_external_tool(parent: &Path, other: &Path) -> std::result::Result<String, String> {
+    let template = crate::ui::load_settings().external_compare_command;
+    if template.trim().is_empty() {
+        return Err("No external compare command is set in Settings".to_string());
+    }
+    validate_external_command(&template)?;
+    let argv = external_command_argv(&template, parent, other);
+    let mut child = std::process::Command::new(&argv[0])
+        .args(&argv[1..])
+        .spawn()
+        .map_err(|err| format!("Failed to start {}: {err}", argv[0].to_string_lossy()))?;
+    // Reap the tool when it exits so it does not linger as a zombie.
+    std::thread::spawn(move || {
+        let _ = child.wait();
+    });
+    Ok(format!("Opened in {}", argv[0].to_string_lossy()))
+}
+
+/// Selects the file via the FileManager1 D-Bus interface, falling back to
+/// opening the parent directory when no file manager implements it.
+fn show_in_file_manager(path: &Path) -> std::result::Result<(), String> {
+    let uri = gtk::gio::File::for_path(path).uri().to_string();
+    let shown = gtk::gio::bus_get_sync(gtk::gio::BusType::Session, None::<&gtk::gio::Cancellable>)
+        .and_then(|bus| {
+            bus.call_sync(
+                Some("org.freedesktop.FileManager1"),
+                "/org/freedesktop/FileManager1",
+                "org.freedesktop.FileManager1",
+                "ShowItems",
+                Some(&(vec![uri], "").to_variant()),
+                None,
+                gtk::gio::DBusCallFlags::NONE,
+                2000,
+                None::<&gtk::gio::Cancellable>,
+            )
+        });
+    if shown.is_ok() {
+        return Ok(());
+    }
+    match path.parent() {
+        Some(parent) => launch_default(parent),
+        None => Err("file has no parent directory".to_string()),
+    }
+}
+
+fn attach_column_menu(
+    column_view: &gtk::ColumnView,
+    row_menu: RowContextMenu,
+    columns: &[(&str, gtk::ColumnViewColumn)],
+) {
     let popover = gtk::Popover::new();
     popover.set_autohide(true);
     popover.set_has_arrow(false);
@@ -401,11 +1052,12 @@ fn attach_column_menu(column_view: &gtk::ColumnView, columns: &[(&str, gtk::Colu
 
     for (label, column) in columns {
         let checkbox = gtk::CheckButton::with_label(label);
-        checkbox.set_active(column.is_visible());
-        let column = column.clone();
-        checkbox.connect_toggled(move |cb| {
-            column.set_visible(cb.is_active());
-        });
+        // Bound both ways so visibility restored from saved state shows up here.
+        column
+            .bind_property("visible", &checkbox, "active")
+            .bidirectional()
+            .sync_create()
+            .build();
         content.append(&checkbox);
     }
 
@@ -413,16 +1065,275 @@ fn attach_column_menu(column_view: &gtk::ColumnView, columns: &[(&str, gtk::Colu
 
     let click = gtk::GestureClick::builder().button(3).build();
     let popover_for_click = popover.clone();
+    let column_view_for_click = column_view.clone();
     click.connect_pressed(move |gesture, _, x, y| {
         let rect = gtk::gdk::Rectangle::new(x as i32, y as i32, 1, 1);
-        popover_for_click.set_pointing_to(Some(&rect));
-        popover_for_click.popup();
+        // File rows get the open actions; headers and empty space keep the column menu.
+        let menu = match row_path_at(&column_view_for_click, x, y) {
+            Some(rel_path) => {
+                row_menu.set_target(rel_path);
+                &row_menu.popover
+            }
+            None => &popover_for_click,
+        };
+        menu.set_pointing_to(Some(&rect));
+        menu.popup();
         gesture.set_state(gtk::EventSequenceState::Claimed);
     });
     column_view.add_controller(click);
 }
 
-pub(crate) fn build_file_action_bar(ui_state: Rc<RefCell<Option<UiState>>>) -> FileActionBar {
+/// A search entry that filters the root rows of the file list as you type.
+pub(crate) fn build_files_filter_bar() -> FilesFilterBar {
+    let query = Rc::new(RefCell::new(String::new()));
+    let full_path = Rc::new(Cell::new(false));
+
+    let query_for_filter = query.clone();
+    let full_path_for_filter = full_path.clone();
+    let filter = gtk::CustomFilter::new(move |obj| {
+        let query = query_for_filter.borrow();
+        if query.is_empty() {
+            return true;
+        }
+        obj.downcast_ref::<gtk::glib::BoxedAnyObject>()
+            .and_then(|o| o.try_borrow::<RowItem>().ok().map(|r| r.clone()))
+            .map(|row| row.matches_query(&query, full_path_for_filter.get()))
+            .unwrap_or(false)
+    });
+
+    let entry = gtk::SearchEntry::builder()
+        .placeholder_text("Filter by filename")
+        .search_delay(200)
+        .hexpand(true)
+        .build();
+    let full_path_check = gtk::CheckButton::with_label("Match full path");
+
+    let query_for_entry = query.clone();
+    let filter_for_entry = filter.clone();
+    entry.connect_search_changed(move |entry| {
+        *query_for_entry.borrow_mut() = entry.text().trim().to_lowercase();
+        filter_for_entry.changed(gtk::FilterChange::Different);
+    });
+    let filter_for_check = filter.clone();
+    full_path_check.connect_toggled(move |cb| {
+        full_path.set(cb.is_active());
+        if !query.borrow().is_empty() {
+            filter_for_check.changed(gtk::FilterChange::Different);
+        }
+    });
+
+    let container = gtk::Box::new(gtk::Orientation::Horizontal, 8);
+    container.set_margin_start(6);
+    container.set_margin_end(6);
+    container.append(&entry);
+    container.append(&full_path_check);
+
+    FilesFilterBar { container, filter }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum KeepPolicy {
+    ShortestPath,
+    Oldest,
+    Newest,
+}
+
+impl KeepPolicy {
+    const CHOICES: [(KeepPolicy, &'static str); 3] = [
+        (KeepPolicy::ShortestPath, "Keep shortest path"),
+        (KeepPolicy::Oldest, "Keep oldest"),
+        (KeepPolicy::Newest, "Keep newest"),
+    ];
+
+    fn keeper(self, files: &[FileRow]) -> Option<&FileRow> {
+        let by_path = |a: &FileRow, b: &FileRow| {
+            a.path
+                .as_os_str()
+                .len()
+                .cmp(&b.path.as_os_str().len())
+                .then_with(|| a.path.cmp(&b.path))
+        };
+        // Files without a modification time never win the oldest/newest choice.
+        let by_time = |a: &FileRow, b: &FileRow, newest: bool| match (a.modified_at, b.modified_at)
+        {
+            (Some(x), Some(y)) if newest => y.cmp(&x),
+            (Some(x), Some(y)) => x.cmp(&y),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        };
+        files.iter().min_by(|a, b| match self {
+            KeepPolicy::ShortestPath => by_path(a, b),
+            KeepPolicy::Oldest => by_time(a, b, false).then_with(|| by_path(a, b)),
+            KeepPolicy::Newest => by_time(a, b, true).then_with(|| by_path(a, b)),
+        })
+    }
+}
+
+/// Selects every file of each expanded exact-match group except the policy's keeper,
+/// replacing the current selection. The keeper becomes each selection's parent, so
+/// symlink replacement points at the file that stays.
+fn select_duplicates_keep_one(
+    ui_state: &Rc<RefCell<Option<UiState>>>,
+    tree_rows: &gtk::gio::ListModel,
+    column_view: &gtk::ColumnView,
+    policy: KeepPolicy,
+) {
+    let row_item = |row: &gtk::TreeListRow| {
+        row.item()
+            .and_then(|o| o.downcast::<gtk::glib::BoxedAnyObject>().ok())
+            .and_then(|o| o.try_borrow::<RowItem>().ok().map(|r| r.clone()))
+    };
+
+    let mut picks = Vec::new();
+    let mut groups = 0;
+    for idx in 0..tree_rows.n_items() {
+        let Some(row) = tree_rows
+            .item(idx)
+            .and_then(|o| o.downcast::<gtk::TreeListRow>().ok())
+        else {
+            continue;
+        };
+        if !row.is_expanded() {
+            continue;
+        }
+        let Some(RowKind::MatchGroup { label, matches }) = row_item(&row).map(|item| item.kind)
+        else {
+            continue;
+        };
+        if !label.starts_with(EXACT_MATCHES_LABEL) {
+            continue;
+        }
+        let Some(anchor) = row
+            .parent()
+            .and_then(|parent| row_item(&parent))
+            .and_then(|item| item.file_ref().cloned())
+        else {
+            continue;
+        };
+        let mut members = vec![anchor];
+        members.extend(matches);
+        let Some(keeper) = policy.keeper(&members).cloned() else {
+            continue;
+        };
+        groups += 1;
+        for file in members.into_iter().filter(|file| file.id != keeper.id) {
+            picks.push((
+                file.id,
+                SelectedFile {
+                    rel_path: file.path,
+                    parent_rel_path: keeper.path.clone(),
+                },
+            ));
+        }
+    }
+
+    let mut state = ui_state.borrow_mut();
+    let Some(state) = state.as_mut() else {
+        return;
+    };
+    state.selected_files.clear();
+    let selected = picks.len();
+    state.selected_files.extend(picks);
+    update_action_bar_state(state);
+    sync_row_checks(column_view.upcast_ref(), state);
+    let text = if groups == 0 {
+        "Status: Expand an exact-match group to select its duplicates".to_string()
+    } else {
+        format!("Status: Selected {selected} duplicates in {groups} groups, keeping one each")
+    };
+    state.status_label.set_text(&text);
+}
+
+/// Applies reloaded match roots without clearing the list. Unchanged roots keep their
+/// row, and with it their expansion; changed roots are replaced where they stand,
+/// vanished ones removed and new ones appended. Selections of files still listed stay.
+pub(crate) fn merge_match_roots(state: &mut UiState, rows: Vec<MatchRootData>) {
+    let live: HashSet<i64> = rows
+        .iter()
+        .flat_map(|row| {
+            std::iter::once(row.file.id).chain(
+                row.groups
+                    .iter()
+                    .flat_map(|group| group.matches.iter().map(|file| file.id)),
+            )
+        })
+        .collect();
+    let mut fresh: Vec<Option<RowItem>> = rows
+        .into_iter()
+        .map(|row| Some(RowItem::from_match_root(row)))
+        .collect();
+    let positions: HashMap<i64, usize> = fresh
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, item)| Some((item.as_ref()?.file_ref()?.id, idx)))
+        .collect();
+
+    let store = &state.files_root_store;
+    let mut pos = 0;
+    while pos < store.n_items() {
+        let Some(existing) = store
+            .item(pos)
+            .and_then(|obj| obj.downcast::<gtk::glib::BoxedAnyObject>().ok())
+        else {
+            store.remove(pos);
+            continue;
+        };
+        let existing_id = existing.borrow::<RowItem>().file_ref().map(|file| file.id);
+        let replacement = existing_id
+            .and_then(|id| positions.get(&id))
+            .and_then(|&idx| fresh[idx].take());
+        match replacement {
+            Some(item) => {
+                if *existing.borrow::<RowItem>() != item {
+                    store.splice(pos, 1, &[gtk::glib::BoxedAnyObject::new(item)]);
+                }
+                pos += 1;
+            }
+            None => store.remove(pos),
+        }
+    }
+    for item in fresh.into_iter().flatten() {
+        store.append(&gtk::glib::BoxedAnyObject::new(item));
+    }
+
+    state
+        .selected_files
+        .retain(|file_id, _| live.contains(file_id));
+    update_action_bar_state(state);
+    sync_row_checks(state.files_view.upcast_ref(), state);
+}
+
+/// Updates the checkboxes of currently bound rows to match `selected_files`.
+fn sync_row_checks(widget: &gtk::Widget, state: &UiState) {
+    if let Some(check) = widget.downcast_ref::<gtk::CheckButton>() {
+        let file_id = unsafe { check.data::<i64>("ddn-file-id").map(|v| *v.as_ref()) };
+        let setting = unsafe {
+            check
+                .data::<Rc<Cell<bool>>>("ddn-setting")
+                .map(|v| v.as_ref().clone())
+        };
+        if let (Some(file_id), Some(setting)) = (file_id, setting) {
+            if check.is_visible() {
+                setting.set(true);
+                check.set_active(state.selected_files.contains_key(&file_id));
+                setting.set(false);
+            }
+        }
+        return;
+    }
+    let mut child = widget.first_child();
+    while let Some(current) = child {
+        sync_row_checks(&current, state);
+        child = current.next_sibling();
+    }
+}
+
+pub(crate) fn build_file_action_bar(
+    ui_state: Rc<RefCell<Option<UiState>>>,
+    tree_rows: gtk::gio::ListModel,
+    column_view: gtk::ColumnView,
+) -> FileActionBar {
     let bar = gtk::Box::new(gtk::Orientation::Vertical, 6);
     bar.set_margin_top(6);
     bar.set_margin_bottom(6);
@@ -437,6 +1348,10 @@ pub(crate) fn build_file_action_bar(ui_state: Rc<RefCell<Option<UiState>>>) -> F
     let show_duplicates = gtk::CheckButton::with_label("Show matched files only (exact + similar)");
     show_duplicates.set_active(true);
     show_duplicates.set_sensitive(false);
+    let show_ignored = gtk::CheckButton::with_label("Show ignored");
+    show_ignored.set_tooltip_text(Some(
+        "Also list exact-match groups marked with \"Ignore this group\"",
+    ));
 
     let trash = gtk::Button::with_label("Move to Trash");
     let delete = gtk::Button::with_label("Delete Permanently");
@@ -444,6 +1359,16 @@ pub(crate) fn build_file_action_bar(ui_state: Rc<RefCell<Option<UiState>>>) -> F
     let move_to = gtk::Button::with_label("Move to...");
     let replace_symlink = gtk::Button::with_label("Replace with Symlink");
     let compare = gtk::Button::with_label("Compare Selected");
+    let keep_policy = gtk::DropDown::from_strings(&KeepPolicy::CHOICES.map(|(_, label)| label));
+    let type_filter = TypeFilterControl::new();
+    let root_sort = gtk::DropDown::from_strings(&ROOT_SORT_CHOICES.map(|(_, label)| label));
+    root_sort.set_tooltip_text(Some(
+        "Reclaimable space is a file's size times its other exact copies",
+    ));
+    let select_keep_one = gtk::Button::with_label("Select all duplicates (keep one)");
+    select_keep_one.set_tooltip_text(Some(
+        "Selects every file in each expanded exact-match group except one",
+    ));
 
     let label_row = gtk::Box::new(gtk::Orientation::Horizontal, 8);
     label_row.append(&label);
@@ -456,6 +1381,13 @@ pub(crate) fn build_file_action_bar(ui_state: Rc<RefCell<Option<UiState>>>) -> F
     actions_wrap.set_min_children_per_line(1);
 
     actions_wrap.append(&show_duplicates);
+    actions_wrap.append(&show_ignored);
+    actions_wrap.append(&type_filter.dropdown);
+    actions_wrap.append(&root_sort);
+    let select_row = gtk::Box::new(gtk::Orientation::Horizontal, 4);
+    select_row.append(&select_keep_one);
+    select_row.append(&keep_policy);
+    actions_wrap.append(&select_row);
     actions_wrap.append(&trash);
     actions_wrap.append(&delete);
     actions_wrap.append(&copy);
@@ -474,22 +1406,123 @@ pub(crate) fn build_file_action_bar(ui_state: Rc<RefCell<Option<UiState>>>) -> F
     };
 
     let ui_state_for_actions = ui_state.clone();
-    trash.connect_clicked(move |_| {
-        apply_to_selected(&ui_state_for_actions, "trash", |path| {
-            let file = gtk::gio::File::for_path(path);
-            file.trash(None::<&gtk::gio::Cancellable>)
-                .map(|_| "Moved to Trash".to_string())
-                .map_err(|e| e.to_string())
+    let type_filter_for_change = type_filter.clone();
+    type_filter
+        .dropdown
+        .connect_selected_notify(move |dropdown| {
+            if type_filter_for_change.syncing.get() {
+                return;
+            }
+            let filter = type_filter_for_change
+                .options
+                .borrow()
+                .get(dropdown.selected() as usize)
+                .copied()
+                .flatten();
+            let mut state = ui_state_for_actions.borrow_mut();
+            let Some(state) = state.as_mut() else {
+                return;
+            };
+            if state.type_filter == filter {
+                return;
+            }
+            state.type_filter = filter;
+            if let Some(active_id) = state.active_fileset_id {
+                if let Some(entry) = state.filesets.iter().find(|entry| entry.id == active_id) {
+                    let db_path = entry.db_path.clone();
+                    crate::ui::load_fileset_rows(state, &db_path);
+                }
+            }
         });
+
+    let ui_state_for_actions = ui_state.clone();
+    root_sort.connect_selected_notify(move |dropdown| {
+        let Some((sort, _)) = ROOT_SORT_CHOICES.get(dropdown.selected() as usize) else {
+            return;
+        };
+        let mut state = ui_state_for_actions.borrow_mut();
+        let Some(state) = state.as_mut() else {
+            return;
+        };
+        if state.root_sort == *sort {
+            return;
+        }
+        state.root_sort = *sort;
+        if let Some(active_id) = state.active_fileset_id {
+            if let Some(entry) = state.filesets.iter().find(|entry| entry.id == active_id) {
+                let db_path = entry.db_path.clone();
+                crate::ui::load_fileset_rows(state, &db_path);
+            }
+        }
+    });
+
+    let ui_state_for_actions = ui_state.clone();
+    show_ignored.connect_toggled(move |check| {
+        let mut state = ui_state_for_actions.borrow_mut();
+        let Some(state) = state.as_mut() else {
+            return;
+        };
+        state.show_ignored_groups = check.is_active();
+        if let Some(active_id) = state.active_fileset_id {
+            if let Some(entry) = state.filesets.iter().find(|entry| entry.id == active_id) {
+                let db_path = entry.db_path.clone();
+                crate::ui::load_fileset_rows(state, &db_path);
+            }
+        }
+    });
+
+    let ui_state_for_actions = ui_state.clone();
+    select_keep_one.connect_clicked(move |_| {
+        let policy = KeepPolicy::CHOICES
+            .get(keep_policy.selected() as usize)
+            .map(|(policy, _)| *policy)
+            .unwrap_or(KeepPolicy::ShortestPath);
+        select_duplicates_keep_one(&ui_state_for_actions, &tree_rows, &column_view, policy);
+    });
+
+    let ui_state_for_actions = ui_state.clone();
+    trash.connect_clicked(move |_| {
+        let ui_state = ui_state_for_actions.clone();
+        let run = move || {
+            apply_to_selected(&ui_state, "trash", trash_file);
+        };
+        let threshold = ui_state_for_actions
+            .borrow()
+            .as_ref()
+            .map(|state| state.trash_confirm_threshold)
+            .unwrap_or(0);
+        let (count, bytes) = selection_summary(&ui_state_for_actions);
+        if threshold == 0 || count <= threshold {
+            run();
+            return;
+        }
+        confirm_action(
+            &ui_state_for_actions,
+            &format!("Move {count} files to Trash?"),
+            &format!("{} will be moved to the Trash.", format_bytes(bytes)),
+            "Move to Trash",
+            false,
+            run,
+        );
     });
 
     let ui_state_for_actions = ui_state.clone();
     delete.connect_clicked(move |_| {
-        apply_to_selected(&ui_state_for_actions, "delete", |path| {
-            std::fs::remove_file(path)
-                .map(|_| "Deleted permanently".to_string())
-                .map_err(|e| e.to_string())
-        });
+        let ui_state = ui_state_for_actions.clone();
+        let (count, bytes) = selection_summary(&ui_state_for_actions);
+        confirm_action(
+            &ui_state_for_actions,
+            &format!("Permanently delete {count} files?"),
+            &format!(
+                "{} will be irreversibly deleted. This cannot be undone.",
+                format_bytes(bytes)
+            ),
+            "Delete",
+            true,
+            move || {
+                apply_to_selected(&ui_state, "delete", delete_file);
+            },
+        );
     });
 
     let ui_state_for_actions = ui_state.clone();
@@ -523,12 +1556,10 @@ pub(crate) fn build_file_action_bar(ui_state: Rc<RefCell<Option<UiState>>>) -> F
             dialog.select_folder(Some(&window), None::<&gtk::gio::Cancellable>, move |res| {
                 if let Ok(dest) = res {
                     if let Some(folder) = dest.path() {
-                        apply_to_selected(&ui_state_for_dialog, "move", |path| {
-                            let file_name = path.file_name().unwrap_or_default().to_os_string();
-                            let target = folder.join(file_name);
-                            std::fs::rename(path, &target)
-                                .map(|_| "Moved file".to_string())
-                                .map_err(|e| e.to_string())
+                        let ui_state = ui_state_for_dialog.clone();
+                        let target = folder.clone();
+                        choose_move_layout(&ui_state_for_dialog, &folder, move |preserve| {
+                            move_selected(&ui_state, &target, preserve);
                         });
                     }
                 }
@@ -586,10 +1617,179 @@ pub(crate) fn build_file_action_bar(ui_state: Rc<RefCell<Option<UiState>>>) -> F
     FileActionBar {
         label,
         buttons,
+        type_filter,
         container: bar,
     }
 }
 
+/// Asks whether a move into `folder` recreates each file's subfolders there or puts
+/// every file directly in it; `on_choice` gets `true` for the former.
+fn choose_move_layout<F>(ui_state: &Rc<RefCell<Option<UiState>>>, folder: &Path, on_choice: F)
+where
+    F: FnOnce(bool) + 'static,
+{
+    let Some(window) = active_window(ui_state) else {
+        return;
+    };
+    let (count, _) = selection_summary(ui_state);
+    let dialog = adw::AlertDialog::new(
+        Some(&format!("Move {count} files to {}?", folder.display())),
+        Some(
+            "Preserve structure recreates each file's subfolders under the destination. \
+             Flatten puts every file directly in it, renaming clashes to \"name (1)\".",
+        ),
+    );
+    dialog.add_response("cancel", "Cancel");
+    dialog.add_response("flatten", "Flatten");
+    dialog.add_response("preserve", "Preserve structure");
+    dialog.set_response_appearance("preserve", adw::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("preserve"));
+    dialog.set_close_response("cancel");
+    let on_choice = RefCell::new(Some(on_choice));
+    dialog.connect_response(None, move |_, response| {
+        let preserve = match response {
+            "preserve" => true,
+            "flatten" => false,
+            _ => return,
+        };
+        let action = on_choice.borrow_mut().take();
+        if let Some(action) = action {
+            action(preserve);
+        }
+    });
+    dialog.present(Some(&window));
+}
+
+fn move_selected(ui_state: &Rc<RefCell<Option<UiState>>>, folder: &Path, preserve: bool) {
+    let root = {
+        let state_ref = ui_state.borrow();
+        state_ref
+            .as_ref()
+            .and_then(|state| {
+                let active_id = state.active_fileset_id?;
+                state.filesets.iter().find(|entry| entry.id == active_id)
+            })
+            .map(|entry| resolve_root_path(&entry.metadata))
+            .unwrap_or_default()
+    };
+    apply_to_selected(ui_state, "move", |path| {
+        let target = if preserve {
+            folder.join(path_under_root(&root, path))
+        } else {
+            folder.join(path.file_name().unwrap_or_default())
+        };
+        let target = unique_destination(&target);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        std::fs::rename(path, &target)
+            .map(|_| "Moved file".to_string())
+            .map_err(|e| e.to_string())
+    });
+}
+
+/// `path` relative to `root`. Paths outside it, from scans that stored absolute
+/// paths, keep all their folders minus the drive or leading separator.
+fn path_under_root(root: &Path, path: &Path) -> PathBuf {
+    match path.strip_prefix(root) {
+        Ok(rel) if !root.as_os_str().is_empty() => rel.to_path_buf(),
+        _ => path
+            .components()
+            .filter(|component| matches!(component, std::path::Component::Normal(_)))
+            .collect(),
+    }
+}
+
+/// `path` if nothing is there yet, otherwise the first free "name (n).ext" beside it.
+fn unique_destination(path: &Path) -> PathBuf {
+    if !path.exists() {
+        return path.to_path_buf();
+    }
+    let stem = path.file_stem().unwrap_or_default();
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    (1..)
+        .map(|idx| {
+            let mut name = stem.to_os_string();
+            name.push(format!(" ({idx})"));
+            if let Some(ext) = path.extension() {
+                name.push(".");
+                name.push(ext);
+            }
+            dir.join(name)
+        })
+        .find(|candidate| !candidate.exists())
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+fn trash_file(path: &Path) -> std::result::Result<String, String> {
+    dupdupninja_core::files::move_to_trash(path)
+        .map(|_| "Moved to Trash".to_string())
+        .map_err(|e| e.to_string())
+}
+
+fn delete_file(path: &Path) -> std::result::Result<String, String> {
+    std::fs::remove_file(path)
+        .map(|_| "Deleted permanently".to_string())
+        .map_err(|e| e.to_string())
+}
+
+/// Counts the selected files and sums the sizes of those still on disk.
+fn selection_summary(ui_state: &Rc<RefCell<Option<UiState>>>) -> (usize, u64) {
+    let rel_paths: Vec<PathBuf> = match ui_state.borrow().as_ref() {
+        Some(state) => state
+            .selected_files
+            .values()
+            .map(|selected| selected.rel_path.clone())
+            .collect(),
+        None => return (0, 0),
+    };
+    let bytes = rel_paths
+        .iter()
+        .filter_map(|rel_path| resolve_active_path(ui_state, rel_path))
+        .filter_map(|path| std::fs::metadata(path).ok())
+        .map(|meta| meta.len())
+        .sum();
+    (rel_paths.len(), bytes)
+}
+
+/// Runs `on_confirm` only if the user picks the confirm response; Cancel is the default.
+fn confirm_action<F>(
+    ui_state: &Rc<RefCell<Option<UiState>>>,
+    heading: &str,
+    body: &str,
+    confirm_label: &str,
+    destructive: bool,
+    on_confirm: F,
+) where
+    F: FnOnce() + 'static,
+{
+    let Some(window) = active_window(ui_state) else {
+        return;
+    };
+    let dialog = adw::AlertDialog::new(Some(heading), Some(body));
+    dialog.add_response("cancel", "Cancel");
+    dialog.add_response("confirm", confirm_label);
+    let appearance = if destructive {
+        adw::ResponseAppearance::Destructive
+    } else {
+        adw::ResponseAppearance::Suggested
+    };
+    dialog.set_response_appearance("confirm", appearance);
+    dialog.set_default_response(Some("cancel"));
+    dialog.set_close_response("cancel");
+    let on_confirm = RefCell::new(Some(on_confirm));
+    dialog.connect_response(None, move |_, response| {
+        if response != "confirm" {
+            return;
+        }
+        let action = on_confirm.borrow_mut().take();
+        if let Some(action) = action {
+            action();
+        }
+    });
+    dialog.present(Some(&window));
+}
+
 #[cfg(unix)]
 fn unique_backup_path(path: &Path) -> PathBuf {
     let base = path.file_name().and_then(|v| v.to_str()).unwrap_or("file");
@@ -628,7 +1828,7 @@ fn apply_to_selected<F>(ui_state: &Rc<RefCell<Option<UiState>>>, action_name: &s
 where
     F: FnMut(&Path) -> std::result::Result<String, String>,
 {
-    let (paths, db_path) = {
+    let collected = {
         let state_ref = ui_state.borrow();
         let Some(state) = state_ref.as_ref() else {
             return;
@@ -642,32 +1842,58 @@ where
             None => return,
         };
         let root = resolve_root_path(&entry.metadata);
-        if root.as_os_str().is_empty() {
-            update_status(
-                ui_state,
-                Err("Fileset root path is missing. Please rescan.".to_string()),
-            );
-            return;
-        }
-        let db_path = entry.db_path.clone();
-        let mut out = Vec::new();
-        for selected in state.selected_files.values() {
-            out.push(root.join(&selected.rel_path));
-        }
-        (out, db_path)
+        let paths: Option<Vec<_>> = state
+            .selected_files
+            .values()
+            .map(|selected| {
+                let path = stored_file_path(&root, &selected.rel_path)?;
+                Some((path, selected.rel_path.clone()))
+            })
+            .collect();
+        let ids: HashSet<i64> = state.selected_files.keys().copied().collect();
+        paths.map(|paths| (paths, ids, entry.db_path.clone(), entry.store_pool().ok()))
+    };
+    let Some((paths, ids, db_path, pool)) = collected else {
+        update_status(
+            ui_state,
+            Err("Fileset root path is missing. Please rescan.".to_string()),
+        );
+        return;
     };
 
+    let store = pool.as_ref().and_then(|pool| pool.get().ok());
+    if matches!(action_name, "trash" | "delete") {
+        // Refuse rather than risk removing the last copy when the fileset cannot be read.
+        let refusal = match store.as_ref() {
+            None => Some("Cannot open the fileset to check for other copies.".to_string()),
+            Some(store) => match last_copy_selected(store, &ids) {
+                Ok(None) => None,
+                Ok(Some(path)) => Some(format!(
+                    "Every copy of {} is selected; deselect at least one to keep it.",
+                    path.display()
+                )),
+                Err(err) => Some(format!("Cannot check for other copies: {err}")),
+            },
+        };
+        if let Some(message) = refusal {
+            drop(store);
+            update_status(ui_state, Err(message));
+            return;
+        }
+    }
     let mut last_result: Option<std::result::Result<String, String>> = None;
-    for path in paths {
+    for (path, stored) in paths {
         let result = action(&path);
         log_action(&db_path, action_name, &path, result.as_ref().err());
         if result.is_ok() {
-            if let Ok(store) = dupdupninja_core::db::SqliteScanStore::open(&db_path) {
-                let _ = store.delete_file_by_path(&path);
+            if let Some(store) = &store {
+                let _ = store.delete_file_by_path(&stored);
             }
         }
         last_result = Some(result);
     }
+    // The reload below borrows from the same pool.
+    drop(store);
 
     if let Some(result) = last_result {
         update_status(ui_state, result);
@@ -687,6 +1913,26 @@ where
     }
 }
 
+/// A selected file whose exact duplicates are all selected too, so removing the
+/// selection would leave no copy of its content.
+fn last_copy_selected(
+    store: &dupdupninja_core::db::SqliteScanStore,
+    selected: &HashSet<i64>,
+) -> dupdupninja_core::Result<Option<PathBuf>> {
+    for &file_id in selected {
+        let peers = store.exact_duplicates_of(file_id)?;
+        if peers.is_empty() || !would_orphan(selected, &peers) {
+            continue;
+        }
+        let path = store
+            .get_file_by_id(file_id)?
+            .map(|file| file.path)
+            .unwrap_or_else(|| PathBuf::from(format!("file #{file_id}")));
+        return Ok(Some(path));
+    }
+    Ok(None)
+}
+
 fn apply_to_selected_with_parent<F>(
     ui_state: &Rc<RefCell<Option<UiState>>>,
     action_name: &str,
@@ -694,7 +1940,7 @@ fn apply_to_selected_with_parent<F>(
 ) where
     F: FnMut(&Path, &Path) -> std::result::Result<String, String>,
 {
-    let (paths, db_path) = {
+    let collected = {
         let state_ref = ui_state.borrow();
         let Some(state) = state_ref.as_ref() else {
             return;
@@ -708,35 +1954,39 @@ fn apply_to_selected_with_parent<F>(
             None => return,
         };
         let root = resolve_root_path(&entry.metadata);
-        if root.as_os_str().is_empty() {
-            update_status(
-                ui_state,
-                Err("Fileset root path is missing. Please rescan.".to_string()),
-            );
-            return;
-        }
-        let db_path = entry.db_path.clone();
-        let mut out = Vec::new();
-        for selected in state.selected_files.values() {
-            out.push((
-                root.join(&selected.rel_path),
-                root.join(&selected.parent_rel_path),
-            ));
-        }
-        (out, db_path)
+        let paths: Option<Vec<_>> = state
+            .selected_files
+            .values()
+            .map(|selected| {
+                let path = stored_file_path(&root, &selected.rel_path)?;
+                let parent_path = stored_file_path(&root, &selected.parent_rel_path)?;
+                Some((path, parent_path, selected.rel_path.clone()))
+            })
+            .collect();
+        paths.map(|paths| (paths, entry.db_path.clone(), entry.store_pool().ok()))
+    };
+    let Some((paths, db_path, pool)) = collected else {
+        update_status(
+            ui_state,
+            Err("Fileset root path is missing. Please rescan.".to_string()),
+        );
+        return;
     };
 
+    let store = pool.as_ref().and_then(|pool| pool.get().ok());
     let mut last_result: Option<std::result::Result<String, String>> = None;
-    for (path, parent_path) in paths {
+    for (path, parent_path, stored) in paths {
         let result = action(&path, &parent_path);
         log_action(&db_path, action_name, &path, result.as_ref().err());
         if result.is_ok() {
-            if let Ok(store) = dupdupninja_core::db::SqliteScanStore::open(&db_path) {
-                let _ = store.delete_file_by_path(&path);
+            if let Some(store) = &store {
+                let _ = store.delete_file_by_path(&stored);
             }
         }
         last_result = Some(result);
     }
+    // The reload below borrows from the same pool.
+    drop(store);
 
     if let Some(result) = last_result {
         update_status(ui_state, result);
@@ -756,7 +2006,7 @@ fn apply_to_selected_with_parent<F>(
     }
 }
 
-fn resolve_root_path(meta: &dupdupninja_core::FilesetMetadata) -> PathBuf {
+pub(crate) fn resolve_root_path(meta: &dupdupninja_core::FilesetMetadata) -> PathBuf {
     let root = &meta.root_path;
     if !root.as_os_str().is_empty() {
         if root.is_absolute() {
@@ -827,7 +2077,7 @@ fn update_status(
 }
 
 fn open_compare_window(ui_state: &Rc<RefCell<Option<UiState>>>) {
-    let (db_path, root_path, selections) = {
+    let (store, root_path, selections) = {
         let state_ref = ui_state.borrow();
         let Some(state) = state_ref.as_ref() else {
             return;
@@ -847,11 +2097,7 @@ fn open_compare_window(ui_state: &Rc<RefCell<Option<UiState>>>) {
                 .or_default()
                 .push(*id);
         }
-        (
-            entry.db_path.clone(),
-            entry.metadata.root_path.clone(),
-            grouped,
-        )
+        (entry.store(), entry.metadata.root_path.clone(), grouped)
     };
 
     if selections.is_empty() {
@@ -859,7 +2105,7 @@ fn open_compare_window(ui_state: &Rc<RefCell<Option<UiState>>>) {
         return;
     }
 
-    let store = match dupdupninja_core::db::SqliteScanStore::open(&db_path) {
+    let store = match store {
         Ok(store) => store,
         Err(err) => {
             update_status(ui_state, Err(format!("Failed to open fileset: {err}")));
@@ -870,7 +2116,39 @@ fn open_compare_window(ui_state: &Rc<RefCell<Option<UiState>>>) {
     let notebook = gtk::Notebook::new();
     notebook.set_hexpand(true);
     notebook.set_vexpand(true);
-    for (parent_rel, matches) in selections {
+    // Files from several groups, or without one, go side by side in a single tab.
+    let combined = selections.len() > 1 || selections.contains_key(Path::new(""));
+    if combined {
+        let mut files: Vec<CompareFile> = selections
+            .values()
+            .flatten()
+            .filter_map(|id| store.get_file_by_id(*id).ok().flatten())
+            .map(|rec| CompareFile {
+                snapshots: load_snapshots(&store, rec.file_id),
+                record: rec,
+            })
+            .collect();
+        files.sort_by(|a, b| a.record.path.cmp(&b.record.path));
+        if files.len() > 1 {
+            let lead = files.remove(0);
+            let max_snapshots = std::iter::once(&lead)
+                .chain(files.iter())
+                .map(|f| f.snapshots.len())
+                .max()
+                .unwrap_or(0);
+            let tab = build_compare_tab(
+                ui_state,
+                &root_path,
+                &display_name(&lead.record),
+                &lead,
+                &files,
+                max_snapshots,
+            );
+            let tab_label = gtk::Label::new(Some("Selected files"));
+            notebook.append_page(&tab, Some(&tab_label));
+        }
+    }
+    for (parent_rel, matches) in selections.into_iter().filter(|_| !combined) {
         let parent = match store.get_file_by_path(&parent_rel).ok().flatten() {
             Some(parent) => parent,
             None => continue,
@@ -899,7 +2177,14 @@ fn open_compare_window(ui_state: &Rc<RefCell<Option<UiState>>>) {
             .map(|f| f.snapshots.len())
             .max()
             .unwrap_or(0);
-        let tab = build_compare_tab(&root_path, &parent_file, &match_records, max_snapshots);
+        let tab = build_compare_tab(
+            ui_state,
+            &root_path,
+            &format!("Parent: {tab_title}"),
+            &parent_file,
+            &match_records,
+            max_snapshots,
+        );
         let tab_label = gtk::Label::new(Some(&tab_title));
         notebook.append_page(&tab, Some(&tab_label));
     }
@@ -971,20 +2256,41 @@ struct CompareFile {
     snapshots: Vec<FileSnapshotRecord>,
 }
 
+/// A compare tab's files as (id, stored path, size), for picking one of them to keep.
+type CompareGroup = Rc<Vec<(i64, PathBuf, u64)>>;
+
 fn build_compare_tab(
+    ui_state: &Rc<RefCell<Option<UiState>>>,
     root_path: &Path,
+    parent_title: &str,
     parent: &CompareFile,
     matches: &[CompareFile],
     max_snapshots: usize,
 ) -> gtk::Widget {
     let rows = metadata_rows(max_snapshots);
-    let parent_title = format!("Parent: {}", display_name(&parent.record));
-    let parent_column = build_metadata_column(&parent_title, &rows, parent, root_path, true);
+    let group: CompareGroup = Rc::new(
+        std::iter::once(parent)
+            .chain(matches)
+            .filter_map(|file| {
+                let record = &file.record;
+                Some((record.file_id?, record.path.clone(), record.size_bytes))
+            })
+            .collect(),
+    );
+    let parent_column = build_metadata_column(
+        ui_state,
+        &group,
+        parent_title,
+        &rows,
+        parent,
+        root_path,
+        true,
+    );
 
     let matches_box = gtk::Box::new(gtk::Orientation::Horizontal, 16);
     for file in matches {
         let title = display_name(&file.record);
-        let column = build_metadata_column(&title, &rows, file, root_path, false);
+        let column = build_metadata_column(ui_state, &group, &title, &rows, file, root_path, false);
         matches_box.append(&column);
     }
 
@@ -1019,6 +2325,8 @@ fn build_compare_tab(
 }
 
 fn build_metadata_column(
+    ui_state: &Rc<RefCell<Option<UiState>>>,
+    group: &CompareGroup,
     title: &str,
     rows: &[CompareRow],
     file: &CompareFile,
@@ -1036,6 +2344,41 @@ fn build_metadata_column(
     header.add_css_class("title-4");
     column.append(&header);
 
+    if let Some(keeper_id) = file.record.file_id.filter(|_| group.len() > 1) {
+        let actions = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+        for (label, delete) in [
+            ("Keep this, trash others", false),
+            ("Keep this, delete others", true),
+        ] {
+            let button = gtk::Button::with_label(label);
+            if delete {
+                button.add_css_class("destructive-action");
+            }
+            let ui_state = ui_state.clone();
+            let group = group.clone();
+            button.connect_clicked(move |button| {
+                keep_one_of_group(&ui_state, button, &group, keeper_id, delete);
+            });
+            actions.append(&button);
+        }
+        // The group leads with the parent, which every other column compares against.
+        if let Some((_, parent_rel, _)) = group.first().filter(|_| !include_labels) {
+            let button = gtk::Button::with_label("Open in external tool");
+            let ui_state = ui_state.clone();
+            let parent = stored_file_path(root_path, parent_rel);
+            let other = stored_file_path(root_path, &file.record.path);
+            button.connect_clicked(move |_| {
+                let result = match (&parent, &other) {
+                    (Some(parent), Some(other)) => open_in_external_tool(parent, other),
+                    _ => Err("The fileset root is unknown".to_string()),
+                };
+                update_status(&ui_state, result);
+            });
+            actions.append(&button);
+        }
+        column.append(&actions);
+    }
+
     for row_def in rows {
         let row = gtk::Box::new(gtk::Orientation::Horizontal, 8);
         let label_text = match row_def {
@@ -1060,7 +2403,12 @@ fn build_metadata_column(
                 row.append(&value_label);
             }
             CompareRow::Snapshot(index) => {
-                row.append(&snapshot_widget(&file.snapshots, *index));
+                row.append(&snapshot_widget(
+                    ui_state,
+                    file.record.file_id,
+                    &file.snapshots,
+                    *index,
+                ));
             }
         }
         column.append(&row);
@@ -1069,6 +2417,77 @@ fn build_metadata_column(
     column.upcast()
 }
 
+/// Selects every file of a compare tab but `keeper_id` and, once confirmed, trashes or
+/// deletes them the way the action bar does, then closes the compare window.
+fn keep_one_of_group(
+    ui_state: &Rc<RefCell<Option<UiState>>>,
+    button: &gtk::Button,
+    group: &[(i64, PathBuf, u64)],
+    keeper_id: i64,
+    delete: bool,
+) {
+    let Some((_, keeper_path, _)) = group.iter().find(|(id, _, _)| *id == keeper_id) else {
+        return;
+    };
+    let others: Vec<_> = group.iter().filter(|(id, _, _)| *id != keeper_id).collect();
+    let bytes: u64 = others.iter().map(|(_, _, size)| size).sum();
+    let count = others.len();
+    let picks: Vec<(i64, SelectedFile)> = others
+        .into_iter()
+        .map(|(id, path, _)| {
+            (
+                *id,
+                SelectedFile {
+                    rel_path: path.clone(),
+                    parent_rel_path: keeper_path.clone(),
+                },
+            )
+        })
+        .collect();
+    let compare_window = button
+        .root()
+        .and_then(|root| root.downcast::<gtk::Window>().ok());
+
+    let ui_state_for_action = ui_state.clone();
+    let run = move || {
+        if let Some(state) = ui_state_for_action.borrow_mut().as_mut() {
+            state.selected_files.clear();
+            state.selected_files.extend(picks);
+            update_action_bar_state(state);
+        }
+        if delete {
+            apply_to_selected(&ui_state_for_action, "delete", delete_file);
+        } else {
+            apply_to_selected(&ui_state_for_action, "trash", trash_file);
+        }
+        if let Some(window) = compare_window {
+            window.close();
+        }
+    };
+    if delete {
+        confirm_action(
+            ui_state,
+            &format!("Keep one and permanently delete {count} files?"),
+            &format!(
+                "{} will be irreversibly deleted. This cannot be undone.",
+                format_bytes(bytes)
+            ),
+            "Delete",
+            true,
+            run,
+        );
+    } else {
+        confirm_action(
+            ui_state,
+            &format!("Keep one and move {count} files to Trash?"),
+            &format!("{} will be moved to the Trash.", format_bytes(bytes)),
+            "Move to Trash",
+            false,
+            run,
+        );
+    }
+}
+
 #[derive(Clone, Copy)]
 enum MetadataField {
     Path,
@@ -1077,6 +2496,10 @@ enum MetadataField {
     FileType,
     Blake3,
     Sha256,
+    Container,
+    Codecs,
+    Resolution,
+    Bitrate,
     Ffmpeg,
 }
 
@@ -1093,6 +2516,10 @@ fn metadata_rows(max_snapshots: usize) -> Vec<CompareRow> {
         CompareRow::Field("File Type".to_string(), MetadataField::FileType),
         CompareRow::Field("Blake3".to_string(), MetadataField::Blake3),
         CompareRow::Field("SHA-256".to_string(), MetadataField::Sha256),
+        CompareRow::Field("Container".to_string(), MetadataField::Container),
+        CompareRow::Field("Codecs".to_string(), MetadataField::Codecs),
+        CompareRow::Field("Resolution".to_string(), MetadataField::Resolution),
+        CompareRow::Field("Bitrate".to_string(), MetadataField::Bitrate),
         CompareRow::Field("FFmpeg metadata".to_string(), MetadataField::Ffmpeg),
     ];
     for idx in 0..max_snapshots {
@@ -1105,17 +2532,35 @@ fn snapshot_label(index: usize) -> String {
     format!("Snapshot {}", index + 1)
 }
 
-fn snapshot_widget(snapshots: &[FileSnapshotRecord], index: usize) -> gtk::Widget {
+fn snapshot_widget(
+    ui_state: &Rc<RefCell<Option<UiState>>>,
+    file_id: Option<i64>,
+    snapshots: &[FileSnapshotRecord],
+    index: usize,
+) -> gtk::Widget {
     if let Some(snapshot) = snapshots.get(index) {
-        let bytes = gtk::glib::Bytes::from(&snapshot.image_avif);
-        if let Ok(texture) = gtk::gdk::Texture::from_bytes(&bytes) {
-            let picture = gtk::Picture::for_paintable(&texture);
-            picture.set_can_shrink(false);
-            picture.set_content_fit(gtk::ContentFit::Contain);
-            picture.set_size_request(160, 90);
-            return picture.upcast();
-        }
-        if let Some(texture) = decode_avif_texture(&snapshot.image_avif) {
+        let decode = || {
+            let bytes = gtk::glib::Bytes::from(&snapshot.image_avif);
+            gtk::gdk::Texture::from_bytes(&bytes)
+                .ok()
+                .or_else(|| decode_avif_texture(&snapshot.image_avif))
+        };
+        let texture = {
+            let mut state_ref = ui_state.borrow_mut();
+            let state = state_ref.as_mut();
+            let db_path = state
+                .as_ref()
+                .and_then(|state| state.files_db_path.borrow().clone());
+            match (state, db_path, file_id) {
+                (Some(state), Some(db_path), Some(file_id)) => state.snapshot_textures.texture(
+                    &db_path,
+                    (file_id, snapshot.snapshot_index),
+                    decode,
+                ),
+                _ => decode(),
+            }
+        };
+        if let Some(texture) = texture {
             let picture = gtk::Picture::for_paintable(&texture);
             picture.set_can_shrink(false);
             picture.set_content_fit(gtk::ContentFit::Contain);
@@ -1146,6 +2591,22 @@ fn metadata_value(field: &MetadataField, record: &MediaFileRecord, root_path: &P
         MetadataField::FileType => record.file_type.clone().unwrap_or_default(),
         MetadataField::Blake3 => record.blake3.as_ref().map(hash_to_hex).unwrap_or_default(),
         MetadataField::Sha256 => record.sha256.as_ref().map(hash_to_hex).unwrap_or_default(),
+        MetadataField::Container => record.media.container.clone().unwrap_or_default(),
+        MetadataField::Codecs => [&record.media.video_codec, &record.media.audio_codec]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" / "),
+        MetadataField::Resolution => match (record.media.width, record.media.height) {
+            (Some(w), Some(h)) => format!("{w}x{h}"),
+            _ => String::new(),
+        },
+        MetadataField::Bitrate => record
+            .media
+            .bit_rate
+            .map(|b| format!("{} kb/s", b / 1000))
+            .unwrap_or_default(),
         MetadataField::Ffmpeg => record.ffmpeg_metadata.clone().unwrap_or_default(),
     }
 }
@@ -1185,22 +2646,6 @@ fn decode_avif_texture(data: &[u8]) -> Option<gtk::gdk::Texture> {
     Some(texture.upcast::<gtk::gdk::Texture>())
 }
 
-fn format_bytes(bytes: u64) -> String {
-    const KB: f64 = 1024.0;
-    const MB: f64 = KB * 1024.0;
-    const GB: f64 = MB * 1024.0;
-    let b = bytes as f64;
-    if b >= GB {
-        format!("{:.2} GB", b / GB)
-    } else if b >= MB {
-        format!("{:.2} MB", b / MB)
-    } else if b >= KB {
-        format!("{:.2} KB", b / KB)
-    } else {
-        format!("{bytes} B")
-    }
-}
-
 fn hash_to_hex(hash: &[u8; 32]) -> String {
     let mut out = String::with_capacity(64);
     for b in hash {
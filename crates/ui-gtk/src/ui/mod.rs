@@ -1,8 +1,12 @@
 mod files_list;
 mod state;
+mod window_state;
 
+#[cfg(all(target_os = "linux", feature = "gtk"))]
+use dupdupninja_core::scan::ScanConfig;
 use files_list::*;
 use state::{FilesetEntry, MatchChildData, MatchRootData, UiState, UiUpdate};
+use window_state::{apply_column_state, load_window_state, save_window_state};
 
 #[cfg(all(target_os = "linux", feature = "gtk"))]
 pub fn run() {
@@ -45,17 +49,7 @@ pub fn run() {
                     match dialog.select_folder_future(Some(&window)).await {
                         Ok(folder) => {
                             if let Some(path) = folder.path() {
-                                let db_path = scan_db_path(&path);
-                                let name = fileset_name_from_path(&path);
-                                let fileset_id =
-                                    add_fileset(ui_state.clone(), name, db_path.clone());
-                                start_scan(
-                                    ui_state.clone(),
-                                    path,
-                                    dupdupninja_core::ScanRootKind::Folder,
-                                    db_path,
-                                    fileset_id,
-                                );
+                                start_folder_scan(ui_state.clone(), path);
                             }
                         }
                         Err(err) => {
@@ -80,7 +74,7 @@ pub fn run() {
                 select_mount_path(&window, move |path| {
                     let ui_state = ui_state.clone();
                     if let Some(path) = path {
-                        let db_path = scan_db_path(&path);
+                        let db_path = scan_db_path(&ui_state, &path);
                         let name = fileset_name_from_path(&path);
                         let fileset_id = add_fileset(ui_state.clone(), name, db_path.clone());
                         start_scan(
@@ -125,6 +119,10 @@ pub fn run() {
                     }
                     if let Ok(file) = dialog.open_future(Some(&window)).await {
                         if let Some(path) = file.path() {
+                            if let Err(err) = dupdupninja_core::db::SqliteScanStore::probe(&path) {
+                                show_open_fileset_error(&window, &path, &err);
+                                return;
+                            }
                             let name = fileset_name_from_db(&path);
                             add_fileset(ui_state.clone(), name, path);
                         }
@@ -148,7 +146,10 @@ pub fn run() {
                     initial_count,
                     initial_max_dim,
                     initial_concurrent,
-                    initial_similar_match_cap,
+                    initial_single_pass,
+                    initial_include_near,
+                    initial_near_distance,
+                    initial_trash_confirm_threshold,
                 ) = ui_state
                     .borrow()
                     .as_ref()
@@ -158,10 +159,13 @@ pub fn run() {
                             s.snapshots_per_video,
                             s.snapshot_max_dim,
                             s.concurrent_processing,
-                            s.similar_match_cap,
+                            s.single_pass_scan,
+                            s.include_near_duplicates,
+                            s.near_duplicate_distance,
+                            s.trash_confirm_threshold,
                         )
                     })
-                    .unwrap_or((true, 3, 1024, true, 2200));
+                    .unwrap_or((true, 3, 1024, true, false, true, 8, 0));
 
                 let settings_window = gtk::Window::builder()
                     .transient_for(&window)
@@ -198,9 +202,9 @@ pub fn run() {
                 label2.set_xalign(0.0);
                 label2.set_hexpand(true);
                 let adjustment = gtk::Adjustment::new(
-                    initial_count.clamp(1, 10) as f64,
-                    1.0,
-                    10.0,
+                    clamp_snapshots_per_video(initial_count) as f64,
+                    f64::from(*ScanConfig::SNAPSHOTS_PER_VIDEO.start()),
+                    f64::from(*ScanConfig::SNAPSHOTS_PER_VIDEO.end()),
                     1.0,
                     1.0,
                     0.0,
@@ -216,7 +220,7 @@ pub fn run() {
                 let label3 = gtk::Label::new(Some("Snapshot max size"));
                 label3.set_xalign(0.0);
                 label3.set_hexpand(true);
-                let sizes = [128_u32, 256, 512, 768, 1024, 1536, 2048];
+                let sizes = [128_u32, 256, 512, 768, 1024, 1536, 2048, 4096];
                 let size_labels = [
                     "128 x 128",
                     "256 x 256",
@@ -225,6 +229,7 @@ pub fn run() {
                     "1024 x 1024",
                     "1536 x 1536",
                     "2048 x 2048",
+                    "4096 x 4096",
                 ];
                 let string_list = gtk::StringList::new(&size_labels);
                 let size_dropdown =
@@ -251,23 +256,45 @@ pub fn run() {
 
                 let row5 = gtk::Box::new(gtk::Orientation::Horizontal, 12);
                 row5.set_hexpand(true);
-                let label5 = gtk::Label::new(Some("Similar match file cap"));
+                let label5 =
+                    gtk::Label::new(Some("Single-pass scan (keeps the file list in memory)"));
                 label5.set_xalign(0.0);
                 label5.set_hexpand(true);
-                let similar_cap_adjustment = gtk::Adjustment::new(
-                    initial_similar_match_cap.clamp(200, 20_000) as f64,
-                    200.0,
-                    20_000.0,
-                    100.0,
-                    500.0,
-                    0.0,
-                );
-                let similar_cap_spin =
-                    gtk::SpinButton::new(Some(&similar_cap_adjustment), 100.0, 0);
+                let single_pass_switch = gtk::Switch::builder().active(initial_single_pass).build();
                 row5.append(&label5);
-                row5.append(&similar_cap_spin);
+                row5.append(&single_pass_switch);
                 content.append(&row5);
 
+                let row6 = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+                row6.set_hexpand(true);
+                let label6 = gtk::Label::new(Some("Include near-duplicates"));
+                label6.set_xalign(0.0);
+                label6.set_hexpand(true);
+                let near_switch = gtk::Switch::builder().active(initial_include_near).build();
+                row6.append(&label6);
+                row6.append(&near_switch);
+                content.append(&row6);
+
+                let row7 = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+                row7.set_hexpand(true);
+                let label7 = gtk::Label::new(Some("Near-duplicate max distance (pHash bits)"));
+                label7.set_xalign(0.0);
+                label7.set_hexpand(true);
+                let near_distance_adjustment = gtk::Adjustment::new(
+                    initial_near_distance.min(32) as f64,
+                    0.0,
+                    32.0,
+                    1.0,
+                    4.0,
+                    0.0,
+                );
+                let near_distance_spin =
+                    gtk::SpinButton::new(Some(&near_distance_adjustment), 1.0, 0);
+                near_distance_spin.set_sensitive(initial_include_near);
+                row7.append(&label7);
+                row7.append(&near_distance_spin);
+                content.append(&row7);
+
                 capture_switch.connect_notify_local(
                     Some("active"),
                     glib::clone!(
@@ -293,7 +320,7 @@ pub fn run() {
                     #[strong]
                     ui_state,
                     move |spin| {
-                        let value = spin.value().round().clamp(1.0, 10.0) as u32;
+                        let value = clamp_snapshots_per_video(spin.value().round() as u32);
                         if let Some(state) = ui_state.borrow_mut().as_mut() {
                             state.snapshots_per_video = value;
                         }
@@ -306,7 +333,7 @@ pub fn run() {
                     ui_state,
                     move |combo| {
                         let idx = combo.selected() as usize;
-                        let sizes = [128_u32, 256, 512, 768, 1024, 1536, 2048];
+                        let sizes = [128_u32, 256, 512, 768, 1024, 1536, 2048, 4096];
                         if let Some(state) = ui_state.borrow_mut().as_mut() {
                             state.snapshot_max_dim = sizes[idx];
                         }
@@ -329,17 +356,69 @@ pub fn run() {
                     ),
                 );
 
-                similar_cap_spin.connect_value_changed(glib::clone!(
+                single_pass_switch.connect_notify_local(
+                    Some("active"),
+                    glib::clone!(
+                        #[strong]
+                        ui_state,
+                        move |sw, _| {
+                            let active = sw.is_active();
+                            if let Some(state) = ui_state.borrow_mut().as_mut() {
+                                state.single_pass_scan = active;
+                            }
+                            persist_scan_settings_from_ui_state(ui_state.clone());
+                        }
+                    ),
+                );
+
+                near_switch.connect_notify_local(
+                    Some("active"),
+                    glib::clone!(
+                        #[strong]
+                        ui_state,
+                        #[weak]
+                        near_distance_spin,
+                        move |sw, _| {
+                            let active = sw.is_active();
+                            near_distance_spin.set_sensitive(active);
+                            let db_path_to_reload = {
+                                let mut state_ref = ui_state.borrow_mut();
+                                let Some(state) = state_ref.as_mut() else {
+                                    return;
+                                };
+                                state.include_near_duplicates = active;
+                                state.active_fileset_id.and_then(|active_id| {
+                                    state
+                                        .filesets
+                                        .iter()
+                                        .find(|entry| entry.id == active_id)
+                                        .map(|entry| entry.db_path.clone())
+                                })
+                            };
+                            persist_scan_settings_from_ui_state(ui_state.clone());
+                            if let Some(db_path) = db_path_to_reload {
+                                if let Some(state) = ui_state.borrow_mut().as_mut() {
+                                    load_fileset_rows(state, &db_path);
+                                }
+                            }
+                        }
+                    ),
+                );
+
+                near_distance_spin.connect_value_changed(glib::clone!(
                     #[strong]
                     ui_state,
                     move |spin| {
-                        let value = spin.value().round().clamp(200.0, 20_000.0) as usize;
+                        let value = spin.value().round().clamp(0.0, 32.0) as u32;
                         let db_path_to_reload = {
                             let mut state_ref = ui_state.borrow_mut();
                             let Some(state) = state_ref.as_mut() else {
                                 return;
                             };
-                            state.similar_match_cap = value;
+                            if state.near_duplicate_distance == value {
+                                return;
+                            }
+                            state.near_duplicate_distance = value;
                             state.active_fileset_id.and_then(|active_id| {
                                 state
                                     .filesets
@@ -357,13 +436,91 @@ pub fn run() {
                     }
                 ));
 
+                let actions_title = gtk::Label::new(Some("File actions"));
+                actions_title.add_css_class("title-3");
+                actions_title.set_xalign(0.0);
+                actions_title.set_margin_top(12);
+                content.append(&actions_title);
+
+                let row8 = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+                row8.set_hexpand(true);
+                let label8 =
+                    gtk::Label::new(Some("Confirm trash above this many files (0 = never)"));
+                label8.set_xalign(0.0);
+                label8.set_hexpand(true);
+                let trash_confirm_adjustment = gtk::Adjustment::new(
+                    initial_trash_confirm_threshold.min(10_000) as f64,
+                    0.0,
+                    10_000.0,
+                    1.0,
+                    10.0,
+                    0.0,
+                );
+                let trash_confirm_spin =
+                    gtk::SpinButton::new(Some(&trash_confirm_adjustment), 1.0, 0);
+                row8.append(&label8);
+                row8.append(&trash_confirm_spin);
+                content.append(&row8);
+
+                trash_confirm_spin.connect_value_changed(glib::clone!(
+                    #[strong]
+                    ui_state,
+                    move |spin| {
+                        let value = spin.value().round().clamp(0.0, 10_000.0) as usize;
+                        if let Some(state) = ui_state.borrow_mut().as_mut() {
+                            state.trash_confirm_threshold = value;
+                        }
+                        persist_scan_settings_from_ui_state(ui_state.clone());
+                    }
+                ));
+
+                let settings_state = std::rc::Rc::new(std::cell::RefCell::new(load_settings()));
+                let external_row = gtk::Box::new(gtk::Orientation::Vertical, 6);
+                let external_label = gtk::Label::new(Some(
+                    "External compare command (%parent and %match are replaced by the two paths)",
+                ));
+                external_label.set_xalign(0.0);
+                external_label.set_wrap(true);
+                external_row.append(&external_label);
+                let external_entry = gtk::Entry::new();
+                external_entry.set_placeholder_text(Some("e.g. meld %parent %match"));
+                external_entry.set_text(&settings_state.borrow().external_compare_command);
+                external_row.append(&external_entry);
+                let external_error = gtk::Label::new(None);
+                external_error.set_xalign(0.0);
+                external_error.add_css_class("error");
+                external_error.set_visible(false);
+                external_row.append(&external_error);
+                content.append(&external_row);
+
+                external_entry.connect_changed(glib::clone!(
+                    #[strong]
+                    settings_state,
+                    #[weak]
+                    external_error,
+                    move |entry| {
+                        let text = entry.text().trim().to_string();
+                        if !text.is_empty() {
+                            if let Err(err) = validate_external_command(&text) {
+                                external_error.set_text(&err);
+                                external_error.set_visible(true);
+                                return;
+                            }
+                        }
+                        external_error.set_visible(false);
+                        settings_state.borrow_mut().external_compare_command = text.clone();
+                        let mut settings = load_settings();
+                        settings.external_compare_command = text;
+                        let _ = save_settings(&settings);
+                    }
+                ));
+
                 let fileset_title = gtk::Label::new(Some("Filesets"));
                 fileset_title.add_css_class("title-3");
                 fileset_title.set_xalign(0.0);
                 fileset_title.set_margin_top(12);
                 content.append(&fileset_title);
 
-                let settings_state = std::rc::Rc::new(std::cell::RefCell::new(load_settings()));
                 let fileset_row = gtk::Box::new(gtk::Orientation::Vertical, 6);
                 let fileset_label = gtk::Label::new(Some("Default fileset folder"));
                 fileset_label.set_xalign(0.0);
@@ -501,9 +658,10 @@ pub fn run() {
             theme.add_search_path("icons/linux");
         }
 
+        let saved_window = load_window_state();
         let window = adw::ApplicationWindow::new(app);
         window.set_title(Some("dupdupninja"));
-        window.set_default_size(1100, 720);
+        window.set_default_size(saved_window.width, saved_window.height);
         window.set_icon_name(Some("dupdupninja"));
 
         let header = adw::HeaderBar::new();
@@ -569,8 +727,13 @@ pub fn run() {
         let files_db_path: std::rc::Rc<std::cell::RefCell<Option<std::path::PathBuf>>> =
             std::rc::Rc::new(std::cell::RefCell::new(None));
         let files_root_store = gio::ListStore::new::<gtk4::glib::BoxedAnyObject>();
+        let filter_bar = build_files_filter_bar();
+        let files_filter_model = gtk::FilterListModel::new(
+            Some(files_root_store.clone()),
+            Some(filter_bar.filter.clone()),
+        );
         let files_tree_model =
-            gtk::TreeListModel::new(files_root_store.clone(), false, false, move |obj| {
+            gtk::TreeListModel::new(files_filter_model, false, false, move |obj| {
                 let row_item = obj
                     .downcast_ref::<gtk4::glib::BoxedAnyObject>()?
                     .borrow::<RowItem>()
@@ -601,19 +764,77 @@ pub fn run() {
                 }
             });
 
-        let files_selection = gtk::NoSelection::new(Some(files_tree_model.clone()));
-        let files_view = build_files_column_view(&files_selection, ui_state_for_activate.clone());
+        let files_sort_model =
+            gtk::SortListModel::new(Some(files_tree_model.clone()), None::<gtk::Sorter>);
+        let files_selection = gtk::NoSelection::new(Some(files_sort_model.clone()));
+        let files_view = build_files_column_view(
+            &files_selection,
+            ui_state_for_activate.clone(),
+            files_db_path.clone(),
+        );
+        // Clicking a column header sorts each tree level while keeping matches under their file.
+        files_sort_model.set_sorter(Some(&gtk::TreeListRowSorter::new(files_view.sorter())));
+        apply_column_state(&files_view, &saved_window.columns);
+        let files_view_for_close = files_view.clone();
+        window.connect_close_request(move |window| {
+            save_window_state(window, &files_view_for_close);
+            glib::Propagation::Proceed
+        });
         let files_scroll = gtk::ScrolledWindow::builder()
             .child(&files_view)
             .hscrollbar_policy(gtk::PolicyType::Automatic)
             .vscrollbar_policy(gtk::PolicyType::Automatic)
             .build();
-        let action_bar = build_file_action_bar(ui_state_for_activate.clone());
+        let action_bar = build_file_action_bar(
+            ui_state_for_activate.clone(),
+            files_sort_model.clone().upcast(),
+            files_view.clone(),
+        );
+
+        let root_banner = adw::Banner::new("");
+        root_banner.set_button_label(Some("Locate folder…"));
+        root_banner.connect_button_clicked(glib::clone!(
+            #[weak]
+            window,
+            #[strong]
+            ui_state_for_activate,
+            move |_| {
+                let ui_state = ui_state_for_activate.clone();
+                glib::MainContext::ref_thread_default().spawn_local(async move {
+                    let dialog = gtk::FileDialog::new();
+                    dialog.set_title("Locate the scanned folder");
+                    if let Ok(folder) = dialog.select_folder_future(Some(&window)).await {
+                        if let Some(path) = folder.path() {
+                            relocate_fileset_root(&ui_state, path);
+                        }
+                    }
+                });
+            }
+        ));
+
+        // Checked off the main thread; the result is cached for later scans.
+        let media_banner = adw::Banner::new("");
+        media_banner.set_button_label(Some("Dismiss"));
+        media_banner.connect_button_clicked(|banner| banner.set_revealed(false));
+        main_area.append(&media_banner);
+        let media_banner_for_check = media_banner.clone();
+        glib::MainContext::ref_thread_default().spawn_local(async move {
+            let notice = gio::spawn_blocking(dupdupninja_core::video::media_tools_notice)
+                .await
+                .ok()
+                .flatten();
+            if let Some(notice) = notice {
+                media_banner_for_check.set_title(notice);
+                media_banner_for_check.set_revealed(true);
+            }
+        });
 
         let files_stack = gtk::Stack::new();
         files_stack.add_named(&placeholder, Some("placeholder"));
         let files_container = gtk::Box::new(gtk::Orientation::Vertical, 8);
+        files_container.append(&root_banner);
         files_container.append(&action_bar.container);
+        files_container.append(&filter_bar.container);
         files_container.append(&files_scroll);
         files_stack.add_named(&files_container, Some("files"));
         files_stack.set_visible_child_name("placeholder");
@@ -636,7 +857,9 @@ pub fn run() {
         detail_status_label.set_hexpand(true);
         detail_status_label.set_visible(false);
         detail_status_label.add_css_class("dim-label");
-        detail_status_label.set_wrap(true);
+        // Long paths are cut in the middle; the tooltip carries the full text.
+        detail_status_label.set_ellipsize(gtk::pango::EllipsizeMode::Middle);
+        detail_status_label.set_single_line_mode(true);
         let progress = gtk::ProgressBar::new();
         progress.set_fraction(0.0);
         progress.set_show_text(true);
@@ -672,6 +895,8 @@ pub fn run() {
             next_fileset_id: 1,
             active_fileset_id: None,
             fileset_placeholder: placeholder.clone(),
+            root_banner: root_banner.clone(),
+            notice_banner: media_banner.clone(),
             files_stack: files_stack.clone(),
             files_root_store: files_root_store.clone(),
             files_db_path: files_db_path.clone(),
@@ -681,12 +906,22 @@ pub fn run() {
             snapshots_per_video: startup_settings.snapshots_per_video,
             snapshot_max_dim: startup_settings.snapshot_max_dim,
             concurrent_processing: startup_settings.concurrent_processing,
-            similar_match_cap: startup_settings.similar_match_cap,
+            single_pass_scan: startup_settings.single_pass_scan,
+            include_near_duplicates: startup_settings.include_near_duplicates,
+            near_duplicate_distance: startup_settings.near_duplicate_distance,
+            trash_confirm_threshold: startup_settings.trash_confirm_threshold,
+            files_view: files_view.clone(),
+            pending_folder_scans: std::collections::VecDeque::new(),
             last_files_refresh: None,
             selected_files: std::collections::HashMap::new(),
             action_bar_label: action_bar.label.clone(),
             action_bar_buttons: action_bar.buttons.clone(),
+            type_filter_control: action_bar.type_filter.clone(),
+            type_filter: None,
+            show_ignored_groups: false,
+            root_sort: dupdupninja_core::models::FileListSort::SizeDesc,
             files_load_generation: 0,
+            snapshot_textures: SnapshotTextureCache::default(),
         });
 
         restore_open_filesets(ui_state_for_activate.clone());
@@ -708,6 +943,43 @@ pub fn run() {
             }
         });
 
+        let drop_target = gtk::DropTarget::new(
+            gtk::gdk::FileList::static_type(),
+            gtk::gdk::DragAction::COPY,
+        );
+        let ui_state_for_drop = ui_state_for_activate.clone();
+        drop_target.connect_drop(move |_, value, _, _| {
+            let Ok(files) = value.get::<gtk::gdk::FileList>() else {
+                return false;
+            };
+            let (folders, others): (Vec<_>, Vec<_>) = files
+                .files()
+                .into_iter()
+                .filter_map(|file| file.path())
+                .partition(|path| path.is_dir());
+            let status_label = ui_state_for_drop
+                .borrow()
+                .as_ref()
+                .map(|state| state.status_label.clone());
+            if folders.is_empty() {
+                if let Some(label) = status_label {
+                    label.set_text("Status: Only folders can be dropped to scan");
+                }
+                return false;
+            }
+            queue_folder_scans(&ui_state_for_drop, folders);
+            if !others.is_empty() {
+                if let Some(label) = status_label {
+                    label.set_text(&format!(
+                        "Status: Scanning dropped folders (ignored {} items that are not folders)",
+                        others.len()
+                    ));
+                }
+            }
+            true
+        });
+        window.add_controller(drop_target);
+
         let ui_state_for_cancel = ui_state_for_activate.clone();
         cancel_button.connect_clicked(move |_| {
             if let Some(state) = ui_state_for_cancel.borrow().as_ref() {
@@ -750,6 +1022,7 @@ pub fn run() {
                             state.status_label.set_text(&text);
                             if let Some(detail) = detail {
                                 state.detail_status_label.set_text(&detail);
+                                state.detail_status_label.set_tooltip_text(Some(&detail));
                                 state.detail_status_label.set_visible(true);
                             } else {
                                 state.detail_status_label.set_visible(false);
@@ -765,6 +1038,23 @@ pub fn run() {
                                 state.progress.set_text(Some("Scanning..."));
                                 state.progress.pulse();
                             }
+                            let refresh_due = state
+                                .last_files_refresh
+                                .map_or(true, |at| at.elapsed() >= FILES_REFRESH_INTERVAL);
+                            if refresh_due
+                                && state.active_scan_fileset_id.is_some()
+                                && state.active_scan_fileset_id == state.active_fileset_id
+                            {
+                                state.last_files_refresh = Some(std::time::Instant::now());
+                                if let Some(db_path) = state
+                                    .filesets
+                                    .iter()
+                                    .find(|entry| Some(entry.id) == state.active_fileset_id)
+                                    .map(|entry| entry.db_path.clone())
+                                {
+                                    request_fileset_rows(state, &db_path, true);
+                                }
+                            }
                         }
                         UiUpdate::Done { text } => {
                             state.status_label.set_text(&text);
@@ -784,7 +1074,7 @@ pub fn run() {
                                     state.filesets.iter().find(|entry| entry.id == active_id)
                                 {
                                     let db_path = entry.db_path.clone();
-                                    load_fileset_rows(state, &db_path);
+                                    request_fileset_rows(state, &db_path, true);
                                 }
                             }
                             if let Some(fileset_id) = state.active_scan_fileset_id.take() {
@@ -805,6 +1095,8 @@ pub fn run() {
                             state.total_bytes = 0;
                             state.last_files_refresh = None;
                             state.active_scan_fileset_id = None;
+                            // Cancelling stops the rest of a multi-folder drop as well.
+                            state.pending_folder_scans.clear();
                             set_fileset_scanning(state, fileset_id, false);
                             set_fileset_status(state, fileset_id, "incomplete");
                             set_scan_actions_enabled(state, true);
@@ -831,12 +1123,22 @@ pub fn run() {
                             generation,
                             rows,
                             note,
+                            file_types,
+                            duplicates,
+                            refresh,
                         } => {
                             if state.active_fileset_id != Some(fileset_id)
                                 || state.files_load_generation != generation
                             {
                                 continue;
                             }
+                            state
+                                .type_filter_control
+                                .set_available(&file_types, state.type_filter);
+                            if refresh {
+                                merge_match_roots(state, rows);
+                                continue;
+                            }
                             state.files_root_store.remove_all();
                             let matched_roots = rows.len();
                             for row in rows {
@@ -848,17 +1150,26 @@ pub fn run() {
                             }
                             state.selected_files.clear();
                             update_action_bar_state(state);
+                            let summary = duplicates
+                                .filter(|(groups, _)| *groups > 0)
+                                .map(|(groups, bytes)| {
+                                    format!(
+                                        " · {groups} duplicate groups · {} reclaimable",
+                                        dupdupninja_core::format::format_bytes(bytes)
+                                    )
+                                })
+                                .unwrap_or_default();
                             if matched_roots == 0 {
                                 state
                                     .status_label
                                     .set_text("Status: No exact/similar matches in this fileset");
                             } else if let Some(note) = note {
                                 state.status_label.set_text(&format!(
-                                    "Status: Loaded {matched_roots} matched roots ({note})"
+                                    "Status: Loaded {matched_roots} matched roots ({note}){summary}"
                                 ));
                             } else {
                                 state.status_label.set_text(&format!(
-                                    "Status: Loaded {matched_roots} matched roots"
+                                    "Status: Loaded {matched_roots} matched roots{summary}"
                                 ));
                             }
                         }
@@ -877,21 +1188,76 @@ pub fn run() {
                     }
                 }
             }
+            start_next_queued_scan(&ui_state_for_updates);
             glib::ControlFlow::Continue
         });
 
         window.present();
-        window.maximize();
+        if saved_window.maximized {
+            window.maximize();
+        }
     });
 
     let ui_state_for_shutdown = ui_state.clone();
-    app.connect_shutdown(move |_| {
+    app.connect_shutdown(move |app| {
         persist_open_filesets(ui_state_for_shutdown.clone());
+        // Quitting via app.quit skips close-request, so save what is still open.
+        if let (Some(window), Some(state)) =
+            (app.active_window(), ui_state_for_shutdown.borrow().as_ref())
+        {
+            save_window_state(&window, &state.files_view);
+        }
     });
 
     app.run();
 }
 
+#[cfg(all(target_os = "linux", feature = "gtk"))]
+fn start_folder_scan(
+    ui_state: std::rc::Rc<std::cell::RefCell<Option<UiState>>>,
+    path: std::path::PathBuf,
+) {
+    let db_path = scan_db_path(&ui_state, &path);
+    let name = fileset_name_from_path(&path);
+    let fileset_id = add_fileset(ui_state.clone(), name, db_path.clone());
+    start_scan(
+        ui_state,
+        path,
+        dupdupninja_core::ScanRootKind::Folder,
+        db_path,
+        fileset_id,
+    );
+}
+
+/// Folders are scanned one at a time, so extra dropped folders wait their turn.
+#[cfg(all(target_os = "linux", feature = "gtk"))]
+fn queue_folder_scans(
+    ui_state: &std::rc::Rc<std::cell::RefCell<Option<UiState>>>,
+    folders: Vec<std::path::PathBuf>,
+) {
+    if let Some(state) = ui_state.borrow_mut().as_mut() {
+        state.pending_folder_scans.extend(folders);
+    }
+    start_next_queued_scan(ui_state);
+}
+
+#[cfg(all(target_os = "linux", feature = "gtk"))]
+fn start_next_queued_scan(ui_state: &std::rc::Rc<std::cell::RefCell<Option<UiState>>>) {
+    let next = {
+        let mut state = ui_state.borrow_mut();
+        let Some(state) = state.as_mut() else {
+            return;
+        };
+        if state.active_scan_fileset_id.is_some() {
+            return;
+        }
+        state.pending_folder_scans.pop_front()
+    };
+    if let Some(path) = next {
+        start_folder_scan(ui_state.clone(), path);
+    }
+}
+
 #[cfg(all(target_os = "linux", feature = "gtk"))]
 fn start_scan(
     ui_state: std::rc::Rc<std::cell::RefCell<Option<UiState>>>,
@@ -910,6 +1276,7 @@ fn start_scan(
         snapshots_per_video,
         snapshot_max_dim,
         concurrent_processing,
+        single_pass,
     ) = {
         let state = ui_state.borrow();
         let Some(state) = state.as_ref() else {
@@ -924,6 +1291,7 @@ fn start_scan(
             state.snapshots_per_video,
             state.snapshot_max_dim,
             state.concurrent_processing,
+            state.single_pass_scan,
         )
     };
 
@@ -957,16 +1325,16 @@ fn start_scan(
         let cfg = dupdupninja_core::scan::ScanConfig {
             root: root.clone(),
             root_kind,
-            hash_files: true,
-            perceptual_hashes: true,
             capture_snapshots,
             snapshots_per_video,
             snapshot_max_dim,
             concurrent_processing,
+            single_pass,
+            ..Default::default()
         };
 
         let prescan_result =
-            dupdupninja_core::scan::prescan(&cfg, Some(&cancel_token), |progress| {
+            dupdupninja_core::scan::prescan_for_scan(&cfg, Some(&cancel_token), |progress| {
                 let folder = progress
                     .current_path
                     .file_name()
@@ -979,8 +1347,8 @@ fn start_scan(
                 let _ = update_tx.send(UiUpdate::PrescanProgress { text });
             });
 
-        let totals = match prescan_result {
-            Ok(totals) => totals,
+        let prescanned = match prescan_result {
+            Ok(prescanned) => prescanned,
             Err(dupdupninja_core::Error::Cancelled) => {
                 let _ = update_tx.send(UiUpdate::Cancelled {
                     text: "Status: Scan cancelled".to_string(),
@@ -996,13 +1364,16 @@ fn start_scan(
             }
         };
 
-        let _ = update_tx.send(UiUpdate::PrescanDone { totals });
+        let _ = update_tx.send(UiUpdate::PrescanDone {
+            totals: prescanned.totals,
+        });
 
-        let result = dupdupninja_core::scan::scan_to_sqlite_with_progress_and_totals(
+        let mut recent_warnings = std::collections::VecDeque::new();
+        let result = dupdupninja_core::scan::scan_with_prescan(
             &cfg,
             &store,
             Some(&cancel_token),
-            Some(totals),
+            prescanned,
             |progress_update| {
                 let path = progress_update
                     .current_path
@@ -1010,18 +1381,16 @@ fn start_scan(
                     .and_then(|p| p.file_name())
                     .and_then(|p| p.to_str())
                     .unwrap_or("folder");
-                let detail = progress_update.current_step.as_deref().map(|step| {
-                    let file = progress_update
-                        .current_path
-                        .file_name()
-                        .and_then(|p| p.to_str())
-                        .unwrap_or("file");
-                    format!("{file} • {step}")
-                });
-                let text = format!(
-                    "Status: Scanning {} ({} / {} files)",
+                let detail = scan_progress_detail(progress_update, &mut recent_warnings);
+                let mut text = format!(
+                    "Status: Scanning {} ({} / {} files",
                     path, progress_update.files_seen, progress_update.total_files
                 );
+                if let Some(eta) = progress_update.eta_secs {
+                    text.push_str(", ");
+                    text.push_str(&dupdupninja_core::format::format_eta(eta));
+                }
+                text.push(')');
                 let fraction = if progress_update.total_files > 0 {
                     Some(progress_update.files_seen as f64 / progress_update.total_files as f64)
                 } else {
@@ -1037,15 +1406,26 @@ fn start_scan(
 
         let update = match result {
             Ok(result) => UiUpdate::Done {
-                text: format!(
-                    "Status: Scan complete ({} files, {} hashed, {} skipped)",
-                    result.stats.files_seen, result.stats.files_hashed, result.stats.files_skipped
+                text: with_warnings_summary(
+                    format!(
+                        "Status: Scan complete ({} files, {} hashed, {} skipped, {} filtered)",
+                        result.stats.files_seen,
+                        result.stats.files_hashed,
+                        result.stats.files_skipped,
+                        result.stats.files_filtered
+                    ),
+                    &result,
                 ),
             },
             Err(dupdupninja_core::Error::Cancelled) => UiUpdate::Cancelled {
                 text: "Status: Scan cancelled".to_string(),
                 fileset_id,
             },
+            // Same outcome as a cancel: the fileset stays incomplete and queued folders are dropped.
+            Err(err @ dupdupninja_core::Error::DiskFull(_)) => UiUpdate::Cancelled {
+                text: format!("Status: Scan stopped, {err}. Free some space and rescan."),
+                fileset_id,
+            },
             Err(err) => UiUpdate::Error {
                 text: format!("Status: Scan error: {err}"),
             },
@@ -1055,21 +1435,194 @@ fn start_scan(
 }
 
 #[cfg(all(target_os = "linux", feature = "gtk"))]
-fn scan_db_path(root: &std::path::Path) -> std::path::PathBuf {
+fn start_snapshot_regeneration(
+    ui_state: std::rc::Rc<std::cell::RefCell<Option<UiState>>>,
+    fileset_id: u64,
+) {
+    use gtk4::prelude::WidgetExt;
+    let cancel_token = dupdupninja_core::scan::ScanCancelToken::new();
+    let (db_path, root, update_tx, snapshots_per_video, snapshot_max_dim) = {
+        let mut state = ui_state.borrow_mut();
+        let Some(state) = state.as_mut() else {
+            return;
+        };
+        if state.cancel_token.is_some() {
+            return;
+        }
+        let Some(entry) = state.filesets.iter().find(|entry| entry.id == fileset_id) else {
+            return;
+        };
+        let db_path = entry.db_path.clone();
+        let root = resolve_root_path(&entry.metadata);
+        state.cancel_token = Some(cancel_token.clone());
+        state
+            .status_label
+            .set_text("Status: Regenerating snapshots...");
+        state.progress.set_text(Some("Scanning..."));
+        state.progress.pulse();
+        state.progress.set_visible(true);
+        state.cancel_button.set_sensitive(true);
+        state.cancel_button.set_visible(true);
+        set_scan_actions_enabled(state, false);
+        (
+            db_path,
+            root,
+            state.update_tx.clone(),
+            state.snapshots_per_video.max(1),
+            state.snapshot_max_dim.max(1),
+        )
+    };
+
+    std::thread::spawn(move || {
+        let store = match dupdupninja_core::db::SqliteScanStore::open(&db_path) {
+            Ok(store) => store,
+            Err(err) => {
+                let msg = format!("Status: DB error: {err}");
+                let _ = update_tx.send(UiUpdate::Error { text: msg });
+                return;
+            }
+        };
+
+        let cfg = dupdupninja_core::scan::ScanConfig {
+            root: root.clone(),
+            capture_snapshots: true,
+            snapshots_per_video,
+            snapshot_max_dim,
+            ..Default::default()
+        };
+
+        let mut recent_warnings = std::collections::VecDeque::new();
+        let result = dupdupninja_core::scan::regenerate_snapshots(
+            &store,
+            &root,
+            &cfg,
+            Some(&cancel_token),
+            |progress_update| {
+                let mut text = format!(
+                    "Status: Regenerating snapshots ({} / {} files",
+                    progress_update.files_seen, progress_update.total_files
+                );
+                if let Some(eta) = progress_update.eta_secs {
+                    text.push_str(", ");
+                    text.push_str(&dupdupninja_core::format::format_eta(eta));
+                }
+                text.push(')');
+                let detail = scan_progress_detail(progress_update, &mut recent_warnings);
+                let fraction = if progress_update.total_files > 0 {
+                    Some(progress_update.files_seen as f64 / progress_update.total_files as f64)
+                } else {
+                    None
+                };
+                let _ = update_tx.send(UiUpdate::Progress {
+                    text,
+                    detail,
+                    fraction,
+                });
+            },
+        );
+
+        // `Cancelled` would mark the fileset incomplete, which a partial snapshot pass does not.
+        let update = match result {
+            Ok(result) => UiUpdate::Done {
+                text: with_warnings_summary(
+                    format!(
+                        "Status: Snapshots complete ({} files, {} captured, {} failed)",
+                        result.stats.files_seen,
+                        result.stats.files_hashed,
+                        result.stats.files_skipped
+                    ),
+                    &result,
+                ),
+            },
+            Err(dupdupninja_core::Error::Cancelled) => UiUpdate::Done {
+                text: "Status: Snapshot regeneration cancelled".to_string(),
+            },
+            Err(err) => UiUpdate::Error {
+                text: format!("Status: Snapshot error: {err}"),
+            },
+        };
+        let _ = update_tx.send(update);
+    });
+}
+
+/// Unreadable files named in the status detail line while a scan runs.
+#[cfg(all(target_os = "linux", feature = "gtk"))]
+const DETAIL_RECENT_WARNINGS: usize = 3;
+
+#[cfg(all(target_os = "linux", feature = "gtk"))]
+fn scan_progress_detail(
+    progress: &dupdupninja_core::scan::ScanProgress,
+    recent_warnings: &mut std::collections::VecDeque<String>,
+) -> Option<String> {
+    if let Some(warning) = &progress.warning {
+        if recent_warnings.len() >= DETAIL_RECENT_WARNINGS {
+            recent_warnings.pop_front();
+        }
+        let name = warning
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| warning.path.display().to_string());
+        recent_warnings.push_back(format!("{name} ({})", warning.reason));
+    }
+    let detail = progress
+        .current_step
+        .as_deref()
+        .map(|step| format!("{step}: {}", progress.current_path.display()));
+    if recent_warnings.is_empty() {
+        return detail;
+    }
+    let unreadable = format!(
+        "unreadable: {}",
+        recent_warnings
+            .iter()
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    Some(match detail {
+        Some(detail) => format!("{detail} | {unreadable}"),
+        None => unreadable,
+    })
+}
+
+#[cfg(all(target_os = "linux", feature = "gtk"))]
+fn with_warnings_summary(text: String, result: &dupdupninja_core::models::ScanResult) -> String {
+    match result.warnings_summary() {
+        Some(summary) => format!("{text}, {summary}"),
+        None => text,
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "gtk"))]
+/// Falls back to the temp dir, with a notice, when the fileset folder cannot be
+/// written.
+fn scan_db_path(
+    ui_state: &std::rc::Rc<std::cell::RefCell<Option<UiState>>>,
+    root: &std::path::Path,
+) -> std::path::PathBuf {
     let ts = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap_or_default()
         .as_secs();
     let name = sanitize_fileset_name(root);
     let file_name = format!("{name}-{ts}.ddn");
-    let mut base = effective_fileset_dir();
-    if std::fs::create_dir_all(&base).is_err() {
-        let mut fallback = std::env::temp_dir();
-        fallback.push(file_name);
-        return fallback;
+    let base = effective_fileset_dir();
+    match dupdupninja_core::files::ensure_writable_dir(&base) {
+        Ok(()) => base.join(file_name),
+        Err(err) => {
+            let fallback = std::env::temp_dir().join(file_name);
+            if let Some(state) = ui_state.borrow().as_ref() {
+                state.notice_banner.set_title(&format!(
+                    "Cannot write filesets to {} ({err}); this one is saved in {}",
+                    base.display(),
+                    fallback.display()
+                ));
+                state.notice_banner.set_revealed(true);
+            }
+            fallback
+        }
     }
-    base.push(file_name);
-    base
 }
 
 #[cfg(all(target_os = "linux", feature = "gtk"))]
@@ -1088,7 +1641,15 @@ struct GtkSettings {
     snapshots_per_video: u32,
     snapshot_max_dim: u32,
     concurrent_processing: bool,
-    similar_match_cap: usize,
+    /// Let prescan keep its walk so the scan does not walk the tree again.
+    single_pass_scan: bool,
+    include_near_duplicates: bool,
+    near_duplicate_distance: u32,
+    /// Trash asks for confirmation above this many files; 0 never asks.
+    trash_confirm_threshold: usize,
+    /// Command the compare window runs on two files, with `%parent` and `%match`
+    /// placeholders. Empty when none is configured.
+    external_compare_command: String,
 }
 
 #[cfg(all(target_os = "linux", feature = "gtk"))]
@@ -1100,11 +1661,27 @@ impl Default for GtkSettings {
             snapshots_per_video: 3,
             snapshot_max_dim: 1024,
             concurrent_processing: true,
-            similar_match_cap: 2200,
+            single_pass_scan: false,
+            include_near_duplicates: true,
+            near_duplicate_distance: 8,
+            trash_confirm_threshold: 0,
+            external_compare_command: String::new(),
         }
     }
 }
 
+#[cfg(all(target_os = "linux", feature = "gtk"))]
+fn clamp_snapshots_per_video(value: u32) -> u32 {
+    let range = ScanConfig::SNAPSHOTS_PER_VIDEO;
+    value.clamp(*range.start(), *range.end())
+}
+
+#[cfg(all(target_os = "linux", feature = "gtk"))]
+fn clamp_snapshot_max_dim(value: u32) -> u32 {
+    let range = ScanConfig::SNAPSHOT_MAX_DIM;
+    value.clamp(*range.start(), *range.end())
+}
+
 #[cfg(all(target_os = "linux", feature = "gtk"))]
 fn effective_fileset_dir() -> std::path::PathBuf {
     load_settings()
@@ -1148,22 +1725,37 @@ fn load_settings() -> GtkSettings {
             }
             "snapshots_per_video" => {
                 if let Ok(v) = value.parse::<u32>() {
-                    settings.snapshots_per_video = v.clamp(1, 10);
+                    settings.snapshots_per_video = clamp_snapshots_per_video(v);
                 }
             }
             "snapshot_max_dim" => {
                 if let Ok(v) = value.parse::<u32>() {
-                    settings.snapshot_max_dim = v.clamp(128, 2048);
+                    settings.snapshot_max_dim = clamp_snapshot_max_dim(v);
                 }
             }
             "concurrent_processing" => {
                 settings.concurrent_processing = value == "1" || value.eq_ignore_ascii_case("true");
             }
-            "similar_match_cap" => {
+            "single_pass_scan" => {
+                settings.single_pass_scan = value == "1" || value.eq_ignore_ascii_case("true");
+            }
+            "include_near_duplicates" => {
+                settings.include_near_duplicates =
+                    value == "1" || value.eq_ignore_ascii_case("true");
+            }
+            "near_duplicate_distance" => {
+                if let Ok(v) = value.parse::<u32>() {
+                    settings.near_duplicate_distance = v.min(32);
+                }
+            }
+            "trash_confirm_threshold" => {
                 if let Ok(v) = value.parse::<usize>() {
-                    settings.similar_match_cap = v.clamp(200, 20_000);
+                    settings.trash_confirm_threshold = v.min(10_000);
                 }
             }
+            "external_compare_command" => {
+                settings.external_compare_command = value.to_string();
+            }
             _ => {}
         }
     }
@@ -1182,10 +1774,10 @@ fn save_settings(settings: &GtkSettings) -> std::io::Result<()> {
     contents.push_str(if settings.capture_snapshots { "1" } else { "0" });
     contents.push('\n');
     contents.push_str("snapshots_per_video=");
-    contents.push_str(&settings.snapshots_per_video.clamp(1, 10).to_string());
+    contents.push_str(&clamp_snapshots_per_video(settings.snapshots_per_video).to_string());
     contents.push('\n');
     contents.push_str("snapshot_max_dim=");
-    contents.push_str(&settings.snapshot_max_dim.clamp(128, 2048).to_string());
+    contents.push_str(&clamp_snapshot_max_dim(settings.snapshot_max_dim).to_string());
     contents.push('\n');
     contents.push_str("concurrent_processing=");
     contents.push_str(if settings.concurrent_processing {
@@ -1194,9 +1786,28 @@ fn save_settings(settings: &GtkSettings) -> std::io::Result<()> {
         "0"
     });
     contents.push('\n');
-    contents.push_str("similar_match_cap=");
-    contents.push_str(&settings.similar_match_cap.clamp(200, 20_000).to_string());
+    contents.push_str("single_pass_scan=");
+    contents.push_str(if settings.single_pass_scan { "1" } else { "0" });
     contents.push('\n');
+    contents.push_str("include_near_duplicates=");
+    contents.push_str(if settings.include_near_duplicates {
+        "1"
+    } else {
+        "0"
+    });
+    contents.push('\n');
+    contents.push_str("near_duplicate_distance=");
+    contents.push_str(&settings.near_duplicate_distance.min(32).to_string());
+    contents.push('\n');
+    contents.push_str("trash_confirm_threshold=");
+    contents.push_str(&settings.trash_confirm_threshold.min(10_000).to_string());
+    contents.push('\n');
+    let external_command = settings.external_compare_command.trim();
+    if !external_command.is_empty() {
+        contents.push_str("external_compare_command=");
+        contents.push_str(&external_command.replace('\n', " "));
+        contents.push('\n');
+    }
     if let Some(dir) = &settings.fileset_dir {
         contents.push_str("fileset_dir=");
         contents.push_str(&dir.display().to_string());
@@ -1212,7 +1823,10 @@ fn persist_scan_settings_from_ui_state(ui_state: std::rc::Rc<std::cell::RefCell<
         snapshots_per_video,
         snapshot_max_dim,
         concurrent_processing,
-        similar_match_cap,
+        single_pass_scan,
+        include_near_duplicates,
+        near_duplicate_distance,
+        trash_confirm_threshold,
     ) = {
         let state = ui_state.borrow();
         let Some(state) = state.as_ref() else {
@@ -1223,16 +1837,22 @@ fn persist_scan_settings_from_ui_state(ui_state: std::rc::Rc<std::cell::RefCell<
             state.snapshots_per_video,
             state.snapshot_max_dim,
             state.concurrent_processing,
-            state.similar_match_cap,
+            state.single_pass_scan,
+            state.include_near_duplicates,
+            state.near_duplicate_distance,
+            state.trash_confirm_threshold,
         )
     };
 
     let mut settings = load_settings();
     settings.capture_snapshots = capture_snapshots;
-    settings.snapshots_per_video = snapshots_per_video.clamp(1, 10);
-    settings.snapshot_max_dim = snapshot_max_dim.clamp(128, 2048);
+    settings.snapshots_per_video = clamp_snapshots_per_video(snapshots_per_video);
+    settings.snapshot_max_dim = clamp_snapshot_max_dim(snapshot_max_dim);
     settings.concurrent_processing = concurrent_processing;
-    settings.similar_match_cap = similar_match_cap.clamp(200, 20_000);
+    settings.single_pass_scan = single_pass_scan;
+    settings.include_near_duplicates = include_near_duplicates;
+    settings.near_duplicate_distance = near_duplicate_distance.min(32);
+    settings.trash_confirm_threshold = trash_confirm_threshold.min(10_000);
     let _ = save_settings(&settings);
 }
 
@@ -1326,6 +1946,10 @@ fn add_fileset(
     let menu_model = gio::Menu::new();
     menu_model.append(Some("Close"), Some("fileset.close"));
     menu_model.append_section(None, &gio::Menu::new());
+    menu_model.append(
+        Some("Regenerate Snapshots"),
+        Some("fileset.regenerate_snapshots"),
+    );
     menu_model.append(Some("Properties"), Some("fileset.properties"));
     menu_button.set_menu_model(Some(&menu_model));
     action_row.add_suffix(&menu_button);
@@ -1364,7 +1988,16 @@ fn add_fileset(
             }
         }
     ));
+    let regenerate_action = gio::SimpleAction::new("regenerate_snapshots", None);
+    regenerate_action.connect_activate(glib::clone!(
+        #[strong]
+        close_handler_state,
+        move |_, _| {
+            start_snapshot_regeneration(close_handler_state.clone(), id);
+        }
+    ));
     action_group.add_action(&close_action);
+    action_group.add_action(&regenerate_action);
     action_group.add_action(&properties_action);
     row.insert_action_group("fileset", Some(&action_group));
 
@@ -1387,6 +2020,7 @@ fn add_fileset(
             action_row: action_row.clone(),
             row: row.clone(),
             metadata,
+            pool: std::cell::OnceCell::new(),
         });
         state.active_fileset_id = Some(id);
         update_fileset_placeholder(state);
@@ -1456,6 +2090,33 @@ fn load_fileset_metadata(
     }
 }
 
+#[cfg(all(target_os = "linux", feature = "gtk"))]
+fn show_open_fileset_error(
+    window: &gtk4::Window,
+    path: &std::path::Path,
+    err: &dupdupninja_core::Error,
+) {
+    use adw::prelude::*;
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string());
+    let body = match err {
+        dupdupninja_core::Error::NotAFileset(_) => format!(
+            "{file_name} is not a DupdupNinja fileset. Choose a .ddn file created by a scan."
+        ),
+        dupdupninja_core::Error::Migration(_) => format!(
+            "{file_name} was created by a newer version of DupdupNinja. Update the app to open it."
+        ),
+        other => format!("{file_name} could not be opened: {other}"),
+    };
+    let dialog = adw::AlertDialog::new(Some("Cannot open fileset"), Some(&body));
+    dialog.add_response("close", "Close");
+    dialog.set_default_response(Some("close"));
+    dialog.set_close_response("close");
+    dialog.present(Some(window));
+}
+
 #[cfg(all(target_os = "linux", feature = "gtk"))]
 fn apply_fileset_metadata(row: &adw::ActionRow, meta: &dupdupninja_core::FilesetMetadata) {
     use adw::prelude::*;
@@ -1493,7 +2154,7 @@ fn set_fileset_status(state: &mut UiState, fileset_id: u64, status: &str) {
         .iter_mut()
         .find(|entry| entry.id == fileset_id)
     {
-        if let Ok(store) = dupdupninja_core::db::SqliteScanStore::open(&entry.db_path) {
+        if let Ok(store) = entry.store() {
             if let Ok(Some(meta)) = store.get_fileset_metadata() {
                 entry.metadata = meta;
             }
@@ -1619,6 +2280,19 @@ fn update_fileset_placeholder(state: &mut UiState) {
             state
                 .fileset_placeholder
                 .set_text(&format!("Active fileset: {}", name));
+            // A store that fails to open is reported by the row load; only flag a
+            // root we know is gone.
+            let root_missing = !entry
+                .store()
+                .and_then(|store| store.root_exists())
+                .unwrap_or(true);
+            if root_missing {
+                state.root_banner.set_title(&format!(
+                    "Scanned folder not found: {}. File actions need it.",
+                    resolve_root_path(&entry.metadata).display()
+                ));
+            }
+            state.root_banner.set_revealed(root_missing);
             state.files_stack.set_visible_child_name("files");
             *state.files_db_path.borrow_mut() = Some(db_path.clone());
             load_fileset_rows(state, &db_path);
@@ -1629,17 +2303,71 @@ fn update_fileset_placeholder(state: &mut UiState) {
         .fileset_placeholder
         .set_text("Select a fileset to view results.");
     state.files_stack.set_visible_child_name("placeholder");
+    state.root_banner.set_revealed(false);
     *state.files_db_path.borrow_mut() = None;
     state.files_root_store.remove_all();
     state.selected_files.clear();
     update_action_bar_state(state);
 }
 
+/// Points the active fileset at `folder`, for a root that moved or was remounted.
+#[cfg(all(target_os = "linux", feature = "gtk"))]
+fn relocate_fileset_root(
+    ui_state: &std::rc::Rc<std::cell::RefCell<Option<UiState>>>,
+    folder: std::path::PathBuf,
+) {
+    let mut state_ref = ui_state.borrow_mut();
+    let Some(state) = state_ref.as_mut() else {
+        return;
+    };
+    let Some(active_id) = state.active_fileset_id else {
+        return;
+    };
+    let Some(entry) = state
+        .filesets
+        .iter_mut()
+        .find(|entry| entry.id == active_id)
+    else {
+        return;
+    };
+    let mut metadata = entry.metadata.clone();
+    metadata.root_path = folder.clone();
+    metadata.root_parent_path = folder.parent().map(std::path::Path::to_path_buf);
+    match entry
+        .store()
+        .and_then(|store| store.set_fileset_metadata(&metadata))
+    {
+        Ok(()) => {
+            entry.metadata = metadata;
+            state
+                .status_label
+                .set_text(&format!("Status: Fileset root set to {}", folder.display()));
+            update_fileset_placeholder(state);
+        }
+        Err(err) => {
+            state
+                .status_label
+                .set_text(&format!("Status: Failed to update fileset root: {err}"));
+        }
+    }
+}
+
 #[cfg(all(target_os = "linux", feature = "gtk"))]
 fn load_fileset_rows(state: &mut UiState, db_path: &std::path::Path) {
     state.files_root_store.remove_all();
     state.selected_files.clear();
     update_action_bar_state(state);
+    request_fileset_rows(state, db_path, false);
+}
+
+/// How often a scan of the shown fileset reloads its matches.
+#[cfg(all(target_os = "linux", feature = "gtk"))]
+const FILES_REFRESH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Loads the active fileset's matches on a worker thread. A `refresh` is merged into
+/// the current list, keeping expansion and selections, rather than replacing it.
+#[cfg(all(target_os = "linux", feature = "gtk"))]
+fn request_fileset_rows(state: &mut UiState, db_path: &std::path::Path, refresh: bool) {
     let Some(fileset_id) = state.active_fileset_id else {
         return;
     };
@@ -1647,21 +2375,62 @@ fn load_fileset_rows(state: &mut UiState, db_path: &std::path::Path) {
     state.files_load_generation = state.files_load_generation.saturating_add(1);
     let generation = state.files_load_generation;
     let update_tx = state.update_tx.clone();
-    let db_path = db_path.to_path_buf();
-    let similar_match_cap = state.similar_match_cap.clamp(200, 20_000);
-
-    state
-        .status_label
-        .set_text("Status: Loading exact/similar matches...");
+    let pool = match state
+        .filesets
+        .iter()
+        .find(|entry| entry.db_path == db_path)
+        .map(|entry| entry.store_pool())
+    {
+        Some(Ok(pool)) => pool,
+        Some(Err(err)) => {
+            state
+                .status_label
+                .set_text(&format!("Status: Failed to open fileset: {err}"));
+            return;
+        }
+        None => return,
+    };
+    let near_distance = state
+        .include_near_duplicates
+        .then(|| state.near_duplicate_distance.min(32));
+    let type_filter = state.type_filter;
+    let include_ignored = state.show_ignored_groups;
+    let root_sort = state.root_sort;
+
+    if !refresh {
+        state
+            .status_label
+            .set_text("Status: Loading exact/similar matches...");
+    }
 
-    std::thread::spawn(
-        move || match compute_match_roots(&db_path, similar_match_cap) {
-            Ok((rows, note)) => {
+    std::thread::spawn(move || {
+        match compute_match_roots(
+            &pool,
+            near_distance,
+            type_filter,
+            include_ignored,
+            root_sort,
+        ) {
+            Ok(LoadedMatches {
+                rows,
+                note,
+                file_types,
+            }) => {
+                // COUNT/SUM queries, so this stays cheap however many rows were loaded.
+                let duplicates = pool.get().ok().and_then(|store| {
+                    Some((
+                        store.duplicate_group_count().ok()?,
+                        store.reclaimable_bytes().ok()?,
+                    ))
+                });
                 let _ = update_tx.send(UiUpdate::FilesLoaded {
                     fileset_id,
                     generation,
                     rows,
                     note,
+                    file_types,
+                    duplicates,
+                    refresh,
                 });
             }
             Err(err) => {
@@ -1671,19 +2440,27 @@ fn load_fileset_rows(state: &mut UiState, db_path: &std::path::Path) {
                     text: format!("Status: Failed to load matches: {err}"),
                 });
             }
-        },
-    );
+        }
+    });
+}
+
+/// What `compute_match_roots` hands back to the UI thread.
+#[cfg(all(target_os = "linux", feature = "gtk"))]
+struct LoadedMatches {
+    rows: Vec<MatchRootData>,
+    note: Option<String>,
+    file_types: Vec<dupdupninja_core::models::FileTypeFilter>,
 }
 
 #[cfg(all(target_os = "linux", feature = "gtk"))]
 fn compute_match_roots(
-    db_path: &std::path::Path,
-    similar_match_cap: usize,
-) -> Result<(Vec<MatchRootData>, Option<String>), String> {
+    pool: &dupdupninja_core::db::SqliteStorePool,
+    near_distance: Option<u32>,
+    type_filter: Option<dupdupninja_core::models::FileTypeFilter>,
+    include_ignored: bool,
+    root_sort: dupdupninja_core::models::FileListSort,
+) -> Result<LoadedMatches, String> {
     const PAGE_SIZE: usize = 1000;
-    const THRESH_AHASH: u32 = 10;
-    const THRESH_DHASH: u32 = 10;
-    const THRESH_PHASH: u32 = 8;
 
     #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
     enum ExactKey {
@@ -1691,15 +2468,19 @@ fn compute_match_roots(
         Sha256([u8; 32]),
     }
 
-    let store = dupdupninja_core::db::SqliteScanStore::open(db_path).map_err(|e| e.to_string())?;
+    let store = pool.get().map_err(|e| e.to_string())?;
     let mut roots: std::collections::HashMap<i64, MatchRootData> = std::collections::HashMap::new();
 
     let mut exact_rows = Vec::new();
     let mut exact_offset = 0;
     loop {
-        let rows = store
-            .list_files_with_duplicates(PAGE_SIZE, exact_offset)
-            .map_err(|e| e.to_string())?;
+        let rows = match type_filter {
+            Some(filter) => {
+                store.list_files_by_type(filter, true, include_ignored, PAGE_SIZE, exact_offset)
+            }
+            None => store.list_files_with_duplicates(include_ignored, PAGE_SIZE, exact_offset),
+        }
+        .map_err(|e| e.to_string())?;
         if rows.is_empty() {
             break;
         }
@@ -1730,82 +2511,68 @@ fn compute_match_roots(
         if members.is_empty() {
             continue;
         }
-        insert_match_group(&mut roots, anchor, "Exact matches".to_string(), members);
-    }
-
-    let mut similar_rows = Vec::new();
-    let mut similar_offset = 0;
-    let mut similar_limited = false;
-    'similar_load: loop {
-        let rows = store
-            .list_files_with_hashes(PAGE_SIZE, similar_offset)
-            .map_err(|e| e.to_string())?;
-        if rows.is_empty() {
-            break;
-        }
-        similar_offset += PAGE_SIZE;
-        for row in rows {
-            if similar_rows.len() >= similar_match_cap {
-                similar_limited = true;
-                break 'similar_load;
-            }
-            similar_rows.push(row);
-        }
+        insert_match_group(&mut roots, anchor, EXACT_MATCHES_LABEL.to_string(), members);
     }
 
-    let mut assigned = vec![false; similar_rows.len()];
-    for i in 0..similar_rows.len() {
-        if assigned[i] {
-            continue;
-        }
-        let mut members = Vec::new();
-        for j in (i + 1)..similar_rows.len() {
-            if assigned[j] {
+    if let Some(max_hamming) = near_distance {
+        let label = format!("Near matches (distance ≤ {max_hamming})");
+        let in_filter = |row: &dupdupninja_core::models::FileListRow| {
+            type_filter.map_or(true, |filter| {
+                dupdupninja_core::models::FileTypeFilter::of(row.file_type.as_deref()) == filter
+            })
+        };
+        for group in store
+            .list_near_image_duplicates(max_hamming)
+            .map_err(|e| e.to_string())?
+        {
+            if !in_filter(&group.anchor) {
                 continue;
             }
-            if is_similar(
-                &similar_rows[i],
-                &similar_rows[j],
-                THRESH_AHASH,
-                THRESH_DHASH,
-                THRESH_PHASH,
-            ) {
-                members.push(j);
-            }
-        }
-        if !members.is_empty() {
-            assigned[i] = true;
-            let anchor = similar_rows[i].clone();
-            let mut match_rows = Vec::with_capacity(members.len());
-            for idx in members {
-                assigned[idx] = true;
-                match_rows.push(similar_rows[idx].clone());
-            }
-            insert_match_group(
-                &mut roots,
-                anchor,
-                "Similar matches".to_string(),
-                match_rows,
-            );
+            let matches: Vec<_> = group.matches.into_iter().filter(in_filter).collect();
+            insert_match_group(&mut roots, group.anchor, label.clone(), matches);
         }
     }
 
+    // Same measure as `FileListSort::ReclaimableDesc`: the other exact copies' bytes.
+    let reclaimable = |root: &MatchRootData| {
+        let copies: usize = root
+            .groups
+            .iter()
+            .filter(|group| group.label.starts_with(EXACT_MATCHES_LABEL))
+            .map(|group| group.matches.len())
+            .sum();
+        root.file.size_bytes.saturating_mul(copies as u64)
+    };
     let mut rows: Vec<MatchRootData> = roots.into_values().collect();
     rows.sort_by(|a, b| {
-        b.file
+        let by_size = b
+            .file
             .size_bytes
             .cmp(&a.file.size_bytes)
-            .then_with(|| a.file.path.cmp(&b.file.path))
+            .then_with(|| a.file.path.cmp(&b.file.path));
+        match root_sort {
+            dupdupninja_core::models::FileListSort::ReclaimableDesc => {
+                reclaimable(b).cmp(&reclaimable(a)).then(by_size)
+            }
+            _ => by_size,
+        }
     });
 
-    let note = if similar_limited {
-        Some(format!(
-            "similarity search limited to first {similar_match_cap} hashed files"
-        ))
-    } else {
-        None
-    };
-    Ok((rows, note))
+    let note = near_distance
+        .is_none()
+        .then(|| "near-duplicates off".to_string());
+    let mut file_types = Vec::new();
+    for stats in store.type_breakdown().map_err(|e| e.to_string())? {
+        let family = dupdupninja_core::models::FileTypeFilter::of(stats.file_type.as_deref());
+        if !file_types.contains(&family) {
+            file_types.push(family);
+        }
+    }
+    Ok(LoadedMatches {
+        rows,
+        note,
+        file_types,
+    })
 }
 
 #[cfg(all(target_os = "linux", feature = "gtk"))]
@@ -1835,31 +2602,6 @@ fn insert_match_group(
     }
 }
 
-#[cfg(all(target_os = "linux", feature = "gtk"))]
-fn is_similar(
-    a: &dupdupninja_core::models::FileListRow,
-    b: &dupdupninja_core::models::FileListRow,
-    ahash_threshold: u32,
-    dhash_threshold: u32,
-    phash_threshold: u32,
-) -> bool {
-    if let (Some(left), Some(right)) = (a.phash, b.phash) {
-        return hamming_distance_u64(left, right) <= phash_threshold;
-    }
-    if let (Some(left), Some(right)) = (a.dhash, b.dhash) {
-        return hamming_distance_u64(left, right) <= dhash_threshold;
-    }
-    if let (Some(left), Some(right)) = (a.ahash, b.ahash) {
-        return hamming_distance_u64(left, right) <= ahash_threshold;
-    }
-    false
-}
-
-#[cfg(all(target_os = "linux", feature = "gtk"))]
-fn hamming_distance_u64(a: u64, b: u64) -> u32 {
-    (a ^ b).count_ones()
-}
-
 #[cfg(all(target_os = "linux", feature = "gtk"))]
 fn open_fileset_properties(
     ui_state: std::rc::Rc<std::cell::RefCell<Option<UiState>>>,
@@ -1868,7 +2610,7 @@ fn open_fileset_properties(
 ) {
     use adw::prelude::*;
 
-    let (db_path, current_meta, total_files) = {
+    let (pool, current_meta, total_files, scan_config) = {
         let state = ui_state.borrow();
         let Some(state) = state.as_ref() else {
             return;
@@ -1877,11 +2619,21 @@ fn open_fileset_properties(
             Some(entry) => entry,
             None => return,
         };
-        let total_files = dupdupninja_core::db::SqliteScanStore::open(&entry.db_path)
-            .ok()
+        let store = entry.store().ok();
+        let total_files = store
+            .as_ref()
             .and_then(|store| store.count_files().ok())
             .unwrap_or(0);
-        (entry.db_path.clone(), entry.metadata.clone(), total_files)
+        let scan_config = store
+            .as_ref()
+            .and_then(|store| store.get_scan_config().ok().flatten());
+        drop(store);
+        (
+            entry.store_pool().ok(),
+            entry.metadata.clone(),
+            total_files,
+            scan_config,
+        )
     };
 
     let content = gtk4::Box::new(gtk4::Orientation::Vertical, 12);
@@ -1933,6 +2685,23 @@ fn open_fileset_properties(
     total_value.set_xalign(0.0);
     content.append(&total_value);
 
+    // Filesets scanned before settings were recorded have none to show.
+    if let Some(json) = scan_config {
+        let pretty = serde_json::from_str::<serde_json::Value>(&json)
+            .and_then(|value| serde_json::to_string_pretty(&value))
+            .unwrap_or(json);
+        let config_label = gtk4::Label::new(Some(&pretty));
+        config_label.set_xalign(0.0);
+        config_label.set_selectable(true);
+        config_label.add_css_class("monospace");
+        let config_scroller = gtk4::ScrolledWindow::new();
+        config_scroller.set_min_content_height(160);
+        config_scroller.set_child(Some(&config_label));
+        let expander = gtk4::Expander::new(Some("Scan settings"));
+        expander.set_child(Some(&config_scroller));
+        content.append(&expander);
+    }
+
     let button_row = gtk4::Box::new(gtk4::Orientation::Horizontal, 8);
     button_row.set_halign(gtk4::Align::End);
     let cancel_button = gtk4::Button::with_label("Cancel");
@@ -1971,7 +2740,7 @@ fn open_fileset_properties(
             ..current_meta.clone()
         };
 
-        if let Ok(store) = dupdupninja_core::db::SqliteScanStore::open(&db_path) {
+        if let Some(store) = pool.as_ref().and_then(|pool| pool.get().ok()) {
             let _ = store.set_fileset_metadata(&meta);
         }
 
@@ -1,5 +1,5 @@
-use std::cell::RefCell;
-use std::collections::HashMap;
+use std::cell::{OnceCell, RefCell};
+use std::collections::{HashMap, VecDeque};
 use std::path::PathBuf;
 use std::rc::Rc;
 use std::time::Instant;
@@ -7,10 +7,13 @@ use std::time::Instant;
 use adw::ActionRow;
 use gtk4 as gtk;
 
-use dupdupninja_core::models::FileListRow;
+use dupdupninja_core::db::{PooledStore, SqliteScanStore, SqliteStorePool};
+use dupdupninja_core::models::{FileListRow, FileListSort, FileTypeFilter};
 use dupdupninja_core::scan::{ScanCancelToken, ScanTotals};
 use dupdupninja_core::FilesetMetadata;
 
+use super::files_list::{SnapshotTextureCache, TypeFilterControl};
+
 #[derive(Clone)]
 pub(crate) struct FileActionButtons {
     pub(crate) trash: gtk::Button,
@@ -28,6 +31,25 @@ pub(crate) struct FilesetEntry {
     pub(crate) action_row: ActionRow,
     pub(crate) row: gtk::ListBoxRow,
     pub(crate) metadata: FilesetMetadata,
+    /// Opened on first use and kept while the fileset is open; `db_path` never changes.
+    pub(crate) pool: OnceCell<SqliteStorePool>,
+}
+
+/// Enough for a background match load and a main-thread lookup to overlap.
+const FILESET_POOL_CONNS: usize = 4;
+
+impl FilesetEntry {
+    pub(crate) fn store_pool(&self) -> dupdupninja_core::Result<SqliteStorePool> {
+        if let Some(pool) = self.pool.get() {
+            return Ok(pool.clone());
+        }
+        let pool = SqliteScanStore::open_pool(&self.db_path, FILESET_POOL_CONNS)?;
+        Ok(self.pool.get_or_init(|| pool).clone())
+    }
+
+    pub(crate) fn store(&self) -> dupdupninja_core::Result<PooledStore> {
+        self.store_pool()?.get()
+    }
 }
 
 pub(crate) struct SelectedFile {
@@ -61,7 +83,15 @@ pub(crate) struct UiState {
     pub(crate) next_fileset_id: u64,
     pub(crate) active_fileset_id: Option<u64>,
     pub(crate) fileset_placeholder: gtk::Label,
+    /// Shown above the files when the active fileset's root folder is missing.
+    pub(crate) root_banner: adw::Banner,
+    /// Dismissible notice above the main area, e.g. missing media tools or an
+    /// unwritable fileset folder.
+    pub(crate) notice_banner: adw::Banner,
     pub(crate) files_stack: gtk::Stack,
+    pub(crate) files_view: gtk::ColumnView,
+    /// Dropped folders waiting for the current scan to finish.
+    pub(crate) pending_folder_scans: VecDeque<PathBuf>,
     pub(crate) files_root_store: gtk::gio::ListStore,
     pub(crate) files_db_path: Rc<RefCell<Option<PathBuf>>>,
     pub(crate) active_scan_fileset_id: Option<u64>,
@@ -70,12 +100,25 @@ pub(crate) struct UiState {
     pub(crate) snapshots_per_video: u32,
     pub(crate) snapshot_max_dim: u32,
     pub(crate) concurrent_processing: bool,
-    pub(crate) similar_match_cap: usize,
+    pub(crate) single_pass_scan: bool,
+    pub(crate) include_near_duplicates: bool,
+    /// Max pHash Hamming distance for near-duplicate groups.
+    pub(crate) near_duplicate_distance: u32,
+    pub(crate) trash_confirm_threshold: usize,
     pub(crate) last_files_refresh: Option<Instant>,
     pub(crate) selected_files: HashMap<i64, SelectedFile>,
     pub(crate) action_bar_label: gtk::Label,
     pub(crate) action_bar_buttons: FileActionButtons,
+    pub(crate) type_filter_control: TypeFilterControl,
+    /// `None` shows every type.
+    pub(crate) type_filter: Option<FileTypeFilter>,
+    /// Whether exact-match groups marked with "Ignore this group" are listed.
+    pub(crate) show_ignored_groups: bool,
+    /// Order of the top-level rows: `SizeDesc` or `ReclaimableDesc`.
+    pub(crate) root_sort: FileListSort,
     pub(crate) files_load_generation: u64,
+    /// Decoded compare-window snapshots of the shown fileset.
+    pub(crate) snapshot_textures: SnapshotTextureCache,
 }
 
 pub(crate) enum UiUpdate {
@@ -105,6 +148,12 @@ pub(crate) enum UiUpdate {
         generation: u64,
         rows: Vec<MatchRootData>,
         note: Option<String>,
+        /// Types present in the whole fileset, for the type dropdown.
+        file_types: Vec<FileTypeFilter>,
+        /// Exact-duplicate group count and reclaimable bytes of the whole fileset.
+        duplicates: Option<(u64, u64)>,
+        /// Merge into the current list instead of replacing it.
+        refresh: bool,
     },
     FilesLoadError {
         fileset_id: u64,
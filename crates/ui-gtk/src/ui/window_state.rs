@@ -0,0 +1,111 @@
+use std::path::PathBuf;
+
+use adw::prelude::*;
+use gtk4 as gtk;
+use serde::{Deserialize, Serialize};
+
+/// Window geometry and file list layout, saved as JSON next to `settings.txt`.
+/// Unknown or missing fields fall back to defaults so the file can grow.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct WindowState {
+    pub(crate) width: i32,
+    pub(crate) height: i32,
+    pub(crate) maximized: bool,
+    pub(crate) columns: Vec<ColumnState>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ColumnState {
+    pub(crate) title: String,
+    /// Fixed width in pixels; -1 when the column was never resized.
+    #[serde(default = "auto_width")]
+    pub(crate) width: i32,
+    #[serde(default = "visible_by_default")]
+    pub(crate) visible: bool,
+}
+
+fn auto_width() -> i32 {
+    -1
+}
+
+fn visible_by_default() -> bool {
+    true
+}
+
+impl Default for WindowState {
+    fn default() -> Self {
+        Self {
+            width: 1100,
+            height: 720,
+            maximized: true,
+            columns: Vec::new(),
+        }
+    }
+}
+
+fn window_state_path() -> PathBuf {
+    let mut path = super::default_config_dir();
+    path.push("window-state.json");
+    path
+}
+
+pub(crate) fn load_window_state() -> WindowState {
+    std::fs::read(window_state_path())
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+pub(crate) fn save_window_state(window: &impl IsA<gtk::Window>, files_view: &gtk::ColumnView) {
+    let (width, height) = window.default_size();
+    let defaults = WindowState::default();
+    let state = WindowState {
+        width: if width > 0 { width } else { defaults.width },
+        height: if height > 0 { height } else { defaults.height },
+        maximized: window.is_maximized(),
+        columns: file_columns(files_view)
+            .into_iter()
+            .filter_map(|column| {
+                Some(ColumnState {
+                    title: column.title()?.to_string(),
+                    width: column.fixed_width(),
+                    visible: column.is_visible(),
+                })
+            })
+            .collect(),
+    };
+    let path = window_state_path();
+    if let Some(parent) = path.parent() {
+        if std::fs::create_dir_all(parent).is_err() {
+            return;
+        }
+    }
+    if let Ok(json) = serde_json::to_vec_pretty(&state) {
+        let _ = std::fs::write(path, json);
+    }
+}
+
+/// Restores saved widths and visibility, matching columns by title.
+pub(crate) fn apply_column_state(files_view: &gtk::ColumnView, columns: &[ColumnState]) {
+    for column in file_columns(files_view) {
+        let Some(title) = column.title() else {
+            continue;
+        };
+        let Some(saved) = columns.iter().find(|saved| saved.title == title.as_str()) else {
+            continue;
+        };
+        if column.is_resizable() && saved.width > 0 {
+            column.set_fixed_width(saved.width);
+        }
+        column.set_visible(saved.visible);
+    }
+}
+
+fn file_columns(files_view: &gtk::ColumnView) -> Vec<gtk::ColumnViewColumn> {
+    let columns = files_view.columns();
+    (0..columns.n_items())
+        .filter_map(|idx| columns.item(idx))
+        .filter_map(|obj| obj.downcast::<gtk::ColumnViewColumn>().ok())
+        .collect()
+}
@@ -98,9 +98,10 @@ public final class Engine {
         let unmanaged = Unmanaged.passRetained(handler)
         defer { unmanaged.release() }
 
-        var totals = DupdupPrescanTotals(total_files: 0, total_bytes: 0)
+        var totals = DupdupTotals(files: 0, bytes: 0)
         let status = rootPath.withCString { rootC in
-            dupdupninja_prescan_folder(
+            dupdupninja_prescan(
+                ptr,
                 rootC,
                 cancel.raw,
                 callback,
@@ -113,7 +114,7 @@ public final class Engine {
             throw lastErrorOrUnknown()
         }
 
-        return ScanTotals(totalFiles: totals.total_files, totalBytes: totals.total_bytes)
+        return ScanTotals(totalFiles: totals.files, totalBytes: totals.bytes)
     }
 
     public func scanFolderToSqliteWithProgress(
@@ -144,15 +145,15 @@ public final class Engine {
         let unmanaged = Unmanaged.passRetained(handler)
         defer { unmanaged.release() }
 
+        var cTotals = DupdupTotals(files: totals.totalFiles, bytes: totals.totalBytes)
         let status = rootPath.withCString { rootC in
             dbPath.withCString { dbC in
-                dupdupninja_scan_folder_to_sqlite_with_progress_and_totals(
+                dupdupninja_scan_folder_to_sqlite_with_progress(
                     ptr,
                     rootC,
                     dbC,
                     cancel.raw,
-                    totals.totalFiles,
-                    totals.totalBytes,
+                    &cTotals,
                     callback,
                     unmanaged.toOpaque()
                 )
@@ -93,6 +93,10 @@ internal static class NativeMethods
         Error = 1,
         InvalidArgument = 2,
         NullPointer = 3,
+        Cancelled = 4,
+        NotFound = 5,
+        Io = 6,
+        Running = 7,
     }
 
     [StructLayout(LayoutKind.Sequential)]
@@ -108,6 +112,14 @@ internal static class NativeMethods
         public IntPtr CurrentStep;
     }
 
+    [StructLayout(LayoutKind.Sequential)]
+    internal struct Totals
+    {
+        public ulong Files;
+        public ulong Bytes;
+    }
+
+    [Obsolete("Use Totals.")]
     [StructLayout(LayoutKind.Sequential)]
     internal struct PrescanTotals
     {
@@ -233,6 +245,16 @@ internal static class NativeMethods
     [DllImport(NativeLibraryName, CallingConvention = CallingConvention.Cdecl)]
     internal static extern IntPtr dupdupninja_last_error_message();
 
+    [DllImport(NativeLibraryName, CallingConvention = CallingConvention.Cdecl)]
+    internal static extern DupdupStatus dupdupninja_prescan(
+        IntPtr engine,
+        [MarshalAs(UnmanagedType.LPUTF8Str)] string rootPath,
+        IntPtr cancelToken,
+        PrescanCallback progressCb,
+        IntPtr userData,
+        out Totals totals);
+
+    [Obsolete("Use dupdupninja_prescan.")]
     [DllImport(NativeLibraryName, CallingConvention = CallingConvention.Cdecl)]
     internal static extern DupdupStatus dupdupninja_prescan_folder(
         [MarshalAs(UnmanagedType.LPUTF8Str)] string rootPath,
@@ -318,6 +340,10 @@ internal static class NativeMethods
         [MarshalAs(UnmanagedType.LPUTF8Str)] string dbPath,
         [MarshalAs(UnmanagedType.LPUTF8Str)] string filePath);
 
+    [DllImport(NativeLibraryName, CallingConvention = CallingConvention.Cdecl)]
+    internal static extern DupdupStatus dupdupninja_move_to_trash(
+        [MarshalAs(UnmanagedType.LPUTF8Str)] string filePath);
+
     [DllImport(NativeLibraryName, CallingConvention = CallingConvention.Cdecl)]
     internal static extern DupdupStatus dupdupninja_fileset_list_snapshots_by_path(
         [MarshalAs(UnmanagedType.LPUTF8Str)] string dbPath,
@@ -121,13 +121,13 @@ public sealed class NativeWinUiDataAdapter : IWinUiDataAdapter
 
             return await Task.Run(() =>
             {
-                var totals = new NativeMethods.PrescanTotals();
-                var prescanStatus = NativeMethods.dupdupninja_prescan_folder(
+                var prescanStatus = NativeMethods.dupdupninja_prescan(
+                    _engine,
                     rootPath,
                     _cancelToken,
                     _prescanCallback,
                     IntPtr.Zero,
-                    ref totals);
+                    out var totals);
 
                 if (prescanStatus != NativeMethods.DupdupStatus.Ok)
                 {
@@ -148,8 +148,8 @@ public sealed class NativeWinUiDataAdapter : IWinUiDataAdapter
                     rootPath,
                     dbPath,
                     _cancelToken,
-                    totals.TotalFiles,
-                    totals.TotalBytes,
+                    totals.Files,
+                    totals.Bytes,
                     ref options,
                     _progressCallback,
                     IntPtr.Zero);